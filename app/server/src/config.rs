@@ -0,0 +1,754 @@
+//! 应用配置
+//!
+//! 从环境变量（以及 .env 文件）加载服务器配置，所有字段都有默认值。
+
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::DiskMapping;
+
+/// 数据库中保存的时间格式
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 文件监听器配置
+#[derive(Debug, Clone)]
+pub struct WatcherConfig {
+    /// 启动时是否自动开启文件监听（WATCHER_ENABLED）
+    pub enabled: bool,
+    /// 文件变化防抖时间，毫秒（WATCHER_DEBOUNCE_MS）
+    pub debounce_ms: u64,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            debounce_ms: 2000,
+        }
+    }
+}
+
+/// 目录扫描配置
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    /// 扫描时忽略的文件/目录名通配符（SCAN_IGNORE，逗号或分号分隔）
+    ///
+    /// 支持 `*` 和 `?`，匹配文件或目录名，被忽略的目录不会继续向下扫描
+    pub ignore: Vec<String>,
+    /// 扫描时是否跟随符号链接（SCAN_FOLLOW_SYMLINKS），链接形成的循环会被跳过
+    pub follow_symlinks: bool,
+    /// 最大扫描深度（SCAN_MAX_DEPTH），数据源根目录下的条目深度为 1，未设置或为 0 时不限制
+    ///
+    /// 达到上限的目录仍然记录为目录，但不再向下扫描
+    pub max_depth: Option<usize>,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            // 默认忽略隐藏文件（.DS_Store 等）和未下载完成的 .part 文件
+            ignore: vec![".*".to_string(), "*.part".to_string()],
+            follow_symlinks: false,
+            max_depth: None,
+        }
+    }
+}
+
+/// 目录同步配置
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    /// 每个并行任务处理的条目数（SYNC_BATCH_SIZE）
+    pub batch_size: usize,
+    /// 同步专用线程池的线程数（SYNC_THREADS），0 表示使用 CPU 核心数
+    ///
+    /// 同步不使用全局 rayon 线程池，避免大规模同步时影响请求处理
+    pub threads: usize,
+    /// 严格元数据模式（STRICT_METADATA）：无法读取元数据的视频在 error 列中标记，
+    /// 而不是只留空元数据
+    pub strict_metadata: bool,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            threads: 0,
+            strict_metadata: false,
+        }
+    }
+}
+
+/// 后台任务队列配置
+#[derive(Debug, Clone)]
+pub struct TaskQueueConfig {
+    /// 最大并发任务数（TASK_CONCURRENCY）
+    pub max_concurrent: usize,
+}
+
+impl Default for TaskQueueConfig {
+    fn default() -> Self {
+        Self { max_concurrent: 4 }
+    }
+}
+
+/// 缩略图配置
+#[derive(Debug, Clone)]
+pub struct ThumbnailConfig {
+    /// 额外生成的缩略图宽度列表（THUMBNAIL_SIZES，逗号或分号分隔，例如 "160,480"）
+    ///
+    /// 生成到 `thumbnails/<size>/...`，为空时只生成默认尺寸
+    pub sizes: Vec<u32>,
+    /// `?inline_thumbnail=true` 时允许内联为 data URI 的最大缩略图大小，字节（THUMBNAIL_INLINE_MAX_BYTES）
+    pub inline_max_bytes: u64,
+    /// 叠加在缩略图右下角的文字（THUMBNAIL_LABEL），为空时不叠加
+    pub label: Option<String>,
+    /// 启动时生成缩略图还是在第一次请求时生成，eager 或 lazy（THUMBNAIL_MODE）
+    pub mode: ThumbnailMode,
+    /// 按扩展名或分类指定缩略图生成方式（THUMBNAIL_STRATEGIES，例如 "gif=first_frame;image=placeholder"）
+    ///
+    /// 键为小写扩展名或分类名（video、playlist、subtitle、image），扩展名优先；
+    /// 未配置的文件可截取画面时使用 frame，否则使用 placeholder
+    pub strategies: BTreeMap<String, ThumbnailStrategy>,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            sizes: Vec::new(),
+            inline_max_bytes: 16 * 1024,
+            label: None,
+            mode: ThumbnailMode::default(),
+            strategies: BTreeMap::new(),
+        }
+    }
+}
+
+/// 缩略图生成时机
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThumbnailMode {
+    /// 启动时为缺少缩略图的文件生成（默认）
+    #[default]
+    Eager,
+    /// 启动时跳过，第一次请求缩略图时在后台生成，生成前返回占位图
+    Lazy,
+}
+
+impl FromStr for ThumbnailMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "eager" => Ok(Self::Eager),
+            "lazy" => Ok(Self::Lazy),
+            other => Err(format!("unknown thumbnail mode: {}", other)),
+        }
+    }
+}
+
+/// 缩略图生成方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailStrategy {
+    /// 在配置的时间点截取一帧
+    Frame,
+    /// 截取第一帧，适合很短的动图
+    FirstFrame,
+    /// 生成占位缩略图
+    Placeholder,
+}
+
+impl ThumbnailStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Frame => "frame",
+            Self::FirstFrame => "first_frame",
+            Self::Placeholder => "placeholder",
+        }
+    }
+}
+
+impl FromStr for ThumbnailStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "frame" => Ok(Self::Frame),
+            "first_frame" => Ok(Self::FirstFrame),
+            "placeholder" => Ok(Self::Placeholder),
+            other => Err(format!("unknown thumbnail strategy: {}", other)),
+        }
+    }
+}
+
+/// 解析 "键=方式" 列表，忽略格式错误的项
+fn parse_thumbnail_strategies(entries: &[String]) -> BTreeMap<String, ThumbnailStrategy> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let (key, strategy) = entry.split_once('=')?;
+            let key = key.trim().trim_start_matches('.').to_ascii_lowercase();
+            let strategy = strategy.parse().ok()?;
+            (!key.is_empty()).then_some((key, strategy))
+        })
+        .collect()
+}
+
+/// 上传配置
+#[derive(Debug, Clone)]
+pub struct UploadConfig {
+    /// 单次上传请求体的最大字节数（UPLOAD_MAX_BYTES）
+    pub max_bytes: u64,
+    /// 分片上传会话的存放目录（UPLOAD_CHUNK_DIR）
+    pub chunk_dir: String,
+    /// 分片的最小字节数，只有一个分片的文件不受限制（UPLOAD_MIN_CHUNK_BYTES）
+    pub min_chunk_bytes: u64,
+    /// 分片上传会话的有效期（秒），过期的会话及其分片会被删除（UPLOAD_SESSION_TTL_SECS）
+    pub session_ttl_secs: u64,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 4 * 1024 * 1024 * 1024,
+            chunk_dir: "uploads".to_string(),
+            min_chunk_bytes: 256 * 1024,
+            session_ttl_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+/// FFmpeg 可执行文件配置
+#[derive(Debug, Clone)]
+pub struct FfmpegBinaryConfig {
+    /// ffmpeg 可执行文件路径（FFMPEG_PATH），默认从 PATH 中查找
+    pub ffmpeg_path: String,
+    /// ffprobe 可执行文件路径（FFPROBE_PATH），默认从 PATH 中查找
+    pub ffprobe_path: String,
+    /// 探测、截图等单次操作的超时时间，秒（FFMPEG_TIMEOUT_SECS），0 表示不限制
+    pub timeout_secs: u64,
+    /// 需要读完整个文件的操作（解码检查、HLS 切片、合并、故事板）的超时时间，秒
+    /// （FFMPEG_LONG_TIMEOUT_SECS），0 表示不限制
+    pub long_timeout_secs: u64,
+    /// 同时进行的实时转码数量上限（MAX_TRANSCODES），已满时新的转码请求返回 503
+    pub max_transcodes: usize,
+}
+
+impl Default for FfmpegBinaryConfig {
+    fn default() -> Self {
+        Self {
+            ffmpeg_path: "ffmpeg".to_string(),
+            ffprobe_path: "ffprobe".to_string(),
+            timeout_secs: 60,
+            long_timeout_secs: 3600,
+            max_transcodes: 2,
+        }
+    }
+}
+
+/// 列表接口配置
+#[derive(Debug, Clone)]
+pub struct ListingConfig {
+    /// 默认排序字段（DEFAULT_SORT_BY）
+    pub default_sort_by: String,
+    /// 默认排序方向，asc 或 desc（DEFAULT_SORT_ORDER）
+    pub default_sort_order: String,
+    /// 列表是否隐藏被标记了元数据错误的条目（HIDE_METADATA_ERRORS），配合 STRICT_METADATA 使用
+    pub hide_metadata_errors: bool,
+}
+
+impl Default for ListingConfig {
+    fn default() -> Self {
+        Self {
+            default_sort_by: "created_at".to_string(),
+            default_sort_order: "desc".to_string(),
+            hide_metadata_errors: false,
+        }
+    }
+}
+
+/// 数据库配置
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    /// SQLite 数据库文件路径（DATABASE_PATH），`:memory:` 表示使用内存数据库
+    pub path: String,
+    /// 数据库被其他连接锁定时等待的时间，毫秒（DATABASE_BUSY_TIMEOUT_MS）
+    pub busy_timeout_ms: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: "videos.db".to_string(),
+            busy_timeout_ms: 5000,
+        }
+    }
+}
+
+/// 数据源配置
+#[derive(Debug, Clone)]
+pub struct DataSourceConfig {
+    /// 数据源目录映射（DATA_SOURCE_DIRS，分号分隔）
+    ///
+    /// 每一项可以是 `路由路径=物理路径`（例如 `/public/movies=D:/videos`），
+    /// 也可以只写物理路径，此时按顺序分配 `/public/disk1`、`/public/disk2` ...
+    pub mappings: Vec<DiskMapping>,
+}
+
+impl Default for DataSourceConfig {
+    fn default() -> Self {
+        Self {
+            mappings: parse_data_sources("F:/public"),
+        }
+    }
+}
+
+/// 解析 DATA_SOURCE_DIRS 格式的数据源列表，忽略空项
+pub fn parse_data_sources(value: &str) -> Vec<DiskMapping> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .enumerate()
+        .map(|(index, entry)| match entry.split_once('=') {
+            Some((route, physical)) => DiskMapping {
+                route_path: route.trim().to_string(),
+                physical_path: physical.trim().to_string(),
+            },
+            None => DiskMapping {
+                route_path: format!("/public/disk{}", index + 1),
+                physical_path: entry.to_string(),
+            },
+        })
+        .collect()
+}
+
+/// 高开销接口（probe、merge、截取画面等）的限流配置
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// 每个客户端每分钟允许的请求数（RATE_LIMIT_PER_MINUTE），每个路由单独计数
+    pub per_minute: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self { per_minute: 10 }
+    }
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// 带颜色的文本（默认）
+    #[default]
+    Text,
+    /// 每行一个 JSON 对象（timestamp, level, target, message），便于日志采集
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!("unknown log format: {}", other)),
+        }
+    }
+}
+
+/// 日志配置
+#[derive(Debug, Clone, Default)]
+pub struct LoggingConfig {
+    /// 输出格式，text 或 json（LOG_FORMAT）
+    pub format: LogFormat,
+}
+
+/// 时间的显示时区
+///
+/// 支持 `UTC`、固定偏移（`+08:00`、`-0530`）和 IANA 时区名（`Asia/Shanghai`）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayTimezone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Default for DisplayTimezone {
+    fn default() -> Self {
+        Self::Fixed(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+impl DisplayTimezone {
+    /// 将 Unix 时间戳（秒）格式化为该时区的 `%Y-%m-%d %H:%M:%S`
+    pub fn format_timestamp(&self, secs: i64) -> Option<String> {
+        let datetime = DateTime::from_timestamp(secs, 0)?;
+        let formatted = match self {
+            Self::Fixed(offset) => datetime.with_timezone(offset).format(TIMESTAMP_FORMAT),
+            Self::Named(tz) => datetime.with_timezone(tz).format(TIMESTAMP_FORMAT),
+        };
+        Some(formatted.to_string())
+    }
+}
+
+impl FromStr for DisplayTimezone {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("utc") || value.eq_ignore_ascii_case("z") {
+            return Ok(Self::default());
+        }
+        if let Some(offset) = parse_utc_offset(value) {
+            return Ok(Self::Fixed(offset));
+        }
+        value
+            .parse::<Tz>()
+            .map(Self::Named)
+            .map_err(|_| format!("unknown timezone: {}", value))
+    }
+}
+
+/// 解析 `+08:00`、`+0800`、`-05` 形式的 UTC 偏移
+fn parse_utc_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse::<i32>().ok()?, digits[2..].parse().ok()?),
+        _ => return None,
+    };
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// 应用配置
+#[derive(Debug, Clone, Default)]
+pub struct AppConfig {
+    pub watcher: WatcherConfig,
+    pub scan: ScanConfig,
+    pub sync: SyncConfig,
+    pub tasks: TaskQueueConfig,
+    pub thumbnail: ThumbnailConfig,
+    pub upload: UploadConfig,
+    pub ffmpeg: FfmpegBinaryConfig,
+    pub listing: ListingConfig,
+    pub database: DatabaseConfig,
+    pub data_sources: DataSourceConfig,
+    pub rate_limit: RateLimitConfig,
+    pub logging: LoggingConfig,
+    /// 管理接口使用的 API Key 列表（API_KEYS，逗号或分号分隔），为空时禁用管理接口
+    pub api_keys: Vec<String>,
+    /// 对外访问的基础地址（PUBLIC_BASE_URL），例如 `https://media.example.com`；
+    /// 设置后播放列表输出绝对地址，否则输出相对路径
+    pub public_base_url: Option<String>,
+    /// 创建时间的显示时区（DISPLAY_TIMEZONE），默认 UTC
+    pub display_timezone: DisplayTimezone,
+}
+
+impl AppConfig {
+    /// 从环境变量加载配置，未设置或解析失败的字段使用默认值
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            watcher: WatcherConfig {
+                enabled: env_bool("WATCHER_ENABLED", defaults.watcher.enabled),
+                debounce_ms: env_parse("WATCHER_DEBOUNCE_MS", defaults.watcher.debounce_ms),
+            },
+            scan: ScanConfig {
+                ignore: env_list_or("SCAN_IGNORE", defaults.scan.ignore),
+                follow_symlinks: env_bool("SCAN_FOLLOW_SYMLINKS", defaults.scan.follow_symlinks),
+                max_depth: Some(env_parse("SCAN_MAX_DEPTH", 0))
+                    .filter(|depth| *depth > 0)
+                    .or(defaults.scan.max_depth),
+            },
+            sync: SyncConfig {
+                batch_size: env_parse("SYNC_BATCH_SIZE", defaults.sync.batch_size).max(1),
+                threads: env_parse("SYNC_THREADS", defaults.sync.threads),
+                strict_metadata: env_bool("STRICT_METADATA", defaults.sync.strict_metadata),
+            },
+            tasks: TaskQueueConfig {
+                max_concurrent: env_parse("TASK_CONCURRENCY", defaults.tasks.max_concurrent).max(1),
+            },
+            thumbnail: ThumbnailConfig {
+                sizes: env_list("THUMBNAIL_SIZES")
+                    .iter()
+                    .filter_map(|size| size.parse().ok())
+                    .filter(|size| *size > 0)
+                    .collect(),
+                inline_max_bytes: env_parse(
+                    "THUMBNAIL_INLINE_MAX_BYTES",
+                    defaults.thumbnail.inline_max_bytes,
+                ),
+                label: Some(env_string("THUMBNAIL_LABEL", String::new()))
+                    .filter(|label| !label.is_empty()),
+                mode: env_parse("THUMBNAIL_MODE", defaults.thumbnail.mode),
+                strategies: parse_thumbnail_strategies(&env_list("THUMBNAIL_STRATEGIES")),
+            },
+            upload: UploadConfig {
+                max_bytes: env_parse("UPLOAD_MAX_BYTES", defaults.upload.max_bytes),
+                chunk_dir: env_string("UPLOAD_CHUNK_DIR", defaults.upload.chunk_dir),
+                min_chunk_bytes: env_parse(
+                    "UPLOAD_MIN_CHUNK_BYTES",
+                    defaults.upload.min_chunk_bytes,
+                ),
+                session_ttl_secs: env_parse(
+                    "UPLOAD_SESSION_TTL_SECS",
+                    defaults.upload.session_ttl_secs,
+                ),
+            },
+            ffmpeg: FfmpegBinaryConfig {
+                ffmpeg_path: env_string("FFMPEG_PATH", defaults.ffmpeg.ffmpeg_path),
+                ffprobe_path: env_string("FFPROBE_PATH", defaults.ffmpeg.ffprobe_path),
+                timeout_secs: env_parse("FFMPEG_TIMEOUT_SECS", defaults.ffmpeg.timeout_secs),
+                long_timeout_secs: env_parse(
+                    "FFMPEG_LONG_TIMEOUT_SECS",
+                    defaults.ffmpeg.long_timeout_secs,
+                ),
+                max_transcodes: env_parse("MAX_TRANSCODES", defaults.ffmpeg.max_transcodes).max(1),
+            },
+            listing: ListingConfig {
+                default_sort_by: env_string("DEFAULT_SORT_BY", defaults.listing.default_sort_by),
+                default_sort_order: env_string(
+                    "DEFAULT_SORT_ORDER",
+                    defaults.listing.default_sort_order,
+                ),
+                hide_metadata_errors: env_bool(
+                    "HIDE_METADATA_ERRORS",
+                    defaults.listing.hide_metadata_errors,
+                ),
+            },
+            database: DatabaseConfig {
+                path: env_string("DATABASE_PATH", defaults.database.path),
+                busy_timeout_ms: env_parse(
+                    "DATABASE_BUSY_TIMEOUT_MS",
+                    defaults.database.busy_timeout_ms,
+                ),
+            },
+            data_sources: DataSourceConfig {
+                mappings: std::env::var("DATA_SOURCE_DIRS")
+                    .map(|value| parse_data_sources(&value))
+                    .unwrap_or(defaults.data_sources.mappings),
+            },
+            rate_limit: RateLimitConfig {
+                per_minute: env_parse("RATE_LIMIT_PER_MINUTE", defaults.rate_limit.per_minute),
+            },
+            logging: LoggingConfig {
+                format: env_parse("LOG_FORMAT", defaults.logging.format),
+            },
+            api_keys: env_list("API_KEYS"),
+            public_base_url: Some(env_string("PUBLIC_BASE_URL", String::new()))
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+            display_timezone: env_parse("DISPLAY_TIMEZONE", defaults.display_timezone),
+        }
+    }
+
+    /// 生效配置的 JSON 表示，用于排查部署问题；API Key 等密钥只保留数量
+    pub fn sanitized(&self) -> serde_json::Value {
+        let mappings: Vec<_> = self
+            .data_sources
+            .mappings
+            .iter()
+            .map(|m| serde_json::json!({ "route_path": m.route_path, "physical_path": m.physical_path }))
+            .collect();
+        let timezone = match self.display_timezone {
+            DisplayTimezone::Fixed(offset) => offset.to_string(),
+            DisplayTimezone::Named(tz) => tz.name().to_string(),
+        };
+
+        serde_json::json!({
+            "data_sources": mappings,
+            "watcher": {
+                "enabled": self.watcher.enabled,
+                "debounce_ms": self.watcher.debounce_ms,
+            },
+            "scan": {
+                "ignore": self.scan.ignore,
+                "follow_symlinks": self.scan.follow_symlinks,
+                "max_depth": self.scan.max_depth,
+            },
+            "sync": {
+                "batch_size": self.sync.batch_size,
+                "threads": self.sync.threads,
+                "strict_metadata": self.sync.strict_metadata,
+            },
+            "tasks": {
+                "max_concurrent": self.tasks.max_concurrent,
+            },
+            "thumbnail": {
+                "sizes": self.thumbnail.sizes,
+                "inline_max_bytes": self.thumbnail.inline_max_bytes,
+                "label": self.thumbnail.label,
+                "mode": format!("{:?}", self.thumbnail.mode).to_lowercase(),
+                "strategies": self
+                    .thumbnail
+                    .strategies
+                    .iter()
+                    .map(|(key, strategy)| (key.clone(), strategy.as_str()))
+                    .collect::<BTreeMap<_, _>>(),
+            },
+            "upload": {
+                "max_bytes": self.upload.max_bytes,
+                "chunk_dir": self.upload.chunk_dir,
+                "min_chunk_bytes": self.upload.min_chunk_bytes,
+                "session_ttl_secs": self.upload.session_ttl_secs,
+            },
+            "ffmpeg": {
+                "ffmpeg_path": self.ffmpeg.ffmpeg_path,
+                "ffprobe_path": self.ffmpeg.ffprobe_path,
+                "timeout_secs": self.ffmpeg.timeout_secs,
+                "long_timeout_secs": self.ffmpeg.long_timeout_secs,
+            },
+            "listing": {
+                "default_sort_by": self.listing.default_sort_by,
+                "default_sort_order": self.listing.default_sort_order,
+                "hide_metadata_errors": self.listing.hide_metadata_errors,
+            },
+            "database": {
+                "path": self.database.path,
+                "busy_timeout_ms": self.database.busy_timeout_ms,
+            },
+            "rate_limit": {
+                "per_minute": self.rate_limit.per_minute,
+            },
+            "logging": {
+                "format": format!("{:?}", self.logging.format).to_lowercase(),
+            },
+            "api_keys": {
+                "configured": self.api_keys.len(),
+            },
+            "public_base_url": self.public_base_url,
+            "display_timezone": timezone,
+        })
+    }
+}
+
+/// 全局配置实例
+static APP_CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+/// 初始化全局配置（只在启动时调用一次）
+pub fn init_config(config: AppConfig) {
+    let _ = APP_CONFIG.set(config);
+}
+
+/// 获取全局配置，供无法访问 AppState 的服务使用；未初始化时使用默认配置
+pub fn get_config() -> &'static AppConfig {
+    APP_CONFIG.get_or_init(AppConfig::default)
+}
+
+/// 读取布尔类型的环境变量（支持 true/false/1/0/yes/no/on/off）
+fn env_bool(key: &str, default: bool) -> bool {
+    match std::env::var(key) {
+        Ok(value) => match value.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => true,
+            "false" | "0" | "no" | "off" => false,
+            _ => default,
+        },
+        Err(_) => default,
+    }
+}
+
+/// 读取字符串类型的环境变量，未设置或为空时使用默认值
+fn env_string(key: &str, default: String) -> String {
+    std::env::var(key)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or(default)
+}
+
+/// 读取可解析类型的环境变量
+fn env_parse<T: FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// 读取列表类型的环境变量（逗号或分号分隔，忽略空项）
+fn env_list(key: &str) -> Vec<String> {
+    std::env::var(key)
+        .map(|value| {
+            value
+                .split([',', ';'])
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 读取列表类型的环境变量，未设置时使用默认值
+fn env_list_or(key: &str, default: Vec<String>) -> Vec<String> {
+    if std::env::var(key).is_ok() {
+        env_list(key)
+    } else {
+        default
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_data_sources() {
+        let mappings = parse_data_sources("D:/videos; /public/movies=/mnt/movies ;;E:/more");
+
+        let pairs: Vec<_> = mappings
+            .iter()
+            .map(|m| (m.route_path.as_str(), m.physical_path.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("/public/disk1", "D:/videos"),
+                ("/public/movies", "/mnt/movies"),
+                ("/public/disk3", "E:/more"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display_timezone_formats_epoch() {
+        // 2025-01-01 00:00:00 UTC
+        let epoch = 1_735_689_600;
+
+        let beijing: DisplayTimezone = "+08:00".parse().unwrap();
+        assert_eq!(
+            beijing.format_timestamp(epoch).as_deref(),
+            Some("2025-01-01 08:00:00")
+        );
+        let new_york: DisplayTimezone = "America/New_York".parse().unwrap();
+        assert_eq!(
+            new_york.format_timestamp(epoch).as_deref(),
+            Some("2024-12-31 19:00:00")
+        );
+        assert_eq!(
+            DisplayTimezone::default()
+                .format_timestamp(epoch)
+                .as_deref(),
+            Some("2025-01-01 00:00:00")
+        );
+
+        assert_eq!(
+            "-0530".parse::<DisplayTimezone>().unwrap(),
+            DisplayTimezone::Fixed(FixedOffset::west_opt(5 * 3600 + 30 * 60).unwrap())
+        );
+        assert!("Mars/Olympus".parse::<DisplayTimezone>().is_err());
+        assert!("+25:00".parse::<DisplayTimezone>().is_err());
+    }
+}
@@ -1,19 +1,29 @@
+mod config;
 mod models;
 mod routes;
 mod services;
+#[cfg(test)]
+mod test_utils;
 mod utils;
 use axum::{
+    extract::DefaultBodyLimit,
     http::{HeaderName, HeaderValue},
-    routing::{delete, get},
+    middleware,
+    routing::{delete, get, post, put, MethodRouter},
     Router,
 };
 use log::info;
 use std::sync::{Arc, Mutex};
 use std::{net::SocketAddr, path::PathBuf};
+use tokio::sync::Semaphore;
 use tower_http::{cors::CorsLayer, services::ServeDir};
 
 use crate::{
-    services::{init_task_queue, VideoDbManager},
+    config::AppConfig,
+    services::{
+        init_task_queue, start_watcher_if_enabled, FileWatcher, RebuildTracker, SyncReport,
+        VideoDbManager,
+    },
     utils::init_logger,
 };
 // 定义一个简单的结构体来存储映射关系
@@ -27,59 +37,100 @@ pub struct DiskMapping {
 pub struct AppState {
     pub db_manager: Arc<Mutex<VideoDbManager>>,
     pub data_source_dirs: Arc<Vec<DiskMapping>>,
+    pub config: Arc<AppConfig>,
+    pub watcher: Arc<FileWatcher>,
+    pub rebuild: Arc<RebuildTracker>,
+    /// 最近一次同步的结果
+    pub last_sync: Arc<Mutex<Option<SyncReport>>>,
+    /// 实时转码的并发许可，数量为 [`config::FfmpegBinaryConfig::max_transcodes`]
+    pub transcodes: Arc<Semaphore>,
 }
 
 #[tokio::main]
 async fn main() {
-    init_logger(); // 初始化日志
-                   // 获取项目根目录的绝对路径
+    // 获取项目根目录的绝对路径
     let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     let env_path = manifest_dir.join(".env");
 
     // 打印路径以便调试
     println!("尝试加载 .env 文件，路径: {:?}", env_path);
-    // 初始化后台任务队列（最大4个并发任务）
-    init_task_queue(4);
     match dotenvy::from_path(&env_path) {
         Ok(_) => println!(".env 文件加载成功"),
         Err(e) => println!(".env 文件加载失败: {}", e),
     }
-    // G:/videos/app/server/public;
-    // 从环境变量获取数据源目录，支持多个目录（用分号分隔）
-    let data_source_dirs_str =
-        std::env::var("DATA_SOURCE_DIRS").unwrap_or_else(|_| "F:/public".to_string());
-    info!("DATA_SOURCE_DIRS: {}", data_source_dirs_str);
-    // 2. 解析为 Vec<String> (纯物理路径列表)
-    let physical_dirs: Vec<String> = data_source_dirs_str
-        .split(';')
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    if physical_dirs.is_empty() {
-        panic!("至少需要配置一个数据源目录");
+    let config = AppConfig::from_env();
+    config::init_config(config.clone());
+    // 初始化日志（格式由 LOG_FORMAT 决定，需要先加载配置）
+    init_logger(config.logging.format);
+    // 初始化后台任务队列（并发数由 TASK_CONCURRENCY 决定）
+    init_task_queue(config.tasks.max_concurrent);
+    if let Err(e) = services::VideoSort::parse(
+        Some(&config.listing.default_sort_by),
+        Some(&config.listing.default_sort_order),
+        &services::VideoSort::default(),
+    ) {
+        log::warn!("默认排序配置无效，使用 created_at desc: {}", e);
+    }
+    // 数据源映射（DATA_SOURCE_DIRS），配置有误时直接退出
+    let disk_mappings = config.data_sources.mappings.clone();
+    for mapping in &disk_mappings {
+        info!(
+            "数据源: {} -> {}",
+            mapping.route_path, mapping.physical_path
+        );
     }
-    let disk_mappings: Vec<DiskMapping> = physical_dirs
+    if let Err(e) = utils::validate_mappings(&disk_mappings) {
+        log::error!("数据源配置无效: {}", e);
+        std::process::exit(1);
+    }
+    let physical_dirs: Vec<String> = disk_mappings
         .iter()
-        .enumerate()
-        .map(|(index, dir)| DiskMapping {
-            route_path: format!("/public/disk{}", index + 1),
-            physical_path: dir.clone(),
-        })
+        .map(|mapping| mapping.physical_path.clone())
         .collect();
     // 初始化缩略图目录
     services::initialize_thumbnails_with_source(&physical_dirs);
     info!("212212121");
     // 初始化数据库
-    let db_manager = VideoDbManager::new("videos.db").expect("Failed to initialize database");
+    let db_manager =
+        VideoDbManager::new(&config.database.path).expect("Failed to initialize database");
+    if let Err(e) = db_manager.set_busy_timeout(std::time::Duration::from_millis(
+        config.database.busy_timeout_ms,
+    )) {
+        log::warn!("设置数据库 busy_timeout 失败: {}", e);
+    }
+
+    // 按配置的显示时区重新格式化创建时间
+    match services::VideoDao::new(&db_manager).reformat_created_at(&config.display_timezone) {
+        Ok(0) => {}
+        Ok(count) => info!("已按显示时区更新 {} 条记录的创建时间", count),
+        Err(e) => log::warn!("更新创建时间失败: {}", e),
+    }
+
+    // 为旧记录补上用于排序的时长（秒）和大小（字节）
+    match services::VideoDao::new(&db_manager).backfill_numeric_columns() {
+        Ok(0) => {}
+        Ok(count) => info!("已为 {} 条记录补上时长和大小的数值", count),
+        Err(e) => log::warn!("补充时长和大小的数值失败: {}", e),
+    }
+    match services::VideoDao::new(&db_manager).backfill_name_search() {
+        Ok(0) => {}
+        Ok(count) => info!("已为 {} 条记录补上搜索用的名称", count),
+        Err(e) => log::warn!("补充搜索用的名称失败: {}", e),
+    }
 
     // 从指定目录中初始化数据库
     let sync = services::DirectorySync::new(&db_manager);
-    if let Err(e) = sync.initialize_from_directory_with_progress(&disk_mappings, false) {
-        println!("警告：无法从数据源目录初始化数据库: {}", e);
-    } else {
-        println!("数据库初始化成功");
-    }
+    let initial_sync =
+        match sync.initialize_from_directory_with_progress(&disk_mappings, false, false) {
+            Ok(report) => {
+                println!("数据库初始化成功");
+                Some(report)
+            }
+            Err(e) => {
+                println!("警告：无法从数据源目录初始化数据库: {}", e);
+                None
+            }
+        };
 
     // 创建共享状态
     let db_manager_arc = Arc::new(Mutex::new(db_manager));
@@ -88,35 +139,211 @@ async fn main() {
     let app_state = Arc::new(AppState {
         db_manager: db_manager_arc,
         data_source_dirs: Arc::new(disk_mappings),
+        transcodes: Arc::new(Semaphore::new(config.ffmpeg.max_transcodes)),
+        config: Arc::new(config),
+        watcher: Arc::new(FileWatcher::new()),
+        rebuild: Arc::new(RebuildTracker::new()),
+        last_sync: Arc::new(Mutex::new(initial_sync)),
     });
+
+    // 根据配置自动启动文件监听
+    start_watcher_if_enabled(
+        &app_state.watcher,
+        &app_state.config.watcher,
+        Arc::clone(&app_state.data_source_dirs),
+        Arc::clone(&app_state.db_manager),
+        Arc::clone(&app_state.last_sync),
+    );
     // 创建 CORS 中间件 - 允许所有来源
     let cors = CorsLayer::new()
         .allow_origin(HeaderValue::from_static("*"))
         .allow_methods(vec![
             axum::http::Method::GET,
             axum::http::Method::POST,
+            axum::http::Method::PUT,
             axum::http::Method::DELETE,
             axum::http::Method::OPTIONS,
         ])
         .allow_headers(vec![HeaderName::from_static("*")]);
 
+    // 高开销接口的限流中间件，每个路由单独计数
+    let per_minute = app_state.config.rate_limit.per_minute;
+    let api_keys = app_state.config.api_keys.clone();
+    let limited = || {
+        middleware::from_fn_with_state(
+            routes::RateLimiter::per_minute(per_minute, api_keys.clone()),
+            routes::rate_limit,
+        )
+    };
+
     // 创建路由，添加静态文件服务和 CORS
     let mut app = Router::new()
         .route("/", get(|| async { "Hello, World!" }))
+        // 服务端和 API 版本
+        .route("/api/version", get(routes::get_version))
+        // 支持的媒体扩展名和 ffmpeg 相关功能
+        .route("/api/capabilities", get(routes::get_capabilities))
         // 列出所有视频文件和目录
         .route("/api/videos", get(routes::list_videos))
         // 列出所有视频文件和目录 - 支持分页
         .route("/api/videos/paginated", get(routes::list_videos_paginated))
+        // 目录下（递归）所有条目的平铺分页列表
+        .route("/api/videos/flat", get(routes::list_videos_flat))
+        // 单个目录的树形结构（只查询该目录下的条目）
+        .route("/api/tree", get(routes::get_video_subtree))
+        // 子孙条目中包含指定类型的目录
+        .route("/api/directories/with", get(routes::list_directories_with))
+        // 最近通过 API 删除的条目
+        .route("/api/deletions", get(routes::get_deletions))
+        // 以 NDJSON 流式输出所有记录
+        .route("/api/videos/stream", get(routes::stream_videos))
+        // 随机获取视频
+        .route("/api/videos/random", get(routes::get_random_videos))
+        // 排行榜：时长最长、文件最大或看完次数最多
+        .route("/api/videos/top", get(routes::get_top_videos))
+        // 时长/大小分布直方图
+        .route("/api/analytics/histogram", get(routes::get_histogram))
+        // 元数据（时长、宽高、缩略图）不完整的视频
+        .route("/api/videos/incomplete", get(routes::get_incomplete_videos))
+        // 单个文件的详细信息（可内联缩略图）
+        .route("/api/videos/detail", get(routes::get_video_detail))
+        // 同一目录下的上一个/下一个视频
+        .route("/api/videos/siblings", get(routes::get_video_siblings))
+        // 获取视频章节
+        .route("/api/videos/chapters", get(routes::get_video_chapters))
+        // 批量标记已看完/未看完
+        .route("/api/videos/watched", post(routes::set_videos_watched))
+        // 设置目录内条目的自定义顺序（sort_by=custom）
+        .route("/api/videos/reorder", post(routes::reorder_videos))
+        // 保存播放进度（超过 90% 自动标记已看完）
+        .route("/api/videos/progress", post(routes::save_watch_progress))
+        // 开始播放时记录一次播放
+        .route("/api/videos/:id/play", post(routes::record_video_play))
+        // 按修改时间排列整个媒体库时的上一个/下一个视频
+        .route(
+            "/api/videos/:id/timeline-neighbors",
+            get(routes::get_timeline_neighbors),
+        )
+        // 收藏/取消收藏，以及收藏列表
+        .route("/api/videos/favorite", post(routes::set_video_favorite))
+        .route("/api/favorites", get(routes::get_favorites))
+        // 标签：查看/修改条目的标签，列出所有标签
+        .route(
+            "/api/videos/:id/tags",
+            get(routes::get_video_tags).post(routes::update_video_tags),
+        )
+        .route("/api/tags", get(routes::list_tags))
+        // 文件移动后重新关联观看状态，以及自动匹配的建议
+        .route("/api/videos/relink", post(routes::relink_video))
+        .route(
+            "/api/videos/relink/suggestions",
+            get(routes::get_relink_suggestions),
+        )
         // 删除视频文件（从数据库和物理文件系统中删除）
         .route("/api/videos/delete", delete(routes::delete_video))
+        // 上传文件到数据源目录（multipart/form-data）
+        .route(
+            "/api/upload",
+            post(routes::upload_video).layer(DefaultBodyLimit::max(
+                usize::try_from(app_state.config.upload.max_bytes).unwrap_or(usize::MAX),
+            )),
+        )
+        // 分片上传：创建会话、查询进度、上传分片、完成上传
+        .route("/api/upload/init", post(routes::init_upload))
+        .route("/api/upload/:id", get(routes::get_upload))
+        .route(
+            "/api/upload/:id/chunk",
+            put(routes::upload_chunk).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/api/upload/:id/complete", post(routes::complete_upload))
+        // 从数据源根目录到指定路径的面包屑
+        .route("/api/breadcrumb", get(routes::get_breadcrumb))
+        // 将目录导出为播放列表（M3U 或 JSON）
+        .route("/api/playlist", get(routes::get_playlist))
+        // 以 JSON 返回 HLS 播放列表的切片（调试用）
+        .route("/api/hls/segments", get(routes::get_hls_segments))
+        // 以正确的 Content-Type 输出媒体文件
+        .route("/api/stream", get(routes::stream_media))
+        // 以附件形式下载媒体文件
+        .route("/api/download", get(routes::download_media))
         // 手动同步数据库
-        .route("/api/sync", get(routes::sync_videos))
+        .route(
+            "/api/sync",
+            get(routes::sync_videos).post(routes::sync_videos),
+        )
+        // 最近一次同步的结果
+        .route("/api/sync/status", get(routes::get_sync_status))
         // 任务队列状态端点
         .route("/api/tasks/status", get(routes::get_task_queue_status))
-        // 静态文件服务，thumbnails 目录下的文件可以通过 /thumbnails/... 访问
-        .nest_service("/thumbnails", ServeDir::new("thumbnails"));
+        .route("/api/tasks/running", get(routes::get_running_tasks))
+        .route("/api/tasks/pause", post(routes::pause_task_queue))
+        .route("/api/tasks/resume", post(routes::resume_task_queue))
+        // 文件监听控制端点
+        .route("/api/watcher/start", post(routes::start_watcher))
+        .route("/api/watcher/stop", post(routes::stop_watcher))
+        .route("/api/watcher/status", get(routes::get_watcher_status))
+        // 管理端点（需要 API Key）
+        .route("/api/config", get(routes::get_config_handler))
+        .route("/api/admin/rebuild", post(routes::rebuild_database_handler))
+        .route("/api/admin/rebuild/status", get(routes::get_rebuild_status))
+        .route(
+            "/api/admin/maintenance",
+            post(routes::run_maintenance_handler),
+        )
+        // 以 SSE 实时输出服务器日志
+        .route("/api/logs/stream", get(routes::stream_logs))
+        // 后台解码检查（/api/validate）发现的损坏文件
+        .route("/api/validate/report", get(routes::get_validation_report))
+        // 清除目录下的缩略图，下次请求或同步时重新生成
+        .route("/api/thumbnails/clear", post(routes::clear_thumbnails))
+        // 缩略图服务，thumbnails 目录下的文件可以通过 /thumbnails/...?size= 访问
+        .route("/thumbnails/*file", get(routes::serve_thumbnail));
     // .layer(&cors);
 
+    // 高开销接口统一加上限流，启动日志中的列表也由此生成
+    let limited_routes: Vec<(&str, MethodRouter<Arc<AppState>>)> = vec![
+        // 为元数据不完整的视频提交重新读取元数据和生成缩略图的后台任务
+        (
+            "/api/videos/reprocess-incomplete",
+            post(routes::reprocess_incomplete_videos),
+        ),
+        // 计算文件的校验和（sha256 或 crc32），按路径和修改时间缓存
+        ("/api/videos/checksum", get(routes::get_video_checksum)),
+        // ffprobe 的原始输出（需要 API Key）
+        ("/api/videos/probe", get(routes::probe_video)),
+        // 实时截取指定时间点的画面（JPEG）
+        ("/api/videos/frame", get(routes::get_video_frame)),
+        // 故事板雪碧图及每一帧的位置
+        (
+            "/api/videos/storyboard.json",
+            get(routes::get_video_storyboard),
+        ),
+        // 从指定时间点重新截取缩略图
+        (
+            "/api/videos/thumbnail/set",
+            post(routes::set_video_thumbnail),
+        ),
+        // 重新读取单个文件的元数据
+        ("/api/videos/refresh", post(routes::refresh_video)),
+        // 将 HLS 目录合并为 MP4
+        ("/api/videos/merge", post(routes::merge_hls_directory)),
+        // 将 MP4 切片为 HLS（后台执行）
+        ("/api/videos/package-hls", post(routes::package_hls_video)),
+        // 实时转码为 H.264 分片 MP4（每个请求一个 ffmpeg 进程，CPU 开销大）
+        ("/api/stream/transcode", get(routes::transcode_media)),
+        // 文件系统与数据库的一致性检查（只读）
+        ("/api/audit", get(routes::audit_videos)),
+        // 校验媒体库：后台逐个解码检查视频，报告损坏的文件
+        ("/api/validate", post(routes::start_validation)),
+        // 校验缩略图并重新生成损坏的缩略图
+        ("/api/thumbnails/verify", post(routes::verify_thumbnails)),
+    ];
+    let limited_paths: Vec<&str> = limited_routes.iter().map(|(path, _)| *path).collect();
+    for (path, route) in limited_routes {
+        app = app.route(path, route.layer(limited()));
+    }
+
+    let app_state_watcher_running = app_state.watcher.status().running;
     let state_clone = app_state.clone();
     for mapping in state_clone.data_source_dirs.iter() {
         app = app.nest_service(&mapping.route_path, ServeDir::new(&mapping.physical_path));
@@ -126,23 +353,113 @@ async fn main() {
         );
     }
 
-    let app = app.with_state(app_state).layer(cors);
+    let app = app
+        .with_state(app_state)
+        .layer(middleware::from_fn(routes::override_media_content_type))
+        .layer(middleware::from_fn(routes::add_api_version_header))
+        .layer(cors);
     let addr = SocketAddr::from(([0, 0, 0, 0], 3003));
     info!("listening on {}", addr);
     info!("CORS enabled - allowing all origins");
     info!("Thumbnails directory initialized");
     info!("Database initialized");
-    info!("Background task queue initialized (max 4 concurrent)");
+    info!(
+        "Background task queue initialized (max {} concurrent)",
+        state_clone.config.tasks.max_concurrent
+    );
     info!("");
     info!("Available API endpoints:");
+    info!("  GET  /api/version             - Get server and API version");
+    info!("  GET  /api/capabilities        - Supported media extensions and ffmpeg features");
     info!("  GET  /api/videos              - List all videos");
-    info!("  GET  /api/videos/paginated    - List all videos with pagination");
+    info!("  GET  /api/videos/paginated    - List all videos with pagination (?page= or ?cursor=)");
+    info!(
+        "  GET  /api/videos/flat?path=   - Flat paginated listing of everything under a directory"
+    );
+    info!(
+        "  GET  /api/tree?path=         - Tree of one directory, built from its descendants only"
+    );
+    info!("  GET  /api/directories/with?type= - Directories containing entries of a type");
+    info!("  GET  /api/deletions?limit=N   - Recently deleted items");
+    info!("  GET  /api/videos/stream       - Stream all videos as NDJSON");
+    info!("  GET  /api/videos/random       - Get N random videos");
+    info!(
+        "  GET  /api/videos/top?by=duration|size|watched - Longest, largest or most-played videos"
+    );
+    info!("  GET  /api/analytics/histogram?metric=duration|size&buckets=N - Duration/size distribution");
+    info!("  GET  /api/videos/detail       - Get one video (?inline_thumbnail=true for data URI, ?refresh=true indexes unsynced files)");
+    info!("  GET  /api/videos/siblings     - Get previous/next video in directory");
+    info!("  GET  /api/videos/checksum     - File checksum (algo=sha256|crc32), cached by mtime");
+    info!("  GET  /api/videos/chapters     - Get video chapters");
+    info!("  GET  /api/videos/probe        - Raw ffprobe output (requires API key)");
+    info!("  GET  /api/videos/frame?t=N    - JPEG frame at the given timestamp");
+    info!("  GET  /api/videos/storyboard.json - Storyboard sprite URL and frame positions");
+    info!("  POST /api/videos/thumbnail/set - Regenerate thumbnail from a chosen frame");
+    info!("  POST /api/videos/refresh      - Re-probe a single file and update its record");
+    info!("  POST /api/videos/watched      - Mark videos watched/unwatched in bulk");
+    info!("  POST /api/videos/reorder      - Set a directory's custom order (sort_by=custom)");
+    info!("  POST /api/videos/progress     - Save playback position (auto-marks watched at 90%)");
+    info!("  POST /api/videos/:id/play     - Count a playback start (watch_count)");
+    info!("  GET  /api/videos/:id/timeline-neighbors - Previous/next video by modification time");
+    info!("  POST /api/videos/favorite     - Star or unstar a video");
+    info!("  GET  /api/favorites           - List starred videos");
+    info!("  GET  /api/videos/:id/tags     - Get a video's tags");
+    info!("  POST /api/videos/:id/tags     - Add/remove tags (created on first use)");
+    info!("  GET  /api/tags                - List tags with usage counts");
+    info!("  POST /api/videos/relink       - Move watch state from an old path to a new one");
+    info!("  GET  /api/videos/relink/suggestions - Moved files that may need relinking");
+    info!("  POST /api/videos/merge        - Merge HLS directory into MP4 (requires API key)");
+    info!("  POST /api/videos/package-hls  - Package MP4 as HLS (background, requires API key)");
     info!("  DELETE /api/videos/delete     - Delete video file (database + physical file)");
+    info!("  POST /api/upload              - Upload a file into a data source directory (requires API key)");
+    info!("  POST /api/upload/init         - Start a chunked upload, returns an upload id (requires API key)");
+    info!("  GET  /api/upload/:id          - Get received chunks of a chunked upload (requires API key)");
+    info!("  PUT  /api/upload/:id/chunk?index=N - Upload one chunk (any order) (requires API key)");
+    info!("  POST /api/upload/:id/complete - Assemble chunks and register the file (requires API key)");
+    info!("  GET  /api/breadcrumb          - Ancestor directories from the data source root");
+    info!("  GET  /api/playlist            - Export a directory as M3U or JSON playlist");
+    info!("  GET  /api/hls/segments        - List an HLS playlist's segments as JSON");
+    info!("  GET  /api/stream              - Stream media file with correct Content-Type");
+    info!("  GET  /api/stream/transcode?codec=h264 - Live transcode to fragmented MP4 (CPU heavy, limited by MAX_TRANSCODES)");
+    info!("  GET  /api/download            - Download media file as attachment");
+    info!("  GET  /thumbnails/*?size=N     - Thumbnail, nearest generated size");
+    info!("  POST /api/thumbnails/verify   - Verify thumbnails and regenerate broken ones");
+    info!("  POST /api/thumbnails/clear?path= - Clear thumbnails under a path so they regenerate (requires API key)");
+    info!("  POST /api/validate?limit=N    - Decode-check up to N videos in the background");
+    info!("  GET  /api/validate/report     - Files that failed the decode check");
     info!("  GET  /api/sync                - Manual database sync");
+    info!("  POST /api/sync?dry_run=true   - Report planned sync changes without writing");
+    info!("  GET  /api/sync/status         - Get last sync result");
+    info!("  GET  /api/audit               - Compare filesystem and database without modifying either");
     info!("  GET  /api/tasks/status        - Get task queue status");
+    info!("  GET  /api/tasks/running       - List running tasks");
+    info!("  POST /api/tasks/pause         - Pause the task queue (running tasks finish)");
+    info!("  POST /api/tasks/resume        - Resume the task queue");
+    info!("  POST /api/watcher/start       - Start file watcher");
+    info!("  POST /api/watcher/stop        - Stop file watcher");
+    info!("  GET  /api/watcher/status      - Get file watcher status");
+    info!("  GET  /api/config              - Effective server config, secrets redacted (auth)");
+    info!("  POST /api/admin/rebuild       - Rebuild database from scratch (auth)");
+    info!("  GET  /api/admin/rebuild/status - Get database rebuild status (auth)");
+    info!("  POST /api/admin/maintenance   - Checkpoint WAL and VACUUM database (auth)");
+    info!("  GET  /api/logs/stream         - Stream server logs live over SSE (auth)");
+    info!(
+        "Rate limit: {} requests/min per client on {}",
+        per_minute,
+        limited_paths.join(", ")
+    );
     info!("");
-    info!("File watcher is NOT running by default. Use /api/watcher/start to enable auto-sync.");
+    if app_state_watcher_running {
+        info!("File watcher is running (WATCHER_ENABLED=true).");
+    } else {
+        info!("File watcher is NOT running. Use /api/watcher/start or set WATCHER_ENABLED=true to enable auto-sync.");
+    }
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
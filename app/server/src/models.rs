@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VideoInfo {
     pub id: i64,
     pub name: String,
@@ -30,11 +30,222 @@ pub struct VideoInfo {
     pub height: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_path: Option<String>,
+    /// 所属数据源的路由路径，例如 "/public/disk1"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// 文件的访问地址（路由路径形式），例如 "/public/disk1/show/a.mp4"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 目录下（递归）可播放视频的数量，只有目录有该字段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub child_count: Option<u64>,
+    /// 目录缩略图的来源："poster"（目录封面文件）或 "first_child"（第一个视频）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_source: Option<String>,
+    /// 是否已看完
+    #[serde(default)]
+    pub watched: bool,
+    /// 上次播放到的位置（秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch_position: Option<f64>,
+    /// 是否已收藏
+    #[serde(default)]
+    pub favorite: bool,
+    /// 播放次数
+    #[serde(default)]
+    pub watch_count: u32,
+    /// 目录下（递归）所有视频的总时长，只在 `?include_totals=true` 时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_duration: Option<String>,
+    /// 目录下（递归）所有视频的总大小，只在 `?include_totals=true` 时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<String>,
+    /// 严格元数据模式下无法读取元数据的原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 轻量级视频信息，只包含列表展示需要的字段（用于 `?fields=light`）
+#[derive(Serialize, Debug, Clone)]
+pub struct VideoInfoLight {
+    pub id: i64,
+    pub name: String,
+    pub path: String,
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl From<VideoInfo> for VideoInfoLight {
+    fn from(video: VideoInfo) -> Self {
+        Self {
+            id: video.id,
+            name: video.name,
+            path: video.path,
+            r#type: video.r#type,
+            thumbnail: video.thumbnail,
+            duration: video.duration,
+            url: video.url,
+        }
+    }
+}
+
+/// 列表接口返回的字段集合
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldSet {
+    /// 完整字段（默认）
+    #[default]
+    Full,
+    /// 轻量字段：id, name, path, type, thumbnail, duration
+    Light,
+}
+
+/// 故事板（雪碧图）中的一帧
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StoryboardFrame {
+    /// 时间点（秒）
+    pub time: f64,
+    /// 在雪碧图中的位置和尺寸（像素）
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// 故事板：雪碧图地址和每一帧的位置
+#[derive(Serialize, Debug)]
+pub struct Storyboard {
+    pub sprite_url: String,
+    pub columns: u32,
+    pub rows: u32,
+    /// 相邻两帧的间隔（秒）
+    pub interval: u32,
+    pub frames: Vec<StoryboardFrame>,
+}
+
+/// 面包屑导航中的一级
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BreadcrumbEntry {
+    pub name: String,
+    /// 路由路径，例如 "/public/disk1/show"
+    pub path: String,
+}
+
+/// 观看状态（已看完标记、播放进度、收藏和标签）
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WatchState {
+    pub watched: bool,
+    /// 上次播放到的位置（秒）
+    pub watch_position: Option<f64>,
+    pub favorite: bool,
+    /// 标签名称，按名称排序
+    pub tags: Vec<String>,
+}
+
+impl WatchState {
+    /// 是否有需要保留的状态
+    pub fn is_empty(&self) -> bool {
+        !self.watched && self.watch_position.is_none() && !self.favorite && self.tags.is_empty()
+    }
+}
+
+/// 删除记录：通过 API 删除的文件或目录
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DeletionLogEntry {
+    /// 被删除的条目（物理路径）
+    pub path: String,
+    pub name: String,
+    /// 删除时间，按显示时区格式化
+    pub deleted_at: String,
+}
+
+/// 建议的重新关联：已删除文件的观看状态可能属于大小和时长都相同的新文件
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RelinkSuggestion {
+    /// 已不存在的文件（物理路径）
+    pub old_path: String,
+    /// 可能是移动后的同一文件（物理路径）
+    pub new_path: String,
+    pub name: String,
+}
+
+/// 标签及使用该标签的条目数量
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagInfo {
+    pub name: String,
+    pub count: u64,
+}
+
+/// 直方图中的一段
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistogramBucket {
+    /// 分段范围 "下界-上界"，时长为秒，大小为字节
+    pub range: String,
+    pub count: u64,
 }
 
-#[derive(Serialize)]
-pub struct VideoList {
-    pub videos: Vec<VideoInfo>,
+/// 上一个/下一个视频（同一目录内，或按修改时间跨整个媒体库）
+#[derive(Serialize, Debug)]
+pub struct VideoSiblings {
+    pub prev: Option<VideoInfo>,
+    pub next: Option<VideoInfo>,
+}
+
+/// 通过路径指定单个文件的查询参数
+#[derive(Deserialize, Debug)]
+pub struct PathParams {
+    /// 路由路径或物理路径
+    pub path: String,
+}
+
+/// 上一个/下一个视频的查询参数
+#[derive(Deserialize, Debug)]
+pub struct SiblingsParams {
+    /// 视频的路由路径或物理路径
+    pub path: String,
+    /// 排序字段（可选），默认使用列表接口的默认排序，应与界面列表使用的排序一致
+    pub sort_by: Option<String>,
+    /// 排序方向（可选）
+    pub sort_order: Option<String>,
+}
+
+/// 列表查询参数
+#[derive(Deserialize, Debug, Default)]
+pub struct ListParams {
+    /// 返回字段集合，默认为 full
+    #[serde(default)]
+    pub fields: FieldSet,
+
+    /// 只返回指定类型（可选），例如 mp4、image、subtitle、directory
+    #[serde(rename = "type")]
+    pub file_type: Option<String>,
+
+    /// 只返回已看完（true）或未看完（false）的条目（可选）
+    pub watched: Option<bool>,
+
+    /// 只返回已收藏（true）或未收藏（false）的条目（可选）
+    pub favorite: Option<bool>,
+
+    /// 只返回带有该标签的条目（可选，不区分大小写）
+    pub tag: Option<String>,
+
+    /// 目录是否附带 total_duration / total_size（开销较大，默认不计算）
+    #[serde(default)]
+    pub include_totals: bool,
+
+    /// 是否包含无法播放的 unknown 类型，默认排除
+    #[serde(default)]
+    pub include_unknown: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VideoList<T = VideoInfo> {
+    pub videos: Vec<T>,
 }
 
 /// 分页查询参数
@@ -51,11 +262,60 @@ pub struct PaginationParams {
     /// 搜索关键词（可选）
     pub search: Option<String>,
 
+    /// 只返回此日期之后修改的文件（可选，ISO 日期，包含）
+    pub created_after: Option<String>,
+
+    /// 只返回此日期之前修改的文件（可选，ISO 日期，包含）
+    pub created_before: Option<String>,
+
+    /// 只返回指定类型（可选），例如 mp4、image、subtitle、directory
+    #[serde(rename = "type")]
+    pub file_type: Option<String>,
+
+    /// 只返回已看完（true）或未看完（false）的条目（可选）
+    pub watched: Option<bool>,
+
+    /// 只返回已收藏（true）或未收藏（false）的条目（可选）
+    pub favorite: Option<bool>,
+
+    /// 只返回带有该标签的条目（可选，不区分大小写）
+    pub tag: Option<String>,
+
+    /// 上一页返回的 next_cursor（可选），指定时忽略 page，从游标位置继续
+    pub cursor: Option<String>,
+
     /// 排序字段（可选），默认按创建时间排序
     pub sort_by: Option<String>,
 
     /// 排序方向（可选），默认为desc
     pub sort_order: Option<String>,
+
+    /// 返回字段集合，默认为 full
+    #[serde(default)]
+    pub fields: FieldSet,
+
+    /// 目录是否附带 total_duration / total_size（开销较大，默认不计算）
+    #[serde(default)]
+    pub include_totals: bool,
+
+    /// 是否包含无法播放的 unknown 类型，默认排除
+    #[serde(default)]
+    pub include_unknown: bool,
+}
+
+/// 平铺列表查询参数
+#[derive(Deserialize, Debug)]
+pub struct FlatListParams {
+    /// 目录的路由路径或物理路径
+    pub path: String,
+
+    /// 页码，从1开始，默认为1
+    #[serde(default = "default_page")]
+    pub page: u32,
+
+    /// 每页数量，默认为20
+    #[serde(default = "default_page_size")]
+    pub page_size: u32,
 }
 
 fn default_page() -> u32 {
@@ -67,14 +327,42 @@ fn default_page_size() -> u32 {
 }
 
 /// 分页响应结构
-#[derive(Serialize)]
-pub struct PaginatedVideoList {
-    pub videos: Vec<VideoInfo>,
+#[derive(Serialize, Debug)]
+pub struct PaginatedVideoList<T = VideoInfo> {
+    pub videos: Vec<T>,
     pub pagination: PaginationInfo,
 }
 
+impl<T> PaginatedVideoList<T> {
+    /// 空的分页结果
+    pub fn empty(page: u32, page_size: u32) -> Self {
+        Self {
+            videos: Vec::new(),
+            pagination: PaginationInfo {
+                page,
+                page_size,
+                total: 0,
+                total_pages: 0,
+                has_next: false,
+                has_prev: page > 1,
+                next_cursor: None,
+            },
+        }
+    }
+}
+
+impl PaginatedVideoList {
+    /// 转换为轻量字段的分页结果
+    pub fn into_light(self) -> PaginatedVideoList<VideoInfoLight> {
+        PaginatedVideoList {
+            videos: self.videos.into_iter().map(VideoInfoLight::from).collect(),
+            pagination: self.pagination,
+        }
+    }
+}
+
 /// 分页信息
-#[derive(Serialize)]
+#[derive(Serialize, Debug)]
 pub struct PaginationInfo {
     pub page: u32,
     pub page_size: u32,
@@ -82,4 +370,7 @@ pub struct PaginationInfo {
     pub total_pages: u32,
     pub has_next: bool,
     pub has_prev: bool,
+    /// 下一页的游标（`?cursor=`），没有下一页或列表不支持游标时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
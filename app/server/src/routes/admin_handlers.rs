@@ -0,0 +1,348 @@
+//! 管理相关的 API 处理器
+//!
+//! 所有接口都需要 API Key 鉴权
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use futures_util::{Stream, StreamExt};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::routes::auth::require_api_key;
+use crate::routes::error::db_error;
+use crate::services::rebuild::{rebuild_database, RebuildState};
+use crate::utils::{get_log_buffer, LogLine};
+use crate::AppState;
+
+/// 重建数据库需要的确认口令，防止误操作
+pub const REBUILD_CONFIRMATION: &str = "REBUILD";
+
+/// 重建数据库请求
+#[derive(Deserialize)]
+pub struct RebuildRequest {
+    /// 必须为 "REBUILD"
+    pub confirm: String,
+}
+
+/// 重建任务状态响应
+#[derive(Serialize)]
+pub struct RebuildStatusResponse {
+    pub job_id: u64,
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+/// 从零重建数据库（后台执行）
+pub async fn rebuild_database_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<RebuildRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+
+    if request.confirm != REBUILD_CONFIRMATION {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Confirmation token must be \"{}\"", REBUILD_CONFIRMATION),
+        )
+            .into_response());
+    }
+
+    let job_id = state
+        .rebuild
+        .try_start()
+        .ok_or_else(|| (StatusCode::CONFLICT, "A rebuild is already running").into_response())?;
+
+    let task_state = Arc::clone(&state);
+    tokio::task::spawn_blocking(move || {
+        let result = {
+            let db_manager = task_state.db_manager.lock().unwrap();
+            rebuild_database(&db_manager, &task_state.data_source_dirs)
+        };
+        let result = match result {
+            Ok(report) => {
+                *task_state.last_sync.lock().unwrap() = Some(report);
+                Ok(())
+            }
+            Err(e) => {
+                error!("重建任务 #{} 失败: {}", job_id, e);
+                Err(e.to_string())
+            }
+        };
+        task_state.rebuild.finish(job_id, result);
+    });
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "数据库重建已开始",
+        "job_id": job_id,
+        "status_url": "/api/admin/rebuild/status"
+    })))
+}
+
+/// 获取重建任务状态
+pub async fn get_rebuild_status(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<RebuildStatusResponse>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+
+    let status = state.rebuild.status();
+    let (state_name, error) = match status.state {
+        RebuildState::Idle => ("idle", None),
+        RebuildState::Running => ("running", None),
+        RebuildState::Completed => ("completed", None),
+        RebuildState::Failed(e) => ("failed", Some(e)),
+    };
+
+    Ok(Json(RebuildStatusResponse {
+        job_id: status.job_id,
+        state: state_name.to_string(),
+        error,
+        started_at: status.started_at,
+        finished_at: status.finished_at,
+    }))
+}
+
+/// 获取服务实际加载的配置，API Key 等密钥已脱敏
+pub async fn get_config_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+    Ok(Json(state.config.sanitized()))
+}
+
+/// 数据库维护结果响应
+#[derive(Debug, Serialize)]
+pub struct MaintenanceResponse {
+    pub size_before: u64,
+    pub size_after: u64,
+    pub freed: u64,
+}
+
+/// 执行数据库维护：回写 WAL 并 VACUUM，返回维护前后的数据库大小
+pub async fn run_maintenance_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<MaintenanceResponse>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+
+    let task_state = Arc::clone(&state);
+    let report = tokio::task::spawn_blocking(move || {
+        let db_manager = task_state.db_manager.lock().unwrap();
+        db_manager.run_maintenance()
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .map_err(|e| {
+        error!("数据库维护失败: {}", e);
+        db_error(e)
+    })?;
+
+    info!(
+        "数据库维护完成: {} -> {} 字节",
+        report.size_before, report.size_after
+    );
+
+    Ok(Json(MaintenanceResponse {
+        size_before: report.size_before,
+        size_after: report.size_after,
+        freed: report.freed(),
+    }))
+}
+
+/// 以 SSE 实时输出服务器日志：先输出缓冲区中最近的日志，之后每条新日志一个 `log` 事件
+pub async fn stream_logs(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+
+    let (history, receiver) = get_log_buffer().subscribe();
+    let live = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(line) => return Some((line, receiver)),
+                // 客户端读取太慢时跳过已被覆盖的日志
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = futures_util::stream::iter(history)
+        .chain(live)
+        .map(|line| Ok(log_event(&line)));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn log_event(line: &LogLine) -> Event {
+    Event::default()
+        .event("log")
+        .json_data(line)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::routes::auth::API_KEY_HEADER;
+    use crate::test_utils::{insert_video, test_state};
+    use crate::DiskMapping;
+    use axum::http::HeaderValue;
+
+    #[tokio::test]
+    async fn test_config_redacts_api_keys() {
+        let mut state = test_state(Vec::new());
+        let mut config = AppConfig {
+            api_keys: vec!["secret".to_string(), "other-secret".to_string()],
+            ..AppConfig::default()
+        };
+        config.thumbnail.sizes = vec![160, 480];
+        config.data_sources.mappings = vec![DiskMapping {
+            route_path: "/public/movies".to_string(),
+            physical_path: "/mnt/movies".to_string(),
+        }];
+        Arc::get_mut(&mut state).unwrap().config = Arc::new(config);
+
+        let response = get_config_handler(State(state.clone()), HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+        let Json(body) = get_config_handler(State(state), headers).await.unwrap();
+        let text = body.to_string();
+        assert!(!text.contains("secret"));
+        assert_eq!(body["api_keys"]["configured"], 2);
+        assert_eq!(
+            body["data_sources"][0]["physical_path"],
+            serde_json::json!("/mnt/movies")
+        );
+        assert_eq!(body["thumbnail"]["sizes"], serde_json::json!([160, 480]));
+        assert_eq!(body["thumbnail"]["mode"], serde_json::json!("eager"));
+        assert_eq!(body["tasks"]["max_concurrent"], 4);
+        assert_eq!(body["database"]["path"], serde_json::json!("videos.db"));
+        assert_eq!(body["display_timezone"], serde_json::json!("+00:00"));
+    }
+
+    #[tokio::test]
+    async fn test_logged_message_appears_on_stream() {
+        use crate::utils::log_buffer::LogBufferAppender;
+        use log4rs::append::Append;
+
+        let mut state = test_state(Vec::new());
+        Arc::get_mut(&mut state).unwrap().config = Arc::new(AppConfig {
+            api_keys: vec!["secret".to_string()],
+            ..AppConfig::default()
+        });
+
+        let response = stream_logs(State(state.clone()), HeaderMap::new())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+        let response = stream_logs(State(state), headers)
+            .await
+            .ok()
+            .unwrap()
+            .into_response();
+        assert_eq!(
+            response.headers()[axum::http::header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        // 订阅之后写入的日志通过实时通道到达
+        LogBufferAppender
+            .append(
+                &log::Record::builder()
+                    .args(format_args!("stream test marker"))
+                    .level(log::Level::Warn)
+                    .target("server::test")
+                    .build(),
+            )
+            .unwrap();
+
+        let mut body = response.into_body().into_data_stream();
+        let mut received = String::new();
+        while !received.contains("stream test marker") {
+            let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+                .await
+                .expect("log line not streamed")
+                .unwrap()
+                .unwrap();
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        let event = received
+            .split("\n\n")
+            .find(|event| event.contains("stream test marker"))
+            .unwrap();
+        assert!(event.starts_with("event: log\n"));
+        let data: serde_json::Value =
+            serde_json::from_str(event.split_once("data: ").unwrap().1).unwrap();
+        assert_eq!(data["level"], "WARN");
+        assert_eq!(data["target"], "server::test");
+        assert_eq!(data["message"], "stream test marker");
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_reports_sizes() {
+        let mut state = test_state(Vec::new());
+        Arc::get_mut(&mut state).unwrap().config = Arc::new(AppConfig {
+            api_keys: vec!["secret".to_string()],
+            ..AppConfig::default()
+        });
+        for i in 0..10 {
+            insert_video(
+                &state.db_manager.lock().unwrap(),
+                &format!("/videos/{}.mp4", i),
+                "mp4",
+                "/videos",
+            );
+        }
+
+        let response = run_maintenance_handler(State(state.clone()), HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+        let Json(report) = run_maintenance_handler(State(state), headers)
+            .await
+            .unwrap();
+        assert!(report.size_before > 0);
+        assert!(report.size_after > 0);
+        assert_eq!(
+            report.freed,
+            report.size_before.saturating_sub(report.size_after)
+        );
+    }
+}
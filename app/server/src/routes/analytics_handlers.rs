@@ -0,0 +1,135 @@
+//! 媒体库统计相关的 API 处理器
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+
+use crate::models::HistogramBucket;
+use crate::routes::error::db_error;
+use crate::services::db::video_dao::MAX_HISTOGRAM_BUCKETS;
+use crate::services::{HistogramMetric, VideoDao};
+use crate::AppState;
+
+/// 直方图查询参数
+#[derive(serde::Deserialize)]
+pub struct HistogramParams {
+    /// 统计指标：duration 或 size
+    pub metric: String,
+    /// 分桶数量，默认为 10，最大为 MAX_HISTOGRAM_BUCKETS
+    #[serde(default = "default_histogram_buckets")]
+    pub buckets: u32,
+}
+
+fn default_histogram_buckets() -> u32 {
+    10
+}
+
+/// 视频时长或大小的分布
+pub async fn get_histogram(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HistogramParams>,
+) -> Result<Json<Vec<HistogramBucket>>, Response> {
+    let metric = HistogramMetric::parse(&params.metric)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    if params.buckets == 0 || params.buckets > MAX_HISTOGRAM_BUCKETS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Buckets must be between 1 and {}", MAX_HISTOGRAM_BUCKETS),
+        )
+            .into_response());
+    }
+
+    let db_manager = state.db_manager.lock().unwrap();
+    VideoDao::new(&db_manager)
+        .get_histogram(metric, params.buckets)
+        .map(Json)
+        .map_err(db_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{insert_video, test_state};
+
+    fn histogram(
+        state: &Arc<AppState>,
+        metric: &str,
+        buckets: u32,
+    ) -> impl std::future::Future<Output = Result<Json<Vec<HistogramBucket>>, Response>> {
+        get_histogram(
+            State(state.clone()),
+            Query(HistogramParams {
+                metric: metric.to_string(),
+                buckets,
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_histogram_bucket_counts() {
+        let state = test_state(Vec::new());
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            for (index, (duration, size)) in [
+                (0.0, 100),
+                (10.0, 100),
+                (20.0, 300),
+                (30.0, 400),
+                (100.0, 500),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let path = format!("/videos/{}.mp4", index);
+                insert_video(&db_manager, &path, "mp4", "/videos");
+                db_manager
+                    .conn
+                    .execute(
+                        "UPDATE videos SET duration_secs = ?1, size_bytes = ?2 WHERE path = ?3",
+                        rusqlite::params![duration, size, path],
+                    )
+                    .unwrap();
+            }
+            // 目录不参与统计
+            insert_video(&db_manager, "/videos/sub", "directory", "/videos");
+        }
+
+        let Json(buckets) = histogram(&state, "duration", 4).await.unwrap();
+        let pairs: Vec<_> = buckets
+            .iter()
+            .map(|b| (b.range.as_str(), b.count))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![("0-25", 3), ("25-50", 1), ("50-75", 0), ("75-100", 1)]
+        );
+
+        let Json(buckets) = histogram(&state, "SIZE", 2).await.unwrap();
+        let pairs: Vec<_> = buckets
+            .iter()
+            .map(|b| (b.range.as_str(), b.count))
+            .collect();
+        assert_eq!(pairs, vec![("100-300", 2), ("300-500", 3)]);
+
+        for (metric, buckets) in [("bitrate", 4), ("duration", 0), ("duration", 101)] {
+            assert_eq!(
+                histogram(&state, metric, buckets)
+                    .await
+                    .unwrap_err()
+                    .status(),
+                StatusCode::BAD_REQUEST
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_histogram_of_empty_library() {
+        let state = test_state(Vec::new());
+        let Json(buckets) = histogram(&state, "size", 5).await.unwrap();
+        assert!(buckets.is_empty());
+    }
+}
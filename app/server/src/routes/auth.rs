@@ -0,0 +1,103 @@
+//! 管理接口鉴权
+
+use axum::http::{header, HeaderMap, StatusCode};
+
+use crate::config::AppConfig;
+
+/// 自定义 API Key 请求头
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// 校验请求携带的 API Key
+///
+/// 支持 `X-API-Key: <key>` 或 `Authorization: Bearer <key>` 两种形式。
+/// 未配置任何 API Key 时管理接口处于禁用状态。
+pub fn require_api_key(
+    headers: &HeaderMap,
+    config: &AppConfig,
+) -> Result<(), (StatusCode, &'static str)> {
+    if config.api_keys.is_empty() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Admin API is disabled (API_KEYS not configured)",
+        ));
+    }
+
+    match provided_api_key(headers) {
+        Some(key) if is_valid_api_key(&config.api_keys, key) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "Invalid or missing API key")),
+    }
+}
+
+/// `key` 是否为配置的 API Key 之一
+///
+/// 逐个比较所有 Key 且比较耗时与内容无关，避免通过响应时间猜测 Key
+pub fn is_valid_api_key(api_keys: &[String], key: &str) -> bool {
+    api_keys.iter().fold(false, |found, k| {
+        constant_time_eq(k.as_bytes(), key.as_bytes()) | found
+    })
+}
+
+/// 长度相同时比较耗时只取决于长度，不会在第一个不同的字节处提前返回
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// 读取请求携带的 API Key（`X-API-Key` 或 `Authorization: Bearer`），不做校验
+pub fn provided_api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        })
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn config() -> AppConfig {
+        AppConfig {
+            api_keys: vec!["secret".to_string()],
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_require_api_key() {
+        let mut headers = HeaderMap::new();
+        assert!(require_api_key(&headers, &config()).is_err());
+
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+        assert!(require_api_key(&headers, &config()).is_ok());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        assert!(require_api_key(&headers, &config()).is_ok());
+
+        // 未配置 API Key 时一律拒绝
+        assert!(require_api_key(&headers, &AppConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_is_valid_api_key() {
+        let keys = vec!["first".to_string(), "secret".to_string()];
+        assert!(is_valid_api_key(&keys, "first"));
+        assert!(is_valid_api_key(&keys, "secret"));
+        assert!(!is_valid_api_key(&keys, "secreT"));
+        assert!(!is_valid_api_key(&keys, "secret2"));
+        assert!(!is_valid_api_key(&keys, ""));
+        assert!(!is_valid_api_key(&[], "secret"));
+    }
+}
@@ -0,0 +1,73 @@
+//! 面包屑导航相关的 API 处理器
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+
+use crate::models::{BreadcrumbEntry, PathParams};
+use crate::utils::{breadcrumb, resolve_media_path};
+use crate::AppState;
+
+/// 获取从数据源根目录到指定路径的各级目录（按层级顺序）
+pub async fn get_breadcrumb(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PathParams>,
+) -> Result<Json<Vec<BreadcrumbEntry>>, Response> {
+    let entries = breadcrumb(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    let exists =
+        resolve_media_path(&params.path, &state.data_source_dirs).is_some_and(|path| path.exists());
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Path not found").into_response());
+    }
+    Ok(Json(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_state;
+    use crate::DiskMapping;
+
+    #[tokio::test]
+    async fn test_breadcrumb_for_nested_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("show/s01/extras")).unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+        let request = |path: &str| {
+            get_breadcrumb(
+                State(state.clone()),
+                Query(PathParams {
+                    path: path.to_string(),
+                }),
+            )
+        };
+
+        let Json(entries) = request("/public/disk1/show/s01/extras").await.unwrap();
+        let body = serde_json::to_value(&entries).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!([
+                { "name": "disk1", "path": "/public/disk1" },
+                { "name": "show", "path": "/public/disk1/show" },
+                { "name": "s01", "path": "/public/disk1/show/s01" },
+                { "name": "extras", "path": "/public/disk1/show/s01/extras" },
+            ])
+        );
+
+        let Json(root) = request("/public/disk1").await.unwrap();
+        assert_eq!(root.len(), 1);
+
+        let missing = request("/public/disk1/nope").await.unwrap_err();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        let outside = request("/public/disk1/../x").await.unwrap_err();
+        assert_eq!(outside.status(), StatusCode::BAD_REQUEST);
+    }
+}
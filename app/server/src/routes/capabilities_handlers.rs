@@ -0,0 +1,100 @@
+//! 服务能力相关的 API 处理器
+
+use axum::Json;
+use serde::Serialize;
+
+use crate::services::ffmpeg::get_ffmpeg_service;
+use crate::utils::media_types;
+
+/// 支持的媒体文件扩展名（小写）
+#[derive(Serialize, Debug)]
+pub struct MediaExtensions {
+    pub video: &'static [&'static str],
+    /// HLS 播放列表
+    pub playlist: &'static [&'static str],
+    /// 独立的 MPEG-TS 视频
+    pub transport_stream: &'static [&'static str],
+    pub image: &'static [&'static str],
+    pub subtitle: &'static [&'static str],
+}
+
+/// 依赖 ffmpeg / ffprobe 的功能是否可用
+#[derive(Serialize, Debug, Default)]
+pub struct MediaFeatures {
+    pub ffmpeg: bool,
+    pub ffprobe: bool,
+    /// 截取画面生成缩略图、`/api/videos/frame`（需要 ffmpeg）
+    pub thumbnails: bool,
+    /// HLS 目录合并为 MP4（需要 ffmpeg）
+    pub merge: bool,
+    /// 读取时长、章节等元数据（需要 ffprobe）
+    pub probe: bool,
+    /// 实时转码 `/api/stream/transcode`（需要 ffmpeg）
+    pub transcode: bool,
+}
+
+/// 服务能力
+#[derive(Serialize, Debug)]
+pub struct Capabilities {
+    pub extensions: MediaExtensions,
+    pub features: MediaFeatures,
+}
+
+/// 获取支持的媒体扩展名和 ffmpeg 相关功能是否可用
+pub async fn get_capabilities() -> Json<Capabilities> {
+    // 首次检查需要执行 ffmpeg/ffprobe，放到阻塞线程中
+    let features = tokio::task::spawn_blocking(|| {
+        let ffmpeg_service = get_ffmpeg_service();
+        let ffmpeg = ffmpeg_service.is_ffmpeg_available();
+        let ffprobe = ffmpeg_service.is_ffprobe_available();
+        MediaFeatures {
+            ffmpeg,
+            ffprobe,
+            thumbnails: ffmpeg,
+            merge: ffmpeg,
+            probe: ffprobe,
+            transcode: ffmpeg,
+        }
+    })
+    .await
+    .unwrap_or_default();
+
+    Json(Capabilities {
+        extensions: MediaExtensions {
+            video: media_types::VIDEO,
+            playlist: media_types::PLAYLIST,
+            transport_stream: media_types::TRANSPORT_STREAM,
+            image: media_types::IMAGE,
+            subtitle: media_types::SUBTITLE,
+        },
+        features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_capabilities_list_supported_extensions() {
+        let Json(capabilities) = get_capabilities().await;
+        let body = serde_json::to_value(&capabilities).unwrap();
+
+        assert_eq!(body["extensions"]["video"], serde_json::json!(["mp4"]));
+        assert_eq!(body["extensions"]["playlist"], serde_json::json!(["m3u8"]));
+        assert_eq!(
+            body["extensions"]["transport_stream"],
+            serde_json::json!(["ts"])
+        );
+        assert_eq!(
+            body["extensions"]["subtitle"],
+            serde_json::json!(["vtt", "srt"])
+        );
+        assert_eq!(
+            body["extensions"]["image"],
+            serde_json::json!(["jpg", "jpeg", "png", "gif", "webp", "bmp"])
+        );
+        assert_eq!(body["features"]["transcode"], body["features"]["ffmpeg"]);
+        assert_eq!(body["features"]["thumbnails"], body["features"]["ffmpeg"]);
+    }
+}
@@ -1,5 +1,50 @@
+pub mod admin_handlers;
+pub mod analytics_handlers;
+pub mod auth;
+pub mod breadcrumb_handlers;
+pub mod capabilities_handlers;
+pub mod error;
+pub mod playlist_handlers;
+pub mod rate_limit;
+pub mod relink_handlers;
+pub mod stream_handlers;
+pub mod tag_handlers;
 pub mod task_handlers;
+pub mod thumbnail_handlers;
+pub mod upload_handlers;
+pub mod validation_handlers;
+pub mod version_handlers;
 pub mod video_handlers;
+pub mod watcher_handlers;
 
-pub use task_handlers::get_task_queue_status;
-pub use video_handlers::{delete_video, list_videos, list_videos_paginated, sync_videos};
+pub use admin_handlers::{
+    get_config_handler, get_rebuild_status, rebuild_database_handler, run_maintenance_handler,
+    stream_logs,
+};
+pub use analytics_handlers::get_histogram;
+pub use breadcrumb_handlers::get_breadcrumb;
+pub use capabilities_handlers::get_capabilities;
+pub use playlist_handlers::{get_hls_segments, get_playlist};
+pub use rate_limit::{rate_limit, RateLimiter};
+pub use relink_handlers::{get_relink_suggestions, relink_video};
+pub use stream_handlers::{
+    download_media, override_media_content_type, stream_media, transcode_media,
+};
+pub use tag_handlers::{get_video_tags, list_tags, update_video_tags};
+pub use task_handlers::{
+    get_running_tasks, get_task_queue_status, pause_task_queue, resume_task_queue,
+};
+pub use thumbnail_handlers::{clear_thumbnails, serve_thumbnail, verify_thumbnails};
+pub use upload_handlers::{complete_upload, get_upload, init_upload, upload_chunk, upload_video};
+pub use validation_handlers::{get_validation_report, start_validation};
+pub use version_handlers::{add_api_version_header, get_version};
+pub use video_handlers::{
+    audit_videos, delete_video, get_deletions, get_favorites, get_incomplete_videos,
+    get_random_videos, get_sync_status, get_timeline_neighbors, get_top_videos, get_video_chapters,
+    get_video_checksum, get_video_detail, get_video_frame, get_video_siblings,
+    get_video_storyboard, get_video_subtree, list_directories_with, list_videos, list_videos_flat,
+    list_videos_paginated, merge_hls_directory, package_hls_video, probe_video, record_video_play,
+    refresh_video, reorder_videos, reprocess_incomplete_videos, save_watch_progress,
+    set_video_favorite, set_video_thumbnail, set_videos_watched, stream_videos, sync_videos,
+};
+pub use watcher_handlers::{get_watcher_status, start_watcher, stop_watcher};
@@ -0,0 +1,438 @@
+//! 播放列表导出相关的 API 处理器
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::models::{PathParams, VideoInfo};
+use crate::routes::error::db_error;
+use crate::services::ffmpeg::find_playlist;
+use crate::services::{VideoDao, VideoSort};
+use crate::utils::media_types::is_playlist;
+use crate::utils::{
+    absolute_url, fill_web_fields, map_physical_to_web, parse_duration, parse_media_segments,
+    resolve_media_path, MediaSegment,
+};
+use crate::{AppState, DiskMapping};
+
+/// 路径段中需要转义的字符（不含 `/`，按段编码）
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// 播放列表格式
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistFormat {
+    /// 扩展 M3U（默认）
+    #[default]
+    M3u,
+    Json,
+}
+
+/// 播放列表查询参数
+#[derive(Deserialize, Debug)]
+pub struct PlaylistParams {
+    /// 目录的路由路径或物理路径
+    pub path: String,
+    #[serde(default)]
+    pub format: PlaylistFormat,
+    /// 排序字段（可选），默认使用列表接口的默认排序
+    pub sort_by: Option<String>,
+    /// 排序方向（可选）
+    pub sort_order: Option<String>,
+}
+
+/// 播放列表中的一项
+#[derive(Serialize, Debug)]
+pub struct PlaylistItem {
+    pub name: String,
+    /// 访问地址，例如 "/public/disk1/show/a.mp4"；配置了 PUBLIC_BASE_URL 时为绝对地址
+    pub url: String,
+    /// 时长（秒），未知时为 None
+    pub duration: Option<f64>,
+}
+
+/// JSON 格式的播放列表
+#[derive(Serialize, Debug)]
+pub struct Playlist {
+    pub name: String,
+    pub items: Vec<PlaylistItem>,
+}
+
+/// 将目录下的视频导出为播放列表（`format=m3u` 或 `format=json`）
+pub async fn get_playlist(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PlaylistParams>,
+) -> Result<Response, Response> {
+    let sort = VideoSort::parse(
+        params.sort_by.as_deref(),
+        params.sort_order.as_deref(),
+        &VideoSort::configured(&state.config.listing),
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+
+    let dir = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    let parent_path = map_physical_to_web(&dir.to_string_lossy(), &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+
+    let mut videos = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_videos_in_directory(
+                &parent_path,
+                &sort,
+                state.config.listing.hide_metadata_errors,
+            )
+            .map_err(db_error)?
+    };
+    if videos.is_empty() && !dir.is_dir() {
+        return Err((StatusCode::NOT_FOUND, "Directory not found").into_response());
+    }
+    fill_web_fields(&mut videos, &state.data_source_dirs);
+
+    let playlist = Playlist {
+        name: dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        items: videos
+            .into_iter()
+            .filter_map(|video| playlist_item(video, state.config.public_base_url.as_deref()))
+            .collect(),
+    };
+
+    match params.format {
+        PlaylistFormat::Json => Ok(Json(playlist).into_response()),
+        PlaylistFormat::M3u => Ok((
+            [(header::CONTENT_TYPE, "audio/x-mpegurl; charset=utf-8")],
+            to_m3u(&playlist),
+        )
+            .into_response()),
+    }
+}
+
+/// HLS 媒体播放列表的切片列表
+#[derive(Serialize, Debug)]
+pub struct HlsSegmentList {
+    /// 播放列表的访问地址
+    pub playlist: String,
+    /// 切片，地址已改写为可以直接访问的 URL
+    pub segments: Vec<MediaSegment>,
+}
+
+/// 以 JSON 返回 HLS 媒体播放列表中的切片（时长、地址、字节范围和加密信息），用于调试
+///
+/// `path` 可以是 m3u8 文件或 HLS 目录（使用目录中的播放列表）
+pub async fn get_hls_segments(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PathParams>,
+) -> Result<Json<HlsSegmentList>, Response> {
+    let path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    let playlist = if path.is_dir() {
+        find_playlist(&path)
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "No m3u8 playlist found").into_response())?
+    } else if is_playlist(&path) {
+        path
+    } else {
+        return Err((StatusCode::BAD_REQUEST, "Not an m3u8 playlist").into_response());
+    };
+
+    let content = tokio::fs::read_to_string(&playlist).await.map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Playlist not readable: {}", e),
+        )
+            .into_response()
+    })?;
+    let mut segments = parse_media_segments(&content).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Master playlist has no segments; request one of its variants",
+        )
+            .into_response()
+    })?;
+
+    let base_url = state.config.public_base_url.as_deref();
+    let dir = playlist.parent().unwrap_or(Path::new(""));
+    let servable = |uri: &str| servable_url(uri, dir, &state.data_source_dirs, base_url);
+    for segment in &mut segments {
+        segment.uri = servable(&segment.uri);
+        if let Some(key_uri) = segment.key.as_mut().and_then(|key| key.uri.as_mut()) {
+            *key_uri = servable(key_uri);
+        }
+    }
+
+    Ok(Json(HlsSegmentList {
+        playlist: servable(&playlist.to_string_lossy()),
+        segments,
+    }))
+}
+
+/// 将播放列表中相对于其所在目录的地址改写为访问地址；带协议的地址和数据源之外的路径保持原样
+fn servable_url(uri: &str, dir: &Path, mappings: &[DiskMapping], base_url: Option<&str>) -> String {
+    if uri.contains("://") {
+        return uri.to_string();
+    }
+    map_physical_to_web(&dir.join(uri).to_string_lossy(), mappings)
+        .map_or_else(|| uri.to_string(), |url| absolute_url(base_url, &url))
+}
+
+fn playlist_item(video: VideoInfo, base_url: Option<&str>) -> Option<PlaylistItem> {
+    Some(PlaylistItem {
+        url: absolute_url(base_url, &video.url?),
+        duration: video.duration.as_deref().and_then(parse_duration),
+        name: video.name,
+    })
+}
+
+/// 生成扩展 M3U 文本，时长未知时按规范写 -1
+fn to_m3u(playlist: &Playlist) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    for item in &playlist.items {
+        let duration = item.duration.map_or(-1, |d| d.round() as i64);
+        // 名称中的换行会破坏 M3U 的行结构
+        let name = item.name.replace(['\r', '\n'], " ");
+        m3u.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            duration,
+            name,
+            encode_url_path(&item.url)
+        ));
+    }
+    m3u
+}
+
+/// 对地址的路径部分逐段做百分号编码，协议和主机保持原样
+///
+/// 播放器按 URL 解析 M3U 中的地址，文件名中的空格、`#`、`?` 等不编码会被截断
+fn encode_url_path(url: &str) -> String {
+    let (origin, path) = match url.find("://") {
+        Some(scheme_end) => {
+            let path_start = url[scheme_end + 3..]
+                .find('/')
+                .map_or(url.len(), |i| scheme_end + 3 + i);
+            url.split_at(path_start)
+        }
+        None => ("", url),
+    };
+    let path: Vec<String> = path
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+        .collect();
+    format!("{}{}", origin, path.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_utils::{insert_video, response_json, test_state, test_state_with_config};
+    use crate::DiskMapping;
+
+    #[tokio::test]
+    async fn test_m3u_lists_directory_videos() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        {
+            // 测试记录的时长为 00:01:00
+            let db_manager = state.db_manager.lock().unwrap();
+            for name in ["ep1.mp4", "ep2.mp4"] {
+                let path = format!("{}/show/{}", root, name);
+                insert_video(&db_manager, &path, "mp4", "/public/disk1/show");
+            }
+            insert_video(
+                &db_manager,
+                &format!("{}/show/ep1.vtt", root),
+                "subtitle",
+                "/public/disk1/show",
+            );
+            insert_video(
+                &db_manager,
+                &format!("{}/other/x.mp4", root),
+                "mp4",
+                "/public/disk1/other",
+            );
+        }
+
+        let playlist = |format: PlaylistFormat| {
+            get_playlist(
+                State(state.clone()),
+                Query(PlaylistParams {
+                    path: "/public/disk1/show".to_string(),
+                    format,
+                    sort_by: Some("name".to_string()),
+                    sort_order: Some("asc".to_string()),
+                }),
+            )
+        };
+
+        let response = playlist(PlaylistFormat::M3u).await.unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "audio/x-mpegurl; charset=utf-8"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            "#EXTM3U\n\
+             #EXTINF:60,ep1.mp4\n/public/disk1/show/ep1.mp4\n\
+             #EXTINF:60,ep2.mp4\n/public/disk1/show/ep2.mp4\n"
+        );
+
+        let body = response_json(playlist(PlaylistFormat::Json).await.unwrap()).await;
+        assert_eq!(body["name"], "show");
+        assert_eq!(body["items"][1]["url"], "/public/disk1/show/ep2.mp4");
+        assert_eq!(body["items"][1]["duration"], 60.0);
+    }
+
+    #[test]
+    fn test_m3u_urls_are_percent_encoded() {
+        let playlist = Playlist {
+            name: "show".to_string(),
+            items: vec![
+                PlaylistItem {
+                    url: "http://example.com:8080/public/disk1/my show/ep 1 #2?.mp4".to_string(),
+                    duration: Some(60.0),
+                    name: "ep 1 #2?.mp4".to_string(),
+                },
+                PlaylistItem {
+                    url: "/public/disk1/节目/100%.mp4".to_string(),
+                    duration: None,
+                    name: "100%.mp4".to_string(),
+                },
+            ],
+        };
+        assert_eq!(
+            to_m3u(&playlist),
+            "#EXTM3U\n\
+             #EXTINF:60,ep 1 #2?.mp4\n\
+             http://example.com:8080/public/disk1/my%20show/ep%201%20%232%3F.mp4\n\
+             #EXTINF:-1,100%.mp4\n\
+             /public/disk1/%E8%8A%82%E7%9B%AE/100%25.mp4\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hls_segments_use_servable_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        std::fs::create_dir(dir.path().join("show")).unwrap();
+        std::fs::write(
+            dir.path().join("show").join("index.m3u8"),
+            "#EXTM3U\n\
+             #EXT-X-KEY:METHOD=AES-128,URI=\"enc.key\"\n\
+             #EXTINF:4.0,\nseg0.ts\n\
+             #EXTINF:2.5,\nhttps://cdn.example.com/seg1.ts\n\
+             #EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+
+        let segments = |path: &str| {
+            get_hls_segments(
+                State(state.clone()),
+                Query(PathParams {
+                    path: path.to_string(),
+                }),
+            )
+        };
+        let Json(list) = segments("/public/disk1/show").await.unwrap();
+        assert_eq!(list.playlist, "/public/disk1/show/index.m3u8");
+        let body = serde_json::to_value(&list.segments).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!([
+                {
+                    "index": 0,
+                    "duration": 4.0,
+                    "uri": "/public/disk1/show/seg0.ts",
+                    "key": { "method": "AES-128", "uri": "/public/disk1/show/enc.key" }
+                },
+                {
+                    "index": 1,
+                    "duration": 2.5,
+                    "uri": "https://cdn.example.com/seg1.ts",
+                    "key": { "method": "AES-128", "uri": "/public/disk1/show/enc.key" }
+                }
+            ])
+        );
+
+        std::fs::write(dir.path().join("show").join("a.mp4"), b"").unwrap();
+        assert_eq!(
+            segments("/public/disk1/show/a.mp4")
+                .await
+                .unwrap_err()
+                .status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_playlist_uses_public_base_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let config = AppConfig {
+            public_base_url: Some("https://media.example.com".to_string()),
+            ..AppConfig::default()
+        };
+        let state = test_state_with_config(
+            vec![DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: root.clone(),
+            }],
+            config,
+        );
+        insert_video(
+            &state.db_manager.lock().unwrap(),
+            &format!("{}/show/ep1.mp4", root),
+            "mp4",
+            "/public/disk1/show",
+        );
+
+        let response = get_playlist(
+            State(state.clone()),
+            Query(PlaylistParams {
+                path: "/public/disk1/show".to_string(),
+                format: PlaylistFormat::M3u,
+                sort_by: None,
+                sort_order: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            "#EXTM3U\n#EXTINF:60,ep1.mp4\nhttps://media.example.com/public/disk1/show/ep1.mp4\n"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! 高开销接口的限流
+//!
+//! 令牌桶算法：每个客户端（有效的 API Key 或 IP）一个桶，桶容量即突发请求数，
+//! 令牌按固定速率补充。超出限制时返回 `429 Too Many Requests` 和 `Retry-After`。
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::routes::auth::{is_valid_api_key, provided_api_key};
+
+/// 超过该数量的客户端时清理已补满的桶
+const MAX_IDLE_BUCKETS: usize = 1024;
+/// 桶数量上限，清理后仍然超出时淘汰最久未使用的桶
+const MAX_BUCKETS: usize = 10_000;
+
+/// 单个客户端的令牌桶
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// 令牌桶限流器，每个路由使用独立的实例
+pub struct RateLimiter {
+    /// 桶容量（允许的突发请求数）
+    capacity: f64,
+    /// 每秒补充的令牌数
+    refill_per_sec: f64,
+    /// 有效的 API Key，只有这些 Key 按 Key 计数，其余请求按 IP 计数
+    api_keys: Vec<String>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// 每分钟最多 `requests` 个请求（允许一次性用完）
+    pub fn per_minute(requests: u32, api_keys: Vec<String>) -> Arc<Self> {
+        let requests = requests.max(1) as f64;
+        Arc::new(Self {
+            capacity: requests,
+            refill_per_sec: requests / 60.0,
+            api_keys,
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 消耗一个令牌，令牌不足时返回需要等待的时间
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // 只在出现新客户端时清理，已有客户端的请求不需要遍历所有桶
+        if !buckets.contains_key(key) {
+            if buckets.len() > MAX_IDLE_BUCKETS {
+                let (capacity, refill) = (self.capacity, self.refill_per_sec);
+                buckets.retain(|_, bucket| {
+                    let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+                    bucket.tokens + elapsed * refill < capacity
+                });
+            }
+            // 大量客户端同时活跃时淘汰最久未使用的桶，保证内存有上限
+            if buckets.len() >= MAX_BUCKETS {
+                let oldest = buckets
+                    .iter()
+                    .min_by_key(|(_, bucket)| bucket.updated_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    buckets.remove(&oldest);
+                }
+            }
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / self.refill_per_sec;
+            Err(Duration::from_secs_f64(wait))
+        }
+    }
+}
+
+/// 限流的客户端标识：有效的 API Key，否则为客户端 IP
+///
+/// 未经校验的 Key 不能作为标识，否则每次请求换一个 Key 就能绕过限流
+fn client_key(request: &Request, api_keys: &[String]) -> String {
+    if let Some(key) = provided_api_key(request.headers()) {
+        if is_valid_api_key(api_keys, key) {
+            return format!("key:{}", key);
+        }
+    }
+    match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+        None => "unknown".to_string(),
+    }
+}
+
+/// 中间件：按客户端限流，通过 `middleware::from_fn_with_state(limiter, rate_limit)` 挂到路由上
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match limiter.check(&client_key(&request, &limiter.api_keys)) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, "Too many requests").into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::Service;
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = RateLimiter::per_minute(2, Vec::new());
+        let start = Instant::now();
+
+        assert!(limiter.check_at("a", start).is_ok());
+        assert!(limiter.check_at("a", start).is_ok());
+        let wait = limiter.check_at("a", start).unwrap_err();
+        assert_eq!(wait.as_secs(), 30);
+        // 其他客户端不受影响
+        assert!(limiter.check_at("b", start).is_ok());
+
+        assert!(limiter
+            .check_at("a", start + Duration::from_secs(30))
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_requests_above_limit_get_429() {
+        let limiter = RateLimiter::per_minute(3, vec!["client-a".to_string()]);
+        let mut app = Router::new().route(
+            "/api/heavy",
+            get(|| async { "ok" }).layer(middleware::from_fn_with_state(limiter, rate_limit)),
+        );
+
+        let request = |key: &str| {
+            Request::builder()
+                .uri("/api/heavy")
+                .header("x-api-key", key)
+                .body(Body::empty())
+                .unwrap()
+        };
+        for _ in 0..3 {
+            let response = app.call(request("client-a")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = app.call(request("client-a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "20");
+
+        // 未配置的 Key 不单独计数，换 Key 也无法绕过限流
+        for i in 0..3 {
+            let response = app.call(request(&format!("random-{}", i))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = app.call(request("random-3")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn test_bucket_count_is_bounded() {
+        let limiter = RateLimiter::per_minute(1, Vec::new());
+        let start = Instant::now();
+        for i in 0..MAX_BUCKETS + 10 {
+            let _ = limiter.check_at(&format!("ip:{}", i), start);
+        }
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_BUCKETS);
+    }
+}
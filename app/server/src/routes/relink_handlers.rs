@@ -0,0 +1,209 @@
+//! 文件移动后重新关联观看状态的 API 处理器
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+
+use crate::models::RelinkSuggestion;
+use crate::routes::error::db_error;
+use crate::services::{DirectorySync, VideoDao};
+use crate::utils::{db_path, resolve_media_path};
+use crate::AppState;
+
+/// 重新关联请求，路径可以是路由路径或物理路径
+#[derive(serde::Deserialize)]
+pub struct RelinkRequest {
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// 将观看状态（已看完、播放进度、收藏）和标签从旧路径移到新路径
+///
+/// 新文件还没有记录时先登记，旧文件的记录已被同步删除时使用同步时保存的状态
+pub async fn relink_video(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<RelinkRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let old_path = resolve_media_path(&request.old_path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid old_path").into_response())?;
+    let new_path = resolve_media_path(&request.new_path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid new_path").into_response())?;
+    if old_path == new_path {
+        return Err((StatusCode::BAD_REQUEST, "old_path and new_path must differ").into_response());
+    }
+    if !new_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "New file not found").into_response());
+    }
+
+    let (old, new) = (db_path(&old_path), db_path(&new_path));
+    let task_state = Arc::clone(&state);
+    let (task_old, task_new) = (old.clone(), new.clone());
+    let relinked = tokio::task::spawn_blocking(move || {
+        let db_manager = task_state.db_manager.lock().unwrap();
+        let video_dao = VideoDao::new(&db_manager);
+        if video_dao.get_video_by_path(&task_new)?.is_none() {
+            DirectorySync::new(&db_manager)
+                .register_file(&new_path, &task_state.data_source_dirs)?;
+        }
+        video_dao.relink_watch_state(&task_old, &task_new)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .map_err(db_error)?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "No watch state for old_path").into_response())?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "old_path": old,
+        "new_path": new,
+        "watched": relinked.watched,
+        "watch_position": relinked.watch_position,
+        "tags": relinked.tags
+    })))
+}
+
+/// 列出可能需要重新关联的文件：同步时被删除的记录与大小、时长相同的新视频
+pub async fn get_relink_suggestions(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<RelinkSuggestion>>, Response> {
+    let db_manager = state.db_manager.lock().unwrap();
+    VideoDao::new(&db_manager)
+        .relink_suggestions()
+        .map(Json)
+        .map_err(db_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_state;
+    use crate::DiskMapping;
+
+    #[tokio::test]
+    async fn test_progress_survives_relink() {
+        let dir = tempfile::tempdir().unwrap();
+        let playlist = "#EXTM3U\n#EXTINF:600.0,\nseg0.ts\n#EXT-X-ENDLIST\n";
+        std::fs::create_dir_all(dir.path().join("old")).unwrap();
+        std::fs::write(dir.path().join("old/ep1.m3u8"), playlist).unwrap();
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        let state = test_state(mappings.clone());
+        let old = db_path(&dir.path().join("old/ep1.m3u8"));
+        let new = db_path(&dir.path().join("new/ep1.m3u8"));
+
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            let sync = DirectorySync::new(&db_manager);
+            sync.initialize_from_directory_with_progress(&mappings, false, false)
+                .unwrap();
+            let dao = VideoDao::new(&db_manager);
+            let id = dao.get_video_by_path(&old).unwrap().unwrap().id;
+            assert_eq!(dao.save_watch_position(id, 120.0).unwrap(), Some(false));
+            dao.add_tags(id, &["anime".to_string()]).unwrap();
+
+            // 移动文件后同步：旧记录被删除，观看状态被保存下来
+            std::fs::create_dir_all(dir.path().join("new")).unwrap();
+            std::fs::rename(
+                dir.path().join("old/ep1.m3u8"),
+                dir.path().join("new/ep1.m3u8"),
+            )
+            .unwrap();
+            sync.initialize_from_directory_with_progress(&mappings, false, false)
+                .unwrap();
+            assert!(dao.get_video_by_path(&old).unwrap().is_none());
+            let moved = dao.get_video_by_path(&new).unwrap().unwrap();
+            assert_eq!(moved.watch_position, None);
+            assert!(dao.get_video_tags(moved.id).unwrap().is_empty());
+
+            // 时长有细微差别时仍然建议
+            db_manager
+                .conn
+                .execute(
+                    "UPDATE videos SET duration_secs = duration_secs + 0.5 WHERE path = ?1",
+                    [&new],
+                )
+                .unwrap();
+        }
+
+        let Json(suggestions) = get_relink_suggestions(State(state.clone())).await.unwrap();
+        assert_eq!(
+            suggestions,
+            vec![RelinkSuggestion {
+                old_path: old.clone(),
+                new_path: new.clone(),
+                name: "ep1.m3u8".to_string(),
+            }]
+        );
+
+        let relink = |old_path: &str, new_path: &str| {
+            relink_video(
+                State(state.clone()),
+                Json(RelinkRequest {
+                    old_path: old_path.to_string(),
+                    new_path: new_path.to_string(),
+                }),
+            )
+        };
+        let Json(body) = relink(&old, "/public/disk1/new/ep1.m3u8").await.unwrap();
+        assert_eq!(body["watch_position"], 120.0);
+        assert_eq!(body["watched"], false);
+        assert_eq!(body["tags"], serde_json::json!(["anime"]));
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            let dao = VideoDao::new(&db_manager);
+            let moved = dao.get_video_by_path(&new).unwrap().unwrap();
+            assert_eq!(moved.watch_position, Some(120.0));
+            assert_eq!(dao.get_video_tags(moved.id).unwrap(), vec!["anime"]);
+        }
+
+        // 状态已被移走，不再建议，也不能再次关联
+        let Json(suggestions) = get_relink_suggestions(State(state.clone())).await.unwrap();
+        assert!(suggestions.is_empty());
+        let err = relink(&old, &new).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        let err = relink(&old, "/public/disk1/missing.mp4").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_expired_watch_state_is_pruned() {
+        let db_manager = crate::services::VideoDbManager::in_memory();
+        for (path, age) in [
+            ("/d/recent.mp4", 60),
+            ("/d/expired.mp4", 200 * 24 * 60 * 60),
+        ] {
+            db_manager
+                .conn
+                .execute(
+                    "INSERT INTO orphaned_watch_state (path, name, watched, removed_at)
+                     VALUES (?1, 'x', 1, CAST(strftime('%s', 'now') AS INTEGER) - ?2)",
+                    rusqlite::params![path, age],
+                )
+                .unwrap();
+        }
+
+        let dao = VideoDao::new(&db_manager);
+        assert_eq!(dao.prune_orphaned_watch_state().unwrap(), 1);
+        let remaining: Vec<String> = db_manager
+            .conn
+            .prepare("SELECT path FROM orphaned_watch_state")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(remaining, vec!["/d/recent.mp4"]);
+    }
+}
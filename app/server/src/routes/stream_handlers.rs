@@ -0,0 +1,407 @@
+//! 媒体流相关的 API 处理器
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use log::info;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncReadExt;
+use tokio::process::Child;
+use tokio::sync::OwnedSemaphorePermit;
+use tower_http::services::ServeFile;
+
+use crate::models::PathParams;
+use crate::services::ffmpeg::{get_ffmpeg_service, transcode_encoder, TRANSCODE_CODECS};
+use crate::utils::media_types::{self, has_extension};
+use crate::utils::{content_type_for, needs_content_type_override, resolve_media_path};
+use crate::AppState;
+
+/// 转码输出每次读取的最大字节数
+const TRANSCODE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// 以正确的 Content-Type 输出媒体文件（支持 Range 请求）
+pub async fn stream_media(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PathParams>,
+    request: Request,
+) -> Result<Response, Response> {
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+
+    if !file_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "File not found").into_response());
+    }
+
+    let response = ServeFile::new(&file_path)
+        .try_call(request)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("File error: {}", e),
+            )
+                .into_response()
+        })?
+        .map(Body::new);
+
+    Ok(with_content_type(response, &file_path))
+}
+
+/// 以附件形式下载媒体文件（支持 Range 请求）
+pub async fn download_media(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PathParams>,
+    request: Request,
+) -> Result<Response, Response> {
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+
+    if !file_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "File not found").into_response());
+    }
+
+    let mut response = ServeFile::new(&file_path)
+        .try_call(request)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("File error: {}", e),
+            )
+                .into_response()
+        })?
+        .map(Body::new);
+
+    let filename = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if let Ok(value) = HeaderValue::from_str(&content_disposition(&filename)) {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, value);
+    }
+
+    Ok(with_content_type(response, &file_path))
+}
+
+/// 实时转码参数
+#[derive(serde::Deserialize)]
+pub struct TranscodeParams {
+    /// 路由路径或物理路径
+    pub path: String,
+    /// 目标编码，默认为 h264
+    #[serde(default = "default_transcode_codec")]
+    pub codec: String,
+}
+
+fn default_transcode_codec() -> String {
+    "h264".to_string()
+}
+
+/// 实时转码为分片 MP4 输出，用于无法播放 HEVC 等编码的客户端
+///
+/// 每个请求启动一个 ffmpeg 进程边转码边输出，不需要预先转码整个文件，但开销很大：
+/// 转码 1080p 通常会占满多个 CPU 核心，速度受 CPU 限制，并发请求会互相拖慢，
+/// 因此同时进行的转码数量受 MAX_TRANSCODES 限制，已满时返回 503。
+/// 输出不支持 Range 请求（无法拖动进度），客户端断开时 ffmpeg 进程随之结束
+pub async fn transcode_media(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TranscodeParams>,
+) -> Result<Response, Response> {
+    let encoder = transcode_encoder(&params.codec).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid codec: {} (allowed: {})",
+                params.codec,
+                TRANSCODE_CODECS.join(", ")
+            ),
+        )
+            .into_response()
+    })?;
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !file_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "File not found").into_response());
+    }
+    if !has_extension(&file_path, media_types::FRAME_SOURCE) {
+        return Err((StatusCode::BAD_REQUEST, "Not a video file").into_response());
+    }
+
+    let permit = Arc::clone(&state.transcodes)
+        .try_acquire_owned()
+        .map_err(|_| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Too many transcodes in progress",
+            )
+                .into_response()
+        })?;
+    let child = get_ffmpeg_service()
+        .spawn_transcode(&file_path, encoder)
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e).into_response())?;
+    info!("开始实时转码: {} -> {}", file_path.display(), params.codec);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "video/mp4"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        child_stdout_body(TranscodeProcess::new(child, permit)),
+    )
+        .into_response())
+}
+
+/// 实时转码的 ffmpeg 进程及其占用的转码许可
+///
+/// 被丢弃时结束进程并归还许可
+struct TranscodeProcess {
+    child: Arc<Mutex<Child>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl TranscodeProcess {
+    fn new(child: Child, permit: OwnedSemaphorePermit) -> Self {
+        Self {
+            child: Arc::new(Mutex::new(child)),
+            _permit: permit,
+        }
+    }
+}
+
+impl Drop for TranscodeProcess {
+    fn drop(&mut self) {
+        let _ = self.child.lock().unwrap().start_kill();
+    }
+}
+
+/// 将转码进程的标准输出作为响应体
+///
+/// 进程归响应体所有：客户端断开后响应体被丢弃，进程随之被结束
+fn child_stdout_body(process: TranscodeProcess) -> Body {
+    let stdout = process.child.lock().unwrap().stdout.take();
+    let chunks = futures_util::stream::unfold((process, stdout), |(process, stdout)| async move {
+        let mut stdout = stdout?;
+        let mut buffer = vec![0; TRANSCODE_CHUNK_SIZE];
+        match stdout.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buffer.truncate(read);
+                Some((Ok(Bytes::from(buffer)), (process, Some(stdout))))
+            }
+            Err(e) => Some((Err(e), (process, None))),
+        }
+    });
+    Body::from_stream(chunks)
+}
+
+/// 构建附件下载的 Content-Disposition
+///
+/// `filename` 为 ASCII 回退名称（非 ASCII 字符替换为 `_`），
+/// `filename*` 为 RFC 5987 编码的原始文件名
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if fallback == filename {
+        return format!("attachment; filename=\"{}\"", filename);
+    }
+
+    let encoded: String = filename
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect();
+
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback, encoded
+    )
+}
+
+/// 中间件：修正静态文件服务对 m3u8/ts/vtt 的 Content-Type 推断
+pub async fn override_media_content_type(request: Request, next: Next) -> Response {
+    let request_path = request.uri().path().to_string();
+    let response = next.run(request).await;
+
+    let path = Path::new(&request_path);
+    if response.status().is_success() && needs_content_type_override(path) {
+        return with_content_type(response, path);
+    }
+    response
+}
+
+/// 设置响应的 Content-Type
+fn with_content_type(mut response: Response, path: &Path) -> Response {
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(content_type_for(path)),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_state;
+    use crate::DiskMapping;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_transcode_disconnect_kills_ffmpeg() {
+        use crate::services::ffmpeg::{FFmpegConfig, FFmpegService};
+        use futures_util::StreamExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        // 用脚本代替 ffmpeg：不停地向标准输出写数据，直到被结束
+        let dir = tempfile::tempdir().unwrap();
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            "#!/bin/sh
+while :; do echo moof; sleep 0.05; done
+",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            ..FFmpegConfig::default()
+        });
+
+        let child = service
+            .spawn_transcode(Path::new("a.mkv"), "libx264")
+            .unwrap();
+        let permits = Arc::new(tokio::sync::Semaphore::new(1));
+        let process =
+            TranscodeProcess::new(child, Arc::clone(&permits).try_acquire_owned().unwrap());
+        let child = Arc::clone(&process.child);
+        let is_running = || child.lock().unwrap().try_wait().unwrap().is_none();
+
+        let mut body = child_stdout_body(process).into_data_stream();
+        let chunk = body.next().await.unwrap().unwrap();
+        assert!(chunk.starts_with(b"moof"));
+        assert!(is_running());
+        assert_eq!(permits.available_permits(), 0);
+
+        // 客户端断开时响应体被丢弃，进程被结束，许可被归还
+        drop(body);
+        assert_eq!(permits.available_permits(), 1);
+        for _ in 0..50 {
+            if !is_running() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("ffmpeg still running after disconnect");
+    }
+
+    #[tokio::test]
+    async fn test_transcode_rejects_when_all_permits_taken() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.mkv"), b"mkv").unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+        let _permits = Arc::clone(&state.transcodes)
+            .try_acquire_many_owned(state.config.ffmpeg.max_transcodes as u32)
+            .unwrap();
+
+        let response = transcode_media(
+            State(state),
+            Query(TranscodeParams {
+                path: "/public/disk1/a.mkv".to_string(),
+                codec: "h264".to_string(),
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_transcode_rejects_unknown_codec() {
+        let response = transcode_media(
+            State(test_state(Vec::new())),
+            Query(TranscodeParams {
+                path: "/public/disk1/a.mp4".to_string(),
+                codec: "theora".to_string(),
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_content_disposition_ascii() {
+        assert_eq!(
+            content_disposition("movie.mp4"),
+            "attachment; filename=\"movie.mp4\""
+        );
+        assert_eq!(
+            content_disposition("a\"b.mp4"),
+            "attachment; filename=\"a_b.mp4\"; filename*=UTF-8''a%22b.mp4"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_sets_encoded_disposition() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("视频 1.mp4"), b"data").unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+
+        let params = PathParams {
+            path: "/public/disk1/视频 1.mp4".to_string(),
+        };
+        let response = download_media(State(state), Query(params), Request::new(Body::empty()))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"__ 1.mp4\"; filename*=UTF-8''%E8%A7%86%E9%A2%91%201.mp4"
+        );
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "video/mp4");
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_traversal() {
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: "/tmp".to_string(),
+        }]);
+
+        let params = PathParams {
+            path: "/public/disk1/../etc/passwd".to_string(),
+        };
+        let response = download_media(State(state), Query(params), Request::new(Body::empty()))
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
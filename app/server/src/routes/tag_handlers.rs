@@ -0,0 +1,223 @@
+//! 标签相关的 API 处理器
+
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+
+use crate::models::TagInfo;
+use crate::routes::error::db_error;
+use crate::services::VideoDao;
+use crate::AppState;
+
+/// 标签名的最大长度（字符）
+const MAX_TAG_LENGTH: usize = 64;
+
+/// 去掉标签名首尾的空白，空标签返回 None
+pub(crate) fn normalize_tag(name: &str) -> Option<String> {
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// 修改标签请求，标签不存在时自动创建
+#[derive(serde::Deserialize)]
+pub struct UpdateTagsRequest {
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+/// 为条目添加或移除标签，返回条目当前的标签
+pub async fn update_video_tags(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<i64>,
+    Json(request): Json<UpdateTagsRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let add = validate_tags(&request.add).map_err(IntoResponse::into_response)?;
+    let remove = validate_tags(&request.remove).map_err(IntoResponse::into_response)?;
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let video_dao = VideoDao::new(&db_manager);
+    let result = video_dao.video_exists_by_id(id).and_then(|exists| {
+        if !exists {
+            return Ok(None);
+        }
+        video_dao.add_tags(id, &add)?;
+        video_dao.remove_tags(id, &remove).map(Some)
+    });
+    let tags = result
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+
+    Ok(Json(serde_json::json!({ "id": id, "tags": tags })))
+}
+
+/// 获取条目的标签
+pub async fn get_video_tags(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<i64>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let db_manager = state.db_manager.lock().unwrap();
+    let video_dao = VideoDao::new(&db_manager);
+    let result = video_dao.video_exists_by_id(id).and_then(|exists| {
+        if !exists {
+            return Ok(None);
+        }
+        video_dao.get_video_tags(id).map(Some)
+    });
+    let tags = result
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+
+    Ok(Json(serde_json::json!({ "id": id, "tags": tags })))
+}
+
+/// 列出所有标签及其条目数量
+pub async fn list_tags(State(state): State<Arc<AppState>>) -> Result<Json<Vec<TagInfo>>, Response> {
+    let db_manager = state.db_manager.lock().unwrap();
+    VideoDao::new(&db_manager)
+        .list_tags()
+        .map(Json)
+        .map_err(db_error)
+}
+
+/// 检查并规范化标签名，空标签或过长的标签返回 400
+fn validate_tags(names: &[String]) -> Result<Vec<String>, (StatusCode, String)> {
+    names
+        .iter()
+        .map(|name| match normalize_tag(name) {
+            Some(tag) if tag.chars().count() <= MAX_TAG_LENGTH => Ok(tag),
+            Some(_) => Err((
+                StatusCode::BAD_REQUEST,
+                format!("Tag must be at most {} characters", MAX_TAG_LENGTH),
+            )),
+            None => Err((StatusCode::BAD_REQUEST, "Tag must not be empty".to_string())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PaginationParams;
+    use crate::routes::list_videos_paginated;
+    use crate::services::DirectorySync;
+    use crate::test_utils::{response_json, test_state};
+    use crate::utils::db_path;
+    use crate::DiskMapping;
+    use axum::extract::Query;
+
+    #[tokio::test]
+    async fn test_tag_video_and_filter_by_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let playlist = "#EXTM3U\n#EXTINF:10.0,\nseg0.ts\n#EXT-X-ENDLIST\n";
+        std::fs::write(dir.path().join("a.m3u8"), playlist).unwrap();
+        std::fs::write(dir.path().join("b.m3u8"), playlist).unwrap();
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        let state = test_state(mappings.clone());
+        let (a, b) = (
+            db_path(&dir.path().join("a.m3u8")),
+            db_path(&dir.path().join("b.m3u8")),
+        );
+        let (id_a, id_b) = {
+            let db_manager = state.db_manager.lock().unwrap();
+            DirectorySync::new(&db_manager)
+                .initialize_from_directory_with_progress(&mappings, false, false)
+                .unwrap();
+            let dao = VideoDao::new(&db_manager);
+            let id_of = |path: &str| dao.get_video_by_path(path).unwrap().unwrap().id;
+            (id_of(&a), id_of(&b))
+        };
+
+        let update = |id: i64, add: &[&str], remove: &[&str]| {
+            update_video_tags(
+                State(state.clone()),
+                UrlPath(id),
+                Json(UpdateTagsRequest {
+                    add: add.iter().map(|t| t.to_string()).collect(),
+                    remove: remove.iter().map(|t| t.to_string()).collect(),
+                }),
+            )
+        };
+        // 标签在第一次使用时创建，大小写不同视为同一个标签
+        let Json(body) = update(id_a, &["Anime", " comedy "], &[]).await.unwrap();
+        assert_eq!(body["tags"], serde_json::json!(["Anime", "comedy"]));
+        let Json(body) = update(id_b, &["anime"], &[]).await.unwrap();
+        assert_eq!(body["tags"], serde_json::json!(["Anime"]));
+        assert_eq!(
+            update(id_a, &["  "], &[]).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            update(-1, &["x"], &[]).await.unwrap_err().status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let Json(tags) = list_tags(State(state.clone())).await.unwrap();
+        assert_eq!(
+            tags,
+            vec![
+                TagInfo {
+                    name: "Anime".to_string(),
+                    count: 2
+                },
+                TagInfo {
+                    name: "comedy".to_string(),
+                    count: 1
+                },
+            ]
+        );
+
+        let tagged = |tag: &str| {
+            let params: PaginationParams =
+                serde_json::from_value(serde_json::json!({ "tag": tag })).unwrap();
+            list_videos_paginated(State(state.clone()), Query(params))
+        };
+        let paths = |body: serde_json::Value| -> Vec<String> {
+            let mut paths: Vec<String> = body["videos"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["path"].as_str().unwrap().to_string())
+                .collect();
+            paths.sort();
+            paths
+        };
+        let body = response_json(tagged("COMEDY").await.unwrap()).await;
+        assert_eq!(paths(body), vec![a.clone()]);
+
+        // 文件被修改后记录被替换，标签仍然保留
+        std::fs::write(
+            dir.path().join("a.m3u8"),
+            "#EXTM3U\n#EXTINF:20.0,\nseg0.ts\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+        let id_a = {
+            let db_manager = state.db_manager.lock().unwrap();
+            DirectorySync::new(&db_manager)
+                .refresh_file(&dir.path().join("a.m3u8"), &mappings)
+                .unwrap();
+            VideoDao::new(&db_manager)
+                .get_video_by_path(&a)
+                .unwrap()
+                .unwrap()
+                .id
+        };
+        let Json(body) = get_video_tags(State(state.clone()), UrlPath(id_a))
+            .await
+            .unwrap();
+        assert_eq!(body["tags"], serde_json::json!(["Anime", "comedy"]));
+
+        let Json(body) = update(id_a, &[], &["anime"]).await.unwrap();
+        assert_eq!(body["tags"], serde_json::json!(["comedy"]));
+        let body = response_json(tagged("anime").await.unwrap()).await;
+        assert_eq!(paths(body), vec![b]);
+    }
+}
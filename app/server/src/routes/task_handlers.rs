@@ -12,6 +12,7 @@ pub struct TaskQueueStatusResponse {
     pub running: usize,
     pub completed: u64,
     pub failed: u64,
+    pub paused: bool,
 }
 
 /// 获取任务队列状态
@@ -24,5 +25,43 @@ pub async fn get_task_queue_status() -> Json<TaskQueueStatusResponse> {
         running: stats.running_count,
         completed: stats.completed_count,
         failed: stats.failed_count,
+        paused: stats.paused,
     })
 }
+
+/// 暂停任务队列（正在执行的任务会继续完成），返回队列状态
+pub async fn pause_task_queue() -> Json<TaskQueueStatusResponse> {
+    get_task_queue().pause();
+    get_task_queue_status().await
+}
+
+/// 恢复任务队列，返回队列状态
+pub async fn resume_task_queue() -> Json<TaskQueueStatusResponse> {
+    get_task_queue().resume();
+    get_task_queue_status().await
+}
+
+/// 正在执行的任务
+#[derive(Serialize)]
+pub struct RunningTaskResponse {
+    pub id: u64,
+    pub task_type: &'static str,
+    pub target_path: String,
+    pub started_at: String,
+}
+
+/// 获取正在执行的任务列表
+pub async fn get_running_tasks() -> Json<Vec<RunningTaskResponse>> {
+    let tasks = get_task_queue()
+        .running_tasks()
+        .into_iter()
+        .map(|task| RunningTaskResponse {
+            id: task.id,
+            task_type: task.task_type,
+            target_path: task.target_path.to_string_lossy().to_string(),
+            started_at: task.started_at,
+        })
+        .collect();
+
+    Json(tasks)
+}
@@ -0,0 +1,693 @@
+//! 缩略图相关的 API 处理器
+
+use axum::{
+    body::Body,
+    extract::{Path as UrlPath, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use log::{debug, error, info};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tower_http::services::ServeFile;
+use walkdir::WalkDir;
+
+use crate::config::{ThumbnailMode, ThumbnailStrategy};
+use crate::models::PathParams;
+use crate::routes::auth::require_api_key;
+use crate::routes::error::db_error;
+use crate::services::db::{DirectorySync, VideoDao};
+use crate::services::ffmpeg::{
+    get_ffmpeg_service, placeholder_svg, thumbnail_variant_path, THUMBNAILS_DIR,
+};
+use crate::services::task_queue::{get_task_queue, TaskPriority, TaskResult, TaskType};
+use crate::utils::{db_path, is_video_or_container, resolve_media_path};
+use crate::AppState;
+
+/// 返回占位图时，后台生成缩略图的任务 ID
+const THUMBNAIL_TASK_HEADER: &str = "x-thumbnail-task-id";
+
+/// 按需生成中的缩略图及其任务 ID，同一个缩略图只提交一次任务
+fn pending_thumbnails() -> &'static Mutex<HashMap<PathBuf, u64>> {
+    static PENDING: OnceLock<Mutex<HashMap<PathBuf, u64>>> = OnceLock::new();
+    PENDING.get_or_init(Default::default)
+}
+
+/// 缩略图查询参数
+#[derive(Deserialize)]
+pub struct ThumbnailParams {
+    /// 期望的宽度（可选），返回最接近的已生成尺寸
+    pub size: Option<u32>,
+}
+
+/// 输出缩略图，`?size=` 指定期望宽度
+///
+/// lazy 模式下缩略图还不存在时在后台生成，生成前返回占位图
+pub async fn serve_thumbnail(
+    State(state): State<Arc<AppState>>,
+    UrlPath(file): UrlPath<String>,
+    Query(params): Query<ThumbnailParams>,
+    request: Request,
+) -> Result<Response, Response> {
+    let relative = Path::new(&file);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err((StatusCode::BAD_REQUEST, "Invalid path").into_response());
+    }
+
+    let base_path = Path::new(THUMBNAILS_DIR).join(relative);
+    if state.config.thumbnail.mode == ThumbnailMode::Lazy {
+        // `?size=` 时只生成最接近的那个尺寸，不生成全部尺寸
+        let width = nearest_width(params.size, &state.config.thumbnail.sizes);
+        let target = width.map_or_else(
+            || base_path.clone(),
+            |width| thumbnail_variant_path(&base_path, width),
+        );
+        if !target.is_file() {
+            if let Some(placeholder) = generate_on_demand(&state, &base_path, width).await? {
+                return Ok(placeholder);
+            }
+        }
+    }
+
+    let thumbnail_path = select_thumbnail(&base_path, params.size, &state.config.thumbnail.sizes);
+    if !thumbnail_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "Thumbnail not found").into_response());
+    }
+
+    let response = ServeFile::new(&thumbnail_path)
+        .try_call(request)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("File error: {}", e),
+            )
+                .into_response()
+        })?
+        .map(Body::new);
+
+    Ok(response)
+}
+
+/// 为缺少的缩略图找到源文件并开始生成
+///
+/// `thumbnail_path` 为默认尺寸缩略图的路径，`width` 为空时生成默认尺寸，否则只生成该宽度的变体。
+/// 可截取画面的视频提交后台任务并返回占位图；其他文件直接生成占位缩略图后返回 None，
+/// 由调用方输出生成的文件。找不到源文件时返回 None
+async fn generate_on_demand(
+    state: &Arc<AppState>,
+    thumbnail_path: &Path,
+    width: Option<u32>,
+) -> Result<Option<Response>, Response> {
+    let target = width.map_or_else(
+        || thumbnail_path.to_path_buf(),
+        |width| thumbnail_variant_path(thumbnail_path, width),
+    );
+    let lookup_state = state.clone();
+    let lookup_path = thumbnail_path.to_path_buf();
+    let source =
+        tokio::task::spawn_blocking(move || find_thumbnail_source(&lookup_state, &lookup_path))
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Task error: {}", e),
+                )
+                    .into_response()
+            })?;
+    let Some(source) = source else {
+        return Ok(None);
+    };
+
+    if get_ffmpeg_service().thumbnail_strategy(&source) == ThumbnailStrategy::Placeholder {
+        let placeholder_path = target.clone();
+        let generated = tokio::task::spawn_blocking(move || {
+            get_ffmpeg_service().generate_placeholder_thumbnail(&placeholder_path, "media")
+        })
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Task error: {}", e),
+            )
+                .into_response()
+        })?;
+        return Ok((!generated).then(|| placeholder_response(None)));
+    }
+
+    let pending_id = pending_thumbnails().lock().unwrap().get(&target).copied();
+    let task_id = match pending_id {
+        Some(task_id) => task_id,
+        None => {
+            let thumbnail_path = thumbnail_path.to_path_buf();
+            let task_type = match width {
+                Some(width) => TaskType::GenerateThumbnailVariant {
+                    video_path: source,
+                    thumbnail_path,
+                    width,
+                },
+                None => TaskType::GenerateThumbnail {
+                    video_path: source,
+                    thumbnail_path,
+                },
+            };
+            let (task_id, receiver) = get_task_queue()
+                .enqueue_with_result(task_type, TaskPriority::High)
+                .await;
+            pending_thumbnails()
+                .lock()
+                .unwrap()
+                .insert(target.clone(), task_id);
+
+            tokio::spawn(async move {
+                match receiver.await {
+                    Ok(Ok(TaskResult::ThumbnailGenerated(path))) => {
+                        debug!("按需生成缩略图完成: {}", path.display())
+                    }
+                    Ok(Err(e)) => error!("按需生成缩略图失败: {} - {}", target.display(), e),
+                    _ => {}
+                }
+                pending_thumbnails().lock().unwrap().remove(&target);
+            });
+            task_id
+        }
+    };
+
+    Ok(Some(placeholder_response(Some(task_id))))
+}
+
+/// 查找缩略图对应的源文件：先查数据库记录，再在各数据源目录中递归查找缩略图路径相同的文件
+///
+/// 会遍历数据源目录，需要在阻塞线程中调用
+fn find_thumbnail_source(state: &AppState, thumbnail_path: &Path) -> Option<PathBuf> {
+    let recorded = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_video_path_by_thumbnail(&db_path(thumbnail_path))
+            .ok()
+            .flatten()
+    };
+    if let Some(path) = recorded.map(PathBuf::from).filter(|path| path.is_file()) {
+        return Some(path);
+    }
+
+    let stem = thumbnail_path.file_stem()?;
+    state.data_source_dirs.iter().find_map(|mapping| {
+        WalkDir::new(&mapping.physical_path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.into_path())
+            .find(|path| {
+                path.file_stem() == Some(stem)
+                    && is_video_or_container(path)
+                    && DirectorySync::get_thumbnail_path(path) == thumbnail_path
+            })
+    })
+}
+
+/// 与期望宽度最接近的配置尺寸，没有指定宽度或没有配置其他尺寸时返回 None
+fn nearest_width(size: Option<u32>, sizes: &[u32]) -> Option<u32> {
+    let size = size?;
+    sizes
+        .iter()
+        .copied()
+        .min_by_key(|&width| width.abs_diff(size))
+}
+
+/// 缩略图生成前返回的占位图，不允许缓存
+fn placeholder_response(task_id: Option<u64>) -> Response {
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        placeholder_svg("video"),
+    )
+        .into_response();
+    if let Some(task_id) = task_id {
+        response
+            .headers_mut()
+            .insert(THUMBNAIL_TASK_HEADER, HeaderValue::from(task_id));
+    }
+    response
+}
+
+/// 选择与期望宽度最接近且已生成的缩略图变体，没有可用变体时返回默认缩略图
+fn select_thumbnail(thumbnail_path: &Path, size: Option<u32>, sizes: &[u32]) -> PathBuf {
+    let Some(size) = size else {
+        return thumbnail_path.to_path_buf();
+    };
+
+    let mut candidates = sizes.to_vec();
+    candidates.sort_by_key(|&width| width.abs_diff(size));
+
+    candidates
+        .into_iter()
+        .map(|width| thumbnail_variant_path(thumbnail_path, width))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| thumbnail_path.to_path_buf())
+}
+
+/// 缩略图校验结果
+#[derive(Serialize)]
+pub struct VerifyThumbnailsResponse {
+    /// 检查的缩略图数量
+    pub checked: usize,
+    /// 损坏或缺失的缩略图数量
+    pub invalid: usize,
+    /// 已提交重新生成的任务数量
+    pub repaired: usize,
+    /// 重新生成任务的 ID
+    pub task_ids: Vec<u64>,
+}
+
+/// 校验所有缩略图，为空文件或损坏的缩略图提交重新生成任务
+pub async fn verify_thumbnails(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<VerifyThumbnailsResponse>, Response> {
+    let thumbnails = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_video_thumbnails()
+            .map_err(|e| {
+                error!("Failed to load thumbnails: {}", e);
+                db_error(e)
+            })?
+    };
+    let checked = thumbnails.len();
+
+    // 每个缩略图都要调用一次 ffprobe，并行检查
+    let invalid = tokio::task::spawn_blocking(move || {
+        let ffmpeg = get_ffmpeg_service();
+        thumbnails
+            .into_par_iter()
+            .filter(|(_, thumbnail)| !ffmpeg.is_valid_image(Path::new(thumbnail)))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?;
+
+    let mut task_ids = Vec::with_capacity(invalid.len());
+    for (video_path, thumbnail_path) in &invalid {
+        let task_type = TaskType::GenerateThumbnail {
+            video_path: PathBuf::from(video_path),
+            thumbnail_path: PathBuf::from(thumbnail_path),
+        };
+        task_ids.push(
+            get_task_queue()
+                .enqueue(task_type, TaskPriority::Normal)
+                .await,
+        );
+    }
+
+    info!(
+        "缩略图校验完成: 检查 {} 个，无效 {} 个",
+        checked,
+        invalid.len()
+    );
+
+    Ok(Json(VerifyThumbnailsResponse {
+        checked,
+        invalid: invalid.len(),
+        repaired: task_ids.len(),
+        task_ids,
+    }))
+}
+
+/// 清除缩略图的结果
+#[derive(Serialize, Debug)]
+pub struct ClearThumbnailsResponse {
+    /// 数据库中被清除缩略图的记录数量
+    pub cleared: usize,
+    /// 删除的缩略图文件数量（包含各尺寸的变体）
+    pub deleted_files: usize,
+}
+
+/// 清除目录（或单个文件）下所有条目的缩略图，下次请求或同步时按当前设置重新生成
+///
+/// 只删除 thumbnails 目录内、且没有被子树之外的条目引用的文件，数据库中的缩略图字段置空；
+/// 目录的封面随后立即重新选择，不会在下次同步前一直没有封面
+pub async fn clear_thumbnails(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<PathParams>,
+) -> Result<Json<ClearThumbnailsResponse>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+
+    let target = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !target.exists() {
+        return Err((StatusCode::NOT_FOUND, "Path not found").into_response());
+    }
+
+    let (cleared, thumbnails) = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .clear_thumbnails_under(&db_path(&target))
+            .map_err(db_error)?
+    };
+
+    let sizes = state.config.thumbnail.sizes.clone();
+    let task_state = Arc::clone(&state);
+    let root = db_path(&target);
+    let deleted_files = tokio::task::spawn_blocking(move || {
+        let deleted_files = thumbnails
+            .iter()
+            .map(PathBuf::from)
+            .filter(|thumbnail| is_inside_thumbnails_dir(thumbnail))
+            .flat_map(|thumbnail| {
+                let variants: Vec<PathBuf> = sizes
+                    .iter()
+                    .map(|width| thumbnail_variant_path(&thumbnail, *width))
+                    .collect();
+                std::iter::once(thumbnail).chain(variants)
+            })
+            .filter(|file| std::fs::remove_file(file).is_ok())
+            .count();
+
+        let db_manager = task_state.db_manager.lock().unwrap();
+        if let Err(e) = DirectorySync::new(&db_manager).refresh_directory_thumbnails(&root) {
+            error!("重新选择目录封面失败: {} - {}", root, e);
+        }
+        deleted_files
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?;
+
+    info!(
+        "已清除缩略图: {} ({} 条记录, {} 个文件)",
+        params.path, cleared, deleted_files
+    );
+    Ok(Json(ClearThumbnailsResponse {
+        cleared,
+        deleted_files,
+    }))
+}
+
+/// 路径是否位于 thumbnails 目录内（不含 `..`），避免误删媒体文件
+fn is_inside_thumbnails_dir(path: &Path) -> bool {
+    path.starts_with(THUMBNAILS_DIR) && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_utils::{insert_video, test_state, test_state_with_config};
+    use crate::DiskMapping;
+
+    #[tokio::test]
+    async fn test_verify_enqueues_empty_thumbnail() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbnail = dir.path().join("a.jpg");
+        std::fs::write(&thumbnail, b"").unwrap();
+
+        let state = test_state(Vec::new());
+        state
+            .db_manager
+            .lock()
+            .unwrap()
+            .conn
+            .execute(
+                "INSERT INTO videos (name, path, type, parent_path, thumbnail) VALUES ('a.mp4', '/videos/a.mp4', 'mp4', '/videos', ?1)",
+                [thumbnail.to_string_lossy()],
+            )
+            .unwrap();
+
+        let Json(report) = verify_thumbnails(State(state)).await.unwrap();
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.invalid, 1);
+        assert_eq!(report.repaired, 1);
+        assert_eq!(report.task_ids.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_clear_thumbnails_for_subtree() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("show/sub")).unwrap();
+        std::fs::create_dir_all(dir.path().join("other")).unwrap();
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        std::fs::write(dir.path().join("show/poster.jpg"), b"poster").unwrap();
+        let mut config = AppConfig::default();
+        config.thumbnail.sizes = vec![160];
+        config.api_keys = vec!["secret".to_string()];
+        let state = test_state_with_config(mappings, config);
+
+        // 缩略图目录是相对于工作目录的，用临时目录名区分，避免和其他测试冲突
+        let thumbnails_root = Path::new(THUMBNAILS_DIR).join(format!(
+            "clear-{}",
+            dir.path()
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .trim_start_matches('.')
+        ));
+        let entries = [
+            ("show", "show.jpg"),
+            ("show/a.mp4", "show/a.jpg"),
+            ("show/sub/b.mp4", "show/sub/b.jpg"),
+            ("other/c.mp4", "other/c.jpg"),
+        ];
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            let dao = VideoDao::new(&db_manager);
+            for (file, thumbnail) in entries {
+                let path = db_path(&dir.path().join(file));
+                let file_type = if file == "show" { "directory" } else { "mp4" };
+                insert_video(&db_manager, &path, file_type, "/public/disk1");
+                let thumbnail = thumbnails_root.join(thumbnail);
+                std::fs::create_dir_all(thumbnail.parent().unwrap()).unwrap();
+                std::fs::write(&thumbnail, b"jpeg").unwrap();
+                let variant = thumbnail_variant_path(&thumbnail, 160);
+                std::fs::create_dir_all(variant.parent().unwrap()).unwrap();
+                std::fs::write(&variant, b"jpeg").unwrap();
+                dao.set_thumbnail(&path, &db_path(&thumbnail), 1).unwrap();
+            }
+            // 子树之外的目录以 show/a.mp4 作为封面
+            let other = db_path(&dir.path().join("other"));
+            insert_video(&db_manager, &other, "directory", "/public/disk1");
+            dao.set_thumbnail(&other, &db_path(&thumbnails_root.join("show/a.jpg")), 1)
+                .unwrap();
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            crate::routes::auth::API_KEY_HEADER,
+            HeaderValue::from_static("secret"),
+        );
+        let clear = |path: &str| {
+            clear_thumbnails(
+                State(state.clone()),
+                headers.clone(),
+                Query(PathParams {
+                    path: path.to_string(),
+                }),
+            )
+        };
+        let unauthorized = clear_thumbnails(
+            State(state.clone()),
+            HeaderMap::new(),
+            Query(PathParams {
+                path: "/public/disk1/show".to_string(),
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+        let Json(report) = clear("/public/disk1/show").await.unwrap();
+        assert_eq!(report.cleared, 3);
+        assert_eq!(report.deleted_files, 4);
+
+        let thumbnail_of = |file: &str| -> (Option<String>, Option<i64>) {
+            state
+                .db_manager
+                .lock()
+                .unwrap()
+                .conn
+                .query_row(
+                    "SELECT thumbnail, thumbnail_generated_at FROM videos WHERE path = ?1",
+                    [db_path(&dir.path().join(file))],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap()
+        };
+        for (file, thumbnail) in [entries[0], entries[2]] {
+            let thumbnail = thumbnails_root.join(thumbnail);
+            assert!(!thumbnail.exists(), "{}", file);
+            assert!(!thumbnail_variant_path(&thumbnail, 160).exists());
+        }
+        for file in ["show/a.mp4", "show/sub/b.mp4"] {
+            assert_eq!(thumbnail_of(file), (None, None), "{}", file);
+        }
+        // 仍被子树之外引用的缩略图保留
+        assert!(thumbnails_root.join("show/a.jpg").exists());
+
+        // 目录的封面立即重新选择
+        let folder = {
+            let db_manager = state.db_manager.lock().unwrap();
+            DirectorySync::new(&db_manager).directory_thumbnail_path(&dir.path().join("show"))
+        };
+        assert_eq!(thumbnail_of("show").0, Some(db_path(&folder)));
+        assert_eq!(std::fs::read(&folder).unwrap(), b"poster");
+        let _ = std::fs::remove_file(&folder);
+        let _ = std::fs::remove_dir(folder.parent().unwrap());
+        let other = thumbnails_root.join("other/c.jpg");
+        assert_eq!(
+            thumbnail_of("other/c.mp4"),
+            (Some(db_path(&other)), Some(1))
+        );
+        assert!(other.exists());
+        assert!(thumbnail_variant_path(&other, 160).exists());
+
+        assert_eq!(
+            clear("/public/disk1/../etc").await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            clear("/public/disk1/missing").await.unwrap_err().status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let _ = std::fs::remove_dir_all(&thumbnails_root);
+        let _ = std::fs::remove_dir_all(
+            Path::new(THUMBNAILS_DIR)
+                .join("160")
+                .join(thumbnails_root.strip_prefix(THUMBNAILS_DIR).unwrap()),
+        );
+        let _ = std::fs::remove_dir(Path::new(THUMBNAILS_DIR).join("160"));
+    }
+
+    #[tokio::test]
+    async fn test_lazy_mode_generates_thumbnail_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("lazy.mp4");
+        std::fs::write(&video, b"video").unwrap();
+        // 缩略图按源文件的完整路径存放，临时目录保证不会与其它测试冲突
+        let thumbnail = DirectorySync::get_thumbnail_path(&video);
+        let relative = thumbnail.strip_prefix(THUMBNAILS_DIR).unwrap();
+        let url = relative.to_string_lossy().replace('\\', "/");
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        let serve = |state: &Arc<AppState>, file: String| {
+            serve_thumbnail(
+                State(state.clone()),
+                UrlPath(file),
+                Query(ThumbnailParams { size: None }),
+                Request::new(Body::empty()),
+            )
+        };
+
+        // eager 模式下缺少的缩略图直接返回 404
+        let eager = test_state(mappings.clone());
+        let response = serve(&eager, url.clone()).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut config = AppConfig::default();
+        config.thumbnail.mode = ThumbnailMode::Lazy;
+        let lazy = test_state_with_config(mappings, config);
+        let response = serve(&lazy, url.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        assert!(response.headers().contains_key(THUMBNAIL_TASK_HEADER));
+
+        // 没有对应源文件的缩略图仍然是 404
+        let response = serve(&lazy, url.replace("lazy.jpg", "lazy-missing.jpg"))
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let _ = std::fs::remove_dir_all(thumbnail.parent().unwrap());
+    }
+
+    #[test]
+    fn test_thumbnail_source_found_in_nested_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("show/season1");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join("ep1.mp4"), b"video").unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+
+        let thumbnail_path = DirectorySync::get_thumbnail_path(&nested.join("ep1.mp4"));
+        assert_eq!(
+            find_thumbnail_source(&state, &thumbnail_path),
+            Some(nested.join("ep1.mp4"))
+        );
+        assert_eq!(
+            find_thumbnail_source(&state, &thumbnail_path.with_file_name("ep2.jpg")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_nearest_width_picks_single_configured_size() {
+        let sizes = [160, 480, 960];
+        assert_eq!(nearest_width(Some(200), &sizes), Some(160));
+        assert_eq!(nearest_width(Some(700), &sizes), Some(480));
+        assert_eq!(nearest_width(None, &sizes), None);
+        assert_eq!(nearest_width(Some(200), &[]), None);
+    }
+
+    #[test]
+    fn test_select_thumbnail_picks_nearest_generated_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbnail_path = dir.path().join("a.jpg");
+        std::fs::write(&thumbnail_path, b"default").unwrap();
+        for width in [160, 480] {
+            let variant = thumbnail_variant_path(&thumbnail_path, width);
+            std::fs::create_dir_all(variant.parent().unwrap()).unwrap();
+            std::fs::write(&variant, b"variant").unwrap();
+        }
+
+        let sizes = [160, 480, 960];
+        assert_eq!(
+            select_thumbnail(&thumbnail_path, Some(200), &sizes),
+            thumbnail_variant_path(&thumbnail_path, 160)
+        );
+        assert_eq!(
+            select_thumbnail(&thumbnail_path, Some(400), &sizes),
+            thumbnail_variant_path(&thumbnail_path, 480)
+        );
+        // 960 未生成，回退到最接近的 480
+        assert_eq!(
+            select_thumbnail(&thumbnail_path, Some(1000), &sizes),
+            thumbnail_variant_path(&thumbnail_path, 480)
+        );
+        assert_eq!(
+            select_thumbnail(&thumbnail_path, None, &sizes),
+            thumbnail_path
+        );
+        assert_eq!(
+            select_thumbnail(&thumbnail_path, Some(160), &[]),
+            thumbnail_path
+        );
+    }
+}
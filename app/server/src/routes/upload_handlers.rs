@@ -0,0 +1,706 @@
+//! 文件上传相关的 API 处理器
+
+use axum::{
+    body::Body,
+    extract::{
+        multipart::{Field, MultipartError},
+        Multipart, Path as UrlPath, Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::ThumbnailStrategy;
+use crate::models::VideoInfo;
+use crate::routes::auth::require_api_key;
+use crate::routes::error::db_error;
+use crate::services::db::{DirectorySync, VideoDao};
+use crate::services::ffmpeg::get_ffmpeg_service;
+use crate::services::task_queue::{get_task_queue, TaskPriority, TaskType};
+use crate::services::upload::{ChunkedUploads, PartFile, UploadSession, MAX_CHUNK_COUNT};
+use crate::utils::media_types::classify;
+use crate::utils::{db_path, fill_web_fields, resolve_media_path};
+use crate::AppState;
+
+/// 上传文件（`multipart/form-data`）
+///
+/// 字段：
+/// - `path`：目标目录的路由路径或物理路径，必须在 `file` 之前
+/// - `file`：文件内容，文件名取自表单中的文件名
+///
+/// 文件以流的方式写入磁盘，不会整体缓存在内存中。写入完成后插入数据库记录，
+/// 并为视频提交缩略图生成任务，返回 201 和新记录（需要 API Key）
+pub async fn upload_video(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Response, Response> {
+    // 在读取请求体之前校验，未授权的请求不会写入任何文件
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+    let max_bytes = state.config.upload.max_bytes;
+    let mut target_dir: Option<PathBuf> = None;
+    let mut uploaded: Option<PathBuf> = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        match field.name() {
+            Some("path") => {
+                let path = field.text().await.map_err(multipart_error)?;
+                let dir = resolve_media_path(&path, &state.data_source_dirs)
+                    .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+                if !dir.is_dir() {
+                    return Err((StatusCode::NOT_FOUND, "Directory not found").into_response());
+                }
+                target_dir = Some(dir);
+            }
+            Some("file") => {
+                let dir = target_dir.as_ref().ok_or_else(|| {
+                    (StatusCode::BAD_REQUEST, "path must be sent before file").into_response()
+                })?;
+                let name = field
+                    .file_name()
+                    .and_then(sanitize_file_name)
+                    .ok_or_else(|| {
+                        (StatusCode::BAD_REQUEST, "Invalid file name").into_response()
+                    })?;
+                if classify(Path::new(&name)).is_none() {
+                    return Err(
+                        (StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported file type")
+                            .into_response(),
+                    );
+                }
+
+                let destination = dir.join(&name);
+                if destination.exists() {
+                    return Err((StatusCode::CONFLICT, "File already exists").into_response());
+                }
+                save_field(field, &destination, max_bytes).await?;
+                uploaded = Some(destination);
+            }
+            // 忽略其他字段
+            _ => {}
+        }
+    }
+
+    let file_path =
+        uploaded.ok_or_else(|| (StatusCode::BAD_REQUEST, "Missing file").into_response())?;
+    info!("文件上传完成: {}", file_path.display());
+
+    let video = register_upload(&state, file_path).await?;
+    Ok((StatusCode::CREATED, Json(video)).into_response())
+}
+
+/// 分片上传初始化请求
+#[derive(Deserialize, Debug)]
+pub struct InitUploadRequest {
+    /// 目标目录的路由路径或物理路径
+    pub path: String,
+    pub file_name: String,
+    /// 文件总字节数
+    pub size: u64,
+    /// 每个分片的字节数
+    pub chunk_size: u64,
+}
+
+/// 分片上传请求参数
+#[derive(Deserialize, Debug)]
+pub struct ChunkParams {
+    pub index: u64,
+}
+
+/// 分片上传进度
+#[derive(Serialize, Debug)]
+pub struct UploadStatus {
+    pub id: String,
+    pub file_name: String,
+    pub size: u64,
+    pub chunk_size: u64,
+    pub chunk_count: u64,
+    /// 已收到的分片序号
+    pub received: Vec<u64>,
+}
+
+/// 创建分片上传会话，返回上传 id
+///
+/// 会话信息保存在磁盘上，服务重启后可以通过 `GET /api/upload/:id` 查询进度继续上传。
+/// 分片上传的每个接口都需要 API Key，上传 id 不能代替 Key
+pub async fn init_upload(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<InitUploadRequest>,
+) -> Result<Json<UploadStatus>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+    let max_bytes = state.config.upload.max_bytes;
+    if request.size > max_bytes {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, "File too large").into_response());
+    }
+    // 分片过小会产生大量分片文件；只有一个分片的小文件不受最小值限制
+    let min_chunk_size = state.config.upload.min_chunk_bytes.max(1);
+    if request.chunk_size > max_bytes
+        || (request.chunk_size < min_chunk_size && request.chunk_size < request.size)
+        || request.chunk_size == 0
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("chunk_size must be at least {} bytes", min_chunk_size),
+        )
+            .into_response());
+    }
+    if request.size.div_ceil(request.chunk_size) > MAX_CHUNK_COUNT {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Too many chunks (max {})", MAX_CHUNK_COUNT),
+        )
+            .into_response());
+    }
+    let dir = resolve_media_path(&request.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !dir.is_dir() {
+        return Err((StatusCode::NOT_FOUND, "Directory not found").into_response());
+    }
+    let name = sanitize_file_name(&request.file_name)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid file name").into_response())?;
+    if classify(Path::new(&name)).is_none() {
+        return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported file type").into_response());
+    }
+    if dir.join(&name).exists() {
+        return Err((StatusCode::CONFLICT, "File already exists").into_response());
+    }
+
+    let uploads = chunked_uploads(&state);
+    let session = uploads
+        .init(dir, name, request.size, request.chunk_size)
+        .await
+        .map_err(upload_error)?;
+    info!(
+        "创建分片上传: {} ({} 个分片)",
+        session.id,
+        session.chunk_count()
+    );
+    Ok(Json(upload_status(&session, Vec::new())))
+}
+
+/// 查询分片上传进度
+pub async fn get_upload(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Json<UploadStatus>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+    let uploads = chunked_uploads(&state);
+    let session = uploads.session(&id).await.map_err(upload_error)?;
+    let received = uploads
+        .received_chunks(&session)
+        .await
+        .map_err(upload_error)?;
+    Ok(Json(upload_status(&session, received)))
+}
+
+/// 上传一个分片（请求体为分片内容），分片可以乱序上传
+pub async fn upload_chunk(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    UrlPath(id): UrlPath<String>,
+    Query(params): Query<ChunkParams>,
+    body: Body,
+) -> Result<Json<UploadStatus>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+    let uploads = chunked_uploads(&state);
+    let session = uploads.session(&id).await.map_err(upload_error)?;
+    uploads
+        .write_chunk(&session, params.index, body.into_data_stream())
+        .await
+        .map_err(upload_error)?;
+    let received = uploads
+        .received_chunks(&session)
+        .await
+        .map_err(upload_error)?;
+    Ok(Json(upload_status(&session, received)))
+}
+
+/// 完成分片上传：检查分片是否齐全，拼接后移动到目标目录，返回 201 和新记录
+pub async fn complete_upload(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Response, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+    let uploads = chunked_uploads(&state);
+    let session = uploads.session(&id).await.map_err(upload_error)?;
+    let file_path = uploads.complete(&session).await.map_err(upload_error)?;
+    info!("分片上传完成: {}", file_path.display());
+
+    let video = register_upload(&state, file_path).await?;
+    Ok((StatusCode::CREATED, Json(video)).into_response())
+}
+
+/// 为上传完成的文件插入数据库记录，视频文件提交缩略图生成任务
+async fn register_upload(state: &Arc<AppState>, file_path: PathBuf) -> Result<VideoInfo, Response> {
+    let task_state = Arc::clone(state);
+    let register_path = file_path.clone();
+    let video = tokio::task::spawn_blocking(move || {
+        let db_manager = task_state.db_manager.lock().unwrap();
+        DirectorySync::new(&db_manager)
+            .register_file(&register_path, &task_state.data_source_dirs)?;
+        VideoDao::new(&db_manager).get_video_by_path(&db_path(&register_path))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .map_err(db_error)?;
+    let mut video = video.ok_or_else(|| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Uploaded file was not registered",
+        )
+            .into_response()
+    })?;
+
+    if get_ffmpeg_service().thumbnail_strategy(&file_path) != ThumbnailStrategy::Placeholder {
+        let task_type = TaskType::GenerateThumbnail {
+            thumbnail_path: DirectorySync::get_thumbnail_path(&file_path),
+            video_path: file_path,
+        };
+        get_task_queue()
+            .enqueue(task_type, TaskPriority::High)
+            .await;
+    }
+
+    fill_web_fields(std::slice::from_mut(&mut video), &state.data_source_dirs);
+    Ok(video)
+}
+
+/// 按配置创建分片上传存储
+fn chunked_uploads(state: &AppState) -> ChunkedUploads {
+    ChunkedUploads::new(&state.config.upload.chunk_dir)
+        .with_ttl(Duration::from_secs(state.config.upload.session_ttl_secs))
+}
+
+fn upload_status(session: &UploadSession, received: Vec<u64>) -> UploadStatus {
+    UploadStatus {
+        id: session.id.clone(),
+        file_name: session.file_name.clone(),
+        size: session.total_size,
+        chunk_size: session.chunk_size,
+        chunk_count: session.chunk_count(),
+        received,
+    }
+}
+
+/// 将分片上传的 IO 错误转换为响应
+fn upload_error(e: std::io::Error) -> Response {
+    let status = match e.kind() {
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+        ErrorKind::AlreadyExists | ErrorKind::InvalidData => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    if status == StatusCode::INTERNAL_SERVER_ERROR {
+        warn!("分片上传失败: {}", e);
+    }
+    (status, e.to_string()).into_response()
+}
+
+/// 将表单字段流式写入临时文件，完成后移动为目标文件
+///
+/// 超过 `max_bytes`、读取失败或客户端中途断开时删除临时文件；
+/// 目标文件在此期间被其他上传创建时返回 409，不覆盖已有文件
+async fn save_field(
+    mut field: Field<'_>,
+    destination: &Path,
+    max_bytes: u64,
+) -> Result<(), Response> {
+    let io_error = |e: std::io::Error| {
+        if e.kind() == ErrorKind::AlreadyExists {
+            return (StatusCode::CONFLICT, "File already exists").into_response();
+        }
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("File error: {}", e),
+        )
+            .into_response()
+    };
+
+    let (part, mut file) = PartFile::create(destination).await.map_err(io_error)?;
+    let mut written: u64 = 0;
+    while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+        written += chunk.len() as u64;
+        if written > max_bytes {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, "File too large").into_response());
+        }
+        file.write_all(&chunk).await.map_err(io_error)?;
+    }
+    file.flush().await.map_err(io_error)?;
+    drop(file);
+
+    part.persist(destination).await.map_err(io_error)
+}
+
+/// 清理上传的文件名：去掉目录部分，拒绝空名称、隐藏文件和包含控制字符的名称
+fn sanitize_file_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    if name.is_empty() || name.starts_with('.') || name.chars().any(|c| c.is_control() || c == ':')
+    {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+fn multipart_error(e: MultipartError) -> Response {
+    (e.status(), e.body_text()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::models::ListParams;
+    use crate::routes::auth::API_KEY_HEADER;
+    use crate::routes::video_handlers::list_videos;
+    use crate::test_utils::{response_json, test_state_with_config};
+    use crate::DiskMapping;
+    use axum::extract::{FromRequest, Request};
+    use axum::http::HeaderValue;
+    use axum::routing::{get, post, put};
+    use axum::Router;
+    use tower::Service;
+
+    const BOUNDARY: &str = "upload-test-boundary";
+
+    fn multipart_request(path: &str, file_name: &str, content: &[u8]) -> Request {
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"path\"\r\n\r\n{path}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            b = BOUNDARY
+        )
+        .into_bytes();
+        body.extend_from_slice(content);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+        Request::builder()
+            .method("POST")
+            .uri("/api/upload")
+            .header(
+                "content-type",
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    fn config() -> AppConfig {
+        AppConfig {
+            api_keys: vec!["secret".to_string()],
+            ..AppConfig::default()
+        }
+    }
+
+    fn api_key_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret"));
+        headers
+    }
+
+    async fn upload(state: &Arc<AppState>, request: Request) -> Result<Response, Response> {
+        let multipart = Multipart::from_request(request, state).await.unwrap();
+        upload_video(State(state.clone()), api_key_headers(), multipart).await
+    }
+
+    #[tokio::test]
+    async fn test_upload_requires_api_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state_with_config(
+            vec![DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: dir.path().to_string_lossy().to_string(),
+            }],
+            config(),
+        );
+
+        let request = multipart_request("/public/disk1", "ep1.vtt", b"WEBVTT\n");
+        let multipart = Multipart::from_request(request, &state).await.unwrap();
+        let response = upload_video(State(state.clone()), HeaderMap::new(), multipart)
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!dir.path().join("ep1.vtt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_uploaded_file_appears_in_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("show")).unwrap();
+        let state = test_state_with_config(
+            vec![DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: dir.path().to_string_lossy().to_string(),
+            }],
+            config(),
+        );
+
+        let content = b"WEBVTT\n\n00:00.000 --> 00:01.000\nhi\n";
+        let response = upload(
+            &state,
+            multipart_request("/public/disk1/show", "../ep1.vtt", content),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response_json(response).await;
+        assert_eq!(body["url"], "/public/disk1/show/ep1.vtt");
+
+        // 文件名中的目录部分被去掉，文件写入目标目录
+        let uploaded = dir.path().join("show").join("ep1.vtt");
+        assert_eq!(std::fs::read(&uploaded).unwrap(), content);
+        // 临时文件已被移走
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("show")).unwrap().count(),
+            1
+        );
+
+        let listing = list_videos(State(state.clone()), Query(ListParams::default()))
+            .await
+            .unwrap();
+        let listing = response_json(listing).await;
+        let videos = listing["videos"].as_array().unwrap();
+        assert_eq!(videos.len(), 1);
+        assert_eq!(videos[0]["name"], "ep1.vtt");
+        assert_eq!(videos[0]["type"], "subtitle");
+
+        // 同名文件、不支持的类型和数据源之外的目录都被拒绝
+        let conflict = upload(
+            &state,
+            multipart_request("/public/disk1/show", "ep1.vtt", b"x"),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(conflict.status(), StatusCode::CONFLICT);
+        let unsupported = upload(
+            &state,
+            multipart_request("/public/disk1/show", "notes.txt", b"x"),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(unsupported.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let outside = upload(&state, multipart_request("/elsewhere", "a.vtt", b"x"))
+            .await
+            .unwrap_err();
+        assert_eq!(outside.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fn json_request(method: &str, uri: &str, body: Body) -> Request {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .header(API_KEY_HEADER, "secret")
+            .body(body)
+            .unwrap()
+    }
+
+    fn chunked_upload_app(state: Arc<AppState>) -> Router {
+        Router::new()
+            .route("/api/upload/init", post(init_upload))
+            .route("/api/upload/:id", get(get_upload))
+            .route("/api/upload/:id/chunk", put(upload_chunk))
+            .route("/api/upload/:id/complete", post(complete_upload))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_chunked_upload_requires_api_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = config();
+        config.upload.chunk_dir = dir.path().join("uploads").to_string_lossy().to_string();
+        let state = test_state_with_config(
+            vec![DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: dir.path().to_string_lossy().to_string(),
+            }],
+            config,
+        );
+        let mut app = chunked_upload_app(state);
+
+        let init = serde_json::json!({
+            "path": "/public/disk1",
+            "file_name": "ep1.srt",
+            "size": 4,
+            "chunk_size": 4,
+        });
+        let response = app
+            .call(json_request(
+                "POST",
+                "/api/upload/init",
+                Body::from(init.to_string()),
+            ))
+            .await
+            .unwrap();
+        let id = response_json(response).await["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        // 知道上传 id 也不能在没有 API Key 的情况下继续上传
+        let without_key = |method: &str, uri: String, body: Body| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(body)
+                .unwrap()
+        };
+        for request in [
+            without_key(
+                "POST",
+                "/api/upload/init".to_string(),
+                Body::from(init.to_string()),
+            ),
+            without_key("GET", format!("/api/upload/{}", id), Body::empty()),
+            without_key(
+                "PUT",
+                format!("/api/upload/{}/chunk?index=0", id),
+                Body::from("0123"),
+            ),
+            without_key(
+                "POST",
+                format!("/api/upload/{}/complete", id),
+                Body::empty(),
+            ),
+        ] {
+            let uri = request.uri().to_string();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", uri);
+        }
+        assert!(!dir.path().join("ep1.srt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_upload_out_of_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let media = dir.path().join("media");
+        std::fs::create_dir(&media).unwrap();
+        let mut config = config();
+        config.upload.chunk_dir = dir.path().join("uploads").to_string_lossy().to_string();
+        config.upload.min_chunk_bytes = 4;
+        let state = test_state_with_config(
+            vec![DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: media.to_string_lossy().to_string(),
+            }],
+            config,
+        );
+        let mut app = chunked_upload_app(state);
+
+        // 小于最小值的分片被拒绝，避免产生大量分片
+        let tiny = serde_json::json!({
+            "path": "/public/disk1",
+            "file_name": "ep1.srt",
+            "size": 10,
+            "chunk_size": 1,
+        });
+        let response = app
+            .call(json_request(
+                "POST",
+                "/api/upload/init",
+                Body::from(tiny.to_string()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let init = serde_json::json!({
+            "path": "/public/disk1",
+            "file_name": "ep1.srt",
+            "size": 10,
+            "chunk_size": 4,
+        });
+        let response = app
+            .call(json_request(
+                "POST",
+                "/api/upload/init",
+                Body::from(init.to_string()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_json(response).await;
+        assert_eq!(body["chunk_count"], 3);
+        let id = body["id"].as_str().unwrap().to_string();
+
+        for (index, data) in [(2, "89"), (0, "0123")] {
+            let uri = format!("/api/upload/{}/chunk?index={}", id, index);
+            let response = app
+                .call(json_request("PUT", &uri, Body::from(data)))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // 缺少分片时不能完成
+        let complete_uri = format!("/api/upload/{}/complete", id);
+        let response = app
+            .call(json_request("POST", &complete_uri, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let uri = format!("/api/upload/{}/chunk?index=1", id);
+        app.call(json_request("PUT", &uri, Body::from("4567")))
+            .await
+            .unwrap();
+        let response = app
+            .call(json_request(
+                "GET",
+                &format!("/api/upload/{}", id),
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            response_json(response).await["received"],
+            serde_json::json!([0, 1, 2])
+        );
+
+        let response = app
+            .call(json_request("POST", &complete_uri, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response_json(response).await["url"],
+            "/public/disk1/ep1.srt"
+        );
+        assert_eq!(std::fs::read(media.join("ep1.srt")).unwrap(), b"0123456789");
+
+        // 会话完成后即被删除
+        let response = app
+            .call(json_request("POST", &complete_uri, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("a.mp4").as_deref(), Some("a.mp4"));
+        assert_eq!(sanitize_file_name("C:\\x\\a.mp4").as_deref(), Some("a.mp4"));
+        assert_eq!(sanitize_file_name("../../a.mp4").as_deref(), Some("a.mp4"));
+        assert_eq!(sanitize_file_name(".."), None);
+        assert_eq!(sanitize_file_name(".hidden.mp4"), None);
+        assert_eq!(sanitize_file_name("dir/"), None);
+        assert_eq!(sanitize_file_name("a\nb.mp4"), None);
+    }
+}
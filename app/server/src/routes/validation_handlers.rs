@@ -0,0 +1,126 @@
+//! 媒体库校验相关的 API 处理器
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::routes::error::db_error;
+use crate::services::task_queue::{get_task_queue, TaskPriority, TaskType};
+use crate::services::validation::get_validation_tracker;
+use crate::services::VideoDao;
+use crate::utils::map_physical_to_web;
+use crate::AppState;
+
+/// 单次校验默认检查的文件数
+const DEFAULT_VALIDATE_LIMIT: u32 = 100;
+/// 单次校验最多检查的文件数
+const MAX_VALIDATE_LIMIT: u32 = 10_000;
+
+/// 校验请求参数
+#[derive(Deserialize, Debug)]
+pub struct ValidateParams {
+    /// 最多检查的文件数，默认 100
+    pub limit: Option<u32>,
+}
+
+/// 解码出错的文件
+#[derive(Serialize, Debug)]
+pub struct InvalidFileResponse {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub error: String,
+}
+
+/// 校验报告响应
+#[derive(Serialize, Debug)]
+pub struct ValidationReportResponse {
+    pub run_id: u64,
+    pub running: bool,
+    pub total: usize,
+    pub checked: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    pub invalid: Vec<InvalidFileResponse>,
+}
+
+/// 开始校验媒体库：为最多 `limit` 个视频提交解码检查任务（低优先级，后台执行）
+pub async fn start_validation(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ValidateParams>,
+) -> Result<Response, Response> {
+    let limit = params.limit.unwrap_or(DEFAULT_VALIDATE_LIMIT);
+    if limit == 0 || limit > MAX_VALIDATE_LIMIT {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {}", MAX_VALIDATE_LIMIT),
+        )
+            .into_response());
+    }
+
+    let paths = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_video_paths(limit)
+            .map_err(db_error)?
+    };
+
+    let run_id = get_validation_tracker()
+        .try_start(paths.len())
+        .ok_or_else(|| (StatusCode::CONFLICT, "A validation is already running").into_response())?;
+    let queue = get_task_queue();
+    for path in &paths {
+        let task_type = TaskType::ValidateFile {
+            video_path: PathBuf::from(path),
+            run_id,
+        };
+        queue.enqueue(task_type, TaskPriority::Low).await;
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "success": true,
+            "run_id": run_id,
+            "queued": paths.len(),
+            "report_url": "/api/validate/report"
+        })),
+    )
+        .into_response())
+}
+
+/// 获取当前（或最近一次）校验的报告
+pub async fn get_validation_report(
+    State(state): State<Arc<AppState>>,
+) -> Json<ValidationReportResponse> {
+    let report = get_validation_tracker().report();
+
+    Json(ValidationReportResponse {
+        run_id: report.run_id,
+        running: report.is_running(),
+        total: report.total,
+        checked: report.checked,
+        started_at: report.started_at,
+        finished_at: report.finished_at,
+        invalid: report
+            .invalid
+            .into_iter()
+            .map(|file| {
+                let path = file.path.to_string_lossy().to_string();
+                InvalidFileResponse {
+                    url: map_physical_to_web(&path, &state.data_source_dirs),
+                    path,
+                    error: file.error,
+                }
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,78 @@
+//! API 版本相关的处理器
+//!
+//! 所有 `/api` 响应都带有 `X-API-Version` 头，客户端可据此检测不兼容的变更。
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use serde::Serialize;
+
+/// API 版本号，响应结构发生不兼容变更时递增
+pub const API_VERSION: &str = "1";
+
+/// 服务端版本号（Cargo.toml 中的 version）
+pub const SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// API 版本响应头
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// 版本信息响应
+#[derive(Serialize)]
+pub struct VersionResponse {
+    pub api_version: &'static str,
+    pub server_version: &'static str,
+}
+
+/// 获取服务端和 API 版本
+pub async fn get_version() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        api_version: API_VERSION,
+        server_version: SERVER_VERSION,
+    })
+}
+
+/// 中间件：为 `/api` 下的响应添加 `X-API-Version` 头
+pub async fn add_api_version_header(request: Request, next: Next) -> Response {
+    let is_api = request.uri().path().starts_with("/api/");
+    let mut response = next.run(request).await;
+
+    if is_api {
+        response.headers_mut().insert(
+            HeaderName::from_static(API_VERSION_HEADER),
+            HeaderValue::from_static(API_VERSION),
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::Service;
+
+    #[tokio::test]
+    async fn test_api_responses_carry_version_header() {
+        let mut app = Router::new()
+            .route("/api/version", get(get_version))
+            .layer(middleware::from_fn(add_api_version_header));
+
+        let request = Request::builder()
+            .uri("/api/version")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+
+        assert_eq!(
+            response.headers().get(API_VERSION_HEADER).unwrap(),
+            API_VERSION
+        );
+        let body = crate::test_utils::response_json(response).await;
+        assert_eq!(body["api_version"], API_VERSION);
+        assert_eq!(body["server_version"], SERVER_VERSION);
+    }
+}
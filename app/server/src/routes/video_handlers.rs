@@ -1,38 +1,103 @@
 use axum::{
+    body::{Body, Bytes},
+    extract::Path as UrlPath,
     extract::Query,
     extract::State,
-    http::StatusCode,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use log::{error, info};
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
-use crate::models::{PaginatedVideoList, PaginationParams, VideoList};
-use crate::services::{DirectorySync, VideoDao};
+use crate::models::{
+    DeletionLogEntry, FieldSet, FlatListParams, ListParams, PaginatedVideoList, PaginationParams,
+    PathParams, SiblingsParams, Storyboard, VideoInfo, VideoInfoLight, VideoList, VideoSiblings,
+};
+use crate::routes::auth::require_api_key;
+use crate::routes::error::db_error;
+use crate::routes::tag_handlers::normalize_tag;
+use crate::services::db::schema::video_types;
+use crate::services::ffmpeg::{
+    find_playlist, get_ffmpeg_service, hls_output_dir, load_storyboard_layout, merged_output_path,
+    storyboard_path, Chapter, StoryboardLayout, HLS_SEGMENT_SECS,
+};
+use crate::services::filesystem::remove_empty_parent_dirs;
+use crate::services::task_queue::{get_task_queue, TaskPriority, TaskQueue, TaskResult, TaskType};
+use crate::services::{
+    AuditReport, DirectorySync, DirectoryTotals, ListCursor, RefreshedFile, TopBy, VideoDao,
+    VideoFilter, VideoSort,
+};
+use crate::utils::media_types::{self, MediaKind};
+use crate::utils::{
+    absolute_url, all_sources_missing, content_type_for, db_path, file_checksum, fill_web_fields,
+    format_duration, format_size, is_video_or_container, map_physical_to_web, parse_date_end,
+    parse_date_start, parse_duration, resolve_media_path, try_acquire_checksum_permit,
+    ChecksumAlgo,
+};
 use crate::AppState;
 
 /// 列出 public 目录下的所有视频文件和目录（从数据库查询）
-pub async fn list_videos(State(state): State<Arc<AppState>>) -> Result<Json<VideoList>, Response> {
+pub async fn list_videos(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ListParams>,
+) -> Result<Response, Response> {
+    let file_type =
+        validate_file_type(params.file_type.as_deref()).map_err(IntoResponse::into_response)?;
+
+    // 数据源目录尚未创建时返回空列表
+    if all_sources_missing(&state.data_source_dirs) {
+        return Ok(Json(VideoList::<VideoInfo> { videos: Vec::new() }).into_response());
+    }
+
     let db_manager = state.db_manager.lock().unwrap();
     let video_dao = VideoDao::new(&db_manager);
 
-    let videos = video_dao.get_root_videos().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database error: {}", e),
-        )
-            .into_response()
-    })?;
+    let mut videos = video_dao.get_root_videos().map_err(db_error)?;
+    match &file_type {
+        Some(file_type) => videos.retain(|video| &video.r#type == file_type),
+        None if !params.include_unknown => {
+            videos.retain(|video| video.r#type != video_types::UNKNOWN)
+        }
+        None => {}
+    }
+    if state.config.listing.hide_metadata_errors {
+        videos.retain(|video| video.error.is_none());
+    }
+    if let Some(watched) = params.watched {
+        videos.retain(|video| video.watched == watched);
+    }
+    if let Some(favorite) = params.favorite {
+        videos.retain(|video| video.favorite == favorite);
+    }
+    if let Some(tag) = params.tag.as_deref().and_then(normalize_tag) {
+        let tagged = video_dao.get_tagged_paths(&tag).map_err(db_error)?;
+        videos.retain(|video| tagged.contains(&video.path));
+    }
+    if params.include_totals {
+        let totals = video_dao.get_directory_totals().map_err(db_error)?;
+        fill_directory_totals(&mut videos, &totals);
+    }
+    fill_web_fields(&mut videos, &state.data_source_dirs);
 
-    Ok(Json(VideoList { videos }))
+    match params.fields {
+        FieldSet::Full => Ok(Json(VideoList { videos }).into_response()),
+        FieldSet::Light => {
+            let videos: Vec<VideoInfoLight> = videos.into_iter().map(Into::into).collect();
+            Ok(Json(VideoList { videos }).into_response())
+        }
+    }
 }
 
 /// 列出 public 目录下的所有视频文件和目录（从数据库查询）- 支持分页
 pub async fn list_videos_paginated(
     State(state): State<Arc<AppState>>,
     Query(params): Query<PaginationParams>,
-) -> Result<Json<PaginatedVideoList>, Response> {
+) -> Result<Response, Response> {
     // 验证分页参数
     if params.page == 0 {
         return Err((
@@ -51,31 +116,186 @@ pub async fn list_videos_paginated(
         return Err((StatusCode::BAD_REQUEST, "Page size cannot exceed 1000").into_response());
     }
 
+    let sort = VideoSort::parse(
+        params.sort_by.as_deref(),
+        params.sort_order.as_deref(),
+        &VideoSort::configured(&state.config.listing),
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+
+    // 数据源目录尚未创建时返回空列表
+    if all_sources_missing(&state.data_source_dirs) {
+        let empty: PaginatedVideoList = PaginatedVideoList::empty(params.page, params.page_size);
+        return Ok(Json(empty).into_response());
+    }
+
+    let file_type =
+        validate_file_type(params.file_type.as_deref()).map_err(IntoResponse::into_response)?;
+    let filter = VideoFilter {
+        search: params.search.clone(),
+        created_after: parse_date_param(params.created_after.as_deref(), parse_date_start)
+            .map_err(IntoResponse::into_response)?,
+        created_before: parse_date_param(params.created_before.as_deref(), parse_date_end)
+            .map_err(IntoResponse::into_response)?,
+        file_type,
+        watched: params.watched,
+        favorite: params.favorite,
+        tag: params.tag.as_deref().and_then(normalize_tag),
+        include_unknown: params.include_unknown,
+        exclude_errors: state.config.listing.hide_metadata_errors,
+    };
+
+    let cursor = params
+        .cursor
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .map(|c| ListCursor::decode(c, &sort))
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+
     let db_manager = state.db_manager.lock().unwrap();
     let video_dao = VideoDao::new(&db_manager);
 
-    let paginated_videos = video_dao
-        .get_root_videos_paginated(
-            params.page,
-            params.page_size,
-            params.search.as_deref(),
-            params.sort_by.as_deref(),
-            params.sort_order.as_deref(),
-        )
-        .map_err(|e| {
+    let page = match &cursor {
+        Some(cursor) => {
+            video_dao.get_root_videos_after(Some(cursor), params.page_size, &filter, &sort)
+        }
+        None => video_dao.get_root_videos_paginated(params.page, params.page_size, &filter, &sort),
+    };
+    let mut paginated_videos = page.map_err(db_error)?;
+    if params.include_totals {
+        let totals = video_dao.get_directory_totals().map_err(db_error)?;
+        fill_directory_totals(&mut paginated_videos.videos, &totals);
+    }
+    fill_web_fields(&mut paginated_videos.videos, &state.data_source_dirs);
+
+    match params.fields {
+        FieldSet::Full => Ok(Json(paginated_videos).into_response()),
+        FieldSet::Light => Ok(Json(paginated_videos.into_light()).into_response()),
+    }
+}
+
+/// 为目录填充 total_duration 和 total_size（不包含视频的目录为 0）
+fn fill_directory_totals(videos: &mut [VideoInfo], totals: &HashMap<String, DirectoryTotals>) {
+    for video in videos.iter_mut().filter(|video| {
+        video.r#type == video_types::DIRECTORY || video.r#type == video_types::HLS_DIRECTORY
+    }) {
+        let total = totals.get(&video.path).copied().unwrap_or_default();
+        video.total_duration = Some(format_duration(total.duration));
+        video.total_size = Some(format_size(total.size));
+    }
+}
+
+/// 校验可选的类型参数，未知类型返回 400
+fn validate_file_type(value: Option<&str>) -> Result<Option<String>, (StatusCode, String)> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) if video_types::is_known(v) => Ok(Some(v.to_string())),
+        Some(v) => Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unknown type: {} (expected one of {})",
+                v,
+                video_types::ALL.join(", ")
+            ),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// 解析可选的日期参数，格式错误时返回 400
+fn parse_date_param(
+    value: Option<&str>,
+    parse: fn(&str) -> Option<i64>,
+) -> Result<Option<i64>, (StatusCode, String)> {
+    match value.filter(|v| !v.trim().is_empty()) {
+        Some(v) => parse(v).map(Some).ok_or_else(|| {
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Database error: {}", e),
+                StatusCode::BAD_REQUEST,
+                format!("Invalid date: {} (expected YYYY-MM-DD)", v),
             )
-                .into_response()
-        })?;
+        }),
+        None => Ok(None),
+    }
+}
+
+/// NDJSON 流式列表每批从数据库读取的记录数
+const STREAM_BATCH_SIZE: u32 = 500;
+
+/// 以 NDJSON 流式输出所有记录（每行一个 VideoInfo），适用于超大媒体库
+///
+/// 按 id 分批读取，每批之间释放数据库锁，客户端读取较慢时不会长时间阻塞其他请求
+pub async fn stream_videos(State(state): State<Arc<AppState>>) -> Response {
+    // 数据源目录尚未创建时返回空列表
+    if all_sources_missing(&state.data_source_dirs) {
+        return ndjson_response(Body::empty());
+    }
+
+    let (tx, mut rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(64);
+    tokio::task::spawn_blocking(move || {
+        let mut after_id = 0;
+        loop {
+            let batch = {
+                let db_manager = state.db_manager.lock().unwrap();
+                VideoDao::new(&db_manager).get_videos_after_id(after_id, STREAM_BATCH_SIZE)
+            };
+            let mut videos = match batch {
+                Ok(videos) => videos,
+                Err(e) => {
+                    error!("Failed to stream videos: {}", e);
+                    let _ = tx.blocking_send(Err(std::io::Error::other(e.to_string())));
+                    return;
+                }
+            };
+            fill_web_fields(&mut videos, &state.data_source_dirs);
+
+            for video in &videos {
+                let mut line = match serde_json::to_vec(video) {
+                    Ok(line) => line,
+                    Err(e) => {
+                        warn!("Failed to serialize video {}: {}", video.path, e);
+                        continue;
+                    }
+                };
+                line.push(b'\n');
+                // 客户端已断开
+                if tx.blocking_send(Ok(Bytes::from(line))).is_err() {
+                    return;
+                }
+            }
+
+            match videos.last() {
+                Some(last) if videos.len() == STREAM_BATCH_SIZE as usize => after_id = last.id,
+                _ => return,
+            }
+        }
+    });
+
+    let stream = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    ndjson_response(Body::from_stream(stream))
+}
 
-    Ok(Json(paginated_videos))
+/// 设置 NDJSON 的 Content-Type
+fn ndjson_response(body: Body) -> Response {
+    let mut response = body.into_response();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+    response
+}
+
+/// 同步请求参数
+#[derive(serde::Deserialize, Default)]
+pub struct SyncParams {
+    /// 只计算将要进行的变更，不写入数据库
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 /// Synchronize database with file system
 pub async fn sync_videos(
     State(state): State<Arc<AppState>>,
+    Query(params): Query<SyncParams>,
 ) -> Result<Json<serde_json::Value>, Response> {
     // 开始时间
     let start = std::time::Instant::now();
@@ -84,23 +304,30 @@ pub async fn sync_videos(
 
     let data_source_dirs = Arc::clone(&state.data_source_dirs);
 
-    match sync.initialize_from_directory_with_progress(&data_source_dirs, false) {
-        Ok(_) => {
+    match sync.initialize_from_directory_with_progress(&data_source_dirs, false, params.dry_run) {
+        Ok(report) if report.dry_run => Ok(Json(serde_json::json!({
+            "success": true,
+            "dry_run": true,
+            "new": report.new,
+            "changed": report.changed,
+            "deleted": report.deleted,
+            "skipped": report.skipped,
+            "unreadable": report.unreadable,
+            "examples": report.examples,
+        }))),
+        Ok(report) => {
             // Get updated count
             let video_dao = VideoDao::new(&db_manager);
-            let videos = video_dao.get_root_videos().map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Database error: {}", e),
-                )
-                    .into_response()
-            })?;
+            let videos = video_dao.get_root_videos().map_err(db_error)?;
             let elapsed = start.elapsed();
             info!("同步消耗时间:{:?}", elapsed);
+            let unreadable = report.unreadable;
+            *state.last_sync.lock().unwrap() = Some(report);
             Ok(Json(serde_json::json!({
                 "success": true,
                 "message": "同步完成",
-                "count": videos.len()
+                "count": videos.len(),
+                "unreadable": unreadable
             })))
         }
         Err(e) => Err((
@@ -111,6 +338,60 @@ pub async fn sync_videos(
     }
 }
 
+/// 获取最近一次同步的结果
+pub async fn get_sync_status(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    match state.last_sync.lock().unwrap().as_ref() {
+        Some(report) => Json(serde_json::json!({
+            "synced": true,
+            "new": report.new,
+            "changed": report.changed,
+            "deleted": report.deleted,
+            "skipped": report.skipped,
+            "unreadable": report.unreadable,
+            "elapsed_ms": report.elapsed_ms,
+            "finished_at": report.finished_at,
+            "message": if report.unreadable > 0 {
+                format!("{} entries unreadable", report.unreadable)
+            } else {
+                "ok".to_string()
+            }
+        })),
+        None => Json(serde_json::json!({ "synced": false })),
+    }
+}
+
+/// 比较文件系统与数据库，报告未入库的文件和文件已不存在的记录（不做任何修改）
+///
+/// 只在读取记录时持有数据库锁，遍历磁盘在阻塞线程中执行
+pub async fn audit_videos(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AuditReport>, Response> {
+    let records = {
+        let db_manager = state.db_manager.lock().unwrap();
+        DirectorySync::new(&db_manager)
+            .record_paths()
+            .map_err(db_error)?
+    };
+
+    let task_state = Arc::clone(&state);
+    let report = tokio::task::spawn_blocking(move || {
+        DirectorySync::audit(
+            &task_state.config.scan,
+            &task_state.data_source_dirs,
+            &records,
+        )
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?;
+    Ok(Json(report))
+}
+
 /// 删除视频文件（从数据库和物理文件系统中删除）
 pub async fn delete_video(
     State(state): State<Arc<AppState>>,
@@ -135,11 +416,7 @@ pub async fn delete_video(
         }
         Err(e) => {
             error!("Error checking video existence: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Database error: {}", e),
-            )
-                .into_response());
+            return Err(db_error(e));
         }
     }
 
@@ -151,11 +428,7 @@ pub async fn delete_video(
         }
         Err(e) => {
             error!("Error getting video path: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Database error: {}", e),
-            )
-                .into_response());
+            return Err(db_error(e));
         }
     };
 
@@ -210,27 +483,2627 @@ pub async fn delete_video(
                 }
             }
 
+            // 清理不再包含媒体文件的父目录
+            let removed_dirs =
+                remove_empty_parent_dirs(&video_dao, full_path, &state.data_source_dirs);
+
+            // 记录删除历史，写入失败不影响删除结果
+            for path in std::iter::once(&video_path).chain(&removed_dirs) {
+                let name = Path::new(path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if let Err(e) = video_dao.log_deletion(path, &name) {
+                    error!("Failed to record deletion of {}: {}", path, e);
+                }
+            }
+
             Ok(Json(serde_json::json!({
                 "success": true,
                 "message": "Video deleted successfully",
                 "deleted_files": deleted_files,
+                "removed_directories": removed_dirs,
                 "database_records_deleted": affected_rows
             })))
         }
         Err(e) => {
             error!("Error deleting video from database: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Database error: {}", e),
-            )
-                .into_response())
+            Err(db_error(e))
         }
     }
 }
 
-/// 删除视频请求参数
+/// 获取视频章节（首次请求时通过 ffprobe 读取并缓存到数据库，文件修改后重新读取）
+pub async fn get_video_chapters(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PathParams>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    let path_str = db_path(&file_path);
+    let modified = std::fs::metadata(&file_path)
+        .ok()
+        .filter(|m| m.is_file())
+        .and_then(|m| m.modified().ok())
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "File not found").into_response())?;
+    let modified = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+
+    // 优先使用缓存
+    let cached = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_cached_chapters(&path_str, modified)
+            .map_err(db_error)?
+    };
+    if let Some(chapters) = cached.and_then(|json| serde_json::from_str::<Vec<Chapter>>(&json).ok())
+    {
+        return Ok(Json(
+            serde_json::json!({ "path": path_str, "chapters": chapters }),
+        ));
+    }
+
+    let probe_path = file_path.clone();
+    let probed =
+        tokio::task::spawn_blocking(move || get_ffmpeg_service().try_get_chapters(&probe_path))
+            .await
+            .unwrap_or(None);
+
+    let chapters = match probed {
+        Some(chapters) => {
+            // 仅在 ffprobe 成功时缓存，避免把失败结果永久保存
+            let db_manager = state.db_manager.lock().unwrap();
+            let json = serde_json::to_string(&chapters).unwrap_or_default();
+            if let Err(e) = VideoDao::new(&db_manager).save_chapters(&path_str, &json, modified) {
+                error!("缓存章节信息失败: {} - {}", path_str, e);
+            }
+            chapters
+        }
+        None => Vec::new(),
+    };
+
+    Ok(Json(
+        serde_json::json!({ "path": path_str, "chapters": chapters }),
+    ))
+}
+
+/// 校验和查询参数
 #[derive(serde::Deserialize)]
-pub struct DeleteVideoParams {
-    /// 视频ID
-    pub id: i64,
+pub struct ChecksumParams {
+    /// 文件的路由路径或物理路径
+    pub path: String,
+    /// 算法：sha256（默认）或 crc32
+    #[serde(default = "default_checksum_algo")]
+    pub algo: String,
+}
+
+fn default_checksum_algo() -> String {
+    "sha256".to_string()
+}
+
+/// 计算文件的校验和，用于确认下载的文件完整；文件未修改时返回缓存的结果
+///
+/// 同时计算的数量有上限，已满时返回 503
+pub async fn get_video_checksum(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ChecksumParams>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let algo = ChecksumAlgo::parse(&params.algo)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !file_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "File not found").into_response());
+    }
+
+    let permit = try_acquire_checksum_permit().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many checksum requests in progress",
+        )
+            .into_response()
+    })?;
+    let hash_path = file_path.clone();
+    let checksum = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        file_checksum(&hash_path, algo)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read file: {}", e),
+        )
+            .into_response()
+    })?;
+
+    Ok(Json(serde_json::json!({
+        "path": db_path(&file_path),
+        "algo": algo.as_str(),
+        "digest": checksum.digest,
+        "size": checksum.size,
+        "cached": checksum.cached
+    })))
+}
+
+/// 随机视频查询参数
+#[derive(serde::Deserialize)]
+pub struct RandomVideosParams {
+    /// 返回数量，默认为 10，最大为 MAX_RANDOM_COUNT
+    #[serde(default = "default_random_count")]
+    pub count: u32,
+}
+
+fn default_random_count() -> u32 {
+    10
+}
+
+/// 随机获取视频（"随便看看"）
+pub async fn get_random_videos(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RandomVideosParams>,
+) -> Result<Json<VideoList>, Response> {
+    if params.count == 0 {
+        return Err((StatusCode::BAD_REQUEST, "Count must be greater than 0").into_response());
+    }
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let mut videos = VideoDao::new(&db_manager)
+        .get_random_videos(params.count)
+        .map_err(db_error)?;
+    fill_web_fields(&mut videos, &state.data_source_dirs);
+
+    Ok(Json(VideoList { videos }))
+}
+
+/// 目录下（递归）所有条目的平铺分页列表（不是树形结构），用于表格视图
+pub async fn list_videos_flat(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<FlatListParams>,
+) -> Result<Json<PaginatedVideoList>, Response> {
+    if params.page == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Page number must be greater than 0",
+        )
+            .into_response());
+    }
+    if params.page_size == 0 {
+        return Err((StatusCode::BAD_REQUEST, "Page size must be greater than 0").into_response());
+    }
+    if params.page_size > 1000 {
+        return Err((StatusCode::BAD_REQUEST, "Page size cannot exceed 1000").into_response());
+    }
+
+    let dir_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !dir_path.is_dir() {
+        return Err((StatusCode::NOT_FOUND, "Directory not found").into_response());
+    }
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let mut list = VideoDao::new(&db_manager)
+        .get_descendants_paginated(
+            &db_path(&dir_path),
+            params.page,
+            params.page_size,
+            state.config.listing.hide_metadata_errors,
+        )
+        .map_err(db_error)?;
+    fill_web_fields(&mut list.videos, &state.data_source_dirs);
+
+    Ok(Json(list))
+}
+
+/// 单个目录的树形结构，只查询该目录下的条目，不必重建整棵树
+pub async fn get_video_subtree(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PathParams>,
+) -> Result<Json<VideoList>, Response> {
+    let dir_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !dir_path.is_dir() {
+        return Err((StatusCode::NOT_FOUND, "Directory not found").into_response());
+    }
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let mut videos = VideoDao::new(&db_manager)
+        .get_subtree(
+            &db_path(&dir_path),
+            state.config.listing.hide_metadata_errors,
+        )
+        .map_err(db_error)?;
+    fill_web_fields(&mut videos, &state.data_source_dirs);
+
+    Ok(Json(VideoList { videos }))
+}
+
+/// 按包含的类型查找目录的查询参数
+#[derive(serde::Deserialize)]
+pub struct DirectoriesWithParams {
+    /// 子孙条目的类型，例如 m3u8、mp4、subtitle、hls_directory
+    #[serde(rename = "type")]
+    pub file_type: String,
+}
+
+/// 子孙条目中包含指定类型的所有目录，例如 `?type=m3u8` 返回所有包含 HLS 播放列表的目录
+pub async fn list_directories_with(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<DirectoriesWithParams>,
+) -> Result<Json<VideoList>, Response> {
+    let file_type = validate_file_type(Some(&params.file_type))
+        .map_err(IntoResponse::into_response)?
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Type must not be empty").into_response())?;
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let mut videos = VideoDao::new(&db_manager)
+        .get_directories_containing(&file_type)
+        .map_err(db_error)?;
+    fill_web_fields(&mut videos, &state.data_source_dirs);
+
+    Ok(Json(VideoList { videos }))
+}
+
+/// 删除记录查询参数
+#[derive(serde::Deserialize)]
+pub struct DeletionLogParams {
+    /// 返回数量，默认为 50，最大为 MAX_DELETION_LOG_LIMIT
+    #[serde(default = "default_deletion_log_limit")]
+    pub limit: u32,
+}
+
+fn default_deletion_log_limit() -> u32 {
+    50
+}
+
+/// 最近通过 API 删除的条目，新的在前
+pub async fn get_deletions(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<DeletionLogParams>,
+) -> Result<Json<Vec<DeletionLogEntry>>, Response> {
+    if params.limit == 0 {
+        return Err((StatusCode::BAD_REQUEST, "Limit must be greater than 0").into_response());
+    }
+
+    let db_manager = state.db_manager.lock().unwrap();
+    VideoDao::new(&db_manager)
+        .get_recent_deletions(params.limit, &state.config.display_timezone)
+        .map(Json)
+        .map_err(db_error)
+}
+
+/// 排行榜查询参数
+#[derive(serde::Deserialize)]
+pub struct TopVideosParams {
+    /// 排序依据：duration、size 或 watched
+    pub by: String,
+    /// 返回数量，默认为 10，最大为 MAX_TOP_LIMIT
+    #[serde(default = "default_top_limit")]
+    pub limit: u32,
+}
+
+fn default_top_limit() -> u32 {
+    10
+}
+
+/// 排行榜：时长最长、文件最大或看完次数最多的视频
+pub async fn get_top_videos(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TopVideosParams>,
+) -> Result<Json<VideoList>, Response> {
+    let by = TopBy::parse(&params.by).map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    if params.limit == 0 {
+        return Err((StatusCode::BAD_REQUEST, "Limit must be greater than 0").into_response());
+    }
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let mut videos = VideoDao::new(&db_manager)
+        .get_top(by, params.limit)
+        .map_err(db_error)?;
+    fill_web_fields(&mut videos, &state.data_source_dirs);
+
+    Ok(Json(VideoList { videos }))
+}
+
+/// 详情查询参数
+#[derive(serde::Deserialize)]
+pub struct DetailParams {
+    /// 路由路径或物理路径
+    pub path: String,
+    /// 缩略图足够小时以 data URI 形式内联到 thumbnail 字段
+    #[serde(default)]
+    pub inline_thumbnail: bool,
+    /// 数据库中没有记录但文件存在时（刚添加、还没同步），立即读取并入库
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// 获取单个文件的详细信息，目录会附带其直接子项
+pub async fn get_video_detail(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<DetailParams>,
+) -> Result<Json<VideoInfo>, Response> {
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+
+    let path_str = db_path(&file_path);
+
+    let recorded = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_video_with_children(&path_str)
+            .map_err(db_error)?
+    };
+    let video = match recorded {
+        Some(video) => Some(video),
+        None if params.refresh && is_video_or_container(&file_path) => {
+            // 与 refresh_video 一样，读取元数据时不持有数据库锁
+            let refreshed = scan_refresh(&state, file_path.clone()).await?;
+            let task_state = Arc::clone(&state);
+            tokio::task::spawn_blocking(move || {
+                let db_manager = task_state.db_manager.lock().unwrap();
+                if let Some(refreshed) = &refreshed {
+                    DirectorySync::new(&db_manager).apply_refresh(refreshed)?;
+                }
+                VideoDao::new(&db_manager).get_video_with_children(&path_str)
+            })
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Task error: {}", e),
+                )
+                    .into_response()
+            })?
+            .map_err(db_error)?
+        }
+        None => None,
+    };
+    let mut video =
+        video.ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+    fill_web_fields(std::slice::from_mut(&mut video), &state.data_source_dirs);
+
+    if params.inline_thumbnail {
+        if let Some(thumbnail) = video.thumbnail.as_deref().filter(|t| !t.is_empty()) {
+            let max_bytes = state.config.thumbnail.inline_max_bytes;
+            if let Some(data_uri) = thumbnail_data_uri(Path::new(thumbnail), max_bytes).await {
+                video.thumbnail = Some(data_uri);
+            }
+        }
+    }
+
+    Ok(Json(video))
+}
+
+/// 读取缩略图并编码为 data URI，超过 `max_bytes` 或读取失败时返回 None
+async fn thumbnail_data_uri(path: &Path, max_bytes: u64) -> Option<String> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    if !metadata.is_file() || metadata.len() > max_bytes {
+        return None;
+    }
+    let bytes = tokio::fs::read(path).await.ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        content_type_for(path),
+        BASE64_STANDARD.encode(bytes)
+    ))
+}
+
+/// 获取同一目录下的上一个/下一个视频
+///
+/// 与目录列表使用同样的排序（默认为配置的排序，也可以是 `sort_by=custom` 的自定义顺序）
+pub async fn get_video_siblings(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SiblingsParams>,
+) -> Result<Json<VideoSiblings>, Response> {
+    let sort = VideoSort::parse(
+        params.sort_by.as_deref(),
+        params.sort_order.as_deref(),
+        &VideoSort::configured(&state.config.listing),
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    let file_path = db_path(&file_path);
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let video_dao = VideoDao::new(&db_manager);
+    let video = video_dao
+        .get_video_by_path(&file_path)
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+    let parent_path = video.parent_path.unwrap_or_default();
+
+    let mut videos = video_dao
+        .get_videos_in_directory(&parent_path, &sort, false)
+        .map_err(db_error)?;
+    // 当前条目本身保留，否则无法定位
+    if state.config.listing.hide_metadata_errors {
+        videos.retain(|v| v.error.is_none() || v.path == file_path);
+    }
+    fill_web_fields(&mut videos, &state.data_source_dirs);
+
+    let index = videos
+        .iter()
+        .position(|v| v.path == file_path)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+    let next = videos.get(index + 1).cloned();
+    let prev = index.checked_sub(1).and_then(|i| videos.get(i)).cloned();
+
+    Ok(Json(VideoSiblings { prev, next }))
+}
+
+/// 按修改时间排列整个媒体库时的上一个/下一个视频（跨目录，类似相册的时间线）
+pub async fn get_timeline_neighbors(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<i64>,
+) -> Result<Json<VideoSiblings>, Response> {
+    let db_manager = state.db_manager.lock().unwrap();
+    let (mut prev, mut next) = VideoDao::new(&db_manager)
+        .get_timeline_neighbors(id)
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+
+    fill_web_fields(prev.as_mut_slice(), &state.data_source_dirs);
+    fill_web_fields(next.as_mut_slice(), &state.data_source_dirs);
+    Ok(Json(VideoSiblings { prev, next }))
+}
+
+/// 将 HLS 目录合并为 MP4，并用新文件替换数据库中的 HLS 记录（需要 API Key）
+///
+/// 合并结果与 HLS 目录同级同名（`show/` -> `show.mp4`），原始切片文件保留在磁盘上，
+/// 目录被标记为已合并，之后的同步不再登记。
+/// 数据源根目录不能合并，否则输出会落在数据源之外
+pub async fn merge_hls_directory(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<PathParams>,
+) -> Result<Json<serde_json::Value>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+
+    let hls_dir = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    let output_path = merged_output_path(&hls_dir);
+    if !state
+        .data_source_dirs
+        .iter()
+        .any(|m| output_path.starts_with(&m.physical_path))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Cannot merge a data source root directory",
+        )
+            .into_response());
+    }
+    let playlist = find_playlist(&hls_dir)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "No m3u8 playlist found").into_response())?;
+
+    if output_path.exists() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Output already exists: {}", output_path.display()),
+        )
+            .into_response());
+    }
+
+    // 合并和读取元数据（可能生成缩略图）都不持有数据库锁
+    let mappings = state.data_source_dirs.clone();
+    let thumbnail_mode = state.config.thumbnail.mode;
+    let (merged, file_info) = tokio::task::spawn_blocking(move || {
+        let merged = get_ffmpeg_service().merge_m3u8_to_mp4(&playlist)?;
+        let file_info = DirectorySync::scan_file(&merged, &mappings, thumbnail_mode);
+        Some((merged, file_info))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "Merge failed").into_response())?;
+    let file_info = file_info
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "Merge failed").into_response())?;
+
+    let task_state = Arc::clone(&state);
+    tokio::task::spawn_blocking(move || {
+        let db_manager = task_state.db_manager.lock().unwrap();
+        DirectorySync::new(&db_manager).replace_hls_directory(&hls_dir, &file_info)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .map_err(db_error)?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "合并完成",
+        "output": merged.to_string_lossy()
+    })))
+}
+
+/// MP4 切片为 HLS 的参数
+#[derive(serde::Deserialize)]
+pub struct PackageHlsParams {
+    pub path: String,
+    /// 切片时长（秒），默认 6 秒
+    pub segment_secs: Option<u32>,
+}
+
+/// 将 MP4 切片为 HLS（后台执行）
+///
+/// 先在系统临时目录中切片，完成后移动到与视频同级的 `.hls` 目录（`show/ep1.mp4` -> `show/ep1.hls/`）
+/// 并登记为 HLS 目录。输出目录位于数据源内，同步扫描会跳过它，只保留这里登记的记录
+pub async fn package_hls_video(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<PackageHlsParams>,
+) -> Result<Response, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+
+    let video_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !video_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "Video not found").into_response());
+    }
+    if media_types::classify(&video_path) != Some(MediaKind::Video) {
+        return Err((StatusCode::BAD_REQUEST, "Not a video").into_response());
+    }
+    let segment_secs = params.segment_secs.unwrap_or(HLS_SEGMENT_SECS);
+    if !(1..=60).contains(&segment_secs) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "segment_secs must be between 1 and 60",
+        )
+            .into_response());
+    }
+
+    let out_dir = hls_output_dir(&video_path);
+    if out_dir.exists() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Output already exists: {}", out_dir.display()),
+        )
+            .into_response());
+    }
+
+    let task_type = TaskType::PackageHls {
+        video_path,
+        out_dir: out_dir.clone(),
+        segment_secs,
+    };
+    let (task_id, result) = get_task_queue()
+        .enqueue_with_result(task_type, TaskPriority::Normal)
+        .await;
+
+    // 切片完成后登记输出目录，失败的任务已由任务队列记录日志
+    let output = out_dir.to_string_lossy().to_string();
+    let task_state = Arc::clone(&state);
+    let hls_dir = out_dir.clone();
+    tokio::spawn(async move {
+        let Ok(Ok(TaskResult::HlsPackaged(playlist))) = result.await else {
+            return;
+        };
+        info!("HLS 切片完成: {}", playlist.display());
+        let registered = tokio::task::spawn_blocking(move || {
+            let db_manager = task_state.db_manager.lock().unwrap();
+            DirectorySync::new(&db_manager)
+                .register_hls_directory(&hls_dir, &task_state.data_source_dirs)
+        })
+        .await;
+        match registered {
+            Ok(Ok(_)) => info!("HLS 目录已登记: {}", out_dir.display()),
+            Ok(Err(e)) => error!("登记 HLS 目录失败: {} - {}", out_dir.display(), e),
+            Err(e) => error!("登记 HLS 目录任务失败: {}", e),
+        }
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "success": true,
+            "task_id": task_id,
+            "output": output
+        })),
+    )
+        .into_response())
+}
+
+/// 批量标记已看完/未看完请求
+#[derive(serde::Deserialize)]
+pub struct SetWatchedRequest {
+    /// 视频 ID 列表
+    pub ids: Vec<i64>,
+    pub watched: bool,
+}
+
+/// 批量标记已看完或未看完
+pub async fn set_videos_watched(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SetWatchedRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    if request.ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "ids must not be empty").into_response());
+    }
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let video_dao = VideoDao::new(&db_manager);
+    let mut updated = 0;
+    for id in &request.ids {
+        updated += video_dao
+            .set_watched(*id, request.watched)
+            .map_err(db_error)?;
+    }
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "updated": updated,
+        "watched": request.watched
+    })))
+}
+
+/// 目录自定义排序请求
+#[derive(serde::Deserialize)]
+pub struct ReorderRequest {
+    /// 目录的路由路径或物理路径
+    pub parent_path: String,
+    /// 目录内条目的 ID，按期望的顺序排列；未列出的条目排在最后
+    pub ordered_ids: Vec<i64>,
+}
+
+/// 设置目录内条目的自定义顺序，之后可以用 `sort_by=custom` 按该顺序列出
+pub async fn reorder_videos(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ReorderRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let mut unique = request.ordered_ids.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    if unique.len() != request.ordered_ids.len() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "ordered_ids must not contain duplicates",
+        )
+            .into_response());
+    }
+
+    let dir = resolve_media_path(&request.parent_path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    let parent_path = map_physical_to_web(&dir.to_string_lossy(), &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let ordered = VideoDao::new(&db_manager)
+        .reorder_directory(&parent_path, &request.ordered_ids)
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "ordered_ids must all belong to the directory",
+            )
+                .into_response()
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "parent_path": parent_path,
+        "ordered": ordered
+    })))
+}
+
+/// 收藏请求
+#[derive(serde::Deserialize)]
+pub struct SetFavoriteRequest {
+    pub id: i64,
+    pub favorite: bool,
+}
+
+/// 收藏或取消收藏
+pub async fn set_video_favorite(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SetFavoriteRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let db_manager = state.db_manager.lock().unwrap();
+    let updated = VideoDao::new(&db_manager)
+        .set_favorite(request.id, request.favorite)
+        .map_err(db_error)?;
+    if updated == 0 {
+        return Err((StatusCode::NOT_FOUND, "Video not found").into_response());
+    }
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "id": request.id,
+        "favorite": request.favorite
+    })))
+}
+
+/// 记录一次播放（开始播放时调用），返回当前的播放次数
+pub async fn record_video_play(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<i64>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let db_manager = state.db_manager.lock().unwrap();
+    let watch_count = VideoDao::new(&db_manager)
+        .record_play(id)
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+
+    Ok(Json(
+        serde_json::json!({ "id": id, "watch_count": watch_count }),
+    ))
+}
+
+/// 列出元数据不完整的视频，用于定位需要重新读取元数据的文件
+pub async fn get_incomplete_videos(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<VideoList>, Response> {
+    let db_manager = state.db_manager.lock().unwrap();
+    let mut videos = VideoDao::new(&db_manager)
+        .get_incomplete()
+        .map_err(db_error)?;
+    fill_web_fields(&mut videos, &state.data_source_dirs);
+    Ok(Json(VideoList { videos }))
+}
+
+/// 为所有元数据不完整的视频提交后台任务（读取元数据并生成缩略图），返回提交的任务数量
+///
+/// 任务以低优先级在任务队列中执行，受队列并发数限制；同一文件的任务已在队列中时跳过
+pub async fn reprocess_incomplete_videos(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let videos = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_incomplete()
+            .map_err(db_error)?
+    };
+
+    let (task_ids, skipped) = enqueue_reprocess(&state, get_task_queue(), &videos).await;
+    info!(
+        "重新处理元数据不完整的视频: 提交 {} 个任务，跳过 {} 个已在队列中的文件",
+        task_ids.len(),
+        skipped
+    );
+
+    Ok(Json(serde_json::json!({
+        "enqueued": task_ids.len(),
+        "skipped": skipped,
+        "task_ids": task_ids
+    })))
+}
+
+/// 逐个提交元数据任务，任务完成后把读取到的元数据写回记录；返回提交的任务 ID 和跳过的数量
+async fn enqueue_reprocess(
+    state: &Arc<AppState>,
+    queue: &TaskQueue,
+    videos: &[VideoInfo],
+) -> (Vec<u64>, usize) {
+    let mut task_ids = Vec::new();
+    let mut skipped = 0;
+    for video in videos {
+        let video_path = PathBuf::from(&video.path);
+        let thumbnail_path = video
+            .thumbnail
+            .as_deref()
+            .filter(|thumbnail| !thumbnail.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| DirectorySync::get_thumbnail_path(&video_path));
+        let task_type = TaskType::ExtractMetadata {
+            video_path,
+            thumbnail_path,
+        };
+        let Some((task_id, receiver)) = queue
+            .enqueue_unique_with_result(task_type, TaskPriority::Low)
+            .await
+        else {
+            skipped += 1;
+            continue;
+        };
+        task_ids.push(task_id);
+
+        let state = Arc::clone(state);
+        let path = video.path.clone();
+        tokio::spawn(async move {
+            let Ok(Ok(TaskResult::MetadataExtracted(metadata))) = receiver.await else {
+                return;
+            };
+            let db_manager = state.db_manager.lock().unwrap();
+            if let Err(e) = VideoDao::new(&db_manager).apply_metadata(&path, &metadata) {
+                error!("写回元数据失败: {} - {}", path, e);
+            }
+        });
+    }
+    (task_ids, skipped)
+}
+
+/// 列出所有已收藏的条目（按名称排序）
+pub async fn get_favorites(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<VideoList>, Response> {
+    let db_manager = state.db_manager.lock().unwrap();
+    let mut videos = VideoDao::new(&db_manager)
+        .get_favorites()
+        .map_err(db_error)?;
+    fill_web_fields(&mut videos, &state.data_source_dirs);
+    Ok(Json(VideoList { videos }))
+}
+
+/// 播放进度请求
+#[derive(serde::Deserialize)]
+pub struct WatchProgressRequest {
+    pub id: i64,
+    /// 当前播放位置（秒）
+    pub position: f64,
+}
+
+/// 保存播放进度，超过时长的 90% 时自动标记为已看完
+pub async fn save_watch_progress(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<WatchProgressRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    if !request.position.is_finite() || request.position < 0.0 {
+        return Err((StatusCode::BAD_REQUEST, "position must be >= 0").into_response());
+    }
+
+    let db_manager = state.db_manager.lock().unwrap();
+    let watched = VideoDao::new(&db_manager)
+        .save_watch_position(request.id, request.position)
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "position": request.position,
+        "watched": watched
+    })))
+}
+
+/// 刷新单个文件请求
+#[derive(serde::Deserialize)]
+pub struct RefreshRequest {
+    /// 路由路径或物理路径
+    pub path: String,
+}
+
+/// 重新读取单个文件的元数据（时长、尺寸、编码、码率）并更新记录，返回更新后的记录
+///
+/// 缩略图比源文件旧时会重新生成，不会扫描文件所在的目录
+pub async fn refresh_video(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<RefreshRequest>,
+) -> Result<Json<VideoInfo>, Response> {
+    let file_path = resolve_media_path(&request.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !file_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "File not found").into_response());
+    }
+
+    // 读取元数据（ffprobe、生成缩略图）不持有数据库锁，只在写入记录时加锁
+    let refreshed = scan_refresh(&state, file_path.clone()).await?;
+    let task_state = Arc::clone(&state);
+    let refresh_path = file_path.clone();
+    let video = tokio::task::spawn_blocking(move || {
+        let db_manager = task_state.db_manager.lock().unwrap();
+        if let Some(refreshed) = &refreshed {
+            DirectorySync::new(&db_manager).apply_refresh(refreshed)?;
+        }
+        VideoDao::new(&db_manager).get_video_by_path(&db_path(&refresh_path))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .map_err(db_error)?;
+
+    let mut video =
+        video.ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?;
+    fill_web_fields(std::slice::from_mut(&mut video), &state.data_source_dirs);
+    Ok(Json(video))
+}
+
+/// 在后台线程中重新读取单个文件的元数据（见 [`DirectorySync::scan_refresh`]），不持有数据库锁
+async fn scan_refresh(
+    state: &Arc<AppState>,
+    file_path: PathBuf,
+) -> Result<Option<RefreshedFile>, Response> {
+    let mappings = state.data_source_dirs.clone();
+    let thumbnail_mode = state.config.thumbnail.mode;
+    tokio::task::spawn_blocking(move || {
+        DirectorySync::scan_refresh(&file_path, &mappings, thumbnail_mode)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .map_err(db_error)
+}
+
+/// 设置自定义缩略图请求
+#[derive(serde::Deserialize)]
+pub struct SetThumbnailRequest {
+    /// 路由路径或物理路径
+    pub path: String,
+    /// 截取画面的时间点（秒）
+    pub at_seconds: f64,
+}
+
+/// 从指定时间点重新截取缩略图，覆盖已有缩略图
+pub async fn set_video_thumbnail(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SetThumbnailRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let file_path = resolve_media_path(&request.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    let path_str = db_path(&file_path);
+
+    let video = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_video_by_path(&path_str)
+            .map_err(db_error)?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "Video not found").into_response())?
+    };
+    if !video_types::VIDEO_TYPES.contains(&video.r#type.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, "Not a video").into_response());
+    }
+
+    // 数据库中没有时长时用 ffprobe 读取
+    let duration = match video.duration.as_deref().and_then(parse_duration) {
+        Some(duration) => Some(duration),
+        None => probe_duration(file_path.clone()).await,
+    };
+    let at_seconds = request.at_seconds;
+    validate_seek_time("at_seconds", at_seconds, duration).map_err(IntoResponse::into_response)?;
+
+    let thumbnail_path = match video.thumbnail.as_deref() {
+        Some(thumbnail) if !thumbnail.is_empty() => std::path::PathBuf::from(thumbnail),
+        _ => DirectorySync::get_thumbnail_path(&file_path),
+    };
+    let output_path = thumbnail_path.clone();
+    let generated = tokio::task::spawn_blocking(move || {
+        get_ffmpeg_service().generate_thumbnail_at(&file_path, &output_path, at_seconds as f32)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?;
+    if !generated {
+        return Err((StatusCode::BAD_GATEWAY, "Thumbnail generation failed").into_response());
+    }
+
+    let thumbnail = db_path(&thumbnail_path);
+    let generated_at = chrono::Utc::now().timestamp();
+    {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .set_thumbnail(&path_str, &thumbnail, generated_at)
+            .map_err(db_error)?;
+    }
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "thumbnail": thumbnail,
+        "at_seconds": at_seconds,
+        "thumbnail_generated_at": generated_at
+    })))
+}
+
+/// 用 ffprobe 读取视频时长（秒），失败时返回 None
+async fn probe_duration(file_path: std::path::PathBuf) -> Option<f64> {
+    tokio::task::spawn_blocking(move || get_ffmpeg_service().probe_video_metadata(&file_path))
+        .await
+        .ok()
+        .flatten()
+        .and_then(|(duration, _, _)| parse_duration(&duration))
+}
+
+/// 校验截取画面的时间点：不能为负数，已知时长时必须小于时长
+fn validate_seek_time(
+    name: &str,
+    at_seconds: f64,
+    duration: Option<f64>,
+) -> Result<(), (StatusCode, String)> {
+    if !at_seconds.is_finite() || at_seconds < 0.0 {
+        return Err((StatusCode::BAD_REQUEST, format!("{} must be >= 0", name)));
+    }
+    if let Some(duration) = duration {
+        if at_seconds >= duration {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("{} must be less than duration ({}s)", name, duration),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 截取画面请求参数
+#[derive(serde::Deserialize)]
+pub struct FrameParams {
+    /// 路由路径或物理路径
+    pub path: String,
+    /// 时间点（秒）
+    pub t: f64,
+}
+
+/// 获取指定时间点的画面（JPEG），每次实时截取，不生成缩略图文件
+pub async fn get_video_frame(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<FrameParams>,
+) -> Result<Response, Response> {
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !file_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "Video not found").into_response());
+    }
+
+    // 优先使用数据库中记录的时长，没有记录时用 ffprobe 读取
+    let recorded = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_video_by_path(&db_path(&file_path))
+            .ok()
+            .flatten()
+    };
+    if let Some(video) = &recorded {
+        if !video_types::VIDEO_TYPES.contains(&video.r#type.as_str()) {
+            return Err((StatusCode::BAD_REQUEST, "Not a video").into_response());
+        }
+    }
+    let duration = match recorded
+        .and_then(|video| video.duration)
+        .as_deref()
+        .and_then(parse_duration)
+    {
+        Some(duration) => Some(duration),
+        None => probe_duration(file_path.clone()).await,
+    };
+    validate_seek_time("t", params.t, duration).map_err(IntoResponse::into_response)?;
+
+    let at_seconds = params.t as f32;
+    let frame = tokio::task::spawn_blocking(move || {
+        get_ffmpeg_service().extract_frame(&file_path, at_seconds)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Task error: {}", e),
+        )
+            .into_response()
+    })?
+    .map_err(|e| (StatusCode::BAD_GATEWAY, e).into_response())?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/jpeg"),
+            (header::CACHE_CONTROL, "public, max-age=60"),
+        ],
+        frame,
+    )
+        .into_response())
+}
+
+/// 获取故事板：雪碧图地址和每一帧的时间点、位置（JSON）
+///
+/// 雪碧图不存在或比视频文件旧时重新生成，和布局一起保存在 `thumbnails/storyboards/` 下
+pub async fn get_video_storyboard(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PathParams>,
+) -> Result<Json<Storyboard>, Response> {
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !file_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "Video not found").into_response());
+    }
+
+    // 优先使用数据库中记录的时长和宽高，没有记录时用 ffprobe 读取
+    let recorded = {
+        let db_manager = state.db_manager.lock().unwrap();
+        VideoDao::new(&db_manager)
+            .get_video_by_path(&db_path(&file_path))
+            .ok()
+            .flatten()
+    };
+    if let Some(video) = &recorded {
+        if !video_types::VIDEO_TYPES.contains(&video.r#type.as_str()) {
+            return Err((StatusCode::BAD_REQUEST, "Not a video").into_response());
+        }
+    }
+    let sprite_path = storyboard_path(&DirectorySync::get_thumbnail_path(&file_path));
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let is_fresh = matches!(
+        (modified(&sprite_path), modified(&file_path)),
+        (Some(sprite), Some(video)) if sprite >= video
+    );
+    // 雪碧图仍然有效时按生成时保存的布局返回，时长或宽高记录变化后帧位置也不会错位
+    let stored = if is_fresh {
+        load_storyboard_layout(&sprite_path)
+    } else {
+        None
+    };
+    let layout = match stored {
+        Some(layout) => layout,
+        None => {
+            let (duration, width, height) = match recorded {
+                Some(video) => (
+                    video.duration.as_deref().and_then(parse_duration),
+                    video.width,
+                    video.height,
+                ),
+                None => (None, None, None),
+            };
+            let (duration, width, height) = match duration {
+                Some(duration) => (duration, width, height),
+                None => {
+                    let probe_path = file_path.clone();
+                    let probed = tokio::task::spawn_blocking(move || {
+                        get_ffmpeg_service().probe_video_metadata(&probe_path)
+                    })
+                    .await
+                    .ok()
+                    .flatten();
+                    let (duration, width, height) = probed
+                        .and_then(|(duration, w, h)| Some((parse_duration(&duration)?, w, h)))
+                        .ok_or_else(|| {
+                            (StatusCode::UNPROCESSABLE_ENTITY, "Unknown duration").into_response()
+                        })?;
+                    (duration, Some(width), Some(height))
+                }
+            };
+
+            let layout = StoryboardLayout::new(duration, width, height);
+            let generate_layout = layout.clone();
+            let output_path = sprite_path.clone();
+            tokio::task::spawn_blocking(move || {
+                get_ffmpeg_service().generate_storyboard(&file_path, &output_path, &generate_layout)
+            })
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Task error: {}", e),
+                )
+                    .into_response()
+            })?
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e).into_response())?;
+            layout
+        }
+    };
+
+    Ok(Json(Storyboard {
+        sprite_url: absolute_url(
+            state.config.public_base_url.as_deref(),
+            &format!("/{}", db_path(&sprite_path)),
+        ),
+        columns: layout.columns,
+        rows: layout.rows,
+        interval: layout.interval,
+        frames: layout.frames(),
+    }))
+}
+
+/// 获取 ffprobe 输出的原始 JSON（调试用，需要 API Key）
+pub async fn probe_video(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<PathParams>,
+) -> Result<Json<serde_json::Value>, Response> {
+    require_api_key(&headers, &state.config).map_err(IntoResponse::into_response)?;
+
+    let file_path = resolve_media_path(&params.path, &state.data_source_dirs)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Invalid path").into_response())?;
+    if !file_path.is_file() {
+        return Err((StatusCode::NOT_FOUND, "File not found").into_response());
+    }
+
+    let probe = tokio::task::spawn_blocking(move || get_ffmpeg_service().probe_json(&file_path))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Task error: {}", e),
+            )
+                .into_response()
+        })?
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e).into_response())?;
+
+    Ok(Json(probe))
+}
+
+/// 删除视频请求参数
+#[derive(serde::Deserialize)]
+pub struct DeleteVideoParams {
+    /// 视频ID
+    pub id: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{insert_video, response_json, test_state};
+    use crate::DiskMapping;
+
+    fn object_keys(value: &serde_json::Value) -> Vec<String> {
+        let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[tokio::test]
+    async fn test_listing_is_empty_when_sources_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().join("public").to_string_lossy().to_string(),
+        }]);
+        insert_video(
+            &state.db_manager.lock().unwrap(),
+            "/stale/a.mp4",
+            "mp4",
+            "/public/disk1",
+        );
+
+        let response = list_videos(State(state.clone()), Query(ListParams::default()))
+            .await
+            .unwrap();
+        let body = response_json(response).await;
+        assert_eq!(body["videos"], serde_json::json!([]));
+
+        let params: PaginationParams = serde_json::from_value(serde_json::json!({})).unwrap();
+        let response = list_videos_paginated(State(state), Query(params))
+            .await
+            .unwrap();
+        let body = response_json(response).await;
+        assert_eq!(body["videos"], serde_json::json!([]));
+        assert_eq!(body["pagination"]["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_package_hls_requires_api_key() {
+        let config = crate::config::AppConfig {
+            api_keys: vec!["secret".to_string()],
+            ..Default::default()
+        };
+        let state = crate::test_utils::test_state_with_config(Vec::new(), config);
+        let response = package_hls_video(
+            State(state),
+            HeaderMap::new(),
+            Query(PackageHlsParams {
+                path: "/public/disk1/ep1.mp4".to_string(),
+                segment_secs: None,
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_merge_rejects_data_source_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.m3u8"), "#EXTM3U\n").unwrap();
+        let config = crate::config::AppConfig {
+            api_keys: vec!["secret".to_string()],
+            ..Default::default()
+        };
+        let state = crate::test_utils::test_state_with_config(
+            vec![DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: dir.path().to_string_lossy().to_string(),
+            }],
+            config,
+        );
+        let merge = |headers: HeaderMap| {
+            merge_hls_directory(
+                State(state.clone()),
+                headers,
+                Query(PathParams {
+                    path: "/public/disk1".to_string(),
+                }),
+            )
+        };
+
+        let response = merge(HeaderMap::new()).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            crate::routes::auth::API_KEY_HEADER,
+            HeaderValue::from_static("secret"),
+        );
+        let response = merge(headers).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(!dir.path().with_extension("mp4").exists());
+    }
+
+    #[tokio::test]
+    async fn test_siblings_follow_listing_sort() {
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: "/data".to_string(),
+        }]);
+        let parent_path = "/public/disk1/show";
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            for name in ["ep3.mp4", "ep2.mp4", "ep1.mp4"] {
+                let path = format!("/data/show/{}", name);
+                insert_video(&db_manager, &path, "mp4", parent_path);
+            }
+            insert_video(&db_manager, "/data/show/ep1.vtt", "subtitle", parent_path);
+        }
+
+        let siblings = |path: &str, sort_by: Option<&str>| {
+            get_video_siblings(
+                State(state.clone()),
+                Query(SiblingsParams {
+                    path: path.to_string(),
+                    sort_by: sort_by.map(str::to_string),
+                    sort_order: sort_by.map(|_| "asc".to_string()),
+                }),
+            )
+        };
+        let names = |siblings: &VideoSiblings| {
+            (
+                siblings.prev.as_ref().map(|v| v.name.clone()),
+                siblings.next.as_ref().map(|v| v.name.clone()),
+            )
+        };
+        let some = |name: &str| Some(name.to_string());
+
+        let middle = siblings("/public/disk1/show/ep2.mp4", Some("name"))
+            .await
+            .unwrap();
+        assert_eq!(names(&middle), (some("ep1.mp4"), some("ep3.mp4")));
+        let first = siblings("/data/show/ep1.mp4", Some("name")).await.unwrap();
+        assert_eq!(names(&first), (None, some("ep2.mp4")));
+        let last = siblings("/data/show/ep3.mp4", Some("name")).await.unwrap();
+        assert_eq!(names(&last), (some("ep2.mp4"), None));
+
+        // 默认按配置的排序（created_at desc，相同时按 id 倒序），与列表接口一致
+        let middle = siblings("/data/show/ep2.mp4", None).await.unwrap();
+        assert_eq!(names(&middle), (some("ep1.mp4"), some("ep3.mp4")));
+
+        // 自定义顺序与 sort_by=custom 的列表一致
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            let video_dao = VideoDao::new(&db_manager);
+            let id = |path: &str| video_dao.get_video_by_path(path).unwrap().unwrap().id;
+            let ordered = [
+                id("/data/show/ep2.mp4"),
+                id("/data/show/ep3.mp4"),
+                id("/data/show/ep1.mp4"),
+            ];
+            video_dao.reorder_directory(parent_path, &ordered).unwrap();
+        }
+        let middle = siblings("/data/show/ep3.mp4", Some("custom"))
+            .await
+            .unwrap();
+        assert_eq!(names(&middle), (some("ep2.mp4"), some("ep1.mp4")));
+
+        let response = siblings("/data/show/ep3.mp4", Some("bogus"))
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_timeline_neighbors_span_directories() {
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: "/data".to_string(),
+        }]);
+        let ids: Vec<i64> = {
+            let db_manager = state.db_manager.lock().unwrap();
+            [
+                ("/data/trip/b.mp4", "/public/disk1/trip", 200),
+                ("/data/a.mp4", "/public/disk1", 100),
+                ("/data/home/c.mp4", "/public/disk1/home", 300),
+            ]
+            .iter()
+            .map(|(path, parent, last_modified)| {
+                insert_video(&db_manager, path, "mp4", parent);
+                db_manager
+                    .conn
+                    .query_row(
+                        "UPDATE videos SET last_modified = ?1 WHERE path = ?2 RETURNING id",
+                        rusqlite::params![last_modified, path],
+                        |row| row.get(0),
+                    )
+                    .unwrap()
+            })
+            .collect()
+        };
+
+        let neighbors = |id: i64| get_timeline_neighbors(State(state.clone()), UrlPath(id));
+
+        let Json(middle) = neighbors(ids[0]).await.unwrap();
+        assert_eq!(middle.prev.as_ref().unwrap().name, "a.mp4");
+        assert_eq!(
+            middle.prev.as_ref().unwrap().url.as_deref(),
+            Some("/public/disk1/a.mp4")
+        );
+        assert_eq!(middle.next.as_ref().unwrap().name, "c.mp4");
+
+        let Json(oldest) = neighbors(ids[1]).await.unwrap();
+        assert!(oldest.prev.is_none());
+        assert_eq!(oldest.next.as_ref().unwrap().name, "b.mp4");
+
+        let Json(newest) = neighbors(ids[2]).await.unwrap();
+        assert_eq!(newest.prev.as_ref().unwrap().name, "b.mp4");
+        assert!(newest.next.is_none());
+
+        assert_eq!(
+            neighbors(9999).await.unwrap_err().status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_removes_empty_parent_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let season = root.join("show").join("season1");
+        std::fs::create_dir_all(&season).unwrap();
+        let video_path = season.join("ep1.mp4");
+        std::fs::write(&video_path, b"video").unwrap();
+        std::fs::write(root.join("other.mp4"), b"video").unwrap();
+
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.to_string_lossy().to_string(),
+        }]);
+        let video_id = {
+            let db_manager = state.db_manager.lock().unwrap();
+            let show = root.join("show");
+            insert_video(
+                &db_manager,
+                show.to_str().unwrap(),
+                "directory",
+                "/public/disk1",
+            );
+            insert_video(
+                &db_manager,
+                season.to_str().unwrap(),
+                "directory",
+                "/public/disk1/show",
+            );
+            insert_video(
+                &db_manager,
+                video_path.to_str().unwrap(),
+                "mp4",
+                "/public/disk1/show/season1",
+            );
+            VideoDao::new(&db_manager)
+                .get_video_by_path(video_path.to_str().unwrap())
+                .unwrap()
+                .unwrap()
+                .id
+        };
+
+        let response = delete_video(
+            State(state.clone()),
+            Query(DeleteVideoParams { id: video_id }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["removed_directories"].as_array().unwrap().len(), 2);
+
+        assert!(!root.join("show").exists());
+        assert!(root.exists());
+        assert!(root.join("other.mp4").exists());
+        let db_manager = state.db_manager.lock().unwrap();
+        assert!(VideoDao::new(&db_manager)
+            .get_root_videos()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_records_deletion_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+        let mut ids = Vec::new();
+        for name in ["a.mp4", "b.mp4"] {
+            let path = db_path(&dir.path().join(name));
+            std::fs::write(dir.path().join(name), b"video").unwrap();
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(&db_manager, &path, "mp4", "/public/disk1");
+            ids.push(
+                VideoDao::new(&db_manager)
+                    .get_video_by_path(&path)
+                    .unwrap()
+                    .unwrap()
+                    .id,
+            );
+        }
+
+        for id in ids {
+            let Json(response) =
+                delete_video(State(state.clone()), Query(DeleteVideoParams { id }))
+                    .await
+                    .unwrap();
+            assert_eq!(response["success"], true);
+        }
+
+        let deletions =
+            |limit: u32| get_deletions(State(state.clone()), Query(DeletionLogParams { limit }));
+        let Json(entries) = deletions(10).await.unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["b.mp4", "a.mp4"]);
+        assert_eq!(entries[1].path, db_path(&dir.path().join("a.mp4")));
+        assert!(!entries[0].deleted_at.is_empty());
+
+        let Json(entries) = deletions(1).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            deletions(0).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listing_type_filter() {
+        let state = test_state(Vec::new());
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(&db_manager, "/data/a.mp4", "mp4", "/public/disk1");
+            insert_video(&db_manager, "/data/a.jpg", "image", "/public/disk1");
+        }
+
+        let response = list_videos(
+            State(state.clone()),
+            Query(ListParams {
+                file_type: Some("image".to_string()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        let body = response_json(response).await;
+        let types: Vec<&str> = body["videos"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, vec!["image"]);
+
+        let response = list_videos(
+            State(state),
+            Query(ListParams {
+                file_type: Some("bogus".to_string()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_listing_urls_use_route_paths_for_each_mapping() {
+        let disk1 = tempfile::tempdir().unwrap();
+        let disk2 = tempfile::tempdir().unwrap();
+        let path1 = disk1.path().join("show").join("a.mp4");
+        let path2 = disk2.path().join("b.mp4");
+        let state = test_state(vec![
+            DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: disk1.path().to_string_lossy().to_string(),
+            },
+            DiskMapping {
+                route_path: "/public/disk2".to_string(),
+                physical_path: disk2.path().to_string_lossy().to_string(),
+            },
+        ]);
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(
+                &db_manager,
+                &path1.to_string_lossy(),
+                "mp4",
+                &disk1.path().join("show").to_string_lossy(),
+            );
+            insert_video(
+                &db_manager,
+                &path2.to_string_lossy(),
+                "mp4",
+                &disk2.path().to_string_lossy(),
+            );
+        }
+
+        let response = list_videos(State(state), Query(ListParams::default()))
+            .await
+            .unwrap();
+        let body = response_json(response).await;
+        let mut urls: Vec<&str> = body["videos"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["url"].as_str().unwrap())
+            .collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec!["/public/disk1/show/a.mp4", "/public/disk2/b.mp4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_outputs_one_video_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            for name in ["a.mp4", "b.mp4", "c.mp4"] {
+                let path = dir.path().join(name);
+                insert_video(&db_manager, &path.to_string_lossy(), "mp4", "/public/disk1");
+            }
+        }
+
+        let response = stream_videos(State(state)).await;
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let videos: Vec<VideoInfo> = std::str::from_utf8(&bytes)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let names: Vec<&str> = videos.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["a.mp4", "b.mp4", "c.mp4"]);
+        assert_eq!(videos[0].url.as_deref(), Some("/public/disk1/a.mp4"));
+    }
+
+    #[tokio::test]
+    async fn test_set_thumbnail_rejects_time_past_duration() {
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: "/data".to_string(),
+        }]);
+        // 测试记录的时长为 00:01:00
+        insert_video(
+            &state.db_manager.lock().unwrap(),
+            "/data/a.mp4",
+            "mp4",
+            "/public/disk1",
+        );
+
+        let request = |at_seconds: f64| {
+            Json(SetThumbnailRequest {
+                path: "/public/disk1/a.mp4".to_string(),
+                at_seconds,
+            })
+        };
+        for at_seconds in [60.0, 90.0, -1.0] {
+            let response = set_video_thumbnail(State(state.clone()), request(at_seconds))
+                .await
+                .unwrap_err();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let response = set_video_thumbnail(
+            State(state),
+            Json(SetThumbnailRequest {
+                path: "/public/disk1/missing.mp4".to_string(),
+                at_seconds: 1.0,
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_frame_rejects_time_past_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        let video_path = dir.path().join("a.mp4");
+        std::fs::write(&video_path, b"video").unwrap();
+        // 测试记录的时长为 00:01:00
+        insert_video(
+            &state.db_manager.lock().unwrap(),
+            &video_path.to_string_lossy(),
+            "mp4",
+            "/public/disk1",
+        );
+
+        let frame = |path: &str, t: f64| {
+            get_video_frame(
+                State(state.clone()),
+                Query(FrameParams {
+                    path: path.to_string(),
+                    t,
+                }),
+            )
+        };
+        for t in [60.0, 90.0, -1.0, f64::NAN] {
+            let response = frame("/public/disk1/a.mp4", t).await.unwrap_err();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let response = frame("/public/disk1/missing.mp4", 1.0).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_of_fixture_matches_known_sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+        std::fs::write(dir.path().join("abc.txt"), b"abc").unwrap();
+
+        let checksum = |path: &str, algo: &str| {
+            get_video_checksum(
+                State(state.clone()),
+                Query(ChecksumParams {
+                    path: path.to_string(),
+                    algo: algo.to_string(),
+                }),
+            )
+        };
+        let body = checksum("/public/disk1/abc.txt", "sha256").await.unwrap().0;
+        assert_eq!(
+            body["digest"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(body["algo"], "sha256");
+        assert_eq!(body["size"], 3);
+        assert_eq!(body["cached"], false);
+
+        let body = checksum("/public/disk1/abc.txt", "sha256").await.unwrap().0;
+        assert_eq!(body["cached"], true);
+
+        let response = checksum("/public/disk1/abc.txt", "md5").await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = checksum("/public/disk1/missing.txt", "sha256")
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_progress_marks_watched_and_filters_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        let (a, b) = (format!("{}/a.mp4", root), format!("{}/b.mp4", root));
+        {
+            // 测试记录的时长为 00:01:00
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(&db_manager, &a, "mp4", "/public/disk1");
+            insert_video(&db_manager, &b, "mp4", "/public/disk1");
+        }
+        let id = {
+            let db_manager = state.db_manager.lock().unwrap();
+            VideoDao::new(&db_manager)
+                .get_video_by_path(&a)
+                .unwrap()
+                .unwrap()
+                .id
+        };
+
+        let progress = |position: f64| {
+            save_watch_progress(
+                State(state.clone()),
+                Json(WatchProgressRequest { id, position }),
+            )
+        };
+        let Json(body) = progress(30.0).await.unwrap();
+        assert_eq!(body["watched"], false);
+        let Json(body) = progress(58.0).await.unwrap();
+        assert_eq!(body["watched"], true);
+        assert_eq!(
+            progress(-1.0).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+
+        let listed = |watched: bool| {
+            let params: PaginationParams =
+                serde_json::from_value(serde_json::json!({ "watched": watched })).unwrap();
+            list_videos_paginated(State(state.clone()), Query(params))
+        };
+        let body = response_json(listed(true).await.unwrap()).await;
+        assert_eq!(body["videos"].as_array().unwrap().len(), 1);
+        assert_eq!(body["videos"][0]["path"], a.as_str());
+        let body = response_json(listed(false).await.unwrap()).await;
+        assert_eq!(body["videos"][0]["path"], b.as_str());
+
+        // 批量取消标记
+        let Json(body) = set_videos_watched(
+            State(state.clone()),
+            Json(SetWatchedRequest {
+                ids: vec![id],
+                watched: false,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(body["updated"], 1);
+        let body = response_json(listed(true).await.unwrap()).await;
+        assert_eq!(body["videos"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_favorites_survive_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let playlist = "#EXTM3U\n#EXTINF:10.0,\nseg0.ts\n#EXT-X-ENDLIST\n";
+        std::fs::write(dir.path().join("a.m3u8"), playlist).unwrap();
+        std::fs::write(dir.path().join("b.m3u8"), playlist).unwrap();
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        let state = test_state(mappings.clone());
+        let a = db_path(&dir.path().join("a.m3u8"));
+        let id = {
+            let db_manager = state.db_manager.lock().unwrap();
+            DirectorySync::new(&db_manager)
+                .initialize_from_directory_with_progress(&mappings, false, false)
+                .unwrap();
+            VideoDao::new(&db_manager)
+                .get_video_by_path(&a)
+                .unwrap()
+                .unwrap()
+                .id
+        };
+
+        let favorite = |id: i64, favorite: bool| {
+            set_video_favorite(
+                State(state.clone()),
+                Json(SetFavoriteRequest { id, favorite }),
+            )
+        };
+        let Json(body) = favorite(id, true).await.unwrap();
+        assert_eq!(body["favorite"], true);
+        assert_eq!(
+            favorite(-1, true).await.unwrap_err().status(),
+            StatusCode::NOT_FOUND
+        );
+
+        // 文件被修改后重新同步，记录被替换但收藏保留
+        std::fs::write(
+            dir.path().join("a.m3u8"),
+            "#EXTM3U\n#EXTINF:20.0,\nseg0.ts\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            let sync = DirectorySync::new(&db_manager);
+            assert!(sync
+                .refresh_file(&dir.path().join("a.m3u8"), &mappings)
+                .unwrap());
+            sync.initialize_from_directory_with_progress(&mappings, false, false)
+                .unwrap();
+        }
+
+        let Json(favorites) = get_favorites(State(state.clone())).await.unwrap();
+        let paths: Vec<&str> = favorites.videos.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, vec![a.as_str()]);
+        assert_eq!(favorites.videos[0].duration.as_deref(), Some("00:00:20"));
+
+        let params: PaginationParams =
+            serde_json::from_value(serde_json::json!({ "favorite": true })).unwrap();
+        let body = response_json(
+            list_videos_paginated(State(state.clone()), Query(params))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(body["videos"].as_array().unwrap().len(), 1);
+        assert_eq!(body["videos"][0]["favorite"], true);
+    }
+
+    #[tokio::test]
+    async fn test_play_increments_watch_count() {
+        let state = test_state(Vec::new());
+        let id = {
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(&db_manager, "/d/a.mp4", "mp4", "/d");
+            VideoDao::new(&db_manager)
+                .get_video_by_path("/d/a.mp4")
+                .unwrap()
+                .unwrap()
+                .id
+        };
+
+        for expected in [1, 2] {
+            let Json(body) = record_video_play(State(state.clone()), UrlPath(id))
+                .await
+                .unwrap();
+            assert_eq!(body["watch_count"], expected);
+        }
+        let video = VideoDao::new(&state.db_manager.lock().unwrap())
+            .get_video_by_path("/d/a.mp4")
+            .unwrap()
+            .unwrap();
+        assert_eq!(video.watch_count, 2);
+        assert_eq!(
+            record_video_play(State(state.clone()), UrlPath(-1))
+                .await
+                .unwrap_err()
+                .status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reprocess_enqueues_each_incomplete_video_once() {
+        let state = test_state(Vec::new());
+        let videos = {
+            let db_manager = state.db_manager.lock().unwrap();
+            for path in ["/d/a.mp4", "/d/b.mp4", "/d/complete.mp4"] {
+                insert_video(&db_manager, path, "mp4", "/d");
+            }
+            db_manager
+                .conn
+                .execute(
+                    "UPDATE videos SET duration = '' WHERE path IN ('/d/a.mp4', '/d/b.mp4')",
+                    [],
+                )
+                .unwrap();
+            VideoDao::new(&db_manager).get_incomplete().unwrap()
+        };
+        assert_eq!(videos.len(), 2);
+
+        // 暂停的队列不会执行任务，任务一直留在待处理队列中
+        let queue = TaskQueue::new(1);
+        queue.pause();
+        let (task_ids, skipped) = enqueue_reprocess(&state, &queue, &videos).await;
+        assert_eq!((task_ids.len(), skipped), (2, 0));
+        assert_eq!(queue.get_stats().await.pending_count, 2);
+
+        let (task_ids, skipped) = enqueue_reprocess(&state, &queue, &videos).await;
+        assert_eq!((task_ids.len(), skipped), (0, 2));
+        assert_eq!(queue.get_stats().await.pending_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_directories_with_type() {
+        let state = test_state(Vec::new());
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            for (path, file_type, parent) in [
+                ("/lib/a", video_types::DIRECTORY, "/lib"),
+                ("/lib/a/s1", video_types::HLS_DIRECTORY, "/lib/a"),
+                ("/lib/a/s1/index.m3u8", video_types::M3U8, "/lib/a/s1"),
+                ("/lib/b", video_types::DIRECTORY, "/lib"),
+                ("/lib/b/x.mp4", video_types::MP4, "/lib/b"),
+                // 名称以 a 开头但不是 /lib/a 的子孙
+                ("/lib/a2", video_types::DIRECTORY, "/lib"),
+                ("/lib/a2/y.mp4", video_types::MP4, "/lib/a2"),
+            ] {
+                insert_video(&db_manager, path, file_type, parent);
+            }
+        }
+
+        let with = |file_type: &str| {
+            list_directories_with(
+                State(state.clone()),
+                Query(DirectoriesWithParams {
+                    file_type: file_type.to_string(),
+                }),
+            )
+        };
+        let paths = |list: &VideoList| -> Vec<String> {
+            list.videos.iter().map(|v| v.path.clone()).collect()
+        };
+
+        let Json(list) = with("m3u8").await.unwrap();
+        assert_eq!(paths(&list), vec!["/lib/a", "/lib/a/s1"]);
+        let Json(list) = with("hls_directory").await.unwrap();
+        assert_eq!(paths(&list), vec!["/lib/a"]);
+        let Json(list) = with("mp4").await.unwrap();
+        assert_eq!(paths(&list), vec!["/lib/a2", "/lib/b"]);
+        assert_eq!(
+            with("avi").await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flat_listing_includes_deep_descendants() {
+        let dir = tempfile::tempdir().unwrap();
+        let playlist = "#EXTM3U\n#EXTINF:10.0,\nseg0.ts\n#EXT-X-ENDLIST\n";
+        for file in [
+            "show/s1/e1.m3u8",
+            "show/s1/extras/deep/e2.m3u8",
+            "other/x.m3u8",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, playlist).unwrap();
+        }
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        let state = test_state(mappings.clone());
+        DirectorySync::new(&state.db_manager.lock().unwrap())
+            .initialize_from_directory_with_progress(&mappings, false, false)
+            .unwrap();
+
+        let flat = |page: u32, page_size: u32| {
+            list_videos_flat(
+                State(state.clone()),
+                Query(FlatListParams {
+                    path: "/public/disk1/show".to_string(),
+                    page,
+                    page_size,
+                }),
+            )
+        };
+        let Json(list) = flat(1, 100).await.unwrap();
+        let paths: Vec<String> = list.videos.iter().map(|v| v.path.clone()).collect();
+        let show = db_path(&dir.path().join("show"));
+        assert!(paths.contains(&db_path(&dir.path().join("show/s1/extras/deep/e2.m3u8"))));
+        assert!(paths.contains(&db_path(&dir.path().join("show/s1/e1.m3u8"))));
+        assert!(!paths.contains(&show));
+        assert!(paths
+            .iter()
+            .all(|path| path.starts_with(&format!("{}/", show))));
+        assert_eq!(list.pagination.total, paths.len() as u64);
+
+        // 分页后每页按路径顺序衔接
+        let Json(first) = flat(1, 2).await.unwrap();
+        assert_eq!(first.videos.len(), 2);
+        assert!(first.pagination.has_next);
+        assert_eq!(first.videos[0].path, paths[0]);
+        assert_eq!(
+            flat(1, 0).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detail_refresh_indexes_new_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.m3u8"),
+            "#EXTM3U\n#EXTINF:10.0,\nseg0.ts\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        let state = test_state(mappings.clone());
+        DirectorySync::new(&state.db_manager.lock().unwrap())
+            .initialize_from_directory_with_progress(&mappings, false, false)
+            .unwrap();
+
+        // 同步之后才添加的文件
+        std::fs::write(
+            dir.path().join("new.m3u8"),
+            "#EXTM3U\n#EXTINF:90.0,\nseg0.ts\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+        let detail = |path: &str, refresh: bool| {
+            get_video_detail(
+                State(state.clone()),
+                Query(DetailParams {
+                    path: path.to_string(),
+                    inline_thumbnail: false,
+                    refresh,
+                }),
+            )
+        };
+
+        let response = detail("/public/disk1/new.m3u8", false).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let Json(video) = detail("/public/disk1/new.m3u8", true).await.unwrap();
+        assert_eq!(video.name, "new.m3u8");
+        assert_eq!(video.duration.as_deref(), Some("00:01:30"));
+        assert_eq!(video.url.as_deref(), Some("/public/disk1/new.m3u8"));
+
+        // 已入库，不带 refresh 也能查到
+        let Json(video) = detail("/public/disk1/new.m3u8", false).await.unwrap();
+        assert_eq!(video.name, "new.m3u8");
+
+        // 文件不存在时仍然是 404
+        let response = detail("/public/disk1/missing.m3u8", true)
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_detail_inlines_only_small_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        let max_bytes = state.config.thumbnail.inline_max_bytes as usize;
+
+        let small = dir.path().join("small.jpg");
+        let large = dir.path().join("large.jpg");
+        std::fs::write(&small, [0xFF, 0xD8, 0xFF, 0xD9]).unwrap();
+        std::fs::write(&large, vec![0u8; max_bytes + 1]).unwrap();
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            let dao = VideoDao::new(&db_manager);
+            for (name, thumbnail) in [("a.mp4", &small), ("b.mp4", &large)] {
+                let path = format!("{}/{}", root, name);
+                insert_video(&db_manager, &path, "mp4", "/public/disk1");
+                dao.set_thumbnail(&path, &thumbnail.to_string_lossy(), 0)
+                    .unwrap();
+            }
+        }
+
+        let detail = |path: &str, inline_thumbnail: bool| {
+            get_video_detail(
+                State(state.clone()),
+                Query(DetailParams {
+                    path: path.to_string(),
+                    inline_thumbnail,
+                    refresh: false,
+                }),
+            )
+        };
+
+        let Json(video) = detail("/public/disk1/a.mp4", true).await.unwrap();
+        assert_eq!(
+            video.thumbnail.as_deref(),
+            Some("data:image/jpeg;base64,/9j/2Q==")
+        );
+        assert_eq!(video.url.as_deref(), Some("/public/disk1/a.mp4"));
+
+        // 超过上限时保留原来的缩略图地址
+        let Json(video) = detail("/public/disk1/b.mp4", true).await.unwrap();
+        assert_eq!(video.thumbnail, Some(large.to_string_lossy().to_string()));
+
+        // 未请求内联
+        let Json(video) = detail("/public/disk1/a.mp4", false).await.unwrap();
+        assert_eq!(video.thumbnail, Some(small.to_string_lossy().to_string()));
+
+        let response = detail("/public/disk1/missing.mp4", true).await.unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_directory_detail_includes_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(
+                &db_manager,
+                &format!("{}/show", root),
+                "directory",
+                "/public/disk1",
+            );
+            for name in ["ep2.mp4", "ep1.mp4", "ep1.vtt"] {
+                let path = format!("{}/show/{}", root, name);
+                let file_type = if name.ends_with(".vtt") {
+                    "subtitle"
+                } else {
+                    "mp4"
+                };
+                insert_video(&db_manager, &path, file_type, "/public/disk1/show");
+            }
+            insert_video(
+                &db_manager,
+                &format!("{}/other/x.mp4", root),
+                "mp4",
+                "/public/disk1/other",
+            );
+        }
+
+        let detail = |path: &str| {
+            get_video_detail(
+                State(state.clone()),
+                Query(DetailParams {
+                    path: path.to_string(),
+                    inline_thumbnail: false,
+                    refresh: false,
+                }),
+            )
+        };
+
+        let Json(show) = detail("/public/disk1/show").await.unwrap();
+        let children = show.children.unwrap();
+        let names: Vec<&str> = children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["ep1.mp4", "ep1.vtt", "ep2.mp4"]);
+        assert_eq!(
+            children[0].url.as_deref(),
+            Some("/public/disk1/show/ep1.mp4")
+        );
+
+        // 文件没有子项
+        let Json(file) = detail("/public/disk1/show/ep1.mp4").await.unwrap();
+        assert!(file.children.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_listing_includes_directory_totals_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        let show = format!("{}/show", root);
+        {
+            // 测试记录的时长为 00:01:00，大小为 1.00 MB
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(&db_manager, &show, "directory", "/public/disk1");
+            for name in ["ep1.mp4", "ep2.mp4"] {
+                let path = format!("{}/{}", show, name);
+                insert_video(&db_manager, &path, "mp4", "/public/disk1/show");
+            }
+        }
+
+        let directory = |include_totals: bool| {
+            let state = state.clone();
+            async move {
+                let params = ListParams {
+                    file_type: Some("directory".to_string()),
+                    include_totals,
+                    ..Default::default()
+                };
+                let response = list_videos(State(state), Query(params)).await.unwrap();
+                response_json(response).await["videos"][0].clone()
+            }
+        };
+
+        let folder = directory(true).await;
+        assert_eq!(folder["total_duration"], "00:02:00");
+        assert_eq!(folder["total_size"], "2.00 MB");
+
+        let folder = directory(false).await;
+        assert!(folder.get("total_duration").is_none());
+        assert!(folder.get("total_size").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_type_hidden_unless_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        {
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(
+                &db_manager,
+                &format!("{}/a.mp4", root),
+                "mp4",
+                "/public/disk1",
+            );
+            insert_video(
+                &db_manager,
+                &format!("{}/notes.txt", root),
+                "unknown",
+                "/public/disk1",
+            );
+        }
+
+        let types = |include_unknown: bool| {
+            let state = state.clone();
+            async move {
+                let params = ListParams {
+                    include_unknown,
+                    ..Default::default()
+                };
+                let response = list_videos(State(state), Query(params)).await.unwrap();
+                let mut types: Vec<String> = response_json(response).await["videos"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|video| video["type"].as_str().unwrap().to_string())
+                    .collect();
+                types.sort();
+                types
+            }
+        };
+
+        assert_eq!(types(false).await, vec!["mp4"]);
+        assert_eq!(types(true).await, vec!["mp4", "unknown"]);
+    }
+
+    #[tokio::test]
+    async fn test_custom_order_is_reflected_in_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        let ids: Vec<i64> = {
+            let db_manager = state.db_manager.lock().unwrap();
+            let dao = VideoDao::new(&db_manager);
+            ["a", "b", "c", "d"]
+                .iter()
+                .map(|name| {
+                    let path = format!("{}/{}.mp4", root, name);
+                    insert_video(&db_manager, &path, "mp4", "/public/disk1");
+                    dao.get_video_by_path(&path).unwrap().unwrap().id
+                })
+                .collect()
+        };
+
+        let reorder = |ordered_ids: Vec<i64>| {
+            reorder_videos(
+                State(state.clone()),
+                Json(ReorderRequest {
+                    parent_path: "/public/disk1".to_string(),
+                    ordered_ids,
+                }),
+            )
+        };
+        let Json(body) = reorder(vec![ids[2], ids[0], ids[3]]).await.unwrap();
+        assert_eq!(body["ordered"], 3);
+
+        let params: PaginationParams =
+            serde_json::from_value(serde_json::json!({ "sort_by": "custom", "sort_order": "asc" }))
+                .unwrap();
+        let body = response_json(
+            list_videos_paginated(State(state.clone()), Query(params))
+                .await
+                .unwrap(),
+        )
+        .await;
+        let names: Vec<&str> = body["videos"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|video| video["name"].as_str().unwrap())
+            .collect();
+        // 没有指定顺序的 b 排在最后
+        assert_eq!(names, ["c.mp4", "a.mp4", "d.mp4", "b.mp4"]);
+
+        assert_eq!(
+            reorder(vec![ids[0], ids[0]]).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            reorder(vec![ids[0], 9999]).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_sort_field_is_rejected() {
+        let state = test_state(Vec::new());
+        let params: PaginationParams =
+            serde_json::from_value(serde_json::json!({ "sort_by": "bogus" })).unwrap();
+
+        let response = list_videos_paginated(State(state), Query(params))
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let message = String::from_utf8(bytes.to_vec()).unwrap();
+        for field in crate::services::db::video_dao::SORT_FIELDS {
+            assert!(
+                message.contains(field),
+                "{} missing from {}",
+                field,
+                message
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_top_videos_by_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, secs) in [("short", 30.0), ("long", 600.0), ("medium", 120.0)] {
+            std::fs::write(
+                dir.path().join(format!("{}.m3u8", name)),
+                format!("#EXTM3U\n#EXTINF:{},\nseg0.ts\n#EXT-X-ENDLIST\n", secs),
+            )
+            .unwrap();
+        }
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        let state = test_state(mappings.clone());
+        DirectorySync::new(&state.db_manager.lock().unwrap())
+            .initialize_from_directory_with_progress(&mappings, false, false)
+            .unwrap();
+
+        let top = |by: &str, limit: u32| {
+            get_top_videos(
+                State(state.clone()),
+                Query(TopVideosParams {
+                    by: by.to_string(),
+                    limit,
+                }),
+            )
+        };
+        let Json(list) = top("duration", 2).await.unwrap();
+        let names: Vec<&str> = list.videos.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["long.m3u8", "medium.m3u8"]);
+
+        assert_eq!(
+            top("bogus", 2).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            top("duration", 0).await.unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_updates_metadata_of_replaced_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: root.clone(),
+        }]);
+        let playlist = dir.path().join("show.m3u8");
+        {
+            // 旧记录的时长为 00:01:00，大小为 1.00 MB
+            let db_manager = state.db_manager.lock().unwrap();
+            insert_video(&db_manager, &db_path(&playlist), "m3u8", "/public/disk1");
+        }
+
+        // 替换文件后刷新
+        std::fs::write(
+            &playlist,
+            "#EXTM3U\n#EXTINF:60.0,\nseg0.ts\n#EXTINF:30.0,\nseg1.ts\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+        let Json(video) = refresh_video(
+            State(state.clone()),
+            Json(RefreshRequest {
+                path: "/public/disk1/show.m3u8".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(video.duration.as_deref(), Some("00:01:30"));
+        let size = std::fs::metadata(&playlist).unwrap().len();
+        assert_eq!(video.size, Some(format_size(size)));
+        assert_eq!(video.url.as_deref(), Some("/public/disk1/show.m3u8"));
+
+        let missing = refresh_video(
+            State(state),
+            Json(RefreshRequest {
+                path: "/public/disk1/missing.mp4".to_string(),
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn test_refresh_probes_without_database_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+        let playlist = dir.path().join("show.m3u8");
+        std::fs::write(
+            &playlist,
+            "#EXTM3U\n#EXTINF:60.0,\nseg0.ts\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+
+        // 其他请求持有数据库锁时，读取元数据不需要等待
+        let guard = state.db_manager.lock().unwrap();
+        let refreshed = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            scan_refresh(&state, playlist.clone()),
+        )
+        .await
+        .expect("scan_refresh waited for the database lock")
+        .unwrap()
+        .unwrap();
+        drop(guard);
+
+        let db_manager = state.db_manager.lock().unwrap();
+        DirectorySync::new(&db_manager)
+            .apply_refresh(&refreshed)
+            .unwrap();
+        let video = VideoDao::new(&db_manager)
+            .get_video_by_path(&db_path(&playlist))
+            .unwrap()
+            .unwrap();
+        assert_eq!(video.duration.as_deref(), Some("00:01:00"));
+    }
+
+    #[tokio::test]
+    async fn test_light_listing_omits_heavy_fields() {
+        let state = test_state(Vec::new());
+        insert_video(
+            &state.db_manager.lock().unwrap(),
+            "/data/a.mp4",
+            "mp4",
+            "/public/disk1",
+        );
+
+        let full = list_videos(State(state.clone()), Query(ListParams::default()))
+            .await
+            .unwrap();
+        let light = list_videos(
+            State(state),
+            Query(ListParams {
+                fields: FieldSet::Light,
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+
+        let full = response_json(full).await;
+        let light = response_json(light).await;
+        let full_keys = object_keys(&full["videos"][0]);
+        let light_keys = object_keys(&light["videos"][0]);
+
+        assert_eq!(
+            light_keys,
+            vec!["duration", "id", "name", "path", "thumbnail", "type"]
+        );
+        assert!(full_keys.contains(&"size".to_string()));
+        assert!(full_keys.contains(&"width".to_string()));
+        assert!(light_keys.iter().all(|key| full_keys.contains(key)));
+    }
 }
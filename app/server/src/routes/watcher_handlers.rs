@@ -0,0 +1,68 @@
+//! 文件监听相关的 API 处理器
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::AppState;
+
+/// 文件监听状态响应
+#[derive(Serialize)]
+pub struct WatcherStatusResponse {
+    pub running: bool,
+    pub debounce_ms: u64,
+    pub watched_paths: Vec<String>,
+}
+
+/// 获取文件监听状态
+pub async fn get_watcher_status(State(state): State<Arc<AppState>>) -> Json<WatcherStatusResponse> {
+    let status = state.watcher.status();
+
+    Json(WatcherStatusResponse {
+        running: status.running,
+        debounce_ms: status.debounce_ms,
+        watched_paths: status.watched_paths,
+    })
+}
+
+/// 启动文件监听（使用配置中的防抖时间）
+pub async fn start_watcher(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, Response> {
+    state
+        .watcher
+        .start(
+            Arc::clone(&state.data_source_dirs),
+            Arc::clone(&state.db_manager),
+            Arc::clone(&state.last_sync),
+            state.config.watcher.debounce_ms,
+        )
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Watcher error: {}", e),
+            )
+                .into_response()
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "文件监听已启动",
+        "debounce_ms": state.config.watcher.debounce_ms
+    })))
+}
+
+/// 停止文件监听
+pub async fn stop_watcher(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let was_running = state.watcher.stop();
+
+    Json(serde_json::json!({
+        "success": true,
+        "message": if was_running { "文件监听已停止" } else { "文件监听未在运行" }
+    }))
+}
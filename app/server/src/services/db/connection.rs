@@ -1,52 +1,249 @@
 use rusqlite::{Connection, Result};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::services::db::count_cache::CountCache;
+use crate::services::db::schema::{ADDED_COLUMNS, ORPHANED_WATCH_STATE_ADDED_COLUMNS};
 
 /// 数据库连接管理器
 ///
 /// 负责数据库连接的创建、初始化和管理
 pub struct VideoDbManager {
     pub(crate) conn: Connection,
+    /// 分页总数缓存
+    pub(crate) count_cache: CountCache,
+}
+
+/// 数据库维护结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// 维护前数据库大小（字节）
+    pub size_before: u64,
+    /// 维护后数据库大小（字节）
+    pub size_after: u64,
+}
+
+impl MaintenanceReport {
+    /// 释放的空间（字节）
+    pub fn freed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+}
+
+/// 内存数据库路径
+pub const IN_MEMORY_PATH: &str = ":memory:";
+
+/// 默认的 busy_timeout：数据库被其他连接锁定时 SQLite 内部等待的时间
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// [`retry_on_busy`] 最多重试的次数
+const BUSY_RETRIES: u32 = 5;
+
+/// 执行写操作，WAL 模式下读事务升级为写事务失败（`SQLITE_BUSY_SNAPSHOT`）时立即重试
+///
+/// 其他连接持有锁的情况由 busy_timeout 在 SQLite 内部等待，这里不再等待；
+/// 只有事务读取的快照已过期时 SQLite 会直接返回 BUSY，此时必须重新开始事务。
+/// `op` 可能执行多次，必须是完整的写操作（包括自己的事务）
+pub fn retry_on_busy<T>(mut op: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if is_stale_snapshot(&e) && attempt < BUSY_RETRIES => {
+                attempt += 1;
+                log::debug!("读事务快照已过期，第 {} 次重试: {}", attempt, e);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 是否为数据库被锁定导致的错误
+#[cfg(test)]
+fn is_busy(error: &rusqlite::Error) -> bool {
+    matches!(
+        error,
+        rusqlite::Error::SqliteFailure(e, _)
+            if matches!(e.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// 是否为读事务的快照过期、无法升级为写事务导致的错误
+fn is_stale_snapshot(error: &rusqlite::Error) -> bool {
+    matches!(
+        error,
+        rusqlite::Error::SqliteFailure(e, _) if e.extended_code == rusqlite::ffi::SQLITE_BUSY_SNAPSHOT
+    )
 }
 
 impl VideoDbManager {
     /// 初始化数据库连接并创建表结构（如果不存在）
+    ///
+    /// `db_path` 为 [`IN_MEMORY_PATH`] 时使用内存数据库，否则打开（或创建）对应文件
     pub fn new(db_path: &str) -> Result<Self> {
+        if db_path != IN_MEMORY_PATH {
+            // 确保数据库文件所在目录存在
+            if let Some(parent) = Path::new(db_path).parent() {
+                if !parent.as_os_str().is_empty() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+            }
+        }
         let conn = Connection::open(db_path)?;
+        conn.busy_timeout(DEFAULT_BUSY_TIMEOUT)?;
 
-        // 创建视频表
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS videos (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                path TEXT UNIQUE NOT NULL,
-                type TEXT NOT NULL,
-                thumbnail TEXT,
-                duration INTEGER,
-                size TEXT,
-                resolution TEXT,
-                bitrate TEXT,
-                codec TEXT,
-                created_at TEXT,
-                subtitle TEXT,
-                parent_path TEXT,
-                last_modified INTEGER NOT NULL DEFAULT 0,
-                width INTEGER,
-                height INTEGER
-            )",
-            [],
-        )?;
+        // 文件数据库使用 WAL 模式，读写互不阻塞；WAL 文件由 SQLite 自动检查点回写
+        if db_path != IN_MEMORY_PATH {
+            conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        }
 
-        // 创建索引以提高查询速度
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_path ON videos(path)", [])?;
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_parent ON videos(parent_path)",
-            [],
-        )?;
+        create_schema(&conn)?;
 
         // 执行数据库迁移（处理旧版本的 is_deleted 列）
         run_migrations(&conn)?;
 
-        Ok(Self { conn })
+        Ok(Self {
+            conn,
+            count_cache: CountCache::default(),
+        })
+    }
+
+    /// 创建已初始化表结构的内存数据库（用于测试）
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self::new(IN_MEMORY_PATH).expect("Failed to create in-memory database")
+    }
+
+    /// 设置数据库被其他连接锁定时等待的时间
+    pub fn set_busy_timeout(&self, timeout: Duration) -> Result<()> {
+        self.conn.busy_timeout(timeout)
     }
+
+    /// 当前数据库大小（页数 × 页大小，字节）
+    pub fn database_size(&self) -> Result<u64> {
+        let page_count: u64 = self
+            .conn
+            .query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: u64 = self
+            .conn
+            .query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok(page_count * page_size)
+    }
+
+    /// 回写并截断 WAL 文件，然后 VACUUM 整理数据库
+    pub fn run_maintenance(&self) -> Result<MaintenanceReport> {
+        let size_before = self.database_size()?;
+
+        // 非 WAL 模式下该 PRAGMA 不做任何事，但同样会返回一行结果
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        self.conn.execute("VACUUM", [])?;
+
+        Ok(MaintenanceReport {
+            size_before,
+            size_after: self.database_size()?,
+        })
+    }
+
+    /// 删除并重新创建所有表（用于数据库损坏后的恢复）
+    ///
+    /// 视频 id 会重新分配，标签、保留的观看状态和删除记录也一并清空，不会留下指向旧记录的数据
+    pub fn recreate_schema(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "BEGIN;
+             DROP TABLE IF EXISTS video_tags;
+             DROP TABLE IF EXISTS tags;
+             DROP TABLE IF EXISTS orphaned_watch_state;
+             DROP TABLE IF EXISTS deletion_log;
+             DROP TABLE IF EXISTS videos;
+             COMMIT;",
+        )?;
+        create_schema(&self.conn)?;
+        run_migrations(&self.conn)?;
+        self.count_cache.invalidate();
+        Ok(())
+    }
+}
+
+/// 创建表结构和索引（如果不存在）
+fn create_schema(conn: &Connection) -> Result<()> {
+    // 创建视频表
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS videos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            path TEXT UNIQUE NOT NULL,
+            type TEXT NOT NULL,
+            thumbnail TEXT,
+            duration INTEGER,
+            size TEXT,
+            resolution TEXT,
+            bitrate TEXT,
+            codec TEXT,
+            created_at TEXT,
+            subtitle TEXT,
+            parent_path TEXT,
+            last_modified INTEGER NOT NULL DEFAULT 0,
+            width INTEGER,
+            height INTEGER
+        )",
+        [],
+    )?;
+
+    // 创建索引以提高查询速度
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_path ON videos(path)", [])?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_parent ON videos(parent_path)",
+        [],
+    )?;
+
+    // 同步时被删除的记录的观看状态（文件移动后可以重新关联）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS orphaned_watch_state (
+            path TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            size TEXT,
+            duration TEXT,
+            watched INTEGER NOT NULL DEFAULT 0,
+            watch_position REAL,
+            favorite INTEGER NOT NULL DEFAULT 0,
+            removed_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // 通过 API 删除的条目记录，不随视频记录一起删除
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS deletion_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            name TEXT NOT NULL,
+            deleted_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // 标签，以及标签与视频的多对多关系（标签名不区分大小写）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT UNIQUE NOT NULL COLLATE NOCASE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS video_tags (
+            video_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (video_id, tag_id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_video_tags_tag ON video_tags(tag_id)",
+        [],
+    )?;
+
+    Ok(())
 }
 
 /// 执行数据库迁移
@@ -138,5 +335,339 @@ fn run_migrations(conn: &Connection) -> Result<()> {
         }
     }
 
+    // 添加后续版本新增的列（如果不存在）
+    add_missing_columns(conn, "videos", ADDED_COLUMNS)?;
+    add_missing_columns(
+        conn,
+        "orphaned_watch_state",
+        ORPHANED_WATCH_STATE_ADDED_COLUMNS,
+    )?;
+
+    normalize_stored_separators(conn)?;
+    normalize_stored_durations(conn)?;
+
+    Ok(())
+}
+
+/// 将旧版本存储的 `MM:SS` 时长改为与 `format_duration` 一致的 `HH:MM:SS`，
+/// 保证时长字符串的比较和排序与数值一致
+fn normalize_stored_durations(conn: &Connection) -> Result<()> {
+    for table in ["videos", "orphaned_watch_state"] {
+        let changed = conn.execute(
+            &format!(
+                "UPDATE {table} SET duration = '00:' || duration WHERE duration GLOB '[0-9][0-9]:[0-9][0-9]'"
+            ),
+            [],
+        )?;
+        if changed > 0 {
+            println!("已统一 {}.duration 中 {} 条时长的格式", table, changed);
+        }
+    }
     Ok(())
 }
+
+/// 需要统一分隔符的列：(表名, 列名, 是否唯一)
+const PATH_COLUMNS: [(&str, &str, bool); 6] = [
+    ("videos", "path", true),
+    ("videos", "parent_path", false),
+    ("videos", "thumbnail", false),
+    ("videos", "subtitle", false),
+    ("orphaned_watch_state", "path", true),
+    ("deletion_log", "path", false),
+];
+
+/// 将旧版本存储的 `\` 分隔路径改为 `/` 分隔（与 `normalize_separators` 的规则一致）
+///
+/// 旧记录与已有的 `/` 分隔记录冲突时保留后者，删除旧记录
+fn normalize_stored_separators(conn: &Connection) -> Result<()> {
+    for (table, column, unique) in PATH_COLUMNS {
+        let condition = windows_style_condition(column);
+        let changed = conn.execute(
+            &format!(
+                "UPDATE OR IGNORE {table} SET {column} = replace({column}, '\\', '/') WHERE {condition}"
+            ),
+            [],
+        )?;
+        if unique {
+            conn.execute(&format!("DELETE FROM {table} WHERE {condition}"), [])?;
+        }
+        if changed > 0 {
+            println!("已统一 {}.{} 中 {} 条路径的分隔符", table, column, changed);
+        }
+    }
+    Ok(())
+}
+
+/// 匹配 Windows 风格路径的 SQL 条件，Windows 上匹配所有包含 `\` 的路径
+fn windows_style_condition(column: &str) -> String {
+    if cfg!(windows) {
+        format!("instr({column}, '\\') > 0")
+    } else {
+        format!(
+            "instr({column}, '\\') > 0 AND ({column} GLOB '[A-Za-z]:*' OR {column} GLOB '\\\\*' OR instr({column}, '/') = 0)"
+        )
+    }
+}
+
+/// 添加 `columns`（schema 中的新增列列表）中定义但表中尚不存在的列
+fn add_missing_columns(conn: &Connection, table: &str, columns: &[(&str, &str)]) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let existing: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<Vec<_>>>()?;
+
+    for (name, definition) in columns {
+        if !existing.iter().any(|c| c == name) {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, definition),
+                [],
+            )?;
+            println!("已添加 {}.{} 列", table, name);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::db::VideoDao;
+    use crate::test_utils::insert_video;
+
+    #[test]
+    fn test_in_memory_manager_end_to_end() {
+        let db_manager = VideoDbManager::in_memory();
+
+        // 内存数据库同样执行了迁移
+        let mut stmt = db_manager
+            .conn
+            .prepare("PRAGMA table_info(videos)")
+            .unwrap();
+        let columns: Vec<String> = stmt
+            .query_map([], |row| row.get(1))
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        for (name, _) in ADDED_COLUMNS {
+            assert!(columns.iter().any(|c| c == name), "missing column {}", name);
+        }
+
+        insert_video(&db_manager, "/videos/a.mp4", "mp4", "/videos");
+        let video = VideoDao::new(&db_manager)
+            .get_video_by_path("/videos/a.mp4")
+            .unwrap()
+            .unwrap();
+        assert_eq!(video.name, "a.mp4");
+        assert_eq!(video.width, Some(1920));
+    }
+
+    #[test]
+    fn test_migration_normalizes_short_durations() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/videos/a.mp4", "mp4", "/videos");
+        insert_video(&db_manager, "/videos/b.mp4", "mp4", "/videos");
+        db_manager
+            .conn
+            .execute_batch(
+                "UPDATE videos SET duration = '03:10' WHERE path = '/videos/a.mp4';
+                 UPDATE videos SET duration = '01:01:01' WHERE path = '/videos/b.mp4';",
+            )
+            .unwrap();
+
+        run_migrations(&db_manager.conn).unwrap();
+
+        let video_dao = VideoDao::new(&db_manager);
+        let duration = |path: &str| video_dao.get_video_by_path(path).unwrap().unwrap().duration;
+        assert_eq!(duration("/videos/a.mp4").as_deref(), Some("00:03:10"));
+        assert_eq!(duration("/videos/b.mp4").as_deref(), Some("01:01:01"));
+    }
+
+    #[test]
+    fn test_recreate_schema_clears_dependent_tables() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/videos/a.mp4", "mp4", "/videos");
+        let video_dao = VideoDao::new(&db_manager);
+        let id = video_dao
+            .get_video_by_path("/videos/a.mp4")
+            .unwrap()
+            .unwrap()
+            .id;
+        video_dao.add_tags(id, &["anime".to_string()]).unwrap();
+        db_manager
+            .conn
+            .execute_batch(
+                "INSERT INTO orphaned_watch_state (path, name, removed_at) VALUES ('/videos/b.mp4', 'b.mp4', 0);
+                 INSERT INTO deletion_log (path, name, deleted_at) VALUES ('/videos/c.mp4', 'c.mp4', 0);",
+            )
+            .unwrap();
+
+        db_manager.recreate_schema().unwrap();
+
+        for table in [
+            "videos",
+            "tags",
+            "video_tags",
+            "orphaned_watch_state",
+            "deletion_log",
+        ] {
+            let count: i64 = db_manager
+                .conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            assert_eq!(count, 0, "{} not cleared", table);
+        }
+    }
+
+    #[test]
+    fn test_migration_normalizes_windows_style_paths() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(
+            &db_manager,
+            "F:\\public\\show\\a.mp4",
+            "mp4",
+            "F:\\public\\show",
+        );
+        // 与已有的 `/` 分隔记录冲突的旧记录被删除
+        insert_video(&db_manager, "F:/public/show/b.mp4", "mp4", "F:/public/show");
+        insert_video(
+            &db_manager,
+            "F:\\public\\show\\b.mp4",
+            "mp4",
+            "F:\\public\\show",
+        );
+        insert_video(&db_manager, "/data/a\\b.mp4", "mp4", "/data");
+
+        run_migrations(&db_manager.conn).unwrap();
+
+        let mut stmt = db_manager
+            .conn
+            .prepare("SELECT path, parent_path FROM videos ORDER BY path")
+            .unwrap();
+        let rows: Vec<(String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let mut expected = vec![
+            ("F:/public/show/a.mp4", "F:/public/show"),
+            ("F:/public/show/b.mp4", "F:/public/show"),
+        ];
+        if !cfg!(windows) {
+            expected.insert(0, ("/data/a\\b.mp4", "/data"));
+        }
+        let expected: Vec<(String, String)> = expected
+            .into_iter()
+            .map(|(path, parent)| (path.to_string(), parent.to_string()))
+            .collect();
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn test_file_database_creates_parent_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("data").join("videos.db");
+
+        let db_manager = VideoDbManager::new(&db_path.to_string_lossy()).unwrap();
+        assert!(db_path.is_file());
+
+        let journal_mode: String = db_manager
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+    }
+
+    #[test]
+    fn test_maintenance_reclaims_deleted_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("videos.db");
+        let db_manager = VideoDbManager::new(&db_path.to_string_lossy()).unwrap();
+
+        for i in 0..200 {
+            insert_video(
+                &db_manager,
+                &format!("/videos/{:03}.mp4", i),
+                "mp4",
+                "/videos",
+            );
+        }
+        db_manager.conn.execute("DELETE FROM videos", []).unwrap();
+
+        let report = db_manager.run_maintenance().unwrap();
+        assert!(report.size_before > 0);
+        assert!(report.size_after < report.size_before);
+        assert_eq!(report.freed(), report.size_before - report.size_after);
+    }
+
+    #[test]
+    fn test_write_waits_for_lock_and_retries_stale_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("videos.db").to_string_lossy().to_string();
+        let writer = VideoDbManager::new(&db_path).unwrap();
+        insert_video(&writer, "/videos/a.mp4", "mp4", "/videos");
+        let id = VideoDao::new(&writer)
+            .get_video_by_path("/videos/a.mp4")
+            .unwrap()
+            .unwrap()
+            .id;
+
+        // 等待时间很短时，锁被占用会很快返回 BUSY
+        let reader = VideoDbManager::new(&db_path).unwrap();
+        reader.set_busy_timeout(Duration::from_millis(10)).unwrap();
+        writer.conn.execute_batch("BEGIN IMMEDIATE").unwrap();
+        let err = reader
+            .conn
+            .execute("UPDATE videos SET watched = 1 WHERE id = ?1", [id])
+            .unwrap_err();
+        assert!(is_busy(&err), "unexpected error: {}", err);
+
+        // 默认的 busy_timeout 会等到锁被释放
+        reader.set_busy_timeout(DEFAULT_BUSY_TIMEOUT).unwrap();
+        let holder = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(150));
+            writer.conn.execute_batch("COMMIT").unwrap();
+            writer
+        });
+        assert_eq!(VideoDao::new(&reader).set_watched(id, true).unwrap(), 1);
+        let writer = holder.join().unwrap();
+
+        // 读事务开始后另一个连接提交了写入，升级为写事务时快照已过期，重新开始事务后成功
+        let mut attempts = 0;
+        let updated = retry_on_busy(|| {
+            attempts += 1;
+            let tx = reader.conn.unchecked_transaction()?;
+            let watched: bool =
+                tx.query_row("SELECT watched FROM videos WHERE id = ?1", [id], |row| {
+                    row.get(0)
+                })?;
+            if attempts == 1 {
+                writer
+                    .conn
+                    .execute("UPDATE videos SET favorite = 1 WHERE id = ?1", [id])?;
+            }
+            let updated = tx.execute(
+                "UPDATE videos SET watched = ?1 WHERE id = ?2",
+                rusqlite::params![!watched, id],
+            )?;
+            tx.commit()?;
+            Ok(updated)
+        })
+        .unwrap();
+        assert_eq!(updated, 1);
+        assert_eq!(attempts, 2);
+
+        let (watched, favorite): (bool, bool) = reader
+            .conn
+            .query_row(
+                "SELECT watched, favorite FROM videos WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(!watched);
+        assert!(favorite);
+    }
+}
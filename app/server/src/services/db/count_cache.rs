@@ -0,0 +1,70 @@
+//! 分页总数缓存
+//!
+//! 翻页时过滤条件不变，总数也不会变化，缓存 `COUNT(*)` 的结果避免每页都重新统计。
+//! 缓存在 TTL 后过期，并在同步、删除、重建等写操作后整体失效。
+
+use rusqlite::Result;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::services::db::video_dao::VideoFilter;
+
+/// 总数缓存的默认有效期
+pub const COUNT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// 按过滤条件缓存的总数
+pub struct CountCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<VideoFilter, (u64, Instant)>>,
+    /// 实际执行的统计查询次数
+    queries: AtomicUsize,
+}
+
+impl CountCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            queries: AtomicUsize::new(0),
+        }
+    }
+
+    /// 获取缓存的总数，未命中或已过期时调用 `count` 重新统计
+    pub fn get_or_count<F>(&self, filter: &VideoFilter, count: F) -> Result<u64>
+    where
+        F: FnOnce() -> Result<u64>,
+    {
+        if let Some((total, cached_at)) = self.entries.lock().unwrap().get(filter) {
+            if cached_at.elapsed() < self.ttl {
+                return Ok(*total);
+            }
+        }
+
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        let total = count()?;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(filter.clone(), (total, Instant::now()));
+        Ok(total)
+    }
+
+    /// 清空所有缓存（数据发生变化后调用）
+    pub fn invalidate(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// 实际执行的统计查询次数
+    #[cfg(test)]
+    pub fn query_count(&self) -> usize {
+        self.queries.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for CountCache {
+    fn default() -> Self {
+        Self::new(COUNT_CACHE_TTL)
+    }
+}
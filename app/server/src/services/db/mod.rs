@@ -3,11 +3,14 @@
 //! 提供视频数据的数据库管理功能，包括连接管理、数据访问、目录同步、文件监听等。
 
 pub mod connection;
+pub mod count_cache;
 pub mod schema;
 pub mod sync;
 pub mod tree;
 pub mod video_dao;
 
 pub use connection::VideoDbManager;
-pub use sync::DirectorySync;
-pub use video_dao::VideoDao;
+pub use sync::{AuditReport, DirectorySync, RefreshedFile, SyncReport};
+pub use video_dao::{
+    DirectoryTotals, HistogramMetric, ListCursor, TopBy, VideoDao, VideoFilter, VideoSort,
+};
@@ -5,24 +5,107 @@
 /// 视频类型常量
 pub mod video_types {
     pub const MP4: &str = "mp4";
+    /// HLS 播放列表
+    pub const M3U8: &str = "m3u8";
+    /// 独立的 MPEG-TS 视频
+    pub const TS: &str = "ts";
     pub const SUBTITLE: &str = "subtitle";
     pub const IMAGE: &str = "image";
+    /// 包含媒体文件的目录
+    pub const DIRECTORY: &str = "directory";
+    /// 直接包含 .m3u8 播放列表的目录（HLS 切片目录）
+    pub const HLS_DIRECTORY: &str = "hls_directory";
     pub const UNKNOWN: &str = "unknown";
+
+    /// 可播放的视频类型
+    pub const VIDEO_TYPES: &[&str] = &[MP4, M3U8, TS];
+
+    /// 所有已知类型
+    pub const ALL: &[&str] = &[
+        MP4,
+        M3U8,
+        TS,
+        SUBTITLE,
+        IMAGE,
+        DIRECTORY,
+        HLS_DIRECTORY,
+        UNKNOWN,
+    ];
+
+    /// 是否为已知类型
+    pub fn is_known(file_type: &str) -> bool {
+        ALL.contains(&file_type)
+    }
 }
 
+/// 目录缩略图来源
+pub mod thumbnail_sources {
+    /// 目录中的 poster.jpg / folder.jpg / cover.jpg
+    pub const POSTER: &str = "poster";
+    /// 目录中第一个视频的缩略图
+    pub const FIRST_CHILD: &str = "first_child";
+
+    /// 目录封面文件名，按优先级排列
+    pub const POSTER_FILE_NAMES: &[&str] = &["poster.jpg", "folder.jpg", "cover.jpg"];
+}
+
+/// 后续版本新增的列（列名, 列定义），启动时自动补齐到旧数据库
+pub const ADDED_COLUMNS: &[(&str, &str)] = &[
+    // 章节信息（ffprobe 输出解析后的 JSON）
+    ("chapters", "TEXT"),
+    // 目录下（递归）可播放视频的数量，同步后统一更新
+    ("child_count", "INTEGER"),
+    // 缩略图生成时间（Unix 秒），源文件修改时间比它新时重新生成缩略图
+    ("thumbnail_generated_at", "INTEGER"),
+    // 目录缩略图的来源（thumbnail_sources）
+    ("thumbnail_source", "TEXT"),
+    // 是否已看完（0/1）
+    ("watched", "INTEGER NOT NULL DEFAULT 0"),
+    // 上次播放到的位置（秒）
+    ("watch_position", "REAL"),
+    // 创建时间（Unix 秒），created_at 是它按显示时区格式化后的结果
+    ("created_epoch", "INTEGER"),
+    // 是否收藏（0/1）
+    ("favorite", "INTEGER NOT NULL DEFAULT 0"),
+    // 时长（秒），duration 字段解析后的数值，用于排序
+    ("duration_secs", "REAL"),
+    // 大小（字节），size 字段解析后的数值，用于排序
+    ("size_bytes", "INTEGER"),
+    // 播放次数，每次开始播放时加一
+    ("watch_count", "INTEGER NOT NULL DEFAULT 0"),
+    // 搜索用的名称：小写并去掉重音符号（normalize_for_search）
+    ("name_search", "TEXT"),
+    // 目录内的自定义顺序（从 0 开始），未设置时为 NULL，按 sort_by=custom 排序时排在最后
+    ("sort_index", "INTEGER"),
+    // 严格元数据模式下无法读取元数据的原因，读取成功时为 NULL
+    ("error", "TEXT"),
+    // 缓存章节时文件的修改时间（Unix 秒），与文件当前的修改时间不一致时缓存失效
+    ("chapters_modified", "INTEGER"),
+];
+
+/// orphaned_watch_state 表在后续版本中新增的列（迁移时自动添加）
+pub const ORPHANED_WATCH_STATE_ADDED_COLUMNS: &[(&str, &str)] = &[
+    // 大小（字节）和时长（秒），用于查找移动后的文件
+    ("size_bytes", "INTEGER"),
+    ("duration_secs", "REAL"),
+    // 标签名称的 JSON 数组
+    ("tags", "TEXT"),
+];
+
+/// 播放进度超过时长的该比例时自动标记为已看完
+pub const WATCHED_THRESHOLD: f64 = 0.9;
+
 /// SQL 查询语句常量
 pub mod queries {
     /// 插入新视频记录
     pub const INSERT_NEW: &str = "INSERT INTO videos
-        (name, path, type, parent_path, thumbnail, size, created_at, subtitle, last_modified, duration, width, height)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)";
+        (name, path, type, parent_path, thumbnail, size, created_at, subtitle, last_modified, duration, width, height, thumbnail_generated_at, thumbnail_source, created_epoch, duration_secs, size_bytes, name_search, error)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)";
+    /// 标准查询列，顺序与 VideoDao 的行映射一致
+    pub const VIDEO_COLUMNS: &str = "name, path, type, thumbnail, duration, size, resolution, bitrate, codec, created_at, subtitle, width, height, id, parent_path, child_count, thumbnail_source, watched, watch_position, favorite, watch_count, error";
     /// 获取视频总数
     pub const SELECT_ALL_COUNT: &str = "SELECT COUNT(*) FROM videos";
-    /// 获取所有视频记录，按创建时间倒序排序（最新在前）
-    pub const SELECT_ALL: &str = "SELECT  name, path, type, thumbnail, duration, size, resolution, bitrate, codec, created_at, subtitle, parent_path, width, height,id
-        FROM videos
-        ORDER BY created_at DESC";
     /// 获取所有视频记录的完整信息（不排序）
-    pub const SELECT_ALL_FULL: &str = "SELECT name, path, type, thumbnail, duration, size, resolution, bitrate, codec, created_at, subtitle, parent_path, width, height
+    pub const SELECT_ALL_FULL: &str = "SELECT name, path, type, thumbnail, duration, size, resolution, bitrate, codec, created_at, subtitle, parent_path, width, height, child_count, thumbnail_generated_at, thumbnail_source, last_modified, watched, watch_position, created_epoch, favorite, watch_count, error
         FROM videos";
 }
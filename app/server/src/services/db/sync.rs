@@ -6,25 +6,104 @@
 //! - 双向同步
 //! - 流式处理优化
 
+use crate::config::{get_config, ScanConfig, SyncConfig, ThumbnailMode};
 use crate::services::db::connection::VideoDbManager;
-use crate::services::db::schema::{queries, video_types};
-use crate::services::ffmpeg::get_ffmpeg_service;
+use crate::services::db::schema::{queries, thumbnail_sources, video_types};
+use crate::services::db::video_dao::VideoDao;
+use crate::services::ffmpeg::{
+    get_ffmpeg_service, is_hls_output_dir, FFmpegService, THUMBNAILS_DIR,
+};
 use crate::DiskMapping;
 use std::time::Instant;
 
-use crate::utils::{format_size, get_systemtime_created, get_video_info, is_video_or_container};
+use crate::utils::media_types::{self, classify, MediaKind};
+use crate::utils::{
+    created_epoch, db_path, format_created_at, format_duration, format_size, get_m3u8_duration,
+    get_systemtime_created, get_ts_duration, get_video_info, is_video_or_container, natural_cmp,
+    normalize_for_search, normalize_separators, parse_duration, parse_size, IgnoreMatcher,
+};
 use log::{debug, info, warn};
-use rayon::prelude::*;
-use rusqlite::Result;
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex as StdMutex};
+use rusqlite::{OptionalExtension, Result};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
 use walkdir::WalkDir;
 
+/// 同步专用的 rayon 线程池，首次使用时按全局配置创建
+static SYNC_POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
+
+/// 按配置创建同步线程池（threads 为 0 时使用 CPU 核心数）
+fn build_sync_pool(config: &SyncConfig) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .thread_name(|index| format!("sync-worker-{}", index))
+        .build()
+        .expect("Failed to build sync thread pool")
+}
+
+/// 获取同步专用线程池
+fn sync_pool() -> &'static rayon::ThreadPool {
+    SYNC_POOL.get_or_init(|| build_sync_pool(&get_config().sync))
+}
+
 /// 目录同步器
 /// 负责扫描文件系统并同步数据到数据库，实现双向同步
 pub struct DirectorySync<'a> {
     db_manager: &'a VideoDbManager,
+    scan_config: ScanConfig,
+    /// 每个并行任务处理的条目数
+    batch_size: usize,
+    /// 是否在 error 列中标记无法读取元数据的视频
+    strict_metadata: bool,
+    /// 生成缩略图和读取元数据使用的设置
+    thumbnails: ThumbnailContext,
+}
+
+/// 处理单个文件时使用的缩略图设置，在同步线程池中共享
+#[derive(Clone)]
+struct ThumbnailContext {
+    /// 缩略图生成时机，lazy 时同步只读取元数据
+    mode: ThumbnailMode,
+    /// 生成缩略图和读取元数据使用的 FFmpeg 服务
+    ffmpeg: &'static FFmpegService,
+    /// 缩略图存放目录，默认为 [`THUMBNAILS_DIR`]
+    dir: Arc<Path>,
+}
+
+impl ThumbnailContext {
+    /// 使用全局 FFmpeg 服务和默认的缩略图目录
+    fn new(mode: ThumbnailMode) -> Self {
+        Self {
+            mode,
+            ffmpeg: get_ffmpeg_service(),
+            dir: Arc::from(Path::new(THUMBNAILS_DIR)),
+        }
+    }
+
+    fn is_lazy(&self) -> bool {
+        self.mode == ThumbnailMode::Lazy
+    }
+
+    /// 文件的缩略图路径
+    fn path_for(&self, file_path: &Path) -> PathBuf {
+        DirectorySync::thumbnail_path_in(&self.dir, file_path)
+    }
+
+    /// 目录封面的缩略图路径
+    fn directory_path_for(&self, dir_path: &Path) -> PathBuf {
+        DirectorySync::directory_thumbnail_path_in(&self.dir, dir_path)
+    }
+}
+
+/// 重新读取单个文件的结果，由 [`DirectorySync::scan_refresh`] 在不持有数据库锁时生成
+#[derive(Debug, Clone)]
+pub struct RefreshedFile {
+    file_info: FileInfo,
+    /// 读取时间（Unix 秒），作为记录的同步时间
+    scanned_at: String,
+    /// MP4 的编码和码率
+    codec_bitrate: Option<(Option<String>, Option<String>)>,
 }
 
 /// 文件信息结构体，用于比较文件和数据库记录
@@ -32,7 +111,10 @@ pub struct DirectorySync<'a> {
 pub struct FileInfo {
     pub name: String,
     pub path: String,
+    /// 按显示时区格式化后的创建时间
     pub created_at: String,
+    /// 创建时间（Unix 秒），修改显示时区后据此重新格式化 created_at
+    pub created_epoch: Option<i64>,
     pub file_type: String,
     pub parent_path: String,
     pub thumbnail: Option<String>,
@@ -41,6 +123,86 @@ pub struct FileInfo {
     pub duration: Option<String>,
     pub width: Option<i32>,
     pub height: Option<i32>,
+    /// 缩略图生成时间（Unix 秒）
+    pub thumbnail_generated_at: Option<i64>,
+    /// 目录缩略图的来源（thumbnail_sources）
+    pub thumbnail_source: Option<String>,
+    /// 文件修改时间（Unix 秒），同步时用来判断文件是否被修改过
+    pub last_modified: Option<i64>,
+}
+
+/// 同步结果统计
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub new: usize,
+    pub changed: usize,
+    pub deleted: usize,
+    pub skipped: usize,
+    /// 无法读取的条目数量（权限不足、断开的符号链接等）
+    pub unreadable: usize,
+    /// 同步耗时（毫秒）
+    pub elapsed_ms: u64,
+    /// 同步完成时间
+    pub finished_at: String,
+    /// 是否为演练（只计算变更，不写入数据库）
+    pub dry_run: bool,
+    /// 各类变更的示例路径（只在演练时填充）
+    pub examples: SyncExamples,
+}
+
+/// 目录封面缩略图的文件名，见 [`DirectorySync::directory_thumbnail_path_in`]
+const DIRECTORY_THUMBNAIL_NAME: &str = ".folder.jpg";
+
+/// 已合并为 MP4 的 HLS 目录中的标记文件，扫描时跳过带有该标记的目录，
+/// 见 [`DirectorySync::replace_hls_directory`]
+const MERGED_HLS_MARKER: &str = ".merged";
+
+/// 演练报告中每类变更最多列出的示例路径数量
+pub const MAX_EXAMPLE_PATHS: usize = 10;
+
+/// 各类变更的示例路径，每类最多 [`MAX_EXAMPLE_PATHS`] 条
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncExamples {
+    pub new: Vec<String>,
+    pub changed: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+impl SyncExamples {
+    fn push(list: &mut Vec<String>, path: &str) {
+        if list.len() < MAX_EXAMPLE_PATHS {
+            list.push(path.to_string());
+        }
+    }
+}
+
+/// 文件系统与数据库的一致性检查结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    /// 磁盘上存在、数据库中没有记录的条目数量
+    pub missing_from_db: usize,
+    /// 数据库中有记录、磁盘上已不存在的条目数量
+    pub missing_on_disk: usize,
+    /// 无法读取的数据源目录或其中的子目录，其下的记录不计入 missing_on_disk
+    pub unreadable_dirs: Vec<String>,
+    /// 两类差异的示例路径，每类最多 [`MAX_EXAMPLE_PATHS`] 条
+    pub examples: AuditExamples,
+}
+
+/// 一致性检查中两类差异的示例路径
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditExamples {
+    pub missing_from_db: Vec<String>,
+    pub missing_on_disk: Vec<String>,
+}
+
+impl SyncReport {
+    /// 记录耗时和完成时间
+    fn finish(mut self, start_time: Instant) -> Self {
+        self.elapsed_ms = start_time.elapsed().as_millis() as u64;
+        self.finished_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self
+    }
 }
 
 /// 待处理的文件条目
@@ -49,20 +211,72 @@ struct PendingEntry {
     path: PathBuf,
 }
 
+/// 替换记录时保留的用户状态：(id, watched, watch_position, favorite, watch_count, sort_index)
+type PreservedState = (i64, bool, Option<f64>, bool, i64, Option<i64>);
+
 impl<'a> DirectorySync<'a> {
-    /// 创建新的目录同步器
+    /// 创建新的目录同步器（使用全局扫描配置）
     pub fn new(db_manager: &'a VideoDbManager) -> Self {
-        Self { db_manager }
+        Self {
+            db_manager,
+            scan_config: get_config().scan.clone(),
+            batch_size: get_config().sync.batch_size.max(1),
+            strict_metadata: get_config().sync.strict_metadata,
+            thumbnails: ThumbnailContext::new(get_config().thumbnail.mode),
+        }
+    }
+
+    /// 使用指定的缩略图生成时机
+    #[cfg(test)]
+    pub fn with_thumbnail_mode(mut self, thumbnail_mode: ThumbnailMode) -> Self {
+        self.thumbnails.mode = thumbnail_mode;
+        self
+    }
+
+    /// 使用指定的 FFmpeg 服务（测试中替换为假的 ffmpeg）
+    #[cfg(test)]
+    pub fn with_ffmpeg(mut self, ffmpeg: &'static FFmpegService) -> Self {
+        self.thumbnails.ffmpeg = ffmpeg;
+        self
+    }
+
+    /// 使用指定的缩略图目录（测试中使用临时目录）
+    #[cfg(test)]
+    pub fn with_thumbnail_dir(mut self, dir: &Path) -> Self {
+        self.thumbnails.dir = Arc::from(dir);
+        self
+    }
+
+    /// 使用指定的扫描配置
+    #[cfg(test)]
+    pub fn with_scan_config(mut self, scan_config: ScanConfig) -> Self {
+        self.scan_config = scan_config;
+        self
+    }
+
+    /// 启用或关闭严格元数据模式
+    #[cfg(test)]
+    pub fn with_strict_metadata(mut self, strict_metadata: bool) -> Self {
+        self.strict_metadata = strict_metadata;
+        self
     }
 
     /// 从多个目录初始化数据库（双向同步）
+    ///
+    /// `dry_run` 为 true 时只计算将要新增、更新、删除的条目，不写入数据库也不生成缩略图
     pub fn initialize_from_directory_with_progress(
         &self,
-        mappings: &Vec<DiskMapping>,
+        mappings: &[DiskMapping],
         force: bool,
-    ) -> Result<()> {
+        dry_run: bool,
+    ) -> Result<SyncReport> {
         let start_time = Instant::now();
 
+        if dry_run {
+            info!("演练同步，不写入数据库");
+            return Ok(self.plan_sync(mappings, force)?.finish(start_time));
+        }
+
         info!("正在初始化同步...");
         // 检查数据库是否已初始化
         let mut stmt = self.db_manager.conn.prepare(queries::SELECT_ALL_COUNT)?;
@@ -71,28 +285,157 @@ impl<'a> DirectorySync<'a> {
         if count > 0 && !force {
             info!("数据库已包含 {} 条记录，执行增量同步", count);
 
-            self.bidirectional_sync_with_progress(mappings)?;
+            let report = self.bidirectional_sync_with_progress(mappings);
+            self.db_manager.count_cache.invalidate();
+            let report = report?;
+            VideoDao::new(self.db_manager).refresh_child_counts()?;
             info!("同步完成，耗时: {}ms", start_time.elapsed().as_millis());
-            return Ok(());
+            return Ok(report.finish(start_time));
         }
 
         // 如果 force 为 true 或数据库为空，则清除并重新初始化
         if force {
             info!("强制重新初始化，清除现有数据");
             self.db_manager.conn.execute("DELETE FROM videos", [])?;
+            VideoDao::new(self.db_manager).prune_tag_links()?;
         }
 
         // 执行完整的双向同步
-        self.bidirectional_sync_with_progress(mappings)?;
+        let report = self.bidirectional_sync_with_progress(mappings);
+        self.db_manager.count_cache.invalidate();
+        let report = report?;
+        VideoDao::new(self.db_manager).refresh_child_counts()?;
 
         info!("同步完成，耗时: {}ms", start_time.elapsed().as_millis());
 
-        Ok(())
+        Ok(report.finish(start_time))
+    }
+
+    /// 数据库中所有记录的路径，供 [`Self::audit`] 与磁盘比较
+    pub fn record_paths(&self) -> Result<HashSet<String>> {
+        let mut stmt = self.db_manager.conn.prepare("SELECT path FROM videos")?;
+        let paths = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<HashSet<String>>>()?;
+        Ok(paths)
+    }
+
+    /// 比较文件系统与数据库记录（只读）：找出尚未入库的文件和文件已不存在的记录
+    ///
+    /// 会遍历所有数据源目录，不访问数据库；`db_records` 由 [`Self::record_paths`] 读取，
+    /// 调用方应在读取后释放数据库锁再调用
+    pub fn audit(
+        scan_config: &ScanConfig,
+        mappings: &[DiskMapping],
+        db_records: &HashSet<String>,
+    ) -> AuditReport {
+        let mut on_disk: HashMap<String, ()> = HashMap::new();
+        let mut unreadable_dirs: Vec<PathBuf> = Vec::new();
+        for mapping in mappings {
+            let root = Path::new(&mapping.physical_path);
+            unreadable_dirs.extend(Self::collect_file_paths(scan_config, root, &mut on_disk));
+        }
+
+        let mut missing_from_db: Vec<&String> = on_disk
+            .keys()
+            .filter(|path| !db_records.contains(*path))
+            .collect();
+        // 与同步一致：目录无法读取时不能据此判断文件已删除
+        let mut missing_on_disk: Vec<&String> = db_records
+            .iter()
+            .filter(|path| !on_disk.contains_key(*path) && !is_outside_scan(path, &unreadable_dirs))
+            .collect();
+        missing_from_db.sort();
+        missing_on_disk.sort();
+
+        let mut report = AuditReport {
+            missing_from_db: missing_from_db.len(),
+            missing_on_disk: missing_on_disk.len(),
+            unreadable_dirs: unreadable_dirs.iter().map(|dir| db_path(dir)).collect(),
+            ..AuditReport::default()
+        };
+        for path in missing_from_db {
+            SyncExamples::push(&mut report.examples.missing_from_db, path);
+        }
+        for path in missing_on_disk {
+            SyncExamples::push(&mut report.examples.missing_on_disk, path);
+        }
+
+        info!(
+            "一致性检查完成: {} 个条目未入库, {} 条记录的文件已不存在",
+            report.missing_from_db, report.missing_on_disk
+        );
+        report
+    }
+
+    /// 计算同步将要进行的变更（不写入数据库），判断规则与实际同步一致
+    fn plan_sync(&self, mappings: &[DiskMapping], force: bool) -> Result<SyncReport> {
+        // 强制重新初始化时会先清空数据库，所有条目都视为新增
+        let db_records = if force {
+            HashMap::new()
+        } else {
+            self.get_all_db_records()?
+        };
+
+        let mut report = SyncReport {
+            dry_run: true,
+            ..SyncReport::default()
+        };
+        let mut found: HashMap<String, ()> = HashMap::new();
+        let mut unreadable_dirs: Vec<PathBuf> = Vec::new();
+
+        for mapping in mappings {
+            let root = Path::new(&mapping.physical_path);
+            if !root.exists() {
+                warn!("根目录不存在: {}", root.display());
+                unreadable_dirs.push(root.to_path_buf());
+                continue;
+            }
+
+            let scan = Self::scan_media_entries(&self.scan_config, root, true);
+            report.unreadable += scan.unreadable;
+            unreadable_dirs.extend(scan.unreadable_paths);
+
+            for path in scan.paths {
+                if !path.is_dir() && !path.is_file() {
+                    continue;
+                }
+                let path_str = db_path(&path);
+                match db_records.get(&path_str) {
+                    None => {
+                        report.new += 1;
+                        SyncExamples::push(&mut report.examples.new, &path_str);
+                    }
+                    Some(record) if Self::is_up_to_date(&path, record, &self.thumbnails) => {
+                        report.skipped += 1
+                    }
+                    Some(_) => {
+                        report.changed += 1;
+                        SyncExamples::push(&mut report.examples.changed, &path_str);
+                    }
+                }
+                found.insert(path_str, ());
+            }
+        }
+
+        for path in db_records.keys() {
+            if found.contains_key(path) || is_outside_scan(path, &unreadable_dirs) {
+                continue;
+            }
+            report.deleted += 1;
+            SyncExamples::push(&mut report.examples.deleted, path);
+        }
+
+        info!(
+            "演练完成: 将新增 {}, 更新 {}, 删除 {}, 跳过 {}",
+            report.new, report.changed, report.deleted, report.skipped
+        );
+        Ok(report)
     }
 
     /// 双向同步：文件系统 -> 数据库 + 数据库 -> 文件系统
     /// 优化版本：使用流式处理，减少内存占用
-    fn bidirectional_sync_with_progress(&self, mappings: &Vec<DiskMapping>) -> Result<()> {
+    fn bidirectional_sync_with_progress(&self, mappings: &[DiskMapping]) -> Result<SyncReport> {
         let current_time = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -104,10 +447,8 @@ impl<'a> DirectorySync<'a> {
         info!("数据库中记录数: {}", db_records.len());
 
         // 2. 使用流式处理同步文件系统
-        let mut new_count = 0;
-        let mut changed_count = 0;
-        let mut skipped_count = 0;
         let mut deleted_count = 0;
+        let mut unreadable_count = 0;
 
         // 使用 Arc 和 Mutex 共享计数器，用于跨线程统计
         let stats = Arc::new(StdMutex::new(Stats {
@@ -126,40 +467,57 @@ impl<'a> DirectorySync<'a> {
             let route_path = &mapping.route_path;
 
             // 流式处理文件系统
-            let (processed_count, error_count) = self.process_filesystem_streaming(
+            let (processed_count, error_count, unreadable) = self.process_filesystem_streaming(
                 physical_path,
                 route_path,
                 &db_records,
                 &current_time,
                 &stats,
             )?;
+            unreadable_count += unreadable;
 
             info!(
-                "处理目录 {} 完成: 处理 {} 个文件, {} 个错误",
-                mapping.route_path, processed_count, error_count
+                "处理目录 {} 完成: 处理 {} 个文件, {} 个错误, {} 个条目无法读取",
+                mapping.route_path, processed_count, error_count, unreadable
             );
         }
 
         // 获取统计结果
         let stats_guard = stats.lock().unwrap();
-        new_count = stats_guard.new;
-        changed_count = stats_guard.changed;
-        skipped_count = stats_guard.skipped;
+        let new_count = stats_guard.new;
+        let changed_count = stats_guard.changed;
+        let skipped_count = stats_guard.skipped;
         drop(stats_guard);
 
         // 3. 处理删除的文件
         let mut processed_files = HashMap::new();
+        let mut unreadable_dirs: Vec<PathBuf> = Vec::new();
         for mapping in mappings.iter() {
-            self.collect_file_paths(Path::new(&mapping.physical_path), &mut processed_files);
+            let root = Path::new(&mapping.physical_path);
+            unreadable_dirs.extend(Self::collect_file_paths(
+                &self.scan_config,
+                root,
+                &mut processed_files,
+            ));
         }
 
         for (path, db_record) in db_records.iter() {
+            // 目录（包括其中的子目录）无法读取，或是扫描跳过的切片输出目录时，不能据此判断文件已删除，保留其记录
+            if is_outside_scan(path, &unreadable_dirs) {
+                continue;
+            }
             if !processed_files.contains_key(path) {
+                // 保留观看状态，文件只是被移动时可以重新关联
+                VideoDao::new(self.db_manager).archive_watch_state(path)?;
                 self.hard_delete_record(path)?;
                 deleted_count += 1;
                 debug!("删除: {}", db_record.name);
             }
         }
+        if deleted_count > 0 {
+            VideoDao::new(self.db_manager).prune_tag_links()?;
+        }
+        VideoDao::new(self.db_manager).prune_orphaned_watch_state()?;
 
         // 输出统计信息
         if new_count > 0 || changed_count > 0 || deleted_count > 0 {
@@ -170,12 +528,24 @@ impl<'a> DirectorySync<'a> {
         } else {
             debug!("无变化，跳过 {} 个文件", skipped_count);
         }
+        if unreadable_count > 0 {
+            warn!("同步过程中有 {} 个条目无法读取", unreadable_count);
+        }
 
-        Ok(())
+        Ok(SyncReport {
+            new: new_count,
+            changed: changed_count,
+            deleted: deleted_count,
+            skipped: skipped_count,
+            unreadable: unreadable_count,
+            ..SyncReport::default()
+        })
     }
 
     /// 流式处理文件系统
     /// 优化版本：边扫描边处理，减少内存占用
+    ///
+    /// 返回 (处理的文件数, 处理失败数, 无法读取的条目数)
     fn process_filesystem_streaming(
         &self,
         root_path: &Path,
@@ -183,43 +553,23 @@ impl<'a> DirectorySync<'a> {
         db_records: &HashMap<String, FileInfo>,
         current_time: &str,
         stats: &Arc<StdMutex<Stats>>,
-    ) -> Result<(usize, usize)> {
+    ) -> Result<(usize, usize, usize)> {
         let root = PathBuf::from(root_path);
 
         // 检查根目录是否存在
         if !root.exists() {
             warn!("根目录不存在: {}", root_path.display());
-            return Ok((0, 0));
+            return Ok((0, 0, 0));
         }
 
-        // 第一步：收集所有待处理的文件条目
-        let mut pending_entries: Vec<PendingEntry> = Vec::new();
-        let mut collect_errors: Vec<String> = Vec::new();
-
-        for entry in WalkDir::new(&root).max_depth(1).into_iter() {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(e) => {
-                    collect_errors.push(format!("无法访问条目: {}", e));
-                    continue;
-                }
-            };
-
-            let path = entry.path();
-            // 跳过根目录本身
-            if path == root {
-                continue;
-            }
-
-            // 检查是否为视频相关文件或目录
-            if !is_video_or_container(path) {
-                continue;
-            }
-
-            pending_entries.push(PendingEntry {
-                path: path.to_path_buf(),
-            });
-        }
+        // 第一步：收集所有待处理的文件和目录条目
+        let scan = Self::scan_media_entries(&self.scan_config, &root, true);
+        let unreadable = scan.unreadable;
+        let pending_entries: Vec<PendingEntry> = scan
+            .paths
+            .into_iter()
+            .map(|path| PendingEntry { path })
+            .collect();
 
         debug!("收集到 {} 个待处理条目", pending_entries.len());
 
@@ -232,8 +582,7 @@ impl<'a> DirectorySync<'a> {
         let (tx, rx) = std::sync::mpsc::channel();
 
         // 分批处理以避免通道过大
-        let batch_size = 100;
-        let batches: Vec<_> = pending_entries.chunks(batch_size).collect();
+        let batches: Vec<_> = pending_entries.chunks(self.batch_size).collect();
 
         // 处理批次
         for batch in batches {
@@ -247,9 +596,10 @@ impl<'a> DirectorySync<'a> {
             let processed_counter_ref = processed_counter.clone();
             let error_counter_ref = error_counter.clone();
             let total_ref = total;
+            let thumbnails = self.thumbnails.clone();
 
-            // 在线程池中处理每个批次
-            rayon::spawn(move || {
+            // 在同步专用线程池中处理每个批次
+            sync_pool().spawn(move || {
                 for entry in batch {
                     let result = Self::process_file_static(
                         &entry.path,
@@ -258,6 +608,7 @@ impl<'a> DirectorySync<'a> {
                         &db_records_ref,
                         &current_time_ref,
                         &stats_ref,
+                        &thumbnails,
                     );
 
                     match result {
@@ -280,7 +631,7 @@ impl<'a> DirectorySync<'a> {
                     *guard += 1;
                     let processed = *guard;
 
-                    if processed % 10 == 0 || processed == total_ref {
+                    if processed.is_multiple_of(10) || processed == total_ref {
                         info!("已处理 {} / {}", processed, total_ref);
                     }
                 }
@@ -303,10 +654,7 @@ impl<'a> DirectorySync<'a> {
                         }
                         Some(db_record) => {
                             if self.is_record_changed(&file_info, db_record) {
-                                if let Err(e) = self.hard_delete_record(&file_info.path) {
-                                    warn!("删除旧记录失败: {} - {}", file_info.name, e);
-                                }
-                                if let Err(e) = self.insert_new_record(&file_info, current_time) {
+                                if let Err(e) = self.replace_record(&file_info, current_time) {
                                     warn!("更新记录失败: {} - {}", file_info.name, e);
                                 }
                             }
@@ -323,21 +671,111 @@ impl<'a> DirectorySync<'a> {
         let processed = *processed_counter.lock().unwrap();
         let errors = *error_counter.lock().unwrap();
 
-        Ok((processed, errors))
+        Ok((processed, errors, unreadable))
     }
 
     /// 收集文件路径（用于检测删除的文件）
-    fn collect_file_paths(&self, root: &Path, paths: &mut HashMap<String, ()>) {
-        for entry in WalkDir::new(root)
-            .max_depth(1)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
+    ///
+    /// 返回无法读取的路径（根目录或其中的子目录），调用方不应删除这些路径下的记录
+    fn collect_file_paths(
+        scan_config: &ScanConfig,
+        root: &Path,
+        paths: &mut HashMap<String, ()>,
+    ) -> Vec<PathBuf> {
+        // 错误已在扫描阶段以 warn 级别记录，这里不再重复
+        let scan = Self::scan_media_entries(scan_config, root, false);
+        for path in scan.paths {
+            paths.insert(db_path(&path), ());
+        }
+
+        scan.unreadable_paths
+    }
+
+    /// 递归扫描根目录，返回媒体文件以及包含媒体文件的目录
+    ///
+    /// 匹配忽略规则的文件和目录会被跳过，被忽略的目录不会继续向下扫描；
+    /// 设置了最大深度时，达到上限的目录只记录目录本身
+    fn scan_media_entries(scan_config: &ScanConfig, root: &Path, log_errors: bool) -> ScanResult {
+        let ignore = IgnoreMatcher::new(&scan_config.ignore);
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut directories: BTreeSet<PathBuf> = BTreeSet::new();
+        // 目录是否为 HLS 目录，避免对每个切片都重新读取目录
+        let mut hls_directories: HashMap<PathBuf, bool> = HashMap::new();
+        let mut unreadable = 0;
+        let mut unreadable_paths: Vec<PathBuf> = Vec::new();
+
+        // 跟随符号链接时 WalkDir 会检测循环并以错误返回
+        let mut walker = WalkDir::new(root).follow_links(scan_config.follow_symlinks);
+        if let Some(max_depth) = scan_config.max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+        let walker = walker.into_iter().filter_entry(|e| {
+            e.depth() == 0
+                || !(ignore.is_ignored(e.path())
+                    || is_merged_hls_directory(e)
+                    || is_packaged_hls_directory(e))
+        });
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) if e.loop_ancestor().is_some() => {
+                    if log_errors {
+                        warn!("跳过符号链接循环: {}", walk_error_path(&e));
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    if log_errors {
+                        warn!("无法访问条目 {}: {}", walk_error_path(&e), e);
+                    } else {
+                        debug!("无法访问条目 {}: {}", walk_error_path(&e), e);
+                    }
+                    unreadable_paths.push(e.path().unwrap_or(root).to_path_buf());
+                    unreadable += 1;
+                    continue;
+                }
+            };
+
             let path = entry.path();
-            if path == root || !is_video_or_container(path) {
+            if path == root {
+                continue;
+            }
+            // 达到深度上限的目录不会继续向下扫描，记录目录本身及其上级目录
+            if entry.file_type().is_dir() && scan_config.max_depth == Some(entry.depth()) {
+                insert_with_ancestors(&mut directories, path, root);
+                continue;
+            }
+            // 跳过非视频相关文件
+            if !is_video_or_container(path) {
                 continue;
             }
-            paths.insert(path.to_string_lossy().to_string(), ());
+            // HLS 目录中的 .ts 切片属于播放列表，不单独入库
+            if classify(path) == Some(MediaKind::TransportStream) {
+                if let Some(parent) = path.parent() {
+                    let is_hls = *hls_directories
+                        .entry(parent.to_path_buf())
+                        .or_insert_with(|| directory_type(parent) == video_types::HLS_DIRECTORY);
+                    if is_hls {
+                        continue;
+                    }
+                }
+            }
+
+            // 记录媒体文件所在的各级目录（不包含根目录）
+            if let Some(parent) = path.parent() {
+                insert_with_ancestors(&mut directories, parent, root);
+            }
+            files.push(path.to_path_buf());
+        }
+
+        let mut paths: Vec<PathBuf> = directories.into_iter().collect();
+        paths.extend(files);
+
+        ScanResult {
+            paths,
+            unreadable,
+            unreadable_paths,
         }
     }
 
@@ -360,6 +798,10 @@ impl<'a> DirectorySync<'a> {
                 parent_path: row.get(11)?,
                 width: row.get(12)?,
                 height: row.get(13)?,
+                thumbnail_generated_at: row.get(15)?,
+                thumbnail_source: row.get(16)?,
+                last_modified: row.get(17)?,
+                created_epoch: row.get(20)?,
             };
             records.insert(record.path.clone(), record);
         }
@@ -371,52 +813,39 @@ impl<'a> DirectorySync<'a> {
     /// 优化版本：增加数据库记录比较，避免不必要的处理
     fn process_file_static(
         path: &Path,
-        _root: &Path,
+        root: &Path,
         route_path: &str,
         db_records: &HashMap<String, FileInfo>,
-        current_time: &str,
+        _current_time: &str,
         stats: &Arc<StdMutex<Stats>>,
+        thumbnails: &ThumbnailContext,
     ) -> std::result::Result<Option<FileInfo>, String> {
-        if !path.is_file() {
+        let lazy = thumbnails.is_lazy();
+        if path.is_dir() {
+            return Ok(Self::process_directory_static(
+                path, root, route_path, db_records, stats, thumbnails,
+            ));
+        }
+        if !path.is_file() || is_hls_segment(path) {
             return Ok(None);
         }
 
-        let path_str = path.to_string_lossy().to_string();
+        let path_str = db_path(path);
+
+        let source_modified = modified_secs(path);
 
         // 检查是否已存在于数据库中，如果存在且未变更，则跳过处理
-        if let Some(db_record) = db_records.get(&path_str) {
-            let metadata = std::fs::metadata(path).ok();
-            let created_at = metadata
-                .as_ref()
-                .and_then(|m| get_systemtime_created(m))
-                .unwrap_or_default();
-
-            // 如果创建时间相同，且已有缩略图和尺寸信息，则跳过详细处理
-            if created_at == db_record.created_at
-                && db_record.thumbnail.is_some()
-                && db_record.width.is_some()
-                && db_record.height.is_some()
-            {
-                // 更新跳过计数
-                let mut stats_guard = stats.lock().unwrap();
-                stats_guard.skipped += 1;
-                return Ok(None);
-            }
+        if db_records
+            .get(&path_str)
+            .is_some_and(|record| Self::is_up_to_date(path, record, thumbnails))
+        {
+            let mut stats_guard = stats.lock().unwrap();
+            stats_guard.skipped += 1;
+            return Ok(None);
         }
 
-        let extension = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
         // 确定文件类型
-        let file_type = match extension.as_str() {
-            "mp4" => video_types::MP4,
-            "vtt" | "srt" => video_types::SUBTITLE,
-            "jpg" | "png" | "gif" => video_types::IMAGE,
-            _ => video_types::UNKNOWN,
-        };
+        let file_type = file_type_for(path);
 
         let name = path
             .file_name()
@@ -427,56 +856,100 @@ impl<'a> DirectorySync<'a> {
         // 获取文件元数据
         let metadata = std::fs::metadata(path).ok();
         let size = metadata.as_ref().map(|m| format_size(m.len()));
-        let created_at = metadata
-            .as_ref()
-            .and_then(|m| get_systemtime_created(m))
+        let created_epoch = metadata.as_ref().and_then(created_epoch);
+        let created_at = created_epoch
+            .and_then(format_created_at)
             .unwrap_or_default();
 
         // 获取缩略图路径
-        let thumb_path = Self::get_thumbnail_path(path);
+        let thumb_path = thumbnails.path_for(path);
+
+        // 源文件在缩略图生成后被修改过（原地编辑），删除旧缩略图以便重新生成
+        let generated_at = db_records
+            .get(&path_str)
+            .and_then(|record| record.thumbnail_generated_at)
+            .or_else(|| modified_secs(&thumb_path));
+        if thumb_path.exists() && is_thumbnail_stale(source_modified, generated_at) {
+            info!("源文件比缩略图新，重新生成缩略图: {}", path_str);
+            if let Err(e) = std::fs::remove_file(&thumb_path) {
+                warn!("删除过期缩略图失败: {} - {}", thumb_path.display(), e);
+            }
+        }
         // 使用统一的 FFmpeg 服务获取视频信息
         let (thumbnail, duration, width, height) = if file_type == video_types::MP4 {
-            let ffmpeg = get_ffmpeg_service();
-            // 检查缩略图是否已存在
-            if thumb_path.exists() {
-                let video_info = get_video_info(&path.to_string_lossy().to_string());
+            let ffmpeg = thumbnails.ffmpeg;
+            // 缩略图已存在，或 lazy 模式下只读取元数据、记录缩略图的预期路径，第一次请求时再生成
+            if thumb_path.exists() || lazy {
+                let video_info = get_video_info(path.to_string_lossy().as_ref());
                 match video_info {
                     Ok(info) => (
-                        Some(thumb_path.to_string_lossy().to_string()),
+                        Some(db_path(&thumb_path)),
                         Some(info.duration.to_string()),
                         Some(info.width as i32),
                         Some(info.height as i32),
                     ),
-                    Err(_) => (
-                        Some(thumb_path.to_string_lossy().to_string()),
-                        None,
-                        None,
-                        None,
-                    ),
+                    Err(e) => {
+                        // 头部损坏时改用 ffprobe，不影响其他文件的同步
+                        warn!("{}，改用 ffprobe 读取元数据", e);
+                        let (duration, width, height) = match ffmpeg.probe_video_metadata(path) {
+                            Some((duration, width, height)) => {
+                                (Some(duration), Some(width), Some(height))
+                            }
+                            None => (None, None, None),
+                        };
+                        (Some(db_path(&thumb_path)), duration, width, height)
+                    }
                 }
             } else {
                 info!("---------------------");
                 let metadata = ffmpeg.extract_video_info(path, &thumb_path);
                 (
-                    metadata.thumbnail_path,
+                    metadata.thumbnail_path.as_deref().map(normalize_separators),
                     metadata.duration,
                     metadata.width,
                     metadata.height,
                 )
             }
+        } else if file_type == video_types::M3U8 {
+            // 直播播放列表（没有 #EXT-X-ENDLIST）的时长未知
+            let duration = get_m3u8_duration(path).map(format_duration);
+            (
+                Self::ensure_thumbnail_static(path, thumbnails),
+                duration,
+                None,
+                None,
+            )
+        } else if file_type == video_types::TS {
+            // MPEG-TS 没有可以快速解析的文件头，用 ffprobe 读取；ffprobe 不可用时根据 PTS 估算时长
+            let (duration, width, height) = match thumbnails.ffmpeg.probe_video_metadata(path) {
+                Some((duration, width, height)) => (Some(duration), Some(width), Some(height)),
+                None => (get_ts_duration(path).map(format_duration), None, None),
+            };
+            (
+                Self::ensure_thumbnail_static(path, thumbnails),
+                duration,
+                width,
+                height,
+            )
         } else {
-            (Self::ensure_thumbnail_static(path), None, None, None)
+            (
+                Self::ensure_thumbnail_static(path, thumbnails),
+                None,
+                None,
+                None,
+            )
         };
 
         // 获取字幕路径
         let subtitle = if file_type == video_types::SUBTITLE {
-            Some(path.to_string_lossy().to_string())
+            Some(db_path(path))
         } else {
             None
         };
 
+        let thumbnail_generated_at = thumbnail.as_ref().and_then(|_| modified_secs(&thumb_path));
+
         // 更新统计信息
-        let path_str = path.to_string_lossy().to_string();
         if db_records.contains_key(&path_str) {
             let mut stats_guard = stats.lock().unwrap();
             info!("changed: {}", path_str);
@@ -490,21 +963,213 @@ impl<'a> DirectorySync<'a> {
             name,
             path: path_str,
             created_at,
+            created_epoch,
             file_type: file_type.to_string(),
-            parent_path: route_path.to_string(),
+            parent_path: web_parent_path(path, root, route_path),
             thumbnail,
             size,
             subtitle,
             duration,
             width,
             height,
+            thumbnail_generated_at,
+            thumbnail_source: None,
+            last_modified: source_modified,
         }))
     }
 
-    /// 获取缩略图路径
-    fn get_thumbnail_path(file_path: &Path) -> PathBuf {
-        let thumbnails_dir = Path::new("thumbnails");
+    /// 静态方法：处理目录（用于并行处理）
+    fn process_directory_static(
+        path: &Path,
+        root: &Path,
+        route_path: &str,
+        db_records: &HashMap<String, FileInfo>,
+        stats: &Arc<StdMutex<Stats>>,
+        thumbnails: &ThumbnailContext,
+    ) -> Option<FileInfo> {
+        let path_str = db_path(path);
+        let created_epoch = std::fs::metadata(path)
+            .ok()
+            .as_ref()
+            .and_then(created_epoch);
+        let created_at = created_epoch
+            .and_then(format_created_at)
+            .unwrap_or_default();
+
+        let mut stats_guard = stats.lock().unwrap();
+        match db_records.get(&path_str) {
+            Some(db_record) if Self::is_up_to_date(path, db_record, thumbnails) => {
+                stats_guard.skipped += 1;
+                return None;
+            }
+            Some(_) => stats_guard.changed += 1,
+            None => stats_guard.new += 1,
+        }
+        drop(stats_guard);
+
+        let (thumbnail, thumbnail_source, thumbnail_generated_at) =
+            Self::directory_thumbnail(path, thumbnails);
+
+        Some(FileInfo {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: path_str,
+            created_at,
+            created_epoch,
+            file_type: directory_type(path).to_string(),
+            parent_path: web_parent_path(path, root, route_path),
+            thumbnail,
+            size: None,
+            subtitle: None,
+            duration: None,
+            width: None,
+            height: None,
+            thumbnail_generated_at,
+            thumbnail_source,
+            last_modified: modified_secs(path),
+        })
+    }
+
+    /// 选择并准备目录的封面，返回 (缩略图路径, 封面来源, 缩略图生成时间)
+    fn directory_thumbnail(
+        path: &Path,
+        thumbnails: &ThumbnailContext,
+    ) -> (Option<String>, Option<String>, Option<i64>) {
+        match directory_cover(path) {
+            Some(DirectoryCover::Poster(poster)) => {
+                // 封面复制到缩略图目录，和其他缩略图一样通过 /thumbnails 访问
+                let thumb_path = thumbnails.directory_path_for(path);
+                if let Some(parent) = thumb_path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                match std::fs::copy(&poster, &thumb_path) {
+                    Ok(_) => (
+                        Some(db_path(&thumb_path)),
+                        Some(thumbnail_sources::POSTER.to_string()),
+                        modified_secs(&thumb_path),
+                    ),
+                    Err(e) => {
+                        warn!("复制目录封面失败: {} - {}", poster.display(), e);
+                        (None, None, None)
+                    }
+                }
+            }
+            Some(DirectoryCover::FirstChild(child)) => (
+                Some(db_path(&thumbnails.path_for(&child))),
+                Some(thumbnail_sources::FIRST_CHILD.to_string()),
+                None,
+            ),
+            None => (None, None, None),
+        }
+    }
+
+    /// 重新选择路径本身及其下所有目录的封面（例如清除缩略图之后），返回设置了封面的目录数量
+    pub fn refresh_directory_thumbnails(&self, path: &str) -> Result<usize> {
+        let path = normalize_separators(path).trim_end_matches('/').to_string();
+        let dirs: Vec<String> = {
+            let mut stmt = self.db_manager.conn.prepare(
+                "SELECT path FROM videos
+                 WHERE (path = ?1 OR (path > ?1 || '/' AND path < ?1 || '0'))
+                   AND type IN (?2, ?3)",
+            )?;
+            let dirs = stmt
+                .query_map(
+                    [
+                        path.as_str(),
+                        video_types::DIRECTORY,
+                        video_types::HLS_DIRECTORY,
+                    ],
+                    |row| row.get(0),
+                )?
+                .collect::<Result<Vec<_>>>()?;
+            dirs
+        };
+
+        let mut updated = 0;
+        for dir in dirs {
+            let (thumbnail, source, generated_at) =
+                Self::directory_thumbnail(Path::new(&dir), &self.thumbnails);
+            if thumbnail.is_none() {
+                continue;
+            }
+            self.db_manager.conn.execute(
+                "UPDATE videos SET thumbnail = ?1, thumbnail_source = ?2, thumbnail_generated_at = ?3
+                 WHERE path = ?4",
+                rusqlite::params![thumbnail, source, generated_at, dir],
+            )?;
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    /// 数据库记录是否与文件一致，一致时同步会跳过该条目
+    ///
+    /// 以修改时间判断文件是否变化（创建时间在原地编辑后不会改变）；
+    /// 文件还要求缩略图未过期，且已有缩略图和尺寸信息
+    fn is_up_to_date(path: &Path, db_record: &FileInfo, thumbnails: &ThumbnailContext) -> bool {
+        if modified_secs(path) != db_record.last_modified {
+            return false;
+        }
+        if path.is_dir() {
+            // 目录的修改时间只反映直接子条目的增删，封面单独比较：
+            // 封面文件在原地被编辑或者复制出的缩略图丢失时，都要重新复制
+            return match directory_cover(path) {
+                Some(DirectoryCover::Poster(poster)) => {
+                    let thumb_path = thumbnails.directory_path_for(path);
+                    db_record.thumbnail_source.as_deref() == Some(thumbnail_sources::POSTER)
+                        && db_record.thumbnail.as_deref() == Some(db_path(&thumb_path).as_str())
+                        && thumb_path.exists()
+                        && !is_thumbnail_stale(
+                            modified_secs(&poster),
+                            db_record.thumbnail_generated_at,
+                        )
+                }
+                cover => db_record.thumbnail_source.as_deref() == cover.map(|c| c.source()),
+            };
+        }
+
+        !is_thumbnail_stale(modified_secs(path), db_record.thumbnail_generated_at)
+            && db_record.thumbnail.is_some()
+            && db_record.width.is_some()
+            && db_record.height.is_some()
+    }
+
+    /// 获取缩略图路径（默认缩略图目录）
+    pub(crate) fn get_thumbnail_path(file_path: &Path) -> PathBuf {
+        Self::thumbnail_path_in(Path::new(THUMBNAILS_DIR), file_path)
+    }
+
+    /// 文件在 `thumbnails_dir` 中的缩略图路径
+    fn thumbnail_path_in(thumbnails_dir: &Path, file_path: &Path) -> PathBuf {
+        thumbnails_dir
+            .join(Self::thumbnail_relative_path(file_path))
+            .with_extension("jpg")
+    }
+
+    /// 目录在 `thumbnails_dir` 中的封面缩略图路径：放在与目录同名的缩略图子目录中
+    /// （`show/` -> `thumbnails/show/.folder.jpg`），不会与同级同名文件（`show.mp4`）的缩略图冲突
+    fn directory_thumbnail_path_in(thumbnails_dir: &Path, dir_path: &Path) -> PathBuf {
+        thumbnails_dir
+            .join(Self::thumbnail_relative_path(dir_path))
+            .join(DIRECTORY_THUMBNAIL_NAME)
+    }
+
+    /// 同步器使用的缩略图路径
+    #[cfg(test)]
+    pub(crate) fn thumbnail_path(&self, file_path: &Path) -> PathBuf {
+        self.thumbnails.path_for(file_path)
+    }
+
+    /// 同步器使用的目录封面缩略图路径
+    #[cfg(test)]
+    pub(crate) fn directory_thumbnail_path(&self, dir_path: &Path) -> PathBuf {
+        self.thumbnails.directory_path_for(dir_path)
+    }
 
+    /// 缩略图目录下的相对路径（不含扩展名的替换）
+    fn thumbnail_relative_path(file_path: &Path) -> PathBuf {
         // 方法1: 找到 "public" 在路径中的位置，取后面的部分
         let relative_path: PathBuf = file_path
             .components() // 分解路径组件
@@ -512,22 +1177,26 @@ impl<'a> DirectorySync<'a> {
             .skip(1) // 跳过 "public" 本身
             .collect(); // 收集剩余部分
 
-        // 如果 relative_path 为空（没找到 public），使用文件名
-        let final_path = if relative_path.as_os_str().is_empty() {
-            file_path.file_name().map(Path::new).unwrap_or(file_path)
-        } else {
-            &relative_path
-        };
+        if !relative_path.as_os_str().is_empty() {
+            return relative_path;
+        }
 
-        thumbnails_dir.join(final_path).with_extension("jpg")
+        // 方法2: 没找到 public 时保留完整路径（去掉根目录和盘符），
+        // 不同目录下的同名文件（`a/ep1.mp4`、`b/ep1.mp4`）不会共用一个缩略图
+        file_path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect()
     }
 
     /// 确保缩略图存在（静态方法）
-    fn ensure_thumbnail_static(file_path: &Path) -> Option<String> {
-        let thumbnail_path = Self::get_thumbnail_path(file_path);
+    ///
+    /// lazy 模式下不生成，只返回缩略图的预期路径
+    fn ensure_thumbnail_static(file_path: &Path, thumbnails: &ThumbnailContext) -> Option<String> {
+        let thumbnail_path = thumbnails.path_for(file_path);
 
-        if thumbnail_path.exists() {
-            return Some(thumbnail_path.to_string_lossy().to_string());
+        if thumbnails.is_lazy() || thumbnail_path.exists() {
+            return Some(db_path(&thumbnail_path));
         }
 
         // 确保父目录存在
@@ -537,23 +1206,12 @@ impl<'a> DirectorySync<'a> {
             }
         }
 
-        let ffmpeg = get_ffmpeg_service();
-        let extension = file_path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        let success =
-            if extension == "mp4" || extension == "avi" || extension == "mkv" || extension == "mov"
-            {
-                ffmpeg.generate_thumbnail(file_path, &thumbnail_path)
-            } else {
-                ffmpeg.generate_placeholder_thumbnail(&thumbnail_path, "file")
-            };
+        let success = thumbnails
+            .ffmpeg
+            .ensure_thumbnail(file_path, &thumbnail_path, "file");
 
         if success && thumbnail_path.exists() {
-            Some(thumbnail_path.to_string_lossy().to_string())
+            Some(db_path(&thumbnail_path))
         } else {
             None
         }
@@ -563,13 +1221,73 @@ impl<'a> DirectorySync<'a> {
     fn is_record_changed(&self, file_info: &FileInfo, db_record: &FileInfo) -> bool {
         file_info.name != db_record.name
             || file_info.created_at != db_record.created_at
+            || file_info.created_epoch != db_record.created_epoch
             || (db_record.width.is_none() && file_info.width.is_some())
             || (db_record.height.is_none() && file_info.height.is_some())
             || (db_record.thumbnail.is_none() && file_info.thumbnail.is_some())
+            || db_record.thumbnail_generated_at != file_info.thumbnail_generated_at
+            || db_record.thumbnail_source != file_info.thumbnail_source
+            || db_record.last_modified != file_info.last_modified
+    }
+
+    /// 用新的文件信息替换已有记录，保留观看状态（watched、watch_position、watch_count）、收藏、自定义顺序和标签
+    fn replace_record(&self, file_info: &FileInfo, current_time: &str) -> Result<()> {
+        let watch_state: Option<PreservedState> = self
+            .db_manager
+            .conn
+            .query_row(
+                "SELECT id, watched, watch_position, favorite, watch_count, sort_index
+                 FROM videos WHERE path = ?1",
+                [&file_info.path],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        self.hard_delete_record(&file_info.path)?;
+        self.insert_new_record(file_info, current_time)?;
+        let new_id = self.db_manager.conn.last_insert_rowid();
+
+        if let Some((old_id, watched, watch_position, favorite, watch_count, sort_index)) =
+            watch_state
+        {
+            self.db_manager.conn.execute(
+                "UPDATE videos SET watched = ?1, watch_position = ?2, favorite = ?3, watch_count = ?4,
+                 sort_index = ?5
+                 WHERE path = ?6",
+                rusqlite::params![
+                    watched,
+                    watch_position,
+                    favorite,
+                    watch_count,
+                    sort_index,
+                    &file_info.path
+                ],
+            )?;
+            // 新记录的 id 不同，标签关联改为指向新记录
+            self.db_manager.conn.execute(
+                "UPDATE video_tags SET video_id = ?1 WHERE video_id = ?2",
+                rusqlite::params![new_id, old_id],
+            )?;
+        }
+        Ok(())
     }
 
     /// 插入新记录
+    ///
+    /// last_modified 记录文件修改时间，无法读取时记录同步时间
     fn insert_new_record(&self, file_info: &FileInfo, current_time: &str) -> Result<()> {
+        let last_modified = file_info
+            .last_modified
+            .unwrap_or_else(|| current_time.parse().unwrap_or_default());
         self.db_manager.conn.execute(
             queries::INSERT_NEW,
             rusqlite::params![
@@ -581,24 +1299,489 @@ impl<'a> DirectorySync<'a> {
                 &file_info.size.clone().unwrap_or_default(),
                 &file_info.created_at,
                 &file_info.subtitle.clone().unwrap_or_default(),
-                current_time,
+                last_modified,
                 &file_info.duration.clone().unwrap_or_default(),
                 &file_info.width,
                 &file_info.height,
+                &file_info.thumbnail_generated_at,
+                &file_info.thumbnail_source,
+                &file_info.created_epoch,
+                file_info.duration.as_deref().and_then(parse_duration),
+                file_info.size.as_deref().and_then(parse_size),
+                normalize_for_search(&file_info.name),
+                self.metadata_error(file_info),
             ],
         )?;
         Ok(())
     }
 
+    /// 严格元数据模式下，需要读取元数据的视频（MP4、TS）没有读到时长时返回错误原因
+    fn metadata_error(&self, file_info: &FileInfo) -> Option<&'static str> {
+        let probed = [video_types::MP4, video_types::TS].contains(&file_info.file_type.as_str());
+        let missing = file_info.duration.as_deref().is_none_or(str::is_empty);
+        (self.strict_metadata && probed && missing).then_some("无法读取视频元数据")
+    }
+
+    /// 读取单个文件的元数据并按需生成缩略图，不访问数据库
+    ///
+    /// 可能执行 ffmpeg，调用方不要在持有数据库锁时调用。
+    /// 文件不在任何数据源目录内时返回错误，不需要入库的条目（例如 HLS 切片）返回 None
+    pub fn scan_file(
+        path: &Path,
+        mappings: &[DiskMapping],
+        thumbnail_mode: ThumbnailMode,
+    ) -> Result<Option<FileInfo>> {
+        let mapping = mappings
+            .iter()
+            .find(|m| path.starts_with(&m.physical_path))
+            .ok_or_else(|| {
+                rusqlite::Error::InvalidParameterName(format!(
+                    "路径不在任何数据源目录内: {}",
+                    path.display()
+                ))
+            })?;
+
+        let stats = Arc::new(StdMutex::new(Stats {
+            new: 0,
+            changed: 0,
+            skipped: 0,
+        }));
+        let current_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string();
+        Self::process_file_static(
+            path,
+            Path::new(&mapping.physical_path),
+            &mapping.route_path,
+            &HashMap::new(),
+            &current_time,
+            &stats,
+            &ThumbnailContext::new(thumbnail_mode),
+        )
+        .map_err(rusqlite::Error::InvalidParameterName)
+    }
+
+    /// 用合并后的 MP4 替换 HLS 目录的记录
+    ///
+    /// 删除 HLS 目录及其子条目的记录，然后插入 MP4 文件的记录（`merged` 由 [`Self::scan_file`] 读取）。
+    /// 原始切片保留在磁盘上，目录中写入 [`MERGED_HLS_MARKER`]，之后的同步不会再把它登记回来
+    pub fn replace_hls_directory(&self, hls_dir: &Path, merged: &FileInfo) -> Result<()> {
+        if let Err(e) = std::fs::write(hls_dir.join(MERGED_HLS_MARKER), &merged.name) {
+            warn!("无法写入合并标记 {}: {}", hls_dir.display(), e);
+        }
+
+        let dir_str = db_path(hls_dir);
+        // 用范围比较代替 LIKE，目录名中的 `%`、`_` 不会被当作通配符
+        self.db_manager.conn.execute(
+            "DELETE FROM videos WHERE path = ?1 OR (path > ?1 || '/' AND path < ?1 || '0')",
+            [&dir_str],
+        )?;
+
+        let current_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string();
+        self.hard_delete_record(&merged.path)?;
+        self.insert_new_record(merged, &current_time)?;
+        VideoDao::new(self.db_manager).prune_tag_links()?;
+        self.db_manager.count_cache.invalidate();
+        VideoDao::new(self.db_manager).refresh_child_counts_for(&merged.path)?;
+        Ok(())
+    }
+
+    /// 重新读取单个文件的元数据并更新其记录，不扫描所在目录
+    ///
+    /// 读取（[`Self::scan_refresh`]）和写入（[`Self::apply_refresh`]）一次完成，
+    /// 文件不在任何数据源目录内时返回 false
+    #[cfg(test)]
+    pub fn refresh_file(&self, path: &Path, mappings: &[DiskMapping]) -> Result<bool> {
+        let Some(refreshed) = Self::probe_refresh(path, mappings, &self.thumbnails)? else {
+            return Ok(false);
+        };
+        self.apply_refresh(&refreshed)?;
+        Ok(true)
+    }
+
+    /// 重新读取单个文件的元数据，不访问数据库
+    ///
+    /// 缩略图比源文件旧时重新生成；MP4 额外读取编码和码率。会执行 ffmpeg，
+    /// 调用方不要在持有数据库锁时调用。文件不在任何数据源目录内或不需要入库时返回 None
+    pub fn scan_refresh(
+        path: &Path,
+        mappings: &[DiskMapping],
+        thumbnail_mode: ThumbnailMode,
+    ) -> Result<Option<RefreshedFile>> {
+        Self::probe_refresh(path, mappings, &ThumbnailContext::new(thumbnail_mode))
+    }
+
+    fn probe_refresh(
+        path: &Path,
+        mappings: &[DiskMapping],
+        thumbnails: &ThumbnailContext,
+    ) -> Result<Option<RefreshedFile>> {
+        let Some(mapping) = mappings.iter().find(|m| path.starts_with(&m.physical_path)) else {
+            return Ok(None);
+        };
+
+        let stats = Arc::new(StdMutex::new(Stats {
+            new: 0,
+            changed: 0,
+            skipped: 0,
+        }));
+        let scanned_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string();
+        // 不传入数据库记录，保证文件一定会被重新处理
+        let file_info = Self::process_file_static(
+            path,
+            Path::new(&mapping.physical_path),
+            &mapping.route_path,
+            &HashMap::new(),
+            &scanned_at,
+            &stats,
+            thumbnails,
+        )
+        .map_err(rusqlite::Error::InvalidParameterName)?;
+        let Some(file_info) = file_info else {
+            return Ok(None);
+        };
+
+        let codec_bitrate = if file_info.file_type == video_types::MP4 {
+            thumbnails.ffmpeg.probe_codec_bitrate(path)
+        } else {
+            None
+        };
+        Ok(Some(RefreshedFile {
+            file_info,
+            scanned_at,
+            codec_bitrate,
+        }))
+    }
+
+    /// 用 [`Self::scan_refresh`] 的结果替换文件的记录，不调用 ffmpeg
+    pub fn apply_refresh(&self, refreshed: &RefreshedFile) -> Result<()> {
+        let file_info = &refreshed.file_info;
+        self.replace_record(file_info, &refreshed.scanned_at)?;
+        if let Some((codec, bitrate)) = &refreshed.codec_bitrate {
+            self.db_manager.conn.execute(
+                "UPDATE videos SET codec = ?1, bitrate = ?2 WHERE path = ?3",
+                rusqlite::params![codec, bitrate, &file_info.path],
+            )?;
+        }
+        self.db_manager.count_cache.invalidate();
+        VideoDao::new(self.db_manager).refresh_child_counts_for(&file_info.path)?;
+        Ok(())
+    }
+
+    /// 登记新增的单个文件（例如上传的文件），不调用 ffmpeg
+    ///
+    /// MP4 从文件头读取时长和分辨率；缩略图留空，由后台任务生成后在下一次同步时写入记录。
+    /// 文件不在任何数据源目录内或不是文件时返回 false
+    pub fn register_file(&self, path: &Path, mappings: &[DiskMapping]) -> Result<bool> {
+        let Some(mapping) = mappings.iter().find(|m| path.starts_with(&m.physical_path)) else {
+            return Ok(false);
+        };
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return Ok(false);
+        };
+        if !metadata.is_file() {
+            return Ok(false);
+        }
+
+        let kind = classify(path);
+        let (duration, width, height) = match kind {
+            Some(MediaKind::Video) => match get_video_info(&path.to_string_lossy()) {
+                Ok(info) => (
+                    Some(info.duration),
+                    Some(info.width as i32),
+                    Some(info.height as i32),
+                ),
+                Err(e) => {
+                    debug!("{}，等待下一次同步读取元数据", e);
+                    (None, None, None)
+                }
+            },
+            Some(MediaKind::Playlist) => (get_m3u8_duration(path).map(format_duration), None, None),
+            Some(MediaKind::TransportStream) => {
+                (get_ts_duration(path).map(format_duration), None, None)
+            }
+            _ => (None, None, None),
+        };
+
+        let file_info = FileInfo {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: db_path(path),
+            created_at: get_systemtime_created(&metadata).unwrap_or_default(),
+            created_epoch: created_epoch(&metadata),
+            file_type: file_type_for(path).to_string(),
+            parent_path: web_parent_path(
+                path,
+                Path::new(&mapping.physical_path),
+                &mapping.route_path,
+            ),
+            thumbnail: None,
+            size: Some(format_size(metadata.len())),
+            subtitle: (kind == Some(MediaKind::Subtitle)).then(|| db_path(path)),
+            duration,
+            width,
+            height,
+            thumbnail_generated_at: None,
+            thumbnail_source: None,
+            last_modified: modified_secs(path),
+        };
+
+        let current_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string();
+        self.replace_record(&file_info, &current_time)?;
+        self.db_manager.count_cache.invalidate();
+        VideoDao::new(self.db_manager).refresh_child_counts_for(&file_info.path)?;
+        Ok(true)
+    }
+
+    /// 登记新生成的 HLS 目录（例如 MP4 切片的输出）及其中的播放列表，不扫描其他目录
+    ///
+    /// 目录不在任何数据源目录内或不是目录时返回 false
+    pub fn register_hls_directory(&self, dir: &Path, mappings: &[DiskMapping]) -> Result<bool> {
+        let Some(mapping) = mappings.iter().find(|m| dir.starts_with(&m.physical_path)) else {
+            return Ok(false);
+        };
+        if !dir.is_dir() {
+            return Ok(false);
+        }
+
+        let stats = Arc::new(StdMutex::new(Stats {
+            new: 0,
+            changed: 0,
+            skipped: 0,
+        }));
+        let current_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string();
+        let Some(dir_info) = Self::process_directory_static(
+            dir,
+            Path::new(&mapping.physical_path),
+            &mapping.route_path,
+            &HashMap::new(),
+            &stats,
+            &self.thumbnails,
+        ) else {
+            return Ok(false);
+        };
+        self.replace_record(&dir_info, &current_time)?;
+
+        let playlists: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| media_types::is_playlist(p))
+            .collect();
+        for playlist in playlists {
+            self.register_file(&playlist, mappings)?;
+        }
+
+        self.db_manager.count_cache.invalidate();
+        VideoDao::new(self.db_manager).refresh_child_counts_for(&dir_info.path)?;
+        Ok(true)
+    }
+
     /// 硬删除记录
     fn hard_delete_record(&self, path: &str) -> Result<()> {
         self.db_manager
             .conn
-            .execute("DELETE FROM videos WHERE path = ?", &[path])?;
+            .execute("DELETE FROM videos WHERE path = ?", [path])?;
         Ok(())
     }
 }
 
+/// 目录扫描结果
+struct ScanResult {
+    /// 媒体文件和包含媒体文件的目录（目录在前）
+    paths: Vec<PathBuf>,
+    /// 无法读取的条目数量
+    unreadable: usize,
+    /// 无法读取的路径（根目录、子目录或文件），其下的记录不能据此判断为已删除
+    unreadable_paths: Vec<PathBuf>,
+}
+
+/// 根据扩展名确定文件在数据库中的类型
+fn file_type_for(path: &Path) -> &'static str {
+    match classify(path) {
+        Some(MediaKind::Video) => video_types::MP4,
+        Some(MediaKind::Playlist) => video_types::M3U8,
+        Some(MediaKind::TransportStream) => video_types::TS,
+        Some(MediaKind::Subtitle) => video_types::SUBTITLE,
+        Some(MediaKind::Image) => video_types::IMAGE,
+        None => video_types::UNKNOWN,
+    }
+}
+
+/// 判断目录类型：直接包含 .m3u8 播放列表的目录为 HLS 目录
+fn directory_type(path: &Path) -> &'static str {
+    let has_playlist = std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .any(|entry| media_types::is_playlist(&entry.path()))
+        })
+        .unwrap_or(false);
+
+    if has_playlist {
+        video_types::HLS_DIRECTORY
+    } else {
+        video_types::DIRECTORY
+    }
+}
+
+/// 是否为已合并为 MP4 的 HLS 目录（带有 [`MERGED_HLS_MARKER`]）
+fn is_merged_hls_directory(entry: &walkdir::DirEntry) -> bool {
+    entry.file_type().is_dir() && entry.path().join(MERGED_HLS_MARKER).is_file()
+}
+
+/// 是否为 MP4 切片的输出目录（`ep1.hls/`），这类目录由切片任务登记，扫描时跳过
+fn is_packaged_hls_directory(entry: &walkdir::DirEntry) -> bool {
+    entry.file_type().is_dir() && is_hls_output_dir(entry.path())
+}
+
+/// 是否为 HLS 目录中的 .ts 切片（属于播放列表，不单独入库）
+fn is_hls_segment(path: &Path) -> bool {
+    classify(path) == Some(MediaKind::TransportStream)
+        && path
+            .parent()
+            .is_some_and(|parent| directory_type(parent) == video_types::HLS_DIRECTORY)
+}
+
+/// 目录缩略图的来源
+enum DirectoryCover {
+    /// 目录中的封面文件
+    Poster(PathBuf),
+    /// 目录中（按自然排序）第一个视频
+    FirstChild(PathBuf),
+}
+
+impl DirectoryCover {
+    fn source(&self) -> &'static str {
+        match self {
+            DirectoryCover::Poster(_) => thumbnail_sources::POSTER,
+            DirectoryCover::FirstChild(_) => thumbnail_sources::FIRST_CHILD,
+        }
+    }
+}
+
+/// 选择目录缩略图：封面文件（poster.jpg > folder.jpg > cover.jpg）优先，其次是第一个视频
+fn directory_cover(path: &Path) -> Option<DirectoryCover> {
+    let entries: Vec<PathBuf> = std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|entry| entry.path())
+        .filter(|entry| entry.is_file())
+        .collect();
+
+    let file_name = |entry: &PathBuf| {
+        entry
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    };
+
+    for poster_name in thumbnail_sources::POSTER_FILE_NAMES {
+        if let Some(poster) = entries
+            .iter()
+            .find(|entry| file_name(entry) == *poster_name)
+        {
+            return Some(DirectoryCover::Poster(poster.clone()));
+        }
+    }
+
+    entries
+        .into_iter()
+        .filter(|entry| classify(entry) == Some(MediaKind::Video))
+        .min_by(|a, b| natural_cmp(&file_name(a), &file_name(b)))
+        .map(DirectoryCover::FirstChild)
+}
+
+/// 文件的修改时间（Unix 秒）
+fn modified_secs(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let secs = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    i64::try_from(secs).ok()
+}
+
+/// 源文件修改时间晚于缩略图生成时间时，缩略图已过期
+fn is_thumbnail_stale(source_modified: Option<i64>, generated_at: Option<i64>) -> bool {
+    matches!((source_modified, generated_at), (Some(source), Some(generated)) if source > generated)
+}
+
+/// 计算条目所在目录的路由路径，例如 `/public/disk1/show`
+fn web_parent_path(path: &Path, root: &Path, route_path: &str) -> String {
+    let relative = path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(root).ok())
+        .map(|rel| {
+            rel.components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default();
+
+    if relative.is_empty() {
+        route_path.to_string()
+    } else {
+        format!("{}/{}", route_path.trim_end_matches('/'), relative)
+    }
+}
+
+/// 记录目录及其各级上级目录（不包含根目录），已记录的上级目录不再重复处理
+fn insert_with_ancestors(directories: &mut BTreeSet<PathBuf>, dir: &Path, root: &Path) {
+    for ancestor in dir.ancestors() {
+        if ancestor == root || !ancestor.starts_with(root) {
+            break;
+        }
+        if !directories.insert(ancestor.to_path_buf()) {
+            break;
+        }
+    }
+}
+
+/// 记录路径是否为 `dirs` 中某个路径本身或其子孙
+fn is_under_any(path: &str, dirs: &[PathBuf]) -> bool {
+    dirs.iter().any(|dir| Path::new(path).starts_with(dir))
+}
+
+/// 记录是否位于扫描没有覆盖的位置：无法读取的目录，或仍在磁盘上的 MP4 切片输出目录。
+/// 这些记录不能因为扫描中没有出现就判断为已删除
+fn is_outside_scan(path: &str, unreadable_dirs: &[PathBuf]) -> bool {
+    is_under_any(path, unreadable_dirs)
+        || Path::new(path)
+            .ancestors()
+            .find(|p| is_hls_output_dir(p))
+            .is_some_and(Path::is_dir)
+}
+
+/// 获取 WalkDir 错误对应的路径，用于日志输出
+fn walk_error_path(error: &walkdir::Error) -> String {
+    error
+        .path()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "<unknown>".to_string())
+}
+
 /// 统计信息结构体
 #[derive(Debug, Default)]
 struct Stats {
@@ -606,3 +1789,1041 @@ struct Stats {
     changed: usize,
     skipped: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::db::{VideoFilter, VideoSort};
+    use crate::test_utils::insert_video;
+
+    /// 缩略图写入独立的临时目录，测试不会在工作目录下留下文件
+    fn sync_in<'a>(
+        db_manager: &'a VideoDbManager,
+        thumbnails: &tempfile::TempDir,
+    ) -> DirectorySync<'a> {
+        DirectorySync::new(db_manager).with_thumbnail_dir(thumbnails.path())
+    }
+
+    fn mapping_for(path: &Path) -> Vec<DiskMapping> {
+        vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: path.to_string_lossy().to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_sync_pool_uses_configured_thread_count() {
+        let pool = build_sync_pool(&SyncConfig {
+            threads: 3,
+            ..SyncConfig::default()
+        });
+        assert_eq!(pool.current_num_threads(), 3);
+
+        // 任务运行在专用线程池中，而不是全局线程池
+        let (threads, name) = pool.install(|| {
+            (
+                rayon::current_num_threads(),
+                std::thread::current().name().map(str::to_string),
+            )
+        });
+        assert_eq!(threads, 3);
+        assert!(name.unwrap().starts_with("sync-worker-"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_directory_is_reported() {
+        // 指向不存在目录的符号链接无法读取（与权限无关，以 root 身份运行时同样有效）
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path().join("mount");
+        std::os::unix::fs::symlink(dir.path().join("unmounted"), &root).unwrap();
+        let subtitle_path = root.join("a.vtt");
+
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(
+            &db_manager,
+            &db_path(&subtitle_path),
+            video_types::SUBTITLE,
+            "/public/disk1",
+        );
+        let sync = sync_in(&db_manager, &thumbs);
+
+        let records = sync.record_paths().unwrap();
+        let audit = DirectorySync::audit(&ScanConfig::default(), &mapping_for(&root), &records);
+        assert_eq!(audit.unreadable_dirs, vec![db_path(&root)]);
+        assert_eq!(audit.missing_on_disk, 0);
+
+        let report = sync
+            .initialize_from_directory_with_progress(&mapping_for(&root), false, false)
+            .unwrap();
+        // 目录无法读取时不应删除已有记录
+        assert_eq!(report.deleted, 0);
+        let videos = VideoDao::new(&db_manager).get_root_videos().unwrap();
+        assert_eq!(videos.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_subdirectory_keeps_its_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path().join("library");
+        let target = dir.path().join("external");
+        std::fs::create_dir_all(root.join("local")).unwrap();
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(root.join("local").join("a.vtt"), "WEBVTT\n").unwrap();
+        std::fs::write(target.join("ep1.vtt"), "WEBVTT\n").unwrap();
+        // show 是指向另一块磁盘的符号链接
+        let show = root.join("show");
+        std::os::unix::fs::symlink(&target, &show).unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs).with_scan_config(ScanConfig {
+            follow_symlinks: true,
+            ..ScanConfig::default()
+        });
+        sync.initialize_from_directory_with_progress(&mapping_for(&root), false, false)
+            .unwrap();
+        let records = sync.get_all_db_records().unwrap();
+        assert!(records.contains_key(&db_path(&show.join("ep1.vtt"))));
+
+        // 磁盘卸载后 show 无法读取，同时 local 中的文件确实被删除
+        std::fs::remove_dir_all(&target).unwrap();
+        std::fs::remove_file(root.join("local").join("a.vtt")).unwrap();
+
+        let plan = sync
+            .initialize_from_directory_with_progress(&mapping_for(&root), false, true)
+            .unwrap();
+        assert!(plan.unreadable >= 1);
+        let audit = DirectorySync::audit(
+            &ScanConfig {
+                follow_symlinks: true,
+                ..ScanConfig::default()
+            },
+            &mapping_for(&root),
+            &sync.record_paths().unwrap(),
+        );
+        assert_eq!(audit.unreadable_dirs, vec![db_path(&show)]);
+
+        let report = sync
+            .initialize_from_directory_with_progress(&mapping_for(&root), false, false)
+            .unwrap();
+        assert!(report.unreadable >= 1);
+        let records = sync.get_all_db_records().unwrap();
+        assert!(records.contains_key(&db_path(&show)));
+        assert!(records.contains_key(&db_path(&show.join("ep1.vtt"))));
+        // 可以读取的目录中已删除的文件仍然会被清理
+        assert!(!records.contains_key(&db_path(&root.join("local").join("a.vtt"))));
+        assert_eq!(plan.deleted, report.deleted);
+    }
+
+    #[test]
+    fn test_readable_directory_reports_no_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.vtt"), "WEBVTT\n").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let report = sync_in(&db_manager, &thumbs)
+            .initialize_from_directory_with_progress(&mapping_for(dir.path()), false, false)
+            .unwrap();
+
+        assert_eq!(report.unreadable, 0);
+        assert_eq!(report.new, 1);
+    }
+
+    #[test]
+    fn test_hls_directory_replaced_by_merged_mp4() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let hls_dir = root.join("show_1");
+        // 名称中的 `_` 不是通配符，同级的 showx1 不受影响
+        let sibling = root.join("showx1");
+        for hls in [&hls_dir, &sibling] {
+            std::fs::create_dir(hls).unwrap();
+            std::fs::write(hls.join("index.m3u8"), "#EXTM3U\n").unwrap();
+        }
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        sync.initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        let dao = VideoDao::new(&db_manager);
+        let show = dao.get_video_by_path(&db_path(&hls_dir)).unwrap().unwrap();
+        assert_eq!(show.r#type, video_types::HLS_DIRECTORY);
+
+        // 模拟 ffmpeg 合并后的输出
+        let mp4_path = crate::services::ffmpeg::merged_output_path(&hls_dir);
+        std::fs::write(&mp4_path, b"merged").unwrap();
+        let merged = DirectorySync::scan_file(&mp4_path, &mapping_for(root), ThumbnailMode::Lazy)
+            .unwrap()
+            .unwrap();
+        sync.replace_hls_directory(&hls_dir, &merged).unwrap();
+
+        let mut videos = dao.get_root_videos().unwrap();
+        videos.sort_by(|a, b| a.path.cmp(&b.path));
+        let paths: Vec<&str> = videos.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                db_path(&mp4_path),
+                db_path(&sibling),
+                db_path(&sibling.join("index.m3u8"))
+            ]
+        );
+        assert_eq!(videos[0].r#type, video_types::MP4);
+        assert_eq!(videos[0].parent_path.as_deref(), Some("/public/disk1"));
+    }
+
+    #[test]
+    fn test_merged_hls_directory_not_resynced() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let hls_dir = root.join("show");
+        std::fs::create_dir(&hls_dir).unwrap();
+        std::fs::write(hls_dir.join("index.m3u8"), "#EXTM3U\n").unwrap();
+        std::fs::write(hls_dir.join("seg0.ts"), b"ts").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        sync.initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        let mp4_path = crate::services::ffmpeg::merged_output_path(&hls_dir);
+        std::fs::write(&mp4_path, b"merged").unwrap();
+        let merged = DirectorySync::scan_file(&mp4_path, &mapping_for(root), ThumbnailMode::Lazy)
+            .unwrap()
+            .unwrap();
+        sync.replace_hls_directory(&hls_dir, &merged).unwrap();
+
+        let report = sync
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        assert_eq!(report.new, 0);
+
+        // 切片仍在磁盘上，数据库中只剩合并后的 MP4
+        assert!(hls_dir.join("seg0.ts").is_file());
+        let records = sync.get_all_db_records().unwrap();
+        let paths: Vec<&String> = records.keys().collect();
+        assert_eq!(paths, [&db_path(&mp4_path)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_directories_followed_when_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let collection = dir.path().join("collection");
+        std::fs::create_dir(&collection).unwrap();
+        std::fs::write(collection.join("a.vtt"), "WEBVTT\n").unwrap();
+
+        let root = dir.path().join("library");
+        std::fs::create_dir(&root).unwrap();
+        std::os::unix::fs::symlink(&collection, root.join("linked")).unwrap();
+        // 指向自身的链接不能导致无限递归
+        std::os::unix::fs::symlink(&root, root.join("loop")).unwrap();
+
+        let names = |follow_symlinks: bool| {
+            let db_manager = VideoDbManager::in_memory();
+            sync_in(&db_manager, &thumbs)
+                .with_scan_config(ScanConfig {
+                    follow_symlinks,
+                    ..ScanConfig::default()
+                })
+                .initialize_from_directory_with_progress(&mapping_for(&root), false, false)
+                .unwrap();
+            let mut names: Vec<String> = VideoDao::new(&db_manager)
+                .get_root_videos()
+                .unwrap()
+                .into_iter()
+                .map(|v| v.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert!(names(false).is_empty());
+        assert_eq!(names(true), vec!["a.vtt", "linked"]);
+    }
+
+    #[test]
+    fn test_ignored_entries_are_excluded_from_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("a.vtt"), "WEBVTT\n").unwrap();
+        std::fs::write(root.join("b.mp4.part"), "partial").unwrap();
+        std::fs::create_dir(root.join("@eaDir")).unwrap();
+        std::fs::write(root.join("@eaDir").join("c.vtt"), "WEBVTT\n").unwrap();
+        std::fs::create_dir(root.join("show")).unwrap();
+        std::fs::write(root.join("show").join("d.vtt"), "WEBVTT\n").unwrap();
+
+        let mut scan_config = ScanConfig::default();
+        scan_config.ignore.push("@eaDir".to_string());
+
+        let db_manager = VideoDbManager::in_memory();
+        sync_in(&db_manager, &thumbs)
+            .with_scan_config(scan_config)
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        let videos = VideoDao::new(&db_manager).get_root_videos().unwrap();
+        let mut names: Vec<&str> = videos.iter().map(|v| v.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["a.vtt", "d.vtt", "show"]);
+
+        let nested = videos.iter().find(|v| v.name == "d.vtt").unwrap();
+        assert_eq!(nested.parent_path.as_deref(), Some("/public/disk1/show"));
+        let show = videos.iter().find(|v| v.name == "show").unwrap();
+        assert_eq!(show.r#type, video_types::DIRECTORY);
+    }
+
+    #[test]
+    fn test_image_extensions_are_classified_as_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in ["a.jpeg", "b.JPEG", "c.webp", "d.bmp", "e.jpg"] {
+            std::fs::write(root.join(name), b"image").unwrap();
+        }
+
+        let db_manager = VideoDbManager::in_memory();
+        sync_in(&db_manager, &thumbs)
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        let videos = VideoDao::new(&db_manager).get_root_videos().unwrap();
+        assert_eq!(videos.len(), 5);
+        for video in &videos {
+            assert_eq!(video.r#type, video_types::IMAGE, "{}", video.name);
+        }
+    }
+
+    #[test]
+    fn test_strict_metadata_flags_and_hides_unprobeable_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("broken.mp4"), b"not really a video").unwrap();
+        std::fs::write(root.join("clip.ts"), crate::test_utils::mpegts_fixture(30)).unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        sync_in(&db_manager, &thumbs)
+            .with_strict_metadata(true)
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        let dao = VideoDao::new(&db_manager);
+        let broken = dao
+            .get_video_by_path(&db_path(&root.join("broken.mp4")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(broken.error.as_deref(), Some("无法读取视频元数据"));
+        let clip = dao
+            .get_video_by_path(&db_path(&root.join("clip.ts")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(clip.error, None);
+
+        let listed = |exclude_errors: bool| {
+            let filter = VideoFilter {
+                exclude_errors,
+                ..Default::default()
+            };
+            let mut names: Vec<String> = dao
+                .get_root_videos_paginated(1, 100, &filter, &VideoSort::default())
+                .unwrap()
+                .videos
+                .into_iter()
+                .map(|v| v.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(listed(false), ["broken.mp4", "clip.ts"]);
+        assert_eq!(listed(true), ["clip.ts"]);
+    }
+
+    #[test]
+    fn test_lazy_mode_records_thumbnail_path_without_generating() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let files = [root.join("a.mp4"), root.join("b.ts"), root.join("c.png")];
+        std::fs::write(&files[0], b"not really a video").unwrap();
+        std::fs::write(&files[1], crate::test_utils::mpegts_fixture(30)).unwrap();
+        std::fs::write(&files[2], b"image").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs).with_thumbnail_mode(ThumbnailMode::Lazy);
+        sync.initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        let dao = VideoDao::new(&db_manager);
+        for file in &files {
+            let thumbnail_path = sync.thumbnail_path(file);
+            let video = dao.get_video_by_path(&db_path(file)).unwrap().unwrap();
+            assert_eq!(
+                video.thumbnail,
+                Some(db_path(&thumbnail_path)),
+                "{}",
+                video.name
+            );
+            assert!(!thumbnail_path.exists(), "{}", video.name);
+        }
+        let clip = dao.get_video_by_path(&db_path(&files[1])).unwrap().unwrap();
+        assert_eq!(clip.duration.as_deref(), Some("00:00:30"));
+    }
+
+    #[test]
+    fn test_same_named_files_in_different_directories_get_distinct_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let files = [dir.path().join("a/ep1.mp4"), dir.path().join("b/ep1.mp4")];
+        for file in &files {
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, b"video").unwrap();
+        }
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs).with_thumbnail_mode(ThumbnailMode::Lazy);
+        sync.initialize_from_directory_with_progress(&mapping_for(dir.path()), false, false)
+            .unwrap();
+
+        let dao = VideoDao::new(&db_manager);
+        let thumbnails: Vec<String> = files
+            .iter()
+            .map(|file| {
+                let record = dao.get_video_by_path(&db_path(file)).unwrap().unwrap();
+                let thumbnail = record.thumbnail.unwrap();
+                assert_eq!(thumbnail, db_path(&sync.thumbnail_path(file)));
+                assert!(sync.thumbnail_path(file).starts_with(thumbs.path()));
+                thumbnail
+            })
+            .collect();
+        assert_ne!(thumbnails[0], thumbnails[1]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_configured_placeholder_extension_is_not_frame_grabbed() {
+        use crate::config::ThumbnailStrategy;
+        use crate::services::ffmpeg::FFmpegConfig;
+        use std::collections::BTreeMap;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path().join("library");
+        std::fs::create_dir(&root).unwrap();
+        let args_log = dir.path().join("args.log");
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> '{}'\nfor last; do :; done\necho jpeg > \"$last\"\n",
+                args_log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let ffmpeg: &'static FFmpegService =
+            Box::leak(Box::new(FFmpegService::new(FFmpegConfig {
+                ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+                ffprobe_path: dir
+                    .path()
+                    .join("missing-ffprobe")
+                    .to_string_lossy()
+                    .to_string(),
+                thumbnail_strategies: BTreeMap::from([(
+                    "mp4".to_string(),
+                    ThumbnailStrategy::Placeholder,
+                )]),
+                ..FFmpegConfig::default()
+            })));
+
+        let video = root.join("placeholder.mp4");
+        std::fs::write(&video, b"video").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs)
+            .with_thumbnail_mode(ThumbnailMode::Eager)
+            .with_ffmpeg(ffmpeg);
+        sync.initialize_from_directory_with_progress(&mapping_for(&root), false, false)
+            .unwrap();
+
+        let thumbnail_path = sync.thumbnail_path(&video);
+        let record = VideoDao::new(&db_manager)
+            .get_video_by_path(&db_path(&video))
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.thumbnail, Some(db_path(&thumbnail_path)));
+        assert!(thumbnail_path.is_file());
+
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        assert_eq!(log.lines().count(), 1, "{}", log);
+        assert!(log.starts_with("-i ") && log.contains(".svg"), "{}", log);
+    }
+
+    #[test]
+    fn test_standalone_ts_gets_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("clip.ts"), crate::test_utils::mpegts_fixture(90)).unwrap();
+        // HLS 目录中的切片不单独入库
+        let hls_dir = root.join("show");
+        std::fs::create_dir_all(&hls_dir).unwrap();
+        std::fs::write(
+            hls_dir.join("index.m3u8"),
+            "#EXTM3U\n#EXTINF:10.0,\nseg0.ts\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+        std::fs::write(
+            hls_dir.join("seg0.ts"),
+            crate::test_utils::mpegts_fixture(10),
+        )
+        .unwrap();
+
+        let mappings = mapping_for(root);
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        sync.initialize_from_directory_with_progress(&mappings, false, false)
+            .unwrap();
+
+        let dao = VideoDao::new(&db_manager);
+        let clip = dao
+            .get_video_by_path(&db_path(&root.join("clip.ts")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(clip.r#type, video_types::TS);
+        assert_eq!(clip.duration.as_deref(), Some("00:01:30"));
+        let segment = db_path(&hls_dir.join("seg0.ts"));
+        assert!(dao.get_video_by_path(&segment).unwrap().is_none());
+
+        // 单独刷新切片时同样跳过
+        assert!(!sync
+            .refresh_file(&hls_dir.join("seg0.ts"), &mappings)
+            .unwrap());
+        assert!(dao.get_video_by_path(&segment).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_search_ignores_case_and_accents() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Café.mp4"), b"video").unwrap();
+        // 分解形式的文件名（e + U+0301），macOS 上常见
+        std::fs::write(root.join("CAFE\u{301} Noir.vtt"), "WEBVTT\n").unwrap();
+        std::fs::write(root.join("tea.vtt"), "WEBVTT\n").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        sync_in(&db_manager, &thumbs)
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        let search = |term: &str| {
+            let filter = VideoFilter {
+                search: Some(term.to_string()),
+                include_unknown: true,
+                ..Default::default()
+            };
+            let mut names: Vec<String> = VideoDao::new(&db_manager)
+                .get_root_videos_paginated(1, 100, &filter, &VideoSort::default())
+                .unwrap()
+                .videos
+                .into_iter()
+                .map(|v| v.name)
+                .collect();
+            names.sort();
+            names
+        };
+        let expected = vec!["CAFE\u{301} Noir.vtt".to_string(), "Café.mp4".to_string()];
+        assert_eq!(search("cafe"), expected);
+        assert_eq!(search("CAFÉ"), expected);
+        assert_eq!(search("café.mp4"), vec!["Café.mp4".to_string()]);
+
+        // 旧记录启动时补上搜索用的名称
+        db_manager
+            .conn
+            .execute("UPDATE videos SET name_search = NULL", [])
+            .unwrap();
+        assert_eq!(
+            VideoDao::new(&db_manager).backfill_name_search().unwrap(),
+            3
+        );
+        assert_eq!(search("cafe"), expected);
+    }
+
+    #[test]
+    fn test_scan_stops_at_max_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("a/b/c/d")).unwrap();
+        for file in [
+            "top.vtt",
+            "a/one.vtt",
+            "a/b/two.vtt",
+            "a/b/c/three.vtt",
+            "a/b/c/d/four.vtt",
+        ] {
+            std::fs::write(root.join(file), "WEBVTT\n").unwrap();
+        }
+
+        let sync_with_depth = |max_depth: Option<usize>| {
+            let db_manager = VideoDbManager::in_memory();
+            sync_in(&db_manager, &thumbs)
+                .with_scan_config(ScanConfig {
+                    max_depth,
+                    ..ScanConfig::default()
+                })
+                .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+                .unwrap();
+            let dao = VideoDao::new(&db_manager);
+            let mut paths: Vec<String> = dao
+                .get_descendants_paginated(&db_path(root), 1, 100, false)
+                .unwrap()
+                .videos
+                .into_iter()
+                .map(|v| {
+                    v.path
+                        .strip_prefix(&format!("{}/", db_path(root)))
+                        .unwrap()
+                        .to_string()
+                })
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        // 深度 2：a/b 记录为目录，但其中的文件和子目录不会被索引
+        assert_eq!(
+            sync_with_depth(Some(2)),
+            vec!["a", "a/b", "a/one.vtt", "top.vtt"]
+        );
+        let unlimited = sync_with_depth(None);
+        assert!(unlimited.contains(&"a/b/c/d/four.vtt".to_string()));
+        assert_eq!(unlimited.len(), 9);
+    }
+
+    #[test]
+    fn test_directory_child_count_is_recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let show = root.join("show");
+        std::fs::create_dir_all(show.join("extras")).unwrap();
+        std::fs::write(show.join("e01.mp4"), "").unwrap();
+        std::fs::write(show.join("e02.mp4"), "").unwrap();
+        std::fs::write(show.join("extras").join("bonus.mp4"), "").unwrap();
+        std::fs::write(show.join("e01.vtt"), "WEBVTT\n").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        sync_in(&db_manager, &thumbs)
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        let dao = VideoDao::new(&db_manager);
+        let child_count = |path: &Path| {
+            dao.get_video_by_path(&path.to_string_lossy())
+                .unwrap()
+                .unwrap()
+                .child_count
+        };
+        assert_eq!(child_count(&show), Some(3));
+        assert_eq!(child_count(&show.join("extras")), Some(1));
+        assert_eq!(child_count(&show.join("e01.mp4")), None);
+
+        dao.delete_from_database_by_path(&show.join("e02.mp4").to_string_lossy())
+            .unwrap();
+        assert_eq!(child_count(&show), Some(2));
+    }
+
+    #[test]
+    fn test_touched_source_regenerates_thumbnail() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let video_path = root.join("touched.mp4");
+        std::fs::write(&video_path, "").unwrap();
+
+        let generated = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        let set_modified = |path: &Path, time: std::time::SystemTime| {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+
+        // 已有缩略图，且比源文件新
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        let thumb_path = sync.thumbnail_path(&video_path);
+        std::fs::create_dir_all(thumb_path.parent().unwrap()).unwrap();
+        std::fs::write(&thumb_path, b"stale").unwrap();
+        set_modified(&thumb_path, generated);
+        set_modified(&video_path, generated - std::time::Duration::from_secs(60));
+
+        sync.initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        let records = sync.get_all_db_records().unwrap();
+        let record = &records[&video_path.to_string_lossy().to_string()];
+        assert_eq!(record.thumbnail_generated_at, modified_secs(&thumb_path));
+        assert_eq!(std::fs::read(&thumb_path).unwrap(), b"stale");
+
+        // 原地修改源文件后，下一次同步重新生成缩略图
+        set_modified(&video_path, std::time::SystemTime::now());
+        sync.initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        assert_ne!(std::fs::read(&thumb_path).ok(), Some(b"stale".to_vec()));
+    }
+
+    #[test]
+    fn test_edited_file_is_reprocessed() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.vtt");
+        std::fs::write(&path, "WEBVTT\n").unwrap();
+        let set_modified = |time: std::time::SystemTime| {
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        let original = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        set_modified(original);
+
+        // 同步后记录的是文件修改时间
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        sync.initialize_from_directory_with_progress(&mapping_for(dir.path()), false, false)
+            .unwrap();
+        let records = sync.get_all_db_records().unwrap();
+        let stored = &records[&path.to_string_lossy().to_string()];
+        assert_eq!(stored.last_modified, modified_secs(&path));
+
+        let record = FileInfo {
+            thumbnail: Some("thumbnails/a.jpg".to_string()),
+            width: Some(1920),
+            height: Some(1080),
+            ..stored.clone()
+        };
+        assert!(DirectorySync::is_up_to_date(
+            &path,
+            &record,
+            &sync.thumbnails
+        ));
+
+        // 原地编辑：修改时间变化，创建时间不变
+        std::fs::write(&path, "WEBVTT\n\n00:00.000 --> 00:01.000\nhi\n").unwrap();
+        set_modified(original + std::time::Duration::from_secs(60));
+        let created_at = std::fs::metadata(&path)
+            .ok()
+            .as_ref()
+            .and_then(get_systemtime_created)
+            .unwrap_or_default();
+        assert_eq!(created_at, record.created_at);
+        assert!(!DirectorySync::is_up_to_date(
+            &path,
+            &record,
+            &sync.thumbnails
+        ));
+    }
+
+    #[test]
+    fn test_refresh_file_updates_single_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let playlist = dir.path().join("index.m3u8");
+        let other = dir.path().join("other.m3u8");
+        let vod =
+            |seconds: u32| format!("#EXTM3U\n#EXTINF:{}.0,\nseg0.ts\n#EXT-X-ENDLIST\n", seconds);
+        std::fs::write(&playlist, vod(10)).unwrap();
+        std::fs::write(&other, vod(10)).unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        sync.initialize_from_directory_with_progress(&mapping_for(dir.path()), false, false)
+            .unwrap();
+
+        // 两个文件都被替换，只刷新其中一个
+        std::fs::write(&playlist, vod(90)).unwrap();
+        std::fs::write(&other, vod(90)).unwrap();
+        assert!(sync
+            .refresh_file(&playlist, &mapping_for(dir.path()))
+            .unwrap());
+
+        let records = sync.get_all_db_records().unwrap();
+        assert_eq!(
+            records[&db_path(&playlist)].duration.as_deref(),
+            Some("00:01:30")
+        );
+        assert_eq!(
+            records[&db_path(&other)].duration.as_deref(),
+            Some("00:00:10")
+        );
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(!sync
+            .refresh_file(&outside.path().join("index.m3u8"), &mapping_for(dir.path()))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_register_hls_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let hls_dir = dir.path().join("show").join("ep1");
+        std::fs::create_dir_all(&hls_dir).unwrap();
+        std::fs::write(
+            hls_dir.join("index.m3u8"),
+            "#EXTM3U\n#EXTINF:6.0,\nsegment_000.ts\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+        std::fs::write(hls_dir.join("segment_000.ts"), b"ts").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        assert!(sync
+            .register_hls_directory(&hls_dir, &mapping_for(dir.path()))
+            .unwrap());
+
+        let records = sync.get_all_db_records().unwrap();
+        let dir_record = &records[&db_path(&hls_dir)];
+        assert_eq!(dir_record.file_type, video_types::HLS_DIRECTORY);
+        assert_eq!(dir_record.parent_path, "/public/disk1/show");
+        let playlist = &records[&db_path(&hls_dir.join("index.m3u8"))];
+        assert_eq!(playlist.parent_path, "/public/disk1/show/ep1");
+        assert_eq!(records.len(), 2);
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(!sync
+            .register_hls_directory(outside.path(), &mapping_for(dir.path()))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_packaged_hls_output_skipped_by_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let video = root.join("ep1.mp4");
+        std::fs::write(&video, b"video").unwrap();
+        let output = crate::services::ffmpeg::hls_output_dir(&video);
+        std::fs::create_dir(&output).unwrap();
+        std::fs::write(output.join("index.m3u8"), "#EXTM3U\n").unwrap();
+        std::fs::write(output.join("segment_000.ts"), b"ts").unwrap();
+
+        // 没有登记的切片输出目录不会被扫描进来
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        let report = sync
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        assert_eq!(report.new, 1);
+
+        // 切片任务登记的记录在同步后保留，审计也不把它们算作缺失
+        assert!(sync
+            .register_hls_directory(&output, &mapping_for(root))
+            .unwrap());
+        let report = sync
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        assert_eq!((report.new, report.deleted), (0, 0));
+        assert_eq!(sync.get_all_db_records().unwrap().len(), 3);
+        let records = sync.record_paths().unwrap();
+        let audit = DirectorySync::audit(&ScanConfig::default(), &mapping_for(root), &records);
+        assert_eq!((audit.missing_from_db, audit.missing_on_disk), (0, 0));
+
+        // 输出目录被删除后记录随之清理
+        std::fs::remove_dir_all(&output).unwrap();
+        let report = sync
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        assert_eq!(report.deleted, 2);
+    }
+
+    #[test]
+    fn test_is_thumbnail_stale() {
+        assert!(is_thumbnail_stale(Some(200), Some(100)));
+        assert!(!is_thumbnail_stale(Some(100), Some(100)));
+        assert!(!is_thumbnail_stale(Some(50), Some(100)));
+        assert!(!is_thumbnail_stale(None, Some(100)));
+        assert!(!is_thumbnail_stale(Some(200), None));
+    }
+
+    #[test]
+    fn test_dry_run_matches_real_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("a.vtt"), "WEBVTT\n").unwrap();
+        std::fs::create_dir(root.join("show")).unwrap();
+        std::fs::write(root.join("show").join("b.vtt"), "WEBVTT\n").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        sync.initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+
+        std::fs::remove_file(root.join("a.vtt")).unwrap();
+        std::fs::write(root.join("c.vtt"), "WEBVTT\n").unwrap();
+
+        let before = sync.get_all_db_records().unwrap().len();
+        let plan = sync
+            .initialize_from_directory_with_progress(&mapping_for(root), false, true)
+            .unwrap();
+        assert!(plan.dry_run);
+        // 演练不写入数据库
+        assert_eq!(sync.get_all_db_records().unwrap().len(), before);
+
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+        assert_eq!(plan.examples.new, vec![path("c.vtt")]);
+        assert_eq!(plan.examples.deleted, vec![path("a.vtt")]);
+
+        let actual = sync
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        assert!(!actual.dry_run);
+        assert_eq!(
+            (plan.new, plan.changed, plan.deleted, plan.skipped),
+            (actual.new, actual.changed, actual.deleted, actual.skipped)
+        );
+    }
+
+    #[test]
+    fn test_audit_reports_both_kinds_of_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+        std::fs::write(root.join("indexed.vtt"), "WEBVTT\n").unwrap();
+        std::fs::write(root.join("unindexed.vtt"), "WEBVTT\n").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(
+            &db_manager,
+            &path("indexed.vtt"),
+            "subtitle",
+            "/public/disk1",
+        );
+        insert_video(&db_manager, &path("deleted.mp4"), "mp4", "/public/disk1");
+        let sync = sync_in(&db_manager, &thumbs);
+
+        let records = sync.record_paths().unwrap();
+        let report = DirectorySync::audit(&ScanConfig::default(), &mapping_for(root), &records);
+        assert_eq!(report.missing_from_db, 1);
+        assert_eq!(report.examples.missing_from_db, vec![path("unindexed.vtt")]);
+        assert_eq!(report.missing_on_disk, 1);
+        assert_eq!(report.examples.missing_on_disk, vec![path("deleted.mp4")]);
+        assert!(report.unreadable_dirs.is_empty());
+        // 只读检查，不修改数据库
+        assert_eq!(sync.get_all_db_records().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_truncated_mp4_does_not_abort_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let video_path = root.join("truncated.mp4");
+        let mut bytes = vec![0, 0, 0, 16];
+        bytes.extend_from_slice(b"ftypisom");
+        bytes.extend_from_slice(&[0, 0, 2, 0]);
+        bytes.extend_from_slice(&[0, 0, 16, 0]);
+        bytes.extend_from_slice(b"moov");
+        std::fs::write(&video_path, &bytes).unwrap();
+        std::fs::write(root.join("notes.vtt"), "WEBVTT\n").unwrap();
+
+        // 缩略图已存在时走 MP4 头部解析分支
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        let thumb_path = sync.thumbnail_path(&video_path);
+        std::fs::create_dir_all(thumb_path.parent().unwrap()).unwrap();
+        std::fs::write(&thumb_path, b"thumbnail").unwrap();
+
+        let report = sync
+            .initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        assert_eq!(report.new, 2);
+
+        let records = sync.get_all_db_records().unwrap();
+        let record = &records[&video_path.to_string_lossy().to_string()];
+        assert_eq!(record.width, None);
+        assert!(records.contains_key(&root.join("notes.vtt").to_string_lossy().to_string()));
+    }
+
+    #[test]
+    fn test_directory_poster_wins_over_first_child() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbs = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let with_poster = root.join("show");
+        let without_poster = root.join("movie");
+        std::fs::create_dir(&with_poster).unwrap();
+        std::fs::create_dir(&without_poster).unwrap();
+        std::fs::write(with_poster.join("poster.jpg"), b"poster").unwrap();
+        std::fs::write(with_poster.join("e01.mp4"), "").unwrap();
+        std::fs::write(without_poster.join("part2.mp4"), "").unwrap();
+        std::fs::write(without_poster.join("part10.mp4"), "").unwrap();
+
+        let db_manager = VideoDbManager::in_memory();
+        let sync = sync_in(&db_manager, &thumbs);
+        sync.initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        let records = sync.get_all_db_records().unwrap();
+
+        let show = &records[&with_poster.to_string_lossy().to_string()];
+        let poster_thumb = sync.directory_thumbnail_path(&with_poster);
+        assert_ne!(
+            poster_thumb,
+            sync.thumbnail_path(&with_poster.with_extension("mp4"))
+        );
+        assert_eq!(
+            show.thumbnail_source.as_deref(),
+            Some(thumbnail_sources::POSTER)
+        );
+        assert_eq!(
+            show.thumbnail.as_deref(),
+            Some(poster_thumb.to_string_lossy().as_ref())
+        );
+        assert_eq!(std::fs::read(&poster_thumb).unwrap(), b"poster");
+
+        // 原地编辑封面（目录的修改时间不变）后重新复制
+        let poster = with_poster.join("poster.jpg");
+        std::fs::write(&poster, b"edited").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&poster)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        sync.initialize_from_directory_with_progress(&mapping_for(root), false, false)
+            .unwrap();
+        assert_eq!(std::fs::read(&poster_thumb).unwrap(), b"edited");
+
+        let movie = &records[&without_poster.to_string_lossy().to_string()];
+        assert_eq!(
+            movie.thumbnail_source.as_deref(),
+            Some(thumbnail_sources::FIRST_CHILD)
+        );
+        assert_eq!(
+            movie.thumbnail.as_deref(),
+            Some(
+                sync.thumbnail_path(&without_poster.join("part2.mp4"))
+                    .to_string_lossy()
+                    .as_ref()
+            )
+        );
+    }
+}
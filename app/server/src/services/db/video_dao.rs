@@ -1,7 +1,367 @@
-use crate::models::{PaginatedVideoList, PaginationInfo, VideoInfo};
-use crate::services::db::connection::VideoDbManager;
-use crate::services::db::schema::queries;
-use rusqlite::{params_from_iter, Result};
+use crate::config::{DisplayTimezone, ListingConfig, TIMESTAMP_FORMAT};
+use crate::models::{
+    DeletionLogEntry, HistogramBucket, PaginatedVideoList, PaginationInfo, RelinkSuggestion,
+    TagInfo, VideoInfo, WatchState,
+};
+use crate::services::db::connection::{retry_on_busy, VideoDbManager};
+use crate::services::db::schema::{queries, video_types, WATCHED_THRESHOLD};
+use crate::services::ffmpeg::VideoMetadata;
+use crate::utils::{normalize_for_search, normalize_separators, parse_duration, parse_size};
+use base64::prelude::{Engine as _, BASE64_URL_SAFE_NO_PAD};
+use chrono::{FixedOffset, NaiveDateTime};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, OptionalExtension, Result, Row};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// 旧版本写入 created_at 时固定使用的时区偏移（北京时间，秒）
+const LEGACY_CREATED_AT_OFFSET: i32 = 8 * 3600;
+
+/// 随机查询允许的最大数量
+pub const MAX_RANDOM_COUNT: u32 = 100;
+
+/// 列表查询的过滤条件
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct VideoFilter {
+    /// 名称或路径包含的关键词
+    pub search: Option<String>,
+    /// 文件修改时间下界（Unix 时间戳，包含）
+    pub created_after: Option<i64>,
+    /// 文件修改时间上界（Unix 时间戳，包含）
+    pub created_before: Option<i64>,
+    /// 文件类型（video_types 中的常量）
+    pub file_type: Option<String>,
+    /// 是否已看完
+    pub watched: Option<bool>,
+    /// 是否已收藏
+    pub favorite: Option<bool>,
+    /// 带有的标签（不区分大小写）
+    pub tag: Option<String>,
+    /// 是否包含无法播放的 unknown 类型（未指定 file_type 时默认排除）
+    pub include_unknown: bool,
+    /// 是否排除被标记了元数据错误的条目
+    pub exclude_errors: bool,
+}
+
+impl VideoFilter {
+    /// 生成 WHERE 子句及其参数，没有过滤条件时返回空字符串
+    fn to_where_clause(&self) -> (String, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+
+        // 添加搜索条件
+        if let Some(search_term) = self.search.as_deref().filter(|s| !s.is_empty()) {
+            // 名称按规范化后的形式比较（不区分大小写和重音），路径保持原样
+            conditions.push("(name_search LIKE ? OR path LIKE ?)");
+            params.push(Value::Text(format!(
+                "%{}%",
+                normalize_for_search(search_term)
+            )));
+            params.push(Value::Text(format!("%{}%", search_term)));
+        }
+
+        // created_at 是格式化后的字符串，按数值型的 last_modified 过滤
+        if let Some(after) = self.created_after {
+            conditions.push("last_modified >= ?");
+            params.push(Value::Integer(after));
+        }
+        if let Some(before) = self.created_before {
+            conditions.push("last_modified <= ?");
+            params.push(Value::Integer(before));
+        }
+
+        if let Some(file_type) = &self.file_type {
+            conditions.push("type = ?");
+            params.push(Value::Text(file_type.clone()));
+        } else if !self.include_unknown {
+            conditions.push("type != ?");
+            params.push(Value::Text(video_types::UNKNOWN.to_string()));
+        }
+
+        if self.exclude_errors {
+            conditions.push("error IS NULL");
+        }
+
+        if let Some(watched) = self.watched {
+            conditions.push("watched = ?");
+            params.push(Value::Integer(watched as i64));
+        }
+        if let Some(favorite) = self.favorite {
+            conditions.push("favorite = ?");
+            params.push(Value::Integer(favorite as i64));
+        }
+        if let Some(tag) = &self.tag {
+            conditions.push(
+                "id IN (SELECT video_tags.video_id FROM video_tags
+                        JOIN tags ON tags.id = video_tags.tag_id WHERE tags.name = ?)",
+            );
+            params.push(Value::Text(tag.clone()));
+        }
+
+        if conditions.is_empty() {
+            (String::new(), params)
+        } else {
+            (format!("WHERE {}", conditions.join(" AND ")), params)
+        }
+    }
+}
+
+/// 目录下（递归）所有视频的汇总
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DirectoryTotals {
+    /// 总时长（秒）
+    pub duration: f64,
+    /// 总大小（字节）
+    pub size: u64,
+}
+
+/// 排行榜允许的排序依据
+pub const TOP_FIELDS: &[&str] = &["duration", "size", "watched"];
+
+/// 排行榜查询允许的最大数量
+pub const MAX_TOP_LIMIT: u32 = 100;
+
+/// 删除记录查询允许的最大数量
+pub const MAX_DELETION_LOG_LIMIT: u32 = 500;
+
+/// 排行榜的排序依据（只能通过 [`TopBy::parse`] 构造）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopBy {
+    /// 时长最长
+    Duration,
+    /// 文件最大
+    Size,
+    /// 播放次数最多
+    Watched,
+}
+
+impl TopBy {
+    /// 解析排序依据，不在 [`TOP_FIELDS`] 中时返回错误信息
+    pub fn parse(by: &str) -> std::result::Result<Self, String> {
+        match by.trim().to_ascii_lowercase().as_str() {
+            "duration" => Ok(Self::Duration),
+            "size" => Ok(Self::Size),
+            "watched" => Ok(Self::Watched),
+            _ => Err(format!(
+                "Invalid by: {} (allowed: {})",
+                by,
+                TOP_FIELDS.join(", ")
+            )),
+        }
+    }
+
+    /// 对应的数值列
+    fn column(self) -> &'static str {
+        match self {
+            Self::Duration => "duration_secs",
+            Self::Size => "size_bytes",
+            Self::Watched => "watch_count",
+        }
+    }
+}
+
+/// 同步时保存的观看状态的保留时间（秒），过期后删除
+pub const ORPHANED_WATCH_STATE_TTL_SECS: i64 = 180 * 24 * 60 * 60;
+
+/// 重新关联建议中允许的时长误差（秒），不同工具读出的时长可能有细微差别
+pub const RELINK_DURATION_TOLERANCE_SECS: f64 = 1.0;
+
+/// 直方图允许的统计指标
+pub const HISTOGRAM_METRICS: &[&str] = &["duration", "size"];
+
+/// 直方图允许的最大分桶数量
+pub const MAX_HISTOGRAM_BUCKETS: u32 = 100;
+
+/// 直方图的统计指标（只能通过 [`HistogramMetric::parse`] 构造）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramMetric {
+    /// 时长（秒）
+    Duration,
+    /// 大小（字节）
+    Size,
+}
+
+impl HistogramMetric {
+    /// 解析统计指标，不在 [`HISTOGRAM_METRICS`] 中时返回错误信息
+    pub fn parse(metric: &str) -> std::result::Result<Self, String> {
+        match metric.trim().to_ascii_lowercase().as_str() {
+            "duration" => Ok(Self::Duration),
+            "size" => Ok(Self::Size),
+            _ => Err(format!(
+                "Invalid metric: {} (allowed: {})",
+                metric,
+                HISTOGRAM_METRICS.join(", ")
+            )),
+        }
+    }
+
+    /// 对应的数值列
+    fn column(self) -> &'static str {
+        match self {
+            Self::Duration => "duration_secs",
+            Self::Size => "size_bytes",
+        }
+    }
+}
+
+/// 允许排序的字段，custom 为通过 `/api/videos/reorder` 设置的自定义顺序
+pub const SORT_FIELDS: &[&str] = &["name", "path", "created_at", "size", "duration", "custom"];
+
+/// 列表排序方式（只能通过 [`VideoSort::parse`] 构造，字段一定在白名单内）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoSort {
+    field: &'static str,
+    descending: bool,
+}
+
+impl Default for VideoSort {
+    /// 按创建时间倒序
+    fn default() -> Self {
+        Self {
+            field: "created_at",
+            descending: true,
+        }
+    }
+}
+
+impl VideoSort {
+    /// 解析排序参数，未指定的部分使用 `default`；字段或方向无效时返回错误信息
+    pub fn parse(
+        sort_by: Option<&str>,
+        sort_order: Option<&str>,
+        default: &VideoSort,
+    ) -> std::result::Result<Self, String> {
+        let field = match sort_by.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(field) => SORT_FIELDS
+                .iter()
+                .find(|f| f.eq_ignore_ascii_case(field))
+                .copied()
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid sort_by: {} (allowed: {})",
+                        field,
+                        SORT_FIELDS.join(", ")
+                    )
+                })?,
+            None => default.field,
+        };
+
+        let descending = match sort_order.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(order) if order.eq_ignore_ascii_case("desc") => true,
+            Some(order) if order.eq_ignore_ascii_case("asc") => false,
+            Some(order) => {
+                return Err(format!(
+                    "Invalid sort_order: {} (allowed: asc, desc)",
+                    order
+                ))
+            }
+            None => default.descending,
+        };
+
+        Ok(Self { field, descending })
+    }
+
+    /// 配置中的默认排序，配置无效时使用 created_at desc
+    pub fn configured(config: &ListingConfig) -> Self {
+        Self::parse(
+            Some(&config.default_sort_by),
+            Some(&config.default_sort_order),
+            &Self::default(),
+        )
+        .unwrap_or_default()
+    }
+
+    /// 排序表达式：NULL 与空字符串一样处理，保证游标比较时的顺序与 ORDER BY 一致
+    ///
+    /// size、duration 按数值列 size_bytes、duration_secs 排序（没有数值的视为 -1），
+    /// custom 按 sort_index 排序，无论升序还是降序，没有设置顺序的条目都排在最后
+    fn sort_key(&self) -> String {
+        match self.field {
+            "size" => "IFNULL(size_bytes, -1)".to_string(),
+            "duration" => "IFNULL(duration_secs, -1)".to_string(),
+            "custom" if self.descending => "IFNULL(sort_index, -1)".to_string(),
+            "custom" => format!("IFNULL(sort_index, {})", i64::MAX),
+            field => format!("IFNULL({}, '')", field),
+        }
+    }
+
+    /// 以 id 作为第二排序键，排序字段相同时顺序也是确定的
+    fn order_by_clause(&self) -> String {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        format!(
+            "ORDER BY {} {}, id {}",
+            self.sort_key(),
+            direction,
+            direction
+        )
+    }
+
+    /// 排在游标之后的条件
+    fn after_cursor_condition(&self) -> String {
+        format!(
+            "({}, id) {} (?, ?)",
+            self.sort_key(),
+            if self.descending { "<" } else { ">" }
+        )
+    }
+}
+
+/// 游标分页的位置：上一页最后一条记录的排序值和 id
+///
+/// 编码为不透明的 base64 字符串，同时记录排序方式，排序方式不同的游标会被拒绝
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListCursor {
+    sort: VideoSort,
+    key: Value,
+    id: i64,
+}
+
+impl ListCursor {
+    /// 编码为 URL 安全的 base64 字符串
+    pub fn encode(&self) -> String {
+        let key = match &self.key {
+            Value::Integer(n) => serde_json::json!(n),
+            Value::Real(n) => serde_json::json!(n),
+            Value::Text(s) => serde_json::json!(s),
+            Value::Null | Value::Blob(_) => serde_json::Value::Null,
+        };
+        let json = serde_json::json!({
+            "s": self.sort.field,
+            "d": self.sort.descending,
+            "k": key,
+            "i": self.id,
+        });
+        BASE64_URL_SAFE_NO_PAD.encode(json.to_string())
+    }
+
+    /// 解析游标，格式无效或与当前排序方式不一致时返回错误信息
+    pub fn decode(cursor: &str, sort: &VideoSort) -> std::result::Result<Self, String> {
+        let invalid = || "Invalid cursor".to_string();
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(cursor.trim())
+            .map_err(|_| invalid())?;
+        let json: serde_json::Value = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+
+        if json["s"].as_str() != Some(sort.field) || json["d"].as_bool() != Some(sort.descending) {
+            return Err("Cursor does not match the requested sort order".to_string());
+        }
+        let key = match &json["k"] {
+            serde_json::Value::String(s) => Value::Text(s.clone()),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(n) => Value::Integer(n),
+                None => Value::Real(n.as_f64().ok_or_else(invalid)?),
+            },
+            _ => return Err(invalid()),
+        };
+        let id = json["i"].as_i64().ok_or_else(invalid)?;
+
+        Ok(Self {
+            sort: sort.clone(),
+            key,
+            id,
+        })
+    }
+}
 
 /// 视频数据访问对象
 ///
@@ -16,40 +376,22 @@ impl<'a> VideoDao<'a> {
         Self { db_manager }
     }
 
-    /// 获取所有视频并重建层次结构
-    #[allow(dead_code)]
-    pub fn get_video_tree(&self) -> Result<Vec<VideoInfo>> {
-        // 获取所有未删除的条目
-        let mut stmt = self.db_manager.conn.prepare(queries::SELECT_ALL)?;
-
-        let video_iter = stmt.query_map([], |row| {
-            Ok(VideoInfo {
-                name: row.get(0)?,
-                path: row.get(1)?,
-                r#type: row.get(2)?,
-                children: None,
-                thumbnail: row.get(3)?,
-                duration: row.get(4)?,
-                size: row.get(5)?,
-                resolution: row.get(6)?,
-                bitrate: row.get(7)?,
-                codec: row.get(8)?,
-                created_at: row.get(9)?,
-                subtitle: row.get(10)?,
-                width: row.get(12)?,
-                height: row.get(13)?,
-                id: row.get(14)?,
-                parent_path: row.get(11)?,
-            })
-        })?;
-
-        let mut videos: Vec<VideoInfo> = Vec::new();
-        for video in video_iter {
-            videos.push(video?);
-        }
+    /// 只查询目录下（递归）的条目并构建该目录的子树，返回目录的直接子节点
+    ///
+    /// `exclude_errors` 为 true 时不包含被标记了元数据错误的条目
+    pub fn get_subtree(&self, path: &str, exclude_errors: bool) -> Result<Vec<VideoInfo>> {
+        let root = normalize_separators(path).trim_end_matches('/').to_string();
+        let mut stmt = self
+            .db_manager
+            .conn
+            .prepare(&subtree_query(exclude_errors))?;
+        let videos = stmt
+            .query_map([&root], row_to_video)?
+            .collect::<Result<Vec<_>>>()?;
 
-        // 使用 TreeBuilder 构建树形结构
-        Ok(crate::services::db::tree::TreeBuilder::build_tree(videos))
+        Ok(crate::services::db::tree::TreeBuilder::build_subtree(
+            &root, videos,
+        ))
     }
 
     /// 获取所有视频（所有数据源目录）
@@ -75,6 +417,17 @@ impl<'a> VideoDao<'a> {
                 height: row.get(13)?,
                 id: 0, // SELECT_ALL_FULL 不包含 id，使用默认值 0
                 parent_path: row.get(11)?,
+                source: None,
+                url: None,
+                child_count: row.get(14)?,
+                thumbnail_source: row.get(16)?,
+                watched: row.get(18)?,
+                watch_position: row.get(19)?,
+                favorite: row.get(21)?,
+                watch_count: row.get(22)?,
+                total_duration: None,
+                total_size: None,
+                error: row.get(23)?,
             })
         })?;
 
@@ -91,117 +444,114 @@ impl<'a> VideoDao<'a> {
         &self,
         page: u32,
         page_size: u32,
-        search: Option<&str>,
-        sort_by: Option<&str>,
-        sort_order: Option<&str>,
+        filter: &VideoFilter,
+        sort: &VideoSort,
     ) -> Result<PaginatedVideoList> {
-        // 计算偏移量
-        let offset = (page - 1) * page_size;
+        self.root_videos_page(page, None, page_size, filter, sort)
+    }
+
+    /// 游标分页：返回排在游标之后的 `page_size` 条记录，没有游标时从头开始
+    ///
+    /// 按 (排序值, id) 定位，翻页期间插入或删除记录不会导致重复或遗漏
+    pub fn get_root_videos_after(
+        &self,
+        cursor: Option<&ListCursor>,
+        page_size: u32,
+        filter: &VideoFilter,
+        sort: &VideoSort,
+    ) -> Result<PaginatedVideoList> {
+        self.root_videos_page(1, cursor, page_size, filter, sort)
+    }
 
+    /// 查询一页记录；有游标时按游标定位（忽略 page），否则按页码偏移
+    fn root_videos_page(
+        &self,
+        page: u32,
+        cursor: Option<&ListCursor>,
+        page_size: u32,
+        filter: &VideoFilter,
+        sort: &VideoSort,
+    ) -> Result<PaginatedVideoList> {
         // 构建查询条件 - 不再限制 parent_path，查询所有数据源目录
-        let mut where_clause = String::new();
-        let mut params: Vec<String> = Vec::new();
+        let (where_clause, params) = filter.to_where_clause();
 
-        // 添加搜索条件
-        if let Some(search_term) = search {
-            if !search_term.is_empty() {
-                where_clause.push_str("WHERE (name LIKE ? OR path LIKE ?)");
-                let search_pattern = format!("%{}%", search_term);
-                params.push(search_pattern.clone());
-                params.push(search_pattern);
-            }
-        }
-
-        // 构建排序
-        let order_by = match (sort_by, sort_order) {
-            (Some(field), Some(order)) => {
-                let valid_fields = ["name", "path", "created_at", "size", "duration"];
-                if valid_fields.contains(&field) {
-                    format!("ORDER BY {} {}", field, order.to_uppercase())
-                } else {
-                    "ORDER BY created_at DESC".to_string()
-                }
-            }
-            (Some(field), None) => {
-                let valid_fields = ["name", "path", "created_at", "size", "duration"];
-                if valid_fields.contains(&field) {
-                    format!("ORDER BY {} DESC", field)
-                } else {
-                    "ORDER BY created_at DESC".to_string()
-                }
-            }
-            (None, Some(order)) => {
-                format!("ORDER BY created_at {}", order.to_uppercase())
-            }
-            (None, None) => "ORDER BY created_at DESC".to_string(),
+        // 获取总数（相同过滤条件翻页时使用缓存）
+        let total = self.db_manager.count_cache.get_or_count(filter, || {
+            let count_query = format!("SELECT COUNT(*) FROM videos {}", where_clause);
+            let mut count_stmt = self.db_manager.conn.prepare(&count_query)?;
+            count_stmt.query_row(params_from_iter(params.iter()), |row| row.get(0))
+        })?;
+
+        let mut page_where = where_clause;
+        let mut page_params = params;
+        let mut has_before_cursor = false;
+        if let Some(cursor) = cursor {
+            let join = if page_where.is_empty() {
+                "WHERE"
+            } else {
+                " AND"
+            };
+            page_params.push(cursor.key.clone());
+            page_params.push(Value::Integer(cursor.id));
+            // 游标本身及之前还有符合条件的记录时才有上一页
+            has_before_cursor = self.db_manager.conn.query_row(
+                &format!(
+                    "SELECT EXISTS(SELECT 1 FROM videos {}{} NOT {})",
+                    page_where,
+                    join,
+                    sort.after_cursor_condition()
+                ),
+                params_from_iter(page_params.iter()),
+                |row| row.get(0),
+            )?;
+            page_where = format!("{}{} {}", page_where, join, sort.after_cursor_condition());
+        }
+        let offset = match cursor {
+            Some(_) => 0,
+            None => (page - 1) * page_size,
         };
 
-        // 构建完整的查询语句
+        // 多取一条用于判断是否还有下一页；最后一列是排序值，用于生成游标
         let query = format!(
-            "SELECT name, path, type, thumbnail, duration, size, resolution, bitrate, codec, created_at, subtitle, width, height,id,parent_path
+            "SELECT {}, {}
              FROM videos
              {}
              {}
              LIMIT ? OFFSET ?",
-            where_clause, order_by
+            queries::VIDEO_COLUMNS,
+            sort.sort_key(),
+            page_where,
+            sort.order_by_clause()
         );
+        page_params.push(Value::Integer(page_size as i64 + 1));
+        page_params.push(Value::Integer(offset as i64));
 
-        // 获取总数
-        let count_query = format!("SELECT COUNT(*) FROM videos {}", where_clause);
-        let mut count_stmt = self.db_manager.conn.prepare(&count_query)?;
-        let total: u64 = match params.len() {
-            0 => count_stmt.query_row([], |row| row.get(0))?,
-            1 => count_stmt.query_row([params[0].as_str()], |row| row.get(0))?,
-            2 => {
-                count_stmt.query_row([params[0].as_str(), params[1].as_str()], |row| row.get(0))?
-            }
-            3 => count_stmt.query_row(
-                [params[0].as_str(), params[1].as_str(), params[2].as_str()],
-                |row| row.get(0),
-            )?,
-            _ => count_stmt.query_row([params[0].as_str()], |row| row.get(0))?,
-        };
-
-        // 获取分页数据
         let mut stmt = self.db_manager.conn.prepare(&query)?;
-
-        // 添加分页参数
-        let page_size_str = page_size.to_string();
-        let offset_str = offset.to_string();
-        let mut params_with_pagination = params.clone();
-        params_with_pagination.push(page_size_str);
-        params_with_pagination.push(offset_str);
-
-        // 将String转换为&str的Vec
-        let param_refs: Vec<&str> = params_with_pagination.iter().map(|s| s.as_str()).collect();
-
-        let videos = stmt
-            .query_map(params_from_iter(param_refs.iter()), |row| {
-                Ok(VideoInfo {
-                    name: row.get(0)?,
-                    path: row.get(1)?,
-                    r#type: row.get(2)?,
-                    children: None,
-                    thumbnail: row.get(3)?,
-                    duration: row.get(4)?,
-                    size: row.get(5)?,
-                    resolution: row.get(6)?,
-                    bitrate: row.get(7)?,
-                    codec: row.get(8)?,
-                    created_at: row.get(9)?,
-                    subtitle: row.get(10)?,
-                    width: row.get(11)?,
-                    height: row.get(12)?,
-                    id: row.get(13)?,
-                    parent_path: row.get(14)?,
-                })
+        let mut rows = stmt
+            .query_map(params_from_iter(page_params.iter()), |row| {
+                Ok((row_to_video(row)?, row.get::<_, Value>(VIDEO_COLUMN_COUNT)?))
             })?
             .collect::<Result<Vec<_>, _>>()?;
 
+        let has_more = rows.len() > page_size as usize;
+        rows.truncate(page_size as usize);
+        let next_cursor = rows
+            .last()
+            .filter(|_| has_more)
+            .map(|(video, key)| ListCursor {
+                sort: sort.clone(),
+                key: key.clone(),
+                id: video.id,
+            })
+            .map(|cursor| cursor.encode());
+        let videos = rows.into_iter().map(|(video, _)| video).collect();
+
         // 计算分页信息
         let total_pages = ((total as f64) / (page_size as f64)).ceil() as u32;
-        let has_next = page < total_pages;
-        let has_prev = page > 1;
+        let (has_next, has_prev) = match cursor {
+            Some(_) => (has_more, has_before_cursor),
+            None => (page < total_pages, page > 1),
+        };
 
         Ok(PaginatedVideoList {
             videos,
@@ -212,19 +562,320 @@ impl<'a> VideoDao<'a> {
                 total_pages,
                 has_next,
                 has_prev,
+                next_cursor,
+            },
+        })
+    }
+
+    /// 目录下（递归）所有条目的平铺分页列表，不包含目录本身，按路径排序
+    ///
+    /// `exclude_errors` 为 true 时不包含被标记了元数据错误的条目
+    pub fn get_descendants_paginated(
+        &self,
+        parent_path: &str,
+        page: u32,
+        page_size: u32,
+        exclude_errors: bool,
+    ) -> Result<PaginatedVideoList> {
+        let offset = (page - 1) * page_size;
+        // 用 substr 精确比较路径前缀：LIKE 会把路径中的 `_`、`%` 当作通配符，且不区分大小写
+        let prefix = format!(
+            "{}/",
+            normalize_separators(parent_path).trim_end_matches('/')
+        );
+        let condition = format!(
+            "substr(path, 1, length(?1)) = ?1{}",
+            error_condition(exclude_errors)
+        );
+
+        let total: u64 = self.db_manager.conn.query_row(
+            &format!("SELECT COUNT(*) FROM videos WHERE {}", condition),
+            [&prefix],
+            |row| row.get(0),
+        )?;
+
+        let query = format!(
+            "SELECT {} FROM videos WHERE {} ORDER BY path LIMIT ?2 OFFSET ?3",
+            queries::VIDEO_COLUMNS,
+            condition
+        );
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let videos = stmt
+            .query_map(rusqlite::params![&prefix, page_size, offset], row_to_video)?
+            .collect::<Result<Vec<_>>>()?;
+
+        let total_pages = ((total as f64) / (page_size as f64)).ceil() as u32;
+        Ok(PaginatedVideoList {
+            videos,
+            pagination: PaginationInfo {
+                page,
+                page_size,
+                total,
+                total_pages,
+                has_next: page < total_pages,
+                has_prev: page > 1,
+                next_cursor: None,
             },
         })
     }
 
+    /// 按 `ordered_ids` 的顺序设置目录内条目的自定义顺序（sort_index 从 0 开始）
+    ///
+    /// 目录内未列出的条目清除自定义顺序；有 id 不属于该目录时不做修改并返回 None，
+    /// 否则返回设置了顺序的条目数量
+    pub fn reorder_directory(
+        &self,
+        parent_path: &str,
+        ordered_ids: &[i64],
+    ) -> Result<Option<usize>> {
+        let parent_path = normalize_separators(parent_path);
+        retry_on_busy(|| {
+            let tx = self.db_manager.conn.unchecked_transaction()?;
+
+            let mut in_directory = 0;
+            for id in ordered_ids {
+                in_directory += tx.query_row(
+                    "SELECT COUNT(*) FROM videos WHERE id = ?1 AND parent_path = ?2",
+                    rusqlite::params![id, &parent_path],
+                    |row| row.get::<_, usize>(0),
+                )?;
+            }
+            if in_directory != ordered_ids.len() {
+                return Ok(None);
+            }
+
+            tx.execute(
+                "UPDATE videos SET sort_index = NULL WHERE parent_path = ?1",
+                [&parent_path],
+            )?;
+            for (index, id) in ordered_ids.iter().enumerate() {
+                tx.execute(
+                    "UPDATE videos SET sort_index = ?1 WHERE id = ?2",
+                    rusqlite::params![index as i64, id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(Some(ordered_ids.len()))
+        })
+    }
+
+    /// 子孙条目中包含指定类型的目录，按路径排序
+    pub fn get_directories_containing(&self, file_type: &str) -> Result<Vec<VideoInfo>> {
+        // 按路径范围查找子孙条目（与 get_subtree 相同的写法），可以使用 path 上的索引
+        let query = format!(
+            "SELECT {} FROM videos dir
+             WHERE dir.type IN (?2, ?3)
+               AND EXISTS (
+                   SELECT 1 FROM videos entry
+                   WHERE entry.path > dir.path || '/' AND entry.path < dir.path || '0'
+                     AND entry.type = ?1
+               )
+             ORDER BY path",
+            queries::VIDEO_COLUMNS
+        );
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let videos = stmt
+            .query_map(
+                [
+                    file_type,
+                    video_types::DIRECTORY,
+                    video_types::HLS_DIRECTORY,
+                ],
+                row_to_video,
+            )?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(videos)
+    }
+
     /// 从数据库中删除视频记录（通过ID）
     /// 返回删除的记录数量
     pub fn delete_from_database_by_id(&self, video_id: i64) -> Result<usize> {
+        let path: Option<String> = self
+            .db_manager
+            .conn
+            .query_row("SELECT path FROM videos WHERE id = ?1", [video_id], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        let Some(path) = path else {
+            return Ok(0);
+        };
         let stmt = "DELETE FROM videos WHERE id = ?1";
         let mut delete_stmt = self.db_manager.conn.prepare(stmt)?;
         let affected_rows = delete_stmt.execute([video_id])?;
+        self.prune_tag_links()?;
+        self.db_manager.count_cache.invalidate();
+        self.refresh_child_counts_for(&path)?;
+        Ok(affected_rows)
+    }
+
+    /// 从数据库中删除记录（通过路径）
+    pub fn delete_from_database_by_path(&self, path: &str) -> Result<usize> {
+        let stmt = "DELETE FROM videos WHERE path = ?1";
+        let mut delete_stmt = self.db_manager.conn.prepare(stmt)?;
+        let affected_rows = delete_stmt.execute([normalize_separators(path)])?;
+        self.prune_tag_links()?;
+        self.db_manager.count_cache.invalidate();
+        self.refresh_child_counts_for(path)?;
         Ok(affected_rows)
     }
 
+    /// 重新计算所有目录的 child_count（目录下递归包含的可播放视频数量）
+    ///
+    /// 返回更新的目录数量
+    pub fn refresh_child_counts(&self) -> Result<usize> {
+        self.update_child_counts(&[])
+    }
+
+    /// 只重新计算 `path` 本身及其各级上级目录的 child_count
+    ///
+    /// 单条记录增删后使用，不必重新统计整个表。返回更新的目录数量
+    pub fn refresh_child_counts_for(&self, path: &str) -> Result<usize> {
+        let path = normalize_separators(path);
+        let mut scope: Vec<&str> = path
+            .match_indices('/')
+            .filter(|(index, _)| *index > 0)
+            .map(|(index, _)| &path[..index])
+            .collect();
+        scope.push(&path);
+        self.update_child_counts(&scope)
+    }
+
+    /// `scope` 为空时更新所有目录，否则只更新其中列出的路径
+    fn update_child_counts(&self, scope: &[&str]) -> Result<usize> {
+        let video_placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let scope_clause = if scope.is_empty() {
+            String::new()
+        } else {
+            format!(" AND path IN ({})", vec!["?"; scope.len()].join(", "))
+        };
+        // 用 substr 精确比较路径前缀，避免 LIKE 把路径中的 `_`、`%` 当作通配符
+        let query = format!(
+            "UPDATE videos SET child_count = (
+                SELECT COUNT(*) FROM videos AS child
+                WHERE child.type IN ({})
+                  AND substr(child.path, 1, length(videos.path) + 1) = videos.path || ?
+            )
+            WHERE type IN (?, ?){}",
+            video_placeholders, scope_clause
+        );
+
+        // 数据库中的路径统一使用 `/` 分隔
+        let params = video_types::VIDEO_TYPES
+            .iter()
+            .copied()
+            .chain(["/", video_types::DIRECTORY, video_types::HLS_DIRECTORY])
+            .chain(scope.iter().copied());
+        self.db_manager
+            .conn
+            .execute(&query, params_from_iter(params))
+    }
+
+    /// 按显示时区重新格式化所有记录的 created_at，不需要重新扫描文件
+    ///
+    /// 旧版本只保存了北京时间的字符串，没有 created_epoch 的记录先据此补上时间戳。
+    /// 返回更新的记录数量
+    pub fn reformat_created_at(&self, timezone: &DisplayTimezone) -> Result<usize> {
+        let rows: Vec<(i64, Option<String>, Option<i64>)> = {
+            let mut stmt = self
+                .db_manager
+                .conn
+                .prepare("SELECT id, created_at, created_epoch FROM videos")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<_>>>()?;
+            rows
+        };
+
+        let legacy_offset = FixedOffset::east_opt(LEGACY_CREATED_AT_OFFSET).unwrap();
+        let tx = self.db_manager.conn.unchecked_transaction()?;
+        let mut updated = 0;
+        {
+            let mut update =
+                tx.prepare("UPDATE videos SET created_at = ?1, created_epoch = ?2 WHERE id = ?3")?;
+            for (id, created_at, created_epoch) in rows {
+                let epoch = created_epoch.or_else(|| {
+                    let naive =
+                        NaiveDateTime::parse_from_str(created_at.as_deref()?, TIMESTAMP_FORMAT)
+                            .ok()?;
+                    let local = naive.and_local_timezone(legacy_offset).single()?;
+                    Some(local.timestamp())
+                });
+                let Some(epoch) = epoch else {
+                    continue;
+                };
+                let formatted = timezone.format_timestamp(epoch);
+                if formatted != created_at || created_epoch.is_none() {
+                    update.execute(rusqlite::params![formatted, epoch, id])?;
+                    updated += 1;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// 为旧版本的记录（包括同步时保存的观看状态）补上 duration_secs 和 size_bytes
+    /// （由 duration、size 字符串解析），返回更新的记录数量
+    pub fn backfill_numeric_columns(&self) -> Result<usize> {
+        let tx = self.db_manager.conn.unchecked_transaction()?;
+        let mut updated = 0;
+        for table in ["videos", "orphaned_watch_state"] {
+            let rows: Vec<(i64, Option<String>, Option<String>)> = {
+                let mut stmt = tx.prepare(&format!(
+                    "SELECT rowid, duration, size FROM {}
+                     WHERE duration_secs IS NULL OR size_bytes IS NULL",
+                    table
+                ))?;
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .collect::<Result<Vec<_>>>()?;
+                rows
+            };
+
+            let mut update = tx.prepare(&format!(
+                "UPDATE {} SET duration_secs = COALESCE(duration_secs, ?1),
+                               size_bytes = COALESCE(size_bytes, ?2)
+                 WHERE rowid = ?3",
+                table
+            ))?;
+            for (id, duration, size) in rows {
+                let duration = duration.as_deref().and_then(parse_duration);
+                let size = size.as_deref().and_then(parse_size);
+                if duration.is_some() || size.is_some() {
+                    update.execute(rusqlite::params![duration, size, id])?;
+                    updated += 1;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// 为旧记录补上搜索用的名称，返回更新的记录数量
+    pub fn backfill_name_search(&self) -> Result<usize> {
+        let rows: Vec<(i64, String)> = {
+            let mut stmt = self
+                .db_manager
+                .conn
+                .prepare("SELECT id, name FROM videos WHERE name_search IS NULL")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>>>()?;
+            rows
+        };
+
+        let tx = self.db_manager.conn.unchecked_transaction()?;
+        {
+            let mut update = tx.prepare("UPDATE videos SET name_search = ?1 WHERE id = ?2")?;
+            for (id, name) in &rows {
+                update.execute(rusqlite::params![normalize_for_search(name), id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(rows.len())
+    }
+
     /// 检查视频记录是否存在（通过ID）
     pub fn video_exists_by_id(&self, video_id: i64) -> Result<bool> {
         let stmt = "SELECT COUNT(*) FROM videos WHERE id = ?1";
@@ -240,4 +891,1664 @@ impl<'a> VideoDao<'a> {
         let result = get_stmt.query_row([video_id], |row| row.get(0)).ok();
         Ok(result)
     }
+
+    /// 根据缩略图路径获取视频路径
+    pub fn get_video_path_by_thumbnail(&self, thumbnail: &str) -> Result<Option<String>> {
+        self.db_manager
+            .conn
+            .query_row(
+                "SELECT path FROM videos WHERE thumbnail = ?1 LIMIT 1",
+                [normalize_separators(thumbnail)],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// 获取缓存的章节 JSON（记录不存在、尚未缓存或缓存后文件被修改过时返回 None）
+    ///
+    /// `modified` 为文件当前的修改时间（Unix 秒）
+    pub fn get_cached_chapters(&self, path: &str, modified: i64) -> Result<Option<String>> {
+        let stmt = "SELECT chapters FROM videos WHERE path = ?1 AND chapters_modified = ?2";
+        let mut get_stmt = self.db_manager.conn.prepare(stmt)?;
+        let result: Option<Option<String>> = get_stmt
+            .query_row(
+                rusqlite::params![normalize_separators(path), modified],
+                |row| row.get(0),
+            )
+            .ok();
+        Ok(result.flatten())
+    }
+
+    /// 用重新读取的元数据补全记录，读取失败（None 或空）的字段保留原值，读到时长时清除 error 标记；
+    /// 返回更新的记录数量
+    pub fn apply_metadata(&self, path: &str, metadata: &VideoMetadata) -> Result<usize> {
+        let duration = metadata.duration.as_deref().filter(|d| !d.is_empty());
+        let thumbnail = metadata
+            .thumbnail_path
+            .as_deref()
+            .filter(|t| !t.is_empty())
+            .map(normalize_separators);
+        let updated = retry_on_busy(|| {
+            self.db_manager.conn.execute(
+                "UPDATE videos SET
+                duration = COALESCE(?1, duration),
+                duration_secs = COALESCE(?2, duration_secs),
+                width = COALESCE(?3, width),
+                height = COALESCE(?4, height),
+                thumbnail = COALESCE(?5, thumbnail),
+                thumbnail_generated_at = CASE WHEN ?5 IS NULL THEN thumbnail_generated_at
+                                              ELSE CAST(strftime('%s', 'now') AS INTEGER) END,
+                error = CASE WHEN ?1 IS NULL THEN error ELSE NULL END
+             WHERE path = ?6",
+                rusqlite::params![
+                    duration,
+                    duration.and_then(parse_duration),
+                    metadata.width,
+                    metadata.height,
+                    thumbnail,
+                    normalize_separators(path)
+                ],
+            )
+        })?;
+        // 错误标记被清除后，隐藏错误条目的列表总数随之变化
+        self.db_manager.count_cache.invalidate();
+        Ok(updated)
+    }
+
+    /// 更新缩略图路径和生成时间（Unix 秒），返回更新的记录数量
+    pub fn set_thumbnail(&self, path: &str, thumbnail: &str, generated_at: i64) -> Result<usize> {
+        retry_on_busy(|| {
+            self.db_manager.conn.execute(
+                "UPDATE videos SET thumbnail = ?1, thumbnail_generated_at = ?2 WHERE path = ?3",
+                rusqlite::params![
+                    normalize_separators(thumbnail),
+                    generated_at,
+                    normalize_separators(path)
+                ],
+            )
+        })
+    }
+
+    /// 清除路径本身及其下所有条目的缩略图信息（thumbnail、生成时间和目录封面来源），
+    /// 下次请求或同步时重新生成；返回清除的记录数量和可以删除的缩略图路径（去重）
+    ///
+    /// 子树之外的条目（例如上级目录以第一个视频作为封面）仍在引用的缩略图不返回
+    pub fn clear_thumbnails_under(&self, path: &str) -> Result<(usize, Vec<String>)> {
+        let path = normalize_separators(path).trim_end_matches('/').to_string();
+        let in_subtree = |alias: &str| {
+            format!(
+                "({alias}.path = ?1 OR ({alias}.path > ?1 || '/' AND {alias}.path < ?1 || '0'))"
+            )
+        };
+
+        let tx = self.db_manager.conn.unchecked_transaction()?;
+        let thumbnails = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT DISTINCT v.thumbnail FROM videos AS v
+                 WHERE {} AND v.thumbnail != ''
+                   AND NOT EXISTS (
+                       SELECT 1 FROM videos AS o
+                       WHERE o.thumbnail = v.thumbnail AND NOT {}
+                   )
+                 ORDER BY v.thumbnail",
+                in_subtree("v"),
+                in_subtree("o")
+            ))?;
+            let thumbnails = stmt
+                .query_map([&path], |row| row.get(0))?
+                .collect::<Result<Vec<String>>>()?;
+            thumbnails
+        };
+
+        let cleared = tx.execute(
+            &format!(
+                "UPDATE videos AS v SET thumbnail = NULL, thumbnail_generated_at = NULL, thumbnail_source = NULL
+                 WHERE {} AND v.thumbnail IS NOT NULL",
+                in_subtree("v")
+            ),
+            [&path],
+        )?;
+        tx.commit()?;
+        Ok((cleared, thumbnails))
+    }
+
+    /// 标记已看完或未看完，返回更新的记录数量
+    pub fn set_watched(&self, video_id: i64, watched: bool) -> Result<usize> {
+        let updated = retry_on_busy(|| {
+            self.db_manager.conn.execute(
+                "UPDATE videos SET watched = ?1 WHERE id = ?2",
+                rusqlite::params![watched, video_id],
+            )
+        })?;
+        self.db_manager.count_cache.invalidate();
+        Ok(updated)
+    }
+
+    /// 记录一次播放（播放次数加一），返回当前的播放次数，记录不存在时返回 None
+    pub fn record_play(&self, video_id: i64) -> Result<Option<u32>> {
+        retry_on_busy(|| {
+            self.db_manager
+                .conn
+                .query_row(
+                    "UPDATE videos SET watch_count = watch_count + 1 WHERE id = ?1 RETURNING watch_count",
+                    [video_id],
+                    |row| row.get(0),
+                )
+                .optional()
+        })
+    }
+
+    /// 收藏或取消收藏，返回更新的记录数量
+    pub fn set_favorite(&self, video_id: i64, favorite: bool) -> Result<usize> {
+        let updated = retry_on_busy(|| {
+            self.db_manager.conn.execute(
+                "UPDATE videos SET favorite = ?1 WHERE id = ?2",
+                rusqlite::params![favorite, video_id],
+            )
+        })?;
+        self.db_manager.count_cache.invalidate();
+        Ok(updated)
+    }
+
+    /// 为条目添加标签，标签不存在时自动创建；返回条目当前的标签
+    pub fn add_tags(&self, video_id: i64, names: &[String]) -> Result<Vec<String>> {
+        retry_on_busy(|| {
+            let tx = self.db_manager.conn.unchecked_transaction()?;
+            for name in names {
+                tx.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", [name])?;
+                tx.execute(
+                    "INSERT OR IGNORE INTO video_tags (video_id, tag_id)
+                     SELECT ?1, id FROM tags WHERE name = ?2",
+                    rusqlite::params![video_id, name],
+                )?;
+            }
+            tx.commit()
+        })?;
+        self.db_manager.count_cache.invalidate();
+        self.get_video_tags(video_id)
+    }
+
+    /// 移除条目的标签（标签本身保留）；返回条目当前的标签
+    pub fn remove_tags(&self, video_id: i64, names: &[String]) -> Result<Vec<String>> {
+        retry_on_busy(|| {
+            let tx = self.db_manager.conn.unchecked_transaction()?;
+            for name in names {
+                tx.execute(
+                    "DELETE FROM video_tags
+                     WHERE video_id = ?1 AND tag_id IN (SELECT id FROM tags WHERE name = ?2)",
+                    rusqlite::params![video_id, name],
+                )?;
+            }
+            tx.commit()
+        })?;
+        self.db_manager.count_cache.invalidate();
+        self.get_video_tags(video_id)
+    }
+
+    /// 条目的标签，按名称排序
+    pub fn get_video_tags(&self, video_id: i64) -> Result<Vec<String>> {
+        let mut stmt = self.db_manager.conn.prepare(
+            "SELECT tags.name FROM video_tags
+             JOIN tags ON tags.id = video_tags.tag_id
+             WHERE video_tags.video_id = ?1
+             ORDER BY tags.name",
+        )?;
+        let tags = stmt.query_map([video_id], |row| row.get(0))?.collect();
+        tags
+    }
+
+    /// 所有标签及其条目数量，按名称排序
+    pub fn list_tags(&self) -> Result<Vec<TagInfo>> {
+        let mut stmt = self.db_manager.conn.prepare(
+            "SELECT tags.name, COUNT(video_tags.video_id) FROM tags
+             LEFT JOIN video_tags ON video_tags.tag_id = tags.id
+             GROUP BY tags.id
+             ORDER BY tags.name",
+        )?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(TagInfo {
+                    name: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })?
+            .collect();
+        tags
+    }
+
+    /// 带有指定标签的条目路径
+    pub fn get_tagged_paths(&self, tag: &str) -> Result<HashSet<String>> {
+        let mut stmt = self.db_manager.conn.prepare(
+            "SELECT videos.path FROM videos
+             JOIN video_tags ON video_tags.video_id = videos.id
+             JOIN tags ON tags.id = video_tags.tag_id
+             WHERE tags.name = ?1",
+        )?;
+        let paths = stmt.query_map([tag], |row| row.get(0))?.collect();
+        paths
+    }
+
+    /// 删除已不存在的条目的标签关联
+    pub fn prune_tag_links(&self) -> Result<usize> {
+        self.db_manager.conn.execute(
+            "DELETE FROM video_tags WHERE video_id NOT IN (SELECT id FROM videos)",
+            [],
+        )
+    }
+
+    /// 元数据不完整的视频（时长或缩略图缺失，MP4 还包括宽高缺失），按路径排序
+    ///
+    /// 同步时读取失败的值以空字符串或 NULL 保存；播放列表不读取宽高
+    pub fn get_incomplete(&self) -> Result<Vec<VideoInfo>> {
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let query = format!(
+            "SELECT {} FROM videos
+             WHERE type IN ({})
+               AND (duration IS NULL OR duration = ''
+                    OR thumbnail IS NULL OR thumbnail = ''
+                    OR (type = ? AND (width IS NULL OR height IS NULL)))
+             ORDER BY path",
+            queries::VIDEO_COLUMNS,
+            placeholders
+        );
+
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let params = video_types::VIDEO_TYPES
+            .iter()
+            .chain(std::iter::once(&video_types::MP4));
+        let videos = stmt
+            .query_map(params_from_iter(params), row_to_video)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(videos)
+    }
+
+    /// 所有已收藏的条目，按名称排序
+    pub fn get_favorites(&self) -> Result<Vec<VideoInfo>> {
+        let query = format!(
+            "SELECT {} FROM videos WHERE favorite = 1 ORDER BY name",
+            queries::VIDEO_COLUMNS
+        );
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let videos = stmt.query_map([], row_to_video)?.collect();
+        videos
+    }
+
+    /// 排行榜：按时长、大小或播放次数从高到低返回前 `limit` 个视频（最多 [`MAX_TOP_LIMIT`] 个）
+    ///
+    /// 只包含可播放的视频类型；没有对应数值（或从未播放）的视频不参与排行
+    pub fn get_top(&self, by: TopBy, limit: u32) -> Result<Vec<VideoInfo>> {
+        let limit = limit.min(MAX_TOP_LIMIT);
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let query = format!(
+            "SELECT {columns} FROM videos
+             WHERE type IN ({placeholders}) AND {column} > 0
+             ORDER BY {column} DESC, name
+             LIMIT {limit}",
+            columns = queries::VIDEO_COLUMNS,
+            column = by.column(),
+        );
+
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let videos = stmt
+            .query_map(
+                params_from_iter(video_types::VIDEO_TYPES.iter()),
+                row_to_video,
+            )?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(videos)
+    }
+
+    /// 视频时长或大小的分布：把最小值到最大值等分为 `buckets` 段，统计每段的视频数量
+    ///
+    /// 每段包含下界、不包含上界（最后一段包含最大值）；没有数据时返回空列表
+    pub fn get_histogram(
+        &self,
+        metric: HistogramMetric,
+        buckets: u32,
+    ) -> Result<Vec<HistogramBucket>> {
+        let buckets = buckets.clamp(1, MAX_HISTOGRAM_BUCKETS);
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let condition = format!(
+            "type IN ({placeholders}) AND {column} IS NOT NULL",
+            column = metric.column()
+        );
+
+        let (min, max): (Option<f64>, Option<f64>) = self.db_manager.conn.query_row(
+            &format!(
+                "SELECT MIN({column}), MAX({column}) FROM videos WHERE {condition}",
+                column = metric.column()
+            ),
+            params_from_iter(video_types::VIDEO_TYPES.iter()),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let (Some(min), Some(max)) = (min, max) else {
+            return Ok(Vec::new());
+        };
+        // 所有值都相同时每段宽度取 1，全部落在第一段
+        let width = match (max - min) / buckets as f64 {
+            width if width > 0.0 => width,
+            _ => 1.0,
+        };
+
+        // 边界值参数在前，类型列表中的 ? 从 ?4 开始编号
+        let query = format!(
+            "SELECT MIN(CAST(({column} - ?1) / ?2 AS INTEGER), ?3) AS bucket, COUNT(*)
+             FROM videos WHERE {condition}
+             GROUP BY bucket",
+            column = metric.column(),
+        );
+        let params = [
+            Value::Real(min),
+            Value::Real(width),
+            Value::Integer(buckets as i64 - 1),
+        ]
+        .into_iter()
+        .chain(
+            video_types::VIDEO_TYPES
+                .iter()
+                .map(|t| Value::Text(t.to_string())),
+        );
+
+        let mut counts = vec![0u64; buckets as usize];
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let rows = stmt.query_map(params_from_iter(params), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, u64>(1)?))
+        })?;
+        for row in rows {
+            let (bucket, count) = row?;
+            if let Some(slot) = usize::try_from(bucket).ok().and_then(|b| counts.get_mut(b)) {
+                *slot += count;
+            }
+        }
+
+        let boundary = |index: u32| format_boundary(min + width * index as f64);
+        Ok(counts
+            .into_iter()
+            .zip(0..)
+            .map(|(count, index)| HistogramBucket {
+                range: format!("{}-{}", boundary(index), boundary(index + 1)),
+                count,
+            })
+            .collect())
+    }
+
+    /// 保存播放进度（秒），进度超过时长的 [`WATCHED_THRESHOLD`] 时同时标记为已看完
+    ///
+    /// 返回记录当前是否已看完，记录不存在时返回 None
+    pub fn save_watch_position(&self, video_id: i64, position: f64) -> Result<Option<bool>> {
+        let Some(duration) = self
+            .db_manager
+            .conn
+            .query_row(
+                "SELECT duration FROM videos WHERE id = ?1",
+                [video_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        let reached = duration
+            .as_deref()
+            .and_then(parse_duration)
+            .is_some_and(|duration| duration > 0.0 && position > duration * WATCHED_THRESHOLD);
+        retry_on_busy(|| {
+            self.db_manager.conn.execute(
+                "UPDATE videos SET watch_position = ?1, watched = watched OR ?2 WHERE id = ?3",
+                rusqlite::params![position, reached, video_id],
+            )
+        })?;
+        if reached {
+            self.db_manager.count_cache.invalidate();
+        }
+
+        self.db_manager
+            .conn
+            .query_row(
+                "SELECT watched FROM videos WHERE id = ?1",
+                [video_id],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// 保存即将被删除的记录的观看状态和标签，文件移动后可以通过 [`Self::relink_watch_state`] 恢复
+    ///
+    /// 没有观看状态（未看完、没有播放进度、未收藏且没有标签）的记录不保存，返回是否保存
+    pub fn archive_watch_state(&self, path: &str) -> Result<bool> {
+        let archived = self.db_manager.conn.execute(
+            "INSERT OR REPLACE INTO orphaned_watch_state
+                (path, name, size, duration, size_bytes, duration_secs, tags,
+                 watched, watch_position, favorite, removed_at)
+             SELECT path, name, size, duration, size_bytes, duration_secs,
+                    (SELECT json_group_array(tags.name) FROM video_tags
+                     JOIN tags ON tags.id = video_tags.tag_id
+                     WHERE video_tags.video_id = videos.id),
+                    watched, watch_position, favorite, strftime('%s', 'now')
+             FROM videos
+             WHERE path = ?1
+               AND (watched = 1 OR watch_position > 0 OR favorite = 1
+                    OR EXISTS (SELECT 1 FROM video_tags WHERE video_id = videos.id))",
+            [normalize_separators(path)],
+        )?;
+        Ok(archived > 0)
+    }
+
+    /// 删除保存超过 [`ORPHANED_WATCH_STATE_TTL_SECS`] 的观看状态，返回删除的数量
+    pub fn prune_orphaned_watch_state(&self) -> Result<usize> {
+        self.db_manager.conn.execute(
+            "DELETE FROM orphaned_watch_state
+             WHERE removed_at < CAST(strftime('%s', 'now') AS INTEGER) - ?1",
+            [ORPHANED_WATCH_STATE_TTL_SECS],
+        )
+    }
+
+    /// 将观看状态和标签从旧路径移到新路径（新路径的记录必须已存在）
+    ///
+    /// 旧路径的记录还在时从记录读取并清空其状态，否则从同步时保存的状态中读取。
+    /// 旧路径没有观看状态或新路径没有记录时返回 None
+    pub fn relink_watch_state(&self, old_path: &str, new_path: &str) -> Result<Option<WatchState>> {
+        let old_path = normalize_separators(old_path);
+        let new_path = normalize_separators(new_path);
+
+        let live = self
+            .db_manager
+            .conn
+            .query_row(
+                "SELECT id, watched, watch_position, favorite FROM videos WHERE path = ?1",
+                [&old_path],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        WatchState {
+                            watched: row.get(1)?,
+                            watch_position: row.get(2)?,
+                            favorite: row.get(3)?,
+                            tags: Vec::new(),
+                        },
+                    ))
+                },
+            )
+            .optional()?;
+        let live = match live {
+            Some((id, state)) => Some(WatchState {
+                tags: self.get_video_tags(id)?,
+                ..state
+            }),
+            None => None,
+        };
+        let state = match live {
+            Some(state) if !state.is_empty() => Some(state),
+            _ => self
+                .db_manager
+                .conn
+                .query_row(
+                    "SELECT watched, watch_position, favorite, tags
+                     FROM orphaned_watch_state WHERE path = ?1",
+                    [&old_path],
+                    |row| {
+                        let tags: Option<String> = row.get(3)?;
+                        Ok(WatchState {
+                            watched: row.get(0)?,
+                            watch_position: row.get(1)?,
+                            favorite: row.get(2)?,
+                            tags: tags
+                                .and_then(|tags| serde_json::from_str(&tags).ok())
+                                .unwrap_or_default(),
+                        })
+                    },
+                )
+                .optional()?,
+        };
+        let Some(state) = state else {
+            return Ok(None);
+        };
+        let tags = serde_json::to_string(&state.tags).unwrap_or_else(|_| "[]".to_string());
+
+        let relinked = retry_on_busy(|| {
+            let tx = self.db_manager.conn.unchecked_transaction()?;
+            let updated = tx.execute(
+                "UPDATE videos SET watched = ?1, watch_position = ?2, favorite = ?3 WHERE path = ?4",
+                rusqlite::params![
+                    state.watched,
+                    state.watch_position,
+                    state.favorite,
+                    &new_path
+                ],
+            )?;
+            if updated == 0 {
+                return Ok(false);
+            }
+            tx.execute(
+                "INSERT OR IGNORE INTO tags (name) SELECT value FROM json_each(?1)",
+                [&tags],
+            )?;
+            tx.execute(
+                "INSERT OR IGNORE INTO video_tags (video_id, tag_id)
+                 SELECT videos.id, tags.id FROM videos, tags
+                 WHERE videos.path = ?1 AND tags.name IN (SELECT value FROM json_each(?2))",
+                [&new_path, &tags],
+            )?;
+            tx.execute(
+                "UPDATE videos SET watched = 0, watch_position = NULL, favorite = 0 WHERE path = ?1",
+                [&old_path],
+            )?;
+            tx.execute(
+                "DELETE FROM video_tags
+                 WHERE video_id IN (SELECT id FROM videos WHERE path = ?1)",
+                [&old_path],
+            )?;
+            tx.execute(
+                "DELETE FROM orphaned_watch_state WHERE path = ?1",
+                [&old_path],
+            )?;
+            tx.commit()?;
+            Ok(true)
+        })?;
+        if !relinked {
+            return Ok(None);
+        }
+        self.db_manager.count_cache.invalidate();
+        Ok(Some(state))
+    }
+
+    /// 为同步时保存的观看状态查找可能的新位置：大小相同、时长相差不超过
+    /// [`RELINK_DURATION_TOLERANCE_SECS`]（都没有时长也算），且还没有观看状态的视频
+    pub fn relink_suggestions(&self) -> Result<Vec<RelinkSuggestion>> {
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let query = format!(
+            "SELECT o.path, v.path, o.name
+             FROM orphaned_watch_state AS o
+             JOIN videos AS v ON v.size_bytes = o.size_bytes
+              AND (abs(v.duration_secs - o.duration_secs) <= ?
+                   OR (v.duration_secs IS NULL AND o.duration_secs IS NULL))
+             WHERE v.type IN ({})
+               AND v.path != o.path
+               AND v.watched = 0
+               AND v.favorite = 0
+               AND v.watch_position IS NULL
+             ORDER BY o.removed_at DESC, o.path, v.path",
+            placeholders
+        );
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let params = std::iter::once(rusqlite::types::Value::Real(RELINK_DURATION_TOLERANCE_SECS))
+            .chain(
+                video_types::VIDEO_TYPES
+                    .iter()
+                    .map(|t| rusqlite::types::Value::Text(t.to_string())),
+            );
+        let suggestions = stmt
+            .query_map(params_from_iter(params), |row| {
+                Ok(RelinkSuggestion {
+                    old_path: row.get(0)?,
+                    new_path: row.get(1)?,
+                    name: row.get(2)?,
+                })
+            })?
+            .collect();
+        suggestions
+    }
+
+    /// 记录一次删除
+    pub fn log_deletion(&self, path: &str, name: &str) -> Result<()> {
+        retry_on_busy(|| {
+            self.db_manager.conn.execute(
+                "INSERT INTO deletion_log (path, name, deleted_at)
+                 VALUES (?1, ?2, CAST(strftime('%s', 'now') AS INTEGER))",
+                [normalize_separators(path), name.to_string()],
+            )
+        })?;
+        Ok(())
+    }
+
+    /// 最近的删除记录，新的在前（最多 [`MAX_DELETION_LOG_LIMIT`] 条）
+    pub fn get_recent_deletions(
+        &self,
+        limit: u32,
+        timezone: &DisplayTimezone,
+    ) -> Result<Vec<DeletionLogEntry>> {
+        let mut stmt = self.db_manager.conn.prepare(
+            "SELECT path, name, deleted_at FROM deletion_log
+             ORDER BY deleted_at DESC, id DESC LIMIT ?1",
+        )?;
+        let entries = stmt
+            .query_map([limit.min(MAX_DELETION_LOG_LIMIT)], |row| {
+                let deleted_at: i64 = row.get(2)?;
+                Ok(DeletionLogEntry {
+                    path: row.get(0)?,
+                    name: row.get(1)?,
+                    deleted_at: timezone.format_timestamp(deleted_at).unwrap_or_default(),
+                })
+            })?
+            .collect();
+        entries
+    }
+
+    /// 缓存章节 JSON 及读取时文件的修改时间（Unix 秒），返回更新的记录数量
+    pub fn save_chapters(&self, path: &str, chapters_json: &str, modified: i64) -> Result<usize> {
+        let stmt = "UPDATE videos SET chapters = ?1, chapters_modified = ?2 WHERE path = ?3";
+        let mut update_stmt = self.db_manager.conn.prepare(stmt)?;
+        retry_on_busy(|| {
+            update_stmt.execute(rusqlite::params![
+                chapters_json,
+                modified,
+                normalize_separators(path)
+            ])
+        })
+    }
+
+    /// 随机获取指定数量的视频（只包含可播放的视频类型）
+    pub fn get_random_videos(&self, count: u32) -> Result<Vec<VideoInfo>> {
+        let count = count.min(MAX_RANDOM_COUNT);
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let query = format!(
+            "SELECT {} FROM videos WHERE type IN ({}) ORDER BY RANDOM() LIMIT {}",
+            queries::VIDEO_COLUMNS,
+            placeholders,
+            count
+        );
+
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let videos = stmt
+            .query_map(
+                params_from_iter(video_types::VIDEO_TYPES.iter()),
+                row_to_video,
+            )?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(videos)
+    }
+
+    /// 按修改时间（last_modified）排列整个媒体库的视频时，指定视频的上一个和下一个
+    ///
+    /// 修改时间相同的按 id 排序；记录不存在时返回 None
+    pub fn get_timeline_neighbors(
+        &self,
+        video_id: i64,
+    ) -> Result<Option<(Option<VideoInfo>, Option<VideoInfo>)>> {
+        let Some(last_modified) = self
+            .db_manager
+            .conn
+            .query_row(
+                "SELECT last_modified FROM videos WHERE id = ?1",
+                [video_id],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let neighbor = |comparison: &str, order: &str| {
+            let query = format!(
+                "SELECT {} FROM videos
+                 WHERE (last_modified, id) {} (?1, ?2) AND type IN ({})
+                 ORDER BY last_modified {order}, id {order} LIMIT 1",
+                queries::VIDEO_COLUMNS,
+                comparison,
+                placeholders,
+                order = order
+            );
+            let mut params: Vec<Value> = vec![last_modified.into(), video_id.into()];
+            params.extend(
+                video_types::VIDEO_TYPES
+                    .iter()
+                    .map(|t| Value::from(t.to_string())),
+            );
+            self.db_manager
+                .conn
+                .query_row(&query, params_from_iter(params), row_to_video)
+                .optional()
+        };
+
+        Ok(Some((neighbor("<", "DESC")?, neighbor(">", "ASC")?)))
+    }
+
+    /// 按 id 顺序分批读取记录，返回 id 大于 `after_id` 的最多 `limit` 条
+    pub fn get_videos_after_id(&self, after_id: i64, limit: u32) -> Result<Vec<VideoInfo>> {
+        let query = format!(
+            "SELECT {} FROM videos WHERE id > ?1 ORDER BY id LIMIT ?2",
+            queries::VIDEO_COLUMNS
+        );
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let videos = stmt
+            .query_map(rusqlite::params![after_id, limit], row_to_video)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(videos)
+    }
+
+    /// 获取可播放视频的路径（按 id 顺序），最多 `limit` 条
+    pub fn get_video_paths(&self, limit: u32) -> Result<Vec<String>> {
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let query = format!(
+            "SELECT path FROM videos WHERE type IN ({}) ORDER BY id LIMIT {}",
+            placeholders, limit
+        );
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let paths = stmt
+            .query_map(params_from_iter(video_types::VIDEO_TYPES), |row| row.get(0))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(paths)
+    }
+
+    /// 根据物理路径获取单条记录
+    pub fn get_video_by_path(&self, path: &str) -> Result<Option<VideoInfo>> {
+        let query = format!(
+            "SELECT {} FROM videos WHERE path = ?1",
+            queries::VIDEO_COLUMNS
+        );
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let mut rows = stmt.query_map([normalize_separators(path)], row_to_video)?;
+        rows.next().transpose()
+    }
+
+    /// 根据物理路径获取单条记录，目录记录会附带其直接子项（按名称排序）
+    ///
+    /// 子项的 parent_path 是目录的路由路径，即目录自身的 parent_path 加上目录名
+    pub fn get_video_with_children(&self, path: &str) -> Result<Option<VideoInfo>> {
+        let Some(mut video) = self.get_video_by_path(path)? else {
+            return Ok(None);
+        };
+        let is_directory = matches!(
+            video.r#type.as_str(),
+            video_types::DIRECTORY | video_types::HLS_DIRECTORY
+        );
+        if let (true, Some(parent_path)) = (is_directory, video.parent_path.as_deref()) {
+            let web_path = format!("{}/{}", parent_path.trim_end_matches('/'), video.name);
+            let query = format!(
+                "SELECT {} FROM videos WHERE parent_path = ?1 ORDER BY name",
+                queries::VIDEO_COLUMNS
+            );
+            let mut stmt = self.db_manager.conn.prepare(&query)?;
+            let children = stmt
+                .query_map([web_path], row_to_video)?
+                .collect::<Result<Vec<_>>>()?;
+            video.children = Some(children);
+        }
+        Ok(Some(video))
+    }
+
+    /// 汇总每个目录下（递归）所有可播放视频的时长和大小，键为目录的物理路径
+    ///
+    /// 时长和大小在数据库中是格式化后的字符串，需要读取全部视频后逐个解析，开销较大
+    pub fn get_directory_totals(&self) -> Result<HashMap<String, DirectoryTotals>> {
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let query = format!(
+            "SELECT path, duration_secs, size_bytes FROM videos WHERE type IN ({})",
+            placeholders
+        );
+
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let mut rows = stmt.query(params_from_iter(video_types::VIDEO_TYPES))?;
+        let mut totals: HashMap<String, DirectoryTotals> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let path: String = row.get(0)?;
+            let duration: f64 = row.get::<_, Option<f64>>(1)?.unwrap_or(0.0);
+            let size: u64 = row.get::<_, Option<i64>>(2)?.unwrap_or(0).max(0) as u64;
+
+            for ancestor in Path::new(&path).ancestors().skip(1) {
+                let entry = totals
+                    .entry(ancestor.to_string_lossy().to_string())
+                    .or_default();
+                entry.duration += duration;
+                entry.size += size;
+            }
+        }
+        Ok(totals)
+    }
+
+    /// 获取所有带缩略图的视频，返回 (视频路径, 缩略图路径)
+    pub fn get_video_thumbnails(&self) -> Result<Vec<(String, String)>> {
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let query = format!(
+            "SELECT path, thumbnail FROM videos WHERE thumbnail != '' AND type IN ({})",
+            placeholders
+        );
+
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let thumbnails = stmt
+            .query_map(params_from_iter(video_types::VIDEO_TYPES), |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(thumbnails)
+    }
+
+    /// 获取同一目录下的所有视频（只包含可播放的视频类型），按 `sort` 排序
+    pub fn get_videos_in_directory(
+        &self,
+        parent_path: &str,
+        sort: &VideoSort,
+        exclude_errors: bool,
+    ) -> Result<Vec<VideoInfo>> {
+        let placeholders = vec!["?"; video_types::VIDEO_TYPES.len()].join(", ");
+        let query = format!(
+            "SELECT {} FROM videos WHERE parent_path = ? AND type IN ({}){} {}",
+            queries::VIDEO_COLUMNS,
+            placeholders,
+            error_condition(exclude_errors),
+            sort.order_by_clause()
+        );
+
+        let parent_path = normalize_separators(parent_path);
+        let params =
+            std::iter::once(parent_path.as_str()).chain(video_types::VIDEO_TYPES.iter().copied());
+        let mut stmt = self.db_manager.conn.prepare(&query)?;
+        let videos = stmt
+            .query_map(params_from_iter(params), row_to_video)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(videos)
+    }
+}
+
+/// 直方图分段边界的文本形式：保留两位小数并去掉多余的 0，例如 `60`、`1.5`
+fn format_boundary(value: f64) -> String {
+    ((value * 100.0).round() / 100.0).to_string()
+}
+
+/// VIDEO_COLUMNS 的列数，查询中附加的列从这个下标开始
+const VIDEO_COLUMN_COUNT: usize = column_count(queries::VIDEO_COLUMNS);
+
+/// 逗号分隔的列名列表中的列数
+const fn column_count(columns: &str) -> usize {
+    let bytes = columns.as_bytes();
+    let mut count = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b',' {
+            count += 1;
+        }
+        i += 1;
+    }
+    count
+}
+
+/// 查询目录下（递归）所有条目的语句，`?1` 为目录路径
+///
+/// `path > ?1 || '/' AND path < ?1 || '0'` 精确匹配路径前缀（`'0'` 是 `/` 的下一个字符），
+/// 不会把 `_`、`%` 当作通配符，并且可以使用 idx_path 索引
+fn subtree_query(exclude_errors: bool) -> String {
+    format!(
+        "SELECT {} FROM videos WHERE path > ?1 || '/' AND path < ?1 || '0'{} ORDER BY path",
+        queries::VIDEO_COLUMNS,
+        error_condition(exclude_errors)
+    )
+}
+
+/// 排除被标记了元数据错误的条目时追加的条件，与 [`VideoFilter::exclude_errors`] 一致
+fn error_condition(exclude_errors: bool) -> &'static str {
+    if exclude_errors {
+        " AND error IS NULL"
+    } else {
+        ""
+    }
+}
+
+/// 将 queries::VIDEO_COLUMNS 顺序的查询结果行映射为 VideoInfo
+fn row_to_video(row: &Row) -> Result<VideoInfo> {
+    Ok(VideoInfo {
+        name: row.get(0)?,
+        path: row.get(1)?,
+        r#type: row.get(2)?,
+        children: None,
+        thumbnail: row.get(3)?,
+        duration: row.get(4)?,
+        size: row.get(5)?,
+        resolution: row.get(6)?,
+        bitrate: row.get(7)?,
+        codec: row.get(8)?,
+        created_at: row.get(9)?,
+        subtitle: row.get(10)?,
+        width: row.get(11)?,
+        height: row.get(12)?,
+        id: row.get(13)?,
+        parent_path: row.get(14)?,
+        source: None,
+        url: None,
+        child_count: row.get(15)?,
+        thumbnail_source: row.get(16)?,
+        watched: row.get(17)?,
+        watch_position: row.get(18)?,
+        favorite: row.get(19)?,
+        watch_count: row.get(20)?,
+        total_duration: None,
+        total_size: None,
+        error: row.get(21)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::insert_video;
+
+    fn set_last_modified(db_manager: &VideoDbManager, path: &str, last_modified: i64) {
+        db_manager
+            .conn
+            .execute(
+                "UPDATE videos SET last_modified = ?1 WHERE path = ?2",
+                rusqlite::params![last_modified, path],
+            )
+            .unwrap();
+    }
+
+    fn paginated_paths(db_manager: &VideoDbManager, filter: &VideoFilter) -> Vec<String> {
+        let mut paths: Vec<String> = VideoDao::new(db_manager)
+            .get_root_videos_paginated(1, 100, filter, &VideoSort::default())
+            .unwrap()
+            .videos
+            .into_iter()
+            .map(|v| v.path)
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_created_range_filter_is_inclusive() {
+        let db_manager = VideoDbManager::in_memory();
+        for (path, last_modified) in [("/d/a.mp4", 100), ("/d/b.mp4", 200), ("/d/c.mp4", 300)] {
+            insert_video(&db_manager, path, "mp4", "/public/disk1");
+            set_last_modified(&db_manager, path, last_modified);
+        }
+
+        let filter = VideoFilter {
+            created_after: Some(100),
+            created_before: Some(200),
+            ..Default::default()
+        };
+        assert_eq!(
+            paginated_paths(&db_manager, &filter),
+            vec!["/d/a.mp4", "/d/b.mp4"]
+        );
+
+        // 与搜索条件组合
+        let filter = VideoFilter {
+            search: Some("b.mp4".to_string()),
+            created_after: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(paginated_paths(&db_manager, &filter), vec!["/d/b.mp4"]);
+    }
+
+    #[test]
+    fn test_cursor_pagination_survives_inserts() {
+        let db_manager = VideoDbManager::in_memory();
+        for name in ["a", "b", "c", "d", "e", "f", "g"] {
+            insert_video(
+                &db_manager,
+                &format!("/d/{}.mp4", name),
+                "mp4",
+                "/public/disk1",
+            );
+        }
+        let dao = VideoDao::new(&db_manager);
+        let filter = VideoFilter::default();
+        let by_name = VideoSort::parse(Some("name"), Some("asc"), &VideoSort::default()).unwrap();
+
+        let mut names = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = dao
+                .get_root_videos_after(cursor.as_ref(), 3, &filter, &by_name)
+                .unwrap();
+            assert_eq!(page.pagination.has_prev, cursor.is_some());
+            names.extend(page.videos.iter().map(|v| v.name.clone()));
+            if names.len() == 3 {
+                // 翻页期间插入：排在游标之前的不会出现，之后的会出现，已返回的不会重复
+                insert_video(&db_manager, "/d/bb.mp4", "mp4", "/public/disk1");
+                insert_video(&db_manager, "/d/dd.mp4", "mp4", "/public/disk1");
+            }
+            let Some(next) = page.pagination.next_cursor else {
+                assert!(!page.pagination.has_next);
+                break;
+            };
+            cursor = Some(ListCursor::decode(&next, &by_name).unwrap());
+        }
+        assert_eq!(
+            names,
+            vec!["a.mp4", "b.mp4", "c.mp4", "d.mp4", "dd.mp4", "e.mp4", "f.mp4", "g.mp4"]
+        );
+
+        // 排序值全部相同（created_at）时按 id 区分，每条记录恰好出现一次
+        let mut ids = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = dao
+                .get_root_videos_after(cursor.as_ref(), 4, &filter, &VideoSort::default())
+                .unwrap();
+            ids.extend(page.videos.iter().map(|v| v.id));
+            match page.pagination.next_cursor {
+                Some(next) => {
+                    cursor = Some(ListCursor::decode(&next, &VideoSort::default()).unwrap())
+                }
+                None => break,
+            }
+        }
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(ids.len(), 9);
+        assert_eq!(unique.len(), 9);
+
+        // 页码分页的第一页也返回游标，可以从这里切换到游标分页
+        let first = dao
+            .get_root_videos_paginated(1, 3, &filter, &by_name)
+            .unwrap();
+        let next = first.pagination.next_cursor.unwrap();
+        assert!(ListCursor::decode(&next, &VideoSort::default()).is_err());
+        assert!(ListCursor::decode("not a cursor", &by_name).is_err());
+
+        // 游标之前的记录都被删除后不再有上一页
+        let cursor = ListCursor::decode(&next, &by_name).unwrap();
+        for name in ["a", "b", "bb", "c"] {
+            dao.delete_from_database_by_path(&format!("/d/{}.mp4", name))
+                .unwrap();
+        }
+        let page = dao
+            .get_root_videos_after(Some(&cursor), 3, &filter, &by_name)
+            .unwrap();
+        assert_eq!(page.videos[0].name, "d.mp4");
+        assert!(!page.pagination.has_prev);
+    }
+
+    #[test]
+    fn test_size_and_duration_sort_numerically() {
+        let db_manager = VideoDbManager::in_memory();
+        for (path, size, bytes, duration, secs) in [
+            ("/d/a.mp4", "10.0 MB", 10_000_000, "1:05:00", 3900.0),
+            ("/d/b.mp4", "9.0 MB", 9_000_000, "00:09:00", 540.0),
+            ("/d/c.mp4", "100 B", 100, "00:10:00", 600.0),
+        ] {
+            insert_video(&db_manager, path, "mp4", "/public/disk1");
+            db_manager
+                .conn
+                .execute(
+                    "UPDATE videos SET size = ?1, size_bytes = ?2, duration = ?3, duration_secs = ?4
+                     WHERE path = ?5",
+                    rusqlite::params![size, bytes, duration, secs, path],
+                )
+                .unwrap();
+        }
+        let dao = VideoDao::new(&db_manager);
+        let filter = VideoFilter {
+            search: Some(".mp4".to_string()),
+            ..Default::default()
+        };
+
+        for (field, expected) in [
+            ("size", vec!["c.mp4", "b.mp4", "a.mp4"]),
+            ("duration", vec!["b.mp4", "c.mp4", "a.mp4"]),
+        ] {
+            let sort = VideoSort::parse(Some(field), Some("asc"), &VideoSort::default()).unwrap();
+            // 每页一条，游标里的排序值也是数值
+            let mut names = Vec::new();
+            let mut cursor = None;
+            loop {
+                let page = dao
+                    .get_root_videos_after(cursor.as_ref(), 1, &filter, &sort)
+                    .unwrap();
+                names.extend(page.videos.iter().map(|v| v.name.clone()));
+                match page.pagination.next_cursor {
+                    Some(next) => cursor = Some(ListCursor::decode(&next, &sort).unwrap()),
+                    None => break,
+                }
+            }
+            assert_eq!(names, expected, "sort by {}", field);
+        }
+
+        let totals = dao.get_directory_totals().unwrap();
+        assert_eq!(totals["/d"].size, 19_000_100);
+        assert_eq!(totals["/d"].duration, 5040.0);
+    }
+
+    #[test]
+    fn test_count_query_cached_across_pages() {
+        let db_manager = VideoDbManager::in_memory();
+        for i in 0..5 {
+            insert_video(
+                &db_manager,
+                &format!("/d/{}.mp4", i),
+                "mp4",
+                "/public/disk1",
+            );
+        }
+
+        let dao = VideoDao::new(&db_manager);
+        let filter = VideoFilter::default();
+        for page in 1..=3 {
+            let result = dao
+                .get_root_videos_paginated(page, 2, &filter, &VideoSort::default())
+                .unwrap();
+            assert_eq!(result.pagination.total, 5);
+        }
+        assert_eq!(db_manager.count_cache.query_count(), 1);
+
+        // 过滤条件变化时重新统计
+        let filter = VideoFilter {
+            search: Some("1.mp4".to_string()),
+            ..Default::default()
+        };
+        let result = dao
+            .get_root_videos_paginated(1, 2, &filter, &VideoSort::default())
+            .unwrap();
+        assert_eq!(result.pagination.total, 1);
+        assert_eq!(db_manager.count_cache.query_count(), 2);
+
+        // 删除后缓存失效
+        let id = result.videos[0].id;
+        dao.delete_from_database_by_id(id).unwrap();
+        let result = dao
+            .get_root_videos_paginated(1, 2, &VideoFilter::default(), &VideoSort::default())
+            .unwrap();
+        assert_eq!(result.pagination.total, 4);
+        assert_eq!(db_manager.count_cache.query_count(), 3);
+    }
+
+    #[test]
+    fn test_type_filter_returns_only_matching_rows() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/d/a.mp4", "mp4", "/public/disk1");
+        insert_video(&db_manager, "/d/a.jpg", "image", "/public/disk1");
+        insert_video(&db_manager, "/d/b.png", "image", "/public/disk1");
+        insert_video(&db_manager, "/d/a.vtt", "subtitle", "/public/disk1");
+
+        let filter = VideoFilter {
+            file_type: Some(video_types::IMAGE.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            paginated_paths(&db_manager, &filter),
+            vec!["/d/a.jpg", "/d/b.png"]
+        );
+
+        // 与搜索条件组合
+        let filter = VideoFilter {
+            search: Some("a.".to_string()),
+            file_type: Some(video_types::IMAGE.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(paginated_paths(&db_manager, &filter), vec!["/d/a.jpg"]);
+    }
+
+    #[test]
+    fn test_created_range_filter_empty_range() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/d/a.mp4", "mp4", "/public/disk1");
+        set_last_modified(&db_manager, "/d/a.mp4", 150);
+
+        let filter = VideoFilter {
+            created_after: Some(200),
+            created_before: Some(100),
+            ..Default::default()
+        };
+        let result = VideoDao::new(&db_manager)
+            .get_root_videos_paginated(1, 20, &filter, &VideoSort::default())
+            .unwrap();
+        assert!(result.videos.is_empty());
+        assert_eq!(result.pagination.total, 0);
+    }
+
+    #[test]
+    fn test_get_random_videos_returns_only_videos() {
+        let db_manager = VideoDbManager::in_memory();
+        for i in 0..3 {
+            insert_video(
+                &db_manager,
+                &format!("/d/{}.mp4", i),
+                "mp4",
+                "/public/disk1",
+            );
+        }
+        insert_video(&db_manager, "/d/a.jpg", "image", "/public/disk1");
+        insert_video(&db_manager, "/d/a.vtt", "subtitle", "/public/disk1");
+        insert_video(&db_manager, "/d/show", "directory", "/public/disk1");
+
+        let dao = VideoDao::new(&db_manager);
+        let videos = dao.get_random_videos(2).unwrap();
+        assert_eq!(videos.len(), 2);
+        assert!(videos.iter().all(|v| v.r#type == "mp4"));
+
+        let videos = dao.get_random_videos(10).unwrap();
+        assert_eq!(videos.len(), 3);
+    }
+
+    #[test]
+    fn test_watched_filter() {
+        let db_manager = VideoDbManager::in_memory();
+        for path in ["/d/a.mp4", "/d/b.mp4", "/d/c.mp4"] {
+            insert_video(&db_manager, path, "mp4", "/public/disk1");
+        }
+        let dao = VideoDao::new(&db_manager);
+        let id_of = |path: &str| dao.get_video_by_path(path).unwrap().unwrap().id;
+
+        let watched = VideoFilter {
+            watched: Some(true),
+            ..Default::default()
+        };
+        let unwatched = VideoFilter {
+            watched: Some(false),
+            ..Default::default()
+        };
+        assert!(paginated_paths(&db_manager, &watched).is_empty());
+
+        assert_eq!(dao.set_watched(id_of("/d/a.mp4"), true).unwrap(), 1);
+        assert_eq!(dao.set_watched(id_of("/d/c.mp4"), true).unwrap(), 1);
+        assert_eq!(
+            paginated_paths(&db_manager, &watched),
+            vec!["/d/a.mp4", "/d/c.mp4"]
+        );
+        assert_eq!(paginated_paths(&db_manager, &unwatched), vec!["/d/b.mp4"]);
+
+        dao.set_watched(id_of("/d/c.mp4"), false).unwrap();
+        assert_eq!(paginated_paths(&db_manager, &watched), vec!["/d/a.mp4"]);
+        assert!(dao.get_video_by_path("/d/a.mp4").unwrap().unwrap().watched);
+    }
+
+    #[test]
+    fn test_favorite_filter() {
+        let db_manager = VideoDbManager::in_memory();
+        for path in ["/d/a.mp4", "/d/b.mp4", "/d/c.mp4"] {
+            insert_video(&db_manager, path, "mp4", "/public/disk1");
+        }
+        let dao = VideoDao::new(&db_manager);
+        let id_of = |path: &str| dao.get_video_by_path(path).unwrap().unwrap().id;
+        let favorite = |value: bool| VideoFilter {
+            favorite: Some(value),
+            ..Default::default()
+        };
+
+        assert!(paginated_paths(&db_manager, &favorite(true)).is_empty());
+        assert_eq!(dao.set_favorite(id_of("/d/c.mp4"), true).unwrap(), 1);
+        assert_eq!(dao.set_favorite(id_of("/d/a.mp4"), true).unwrap(), 1);
+        assert_eq!(dao.set_favorite(-1, true).unwrap(), 0);
+        assert_eq!(
+            paginated_paths(&db_manager, &favorite(true)),
+            vec!["/d/a.mp4", "/d/c.mp4"]
+        );
+        assert_eq!(
+            paginated_paths(&db_manager, &favorite(false)),
+            vec!["/d/b.mp4"]
+        );
+
+        dao.set_favorite(id_of("/d/c.mp4"), false).unwrap();
+        let favorites: Vec<String> = dao
+            .get_favorites()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.path)
+            .collect();
+        assert_eq!(favorites, vec!["/d/a.mp4"]);
+        assert!(dao.get_video_by_path("/d/a.mp4").unwrap().unwrap().favorite);
+    }
+
+    #[test]
+    fn test_progress_past_threshold_marks_watched() {
+        let db_manager = VideoDbManager::in_memory();
+        // 测试记录的时长为 00:01:00
+        insert_video(&db_manager, "/d/a.mp4", "mp4", "/public/disk1");
+        let dao = VideoDao::new(&db_manager);
+        let id = dao.get_video_by_path("/d/a.mp4").unwrap().unwrap().id;
+
+        assert_eq!(dao.save_watch_position(id, 30.0).unwrap(), Some(false));
+        assert_eq!(dao.save_watch_position(id, 54.0).unwrap(), Some(false));
+        assert_eq!(dao.save_watch_position(id, 54.5).unwrap(), Some(true));
+        let video = dao.get_video_by_path("/d/a.mp4").unwrap().unwrap();
+        assert_eq!(video.watch_position, Some(54.5));
+
+        // 回看前面的内容不会取消已看完
+        assert_eq!(dao.save_watch_position(id, 5.0).unwrap(), Some(true));
+        assert_eq!(dao.save_watch_position(id + 100, 5.0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_top_by_play_count_and_backfill() {
+        let db_manager = VideoDbManager::in_memory();
+        for path in ["/d/a.mp4", "/d/b.mp4"] {
+            insert_video(&db_manager, path, "mp4", "/public/disk1");
+        }
+        let dao = VideoDao::new(&db_manager);
+        let id_of = |path: &str| dao.get_video_by_path(path).unwrap().unwrap().id;
+        let top = |by: TopBy| -> Vec<String> {
+            dao.get_top(by, 10)
+                .unwrap()
+                .into_iter()
+                .map(|v| v.path)
+                .collect()
+        };
+
+        let (a, b) = (id_of("/d/a.mp4"), id_of("/d/b.mp4"));
+        assert!(top(TopBy::Watched).is_empty());
+        assert_eq!(dao.record_play(b).unwrap(), Some(1));
+        for _ in 0..2 {
+            dao.record_play(a).unwrap();
+        }
+        assert_eq!(dao.record_play(-1).unwrap(), None);
+        assert_eq!(top(TopBy::Watched), vec!["/d/a.mp4", "/d/b.mp4"]);
+
+        // 旧版本的记录没有数值列，补上后才参与排行
+        db_manager
+            .conn
+            .execute(
+                "UPDATE videos SET duration_secs = NULL, duration = '01:00:00' WHERE id = ?1",
+                [b],
+            )
+            .unwrap();
+        assert_eq!(top(TopBy::Duration), vec!["/d/a.mp4"]);
+        assert_eq!(dao.backfill_numeric_columns().unwrap(), 1);
+        assert_eq!(dao.backfill_numeric_columns().unwrap(), 0);
+        assert_eq!(top(TopBy::Duration), vec!["/d/b.mp4", "/d/a.mp4"]);
+        assert!(TopBy::parse("bogus").is_err());
+    }
+
+    #[test]
+    fn test_get_incomplete_returns_rows_missing_metadata() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/d/complete.mp4", "mp4", "/public/disk1");
+        insert_video(&db_manager, "/d/broken.mp4", "mp4", "/public/disk1");
+        db_manager
+            .conn
+            .execute(
+                "UPDATE videos SET duration = '', width = NULL WHERE path = '/d/broken.mp4'",
+                [],
+            )
+            .unwrap();
+
+        let incomplete: Vec<String> = VideoDao::new(&db_manager)
+            .get_incomplete()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.path)
+            .collect();
+        assert_eq!(incomplete, vec!["/d/broken.mp4"]);
+    }
+
+    #[test]
+    fn test_directory_totals_sum_descendant_videos() {
+        let db_manager = VideoDbManager::in_memory();
+        // 测试记录的时长为 00:01:00，大小为 1.00 MB
+        insert_video(&db_manager, "/d/show", "directory", "/public/disk1");
+        insert_video(&db_manager, "/d/show/ep1.mp4", "mp4", "/public/disk1/show");
+        insert_video(&db_manager, "/d/show/ep2.mp4", "mp4", "/public/disk1/show");
+        insert_video(
+            &db_manager,
+            "/d/show/ep1.vtt",
+            "subtitle",
+            "/public/disk1/show",
+        );
+        insert_video(&db_manager, "/d/other.mp4", "mp4", "/public/disk1");
+
+        let totals = VideoDao::new(&db_manager).get_directory_totals().unwrap();
+        assert_eq!(
+            totals["/d/show"],
+            DirectoryTotals {
+                duration: 120.0,
+                size: 2 * 1024 * 1024,
+            }
+        );
+        assert_eq!(totals["/d"].duration, 180.0);
+    }
+
+    #[test]
+    fn test_unknown_type_hidden_by_default() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/d/a.mp4", "mp4", "/public/disk1");
+        insert_video(&db_manager, "/d/notes.txt", "unknown", "/public/disk1");
+
+        assert_eq!(
+            paginated_paths(&db_manager, &VideoFilter::default()),
+            vec!["/d/a.mp4"]
+        );
+
+        let filter = VideoFilter {
+            include_unknown: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            paginated_paths(&db_manager, &filter),
+            vec!["/d/a.mp4", "/d/notes.txt"]
+        );
+
+        // 明确指定类型时不再额外排除
+        let filter = VideoFilter {
+            file_type: Some(video_types::UNKNOWN.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(paginated_paths(&db_manager, &filter), vec!["/d/notes.txt"]);
+    }
+
+    #[test]
+    fn test_backslash_paths_are_linked_in_tree() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "public\\show", "directory", "public");
+        insert_video(&db_manager, "public\\show\\ep1.mp4", "mp4", "public\\show");
+        let dao = VideoDao::new(&db_manager);
+
+        let videos = dao.get_root_videos().unwrap();
+        let tree = crate::services::db::tree::TreeBuilder::build_subtree("public", videos);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].path, "public/show");
+        let children = tree[0].children.as_ref().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].path, "public/show/ep1.mp4");
+    }
+
+    #[test]
+    fn test_subtree_contains_only_descendants() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/d/show", "directory", "/d");
+        insert_video(&db_manager, "/d/show/s1", "directory", "/d/show");
+        insert_video(&db_manager, "/d/show/s1/ep1.mp4", "mp4", "/d/show/s1");
+        insert_video(&db_manager, "/d/show/extra.mp4", "mp4", "/d/show");
+        // 名称以目录名开头的兄弟条目不属于子树
+        insert_video(&db_manager, "/d/show2", "directory", "/d");
+        insert_video(&db_manager, "/d/show2/ep1.mp4", "mp4", "/d/show2");
+        insert_video(&db_manager, "/d/other.mp4", "mp4", "/d");
+        let dao = VideoDao::new(&db_manager);
+
+        let subtree = dao.get_subtree("/d/show/", false).unwrap();
+        let paths: Vec<&str> = subtree.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, ["/d/show/extra.mp4", "/d/show/s1"]);
+        assert!(subtree[0].children.is_none());
+        let season = subtree[1].children.as_ref().unwrap();
+        assert_eq!(season.len(), 1);
+        assert_eq!(season[0].path, "/d/show/s1/ep1.mp4");
+
+        assert!(dao
+            .get_subtree("/d/show/s1/ep1.mp4", false)
+            .unwrap()
+            .is_empty());
+
+        // 前缀查询使用索引，而不是扫描整张表
+        let plan: Vec<String> = db_manager
+            .conn
+            .prepare(&format!("EXPLAIN QUERY PLAN {}", subtree_query(false)))
+            .unwrap()
+            .query_map(["/d/show"], |row| row.get(3))
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert!(
+            plan.iter().any(
+                |detail| detail.starts_with("SEARCH") && detail.contains("(path>? AND path<?)")
+            ),
+            "{:?}",
+            plan
+        );
+    }
+
+    #[test]
+    fn test_query_inputs_are_normalized() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "F:/public/show", "directory", "/public");
+        insert_video(
+            &db_manager,
+            "F:/public/show/ep1.mp4",
+            "mp4",
+            "F:/public/show",
+        );
+        let dao = VideoDao::new(&db_manager);
+
+        assert!(dao
+            .get_video_by_path("F:\\public\\show\\ep1.mp4")
+            .unwrap()
+            .is_some());
+        let videos = dao
+            .get_videos_in_directory("F:\\public\\show", &VideoSort::default(), false)
+            .unwrap();
+        assert_eq!(videos.len(), 1);
+
+        dao.refresh_child_counts().unwrap();
+        let dir = dao.get_video_by_path("F:/public/show").unwrap().unwrap();
+        assert_eq!(dir.child_count, Some(1));
+    }
+
+    #[test]
+    fn test_cached_chapters_expire_when_file_changes() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/d/a.mp4", "mp4", "/d");
+        let dao = VideoDao::new(&db_manager);
+
+        assert_eq!(dao.get_cached_chapters("/d/a.mp4", 100).unwrap(), None);
+        dao.save_chapters("/d/a.mp4", "[]", 100).unwrap();
+        assert_eq!(
+            dao.get_cached_chapters("/d/a.mp4", 100).unwrap().as_deref(),
+            Some("[]")
+        );
+        // 文件修改后缓存失效
+        assert_eq!(dao.get_cached_chapters("/d/a.mp4", 200).unwrap(), None);
+    }
+
+    #[test]
+    fn test_metadata_errors_hidden_from_every_listing() {
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/d/show", "directory", "/d");
+        insert_video(&db_manager, "/d/show/good.mp4", "mp4", "/d/show");
+        insert_video(&db_manager, "/d/show/broken.mp4", "mp4", "/d/show");
+        db_manager
+            .conn
+            .execute(
+                "UPDATE videos SET error = 'broken' WHERE path = '/d/show/broken.mp4'",
+                [],
+            )
+            .unwrap();
+        let dao = VideoDao::new(&db_manager);
+        let names = |videos: Vec<VideoInfo>| -> Vec<String> {
+            let mut names: Vec<String> = videos.into_iter().map(|v| v.name).collect();
+            names.sort();
+            names
+        };
+
+        for exclude_errors in [false, true] {
+            let expected: &[&str] = if exclude_errors {
+                &["good.mp4"]
+            } else {
+                &["broken.mp4", "good.mp4"]
+            };
+            let subtree = dao.get_subtree("/d/show", exclude_errors).unwrap();
+            assert_eq!(names(subtree), expected);
+            let descendants = dao
+                .get_descendants_paginated("/d/show", 1, 10, exclude_errors)
+                .unwrap();
+            assert_eq!(descendants.pagination.total, expected.len() as u64);
+            assert_eq!(names(descendants.videos), expected);
+            let children = dao
+                .get_videos_in_directory("/d/show", &VideoSort::default(), exclude_errors)
+                .unwrap();
+            assert_eq!(names(children), expected);
+        }
+
+        // 重新读取到元数据后错误清除，缓存的总数同步失效
+        let filter = VideoFilter {
+            file_type: Some("mp4".to_string()),
+            exclude_errors: true,
+            ..Default::default()
+        };
+        let total = |dao: &VideoDao| {
+            dao.get_root_videos_paginated(1, 10, &filter, &VideoSort::default())
+                .unwrap()
+                .pagination
+                .total
+        };
+        assert_eq!(total(&dao), 1);
+        let metadata = VideoMetadata {
+            duration: Some("00:00:10".to_string()),
+            width: None,
+            height: None,
+            thumbnail_path: None,
+        };
+        dao.apply_metadata("/d/show/broken.mp4", &metadata).unwrap();
+        assert_eq!(total(&dao), 2);
+    }
+
+    #[test]
+    fn test_refresh_child_counts_for_only_touches_ancestors() {
+        let db_manager = VideoDbManager::in_memory();
+        for dir in ["F:/public/show", "F:/public/show/s1", "F:/public/movie"] {
+            insert_video(&db_manager, dir, "directory", "F:/public");
+        }
+        insert_video(
+            &db_manager,
+            "F:/public/movie/a.mp4",
+            "mp4",
+            "F:/public/movie",
+        );
+        insert_video(
+            &db_manager,
+            "F:/public/show/s1/e1.mp4",
+            "mp4",
+            "F:/public/show/s1",
+        );
+        let dao = VideoDao::new(&db_manager);
+        let child_count = |path: &str| dao.get_video_by_path(path).unwrap().unwrap().child_count;
+
+        assert_eq!(
+            dao.refresh_child_counts_for("F:\\public\\show\\s1\\e1.mp4")
+                .unwrap(),
+            2
+        );
+        assert_eq!(child_count("F:/public/show"), Some(1));
+        assert_eq!(child_count("F:/public/show/s1"), Some(1));
+        // 其他目录不在更新范围内
+        assert_eq!(child_count("F:/public/movie"), None);
+
+        dao.delete_from_database_by_path("F:/public/show/s1/e1.mp4")
+            .unwrap();
+        assert_eq!(child_count("F:/public/show"), Some(0));
+        assert_eq!(child_count("F:/public/show/s1"), Some(0));
+    }
+
+    #[test]
+    fn test_reformat_created_at_uses_display_timezone() {
+        let db_manager = VideoDbManager::in_memory();
+        // 旧记录只有北京时间的字符串
+        insert_video(&db_manager, "F:/public/a.mp4", "mp4", "/public");
+        let dao = VideoDao::new(&db_manager);
+        let created = |dao: &VideoDao| -> (String, i64) {
+            dao.db_manager
+                .conn
+                .query_row(
+                    "SELECT created_at, created_epoch FROM videos WHERE path = 'F:/public/a.mp4'",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap()
+        };
+
+        let utc = DisplayTimezone::default();
+        assert_eq!(dao.reformat_created_at(&utc).unwrap(), 1);
+        assert_eq!(
+            created(&dao),
+            ("2024-12-31 16:00:00".to_string(), 1_735_660_800)
+        );
+        assert_eq!(dao.reformat_created_at(&utc).unwrap(), 0);
+
+        let tokyo: DisplayTimezone = "Asia/Tokyo".parse().unwrap();
+        assert_eq!(dao.reformat_created_at(&tokyo).unwrap(), 1);
+        assert_eq!(
+            created(&dao),
+            ("2025-01-01 01:00:00".to_string(), 1_735_660_800)
+        );
+    }
 }
@@ -1,14 +1,56 @@
 //! FFmpeg 统一服务层
 //!
 //! 提供统一的 FFmpeg 调用接口，支持：
-//! - 视频缩略图生成
+//! - 视频缩略图生成（优先使用内嵌封面图）
 //! - 视频元数据提取（时长、分辨率）
 //! - M3U8 合并为 MP4
+//! - 章节信息读取
+//! - 故事板（雪碧图）生成
 //! - 批量处理优化
 
 use log::{debug, error, warn};
-use std::path::Path;
-use std::process::Command;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::config::{get_config, ThumbnailStrategy};
+use crate::models::StoryboardFrame;
+use crate::services::ffmpeg_command::{
+    chapters_args, cover_art_args, decode_check_args, frame_args, image_dimensions_args,
+    merge_m3u8_args, package_hls_args, placeholder_args, probe_json_args, probe_metadata_args,
+    storyboard_args, thumbnail_args, transcode_fmp4_args,
+};
+use crate::services::ffmpeg_process::OutputWithTimeout;
+use crate::utils::{format_duration, media_types, move_path};
+
+/// 缩略图根目录
+pub const THUMBNAILS_DIR: &str = "thumbnails";
+
+/// 故事板帧间隔（秒），视频较长时自动加大以控制帧数
+const STORYBOARD_INTERVAL: u32 = 10;
+/// 故事板最多包含的帧数
+const STORYBOARD_MAX_FRAMES: u32 = 100;
+/// 故事板每行的帧数
+const STORYBOARD_COLUMNS: u32 = 10;
+/// 故事板中每帧的宽度（像素）
+const STORYBOARD_TILE_WIDTH: u32 = 160;
+
+/// HLS 目录中默认的播放列表文件名
+const HLS_PLAYLIST_NAME: &str = "index.m3u8";
+/// MP4 切片输出目录的扩展名，见 [`hls_output_dir`]
+const HLS_OUTPUT_EXTENSION: &str = "hls";
+/// HLS 切片默认时长（秒）
+pub const HLS_SEGMENT_SECS: u32 = 6;
+
+/// 实时转码支持的目标编码
+pub const TRANSCODE_CODECS: &[&str] = &["h264"];
+
+/// 命令失败时保留的 stderr 末尾行数
+const STDERR_TAIL_LINES: usize = 10;
 
 /// FFmpeg 操作结果
 #[derive(Debug, Clone)]
@@ -19,6 +61,74 @@ pub struct VideoMetadata {
     pub thumbnail_path: Option<String>,
 }
 
+/// 视频章节
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub id: i64,
+    /// 开始时间（秒）
+    pub start: f64,
+    /// 结束时间（秒）
+    pub end: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// 故事板布局：帧间隔、网格大小和每帧尺寸
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoryboardLayout {
+    pub interval: u32,
+    pub frame_count: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+}
+
+impl StoryboardLayout {
+    /// 根据时长和视频宽高计算布局，宽高未知时按 16:9 计算
+    pub fn new(duration: f64, width: Option<i32>, height: Option<i32>) -> Self {
+        let duration = duration.max(0.0);
+        let interval =
+            STORYBOARD_INTERVAL.max((duration / STORYBOARD_MAX_FRAMES as f64).ceil() as u32);
+        let frame_count =
+            ((duration / interval as f64).ceil() as u32).clamp(1, STORYBOARD_MAX_FRAMES);
+        let columns = frame_count.min(STORYBOARD_COLUMNS);
+        let rows = frame_count.div_ceil(columns);
+
+        let tile_width = STORYBOARD_TILE_WIDTH;
+        let tile_height = match (width, height) {
+            (Some(w), Some(h)) if w > 0 && h > 0 => {
+                // 高度取偶数，兼容 yuv420 缩放
+                let height = (tile_width as f64 * h as f64 / w as f64 / 2.0).round() as u32 * 2;
+                height.max(2)
+            }
+            _ => tile_width * 9 / 16,
+        };
+
+        Self {
+            interval,
+            frame_count,
+            columns,
+            rows,
+            tile_width,
+            tile_height,
+        }
+    }
+
+    /// 每一帧的时间点和在雪碧图中的位置
+    pub fn frames(&self) -> Vec<StoryboardFrame> {
+        (0..self.frame_count)
+            .map(|index| StoryboardFrame {
+                time: (index * self.interval) as f64,
+                x: (index % self.columns) * self.tile_width,
+                y: (index / self.columns) * self.tile_height,
+                w: self.tile_width,
+                h: self.tile_height,
+            })
+            .collect()
+    }
+}
+
 /// FFmpeg 服务配置
 #[derive(Clone)]
 pub struct FFmpegConfig {
@@ -28,6 +138,20 @@ pub struct FFmpegConfig {
     pub thumbnail_seek_time: f32,
     /// 缩略图宽度
     pub thumbnail_width: u32,
+    /// 额外生成的缩略图宽度，输出到 `thumbnails/<size>/...`
+    pub thumbnail_sizes: Vec<u32>,
+    /// ffmpeg 可执行文件路径
+    pub ffmpeg_path: String,
+    /// ffprobe 可执行文件路径
+    pub ffprobe_path: String,
+    /// 叠加在缩略图上的文字，None 时不叠加
+    pub thumbnail_label: Option<String>,
+    /// 按扩展名或分类名指定的缩略图生成方式
+    pub thumbnail_strategies: BTreeMap<String, ThumbnailStrategy>,
+    /// 探测、截图等单次操作的超时时间，None 时不限制
+    pub timeout: Option<Duration>,
+    /// 解码检查、HLS 切片等读完整个文件的操作的超时时间，None 时不限制
+    pub long_timeout: Option<Duration>,
 }
 
 impl Default for FFmpegConfig {
@@ -36,6 +160,13 @@ impl Default for FFmpegConfig {
             thumbnail_quality: 2,
             thumbnail_seek_time: 1.0,
             thumbnail_width: 320,
+            thumbnail_sizes: Vec::new(),
+            ffmpeg_path: "ffmpeg".to_string(),
+            ffprobe_path: "ffprobe".to_string(),
+            thumbnail_label: None,
+            thumbnail_strategies: BTreeMap::new(),
+            timeout: Some(Duration::from_secs(60)),
+            long_timeout: Some(Duration::from_secs(3600)),
         }
     }
 }
@@ -43,19 +174,54 @@ impl Default for FFmpegConfig {
 /// FFmpeg 统一服务
 pub struct FFmpegService {
     config: FFmpegConfig,
+    /// drawtext 不可用（ffmpeg 未启用 libfreetype 或缺少 fontconfig）时不再叠加文字
+    label_disabled: AtomicBool,
+    /// ffmpeg / ffprobe 能否执行，首次检查后缓存
+    ffmpeg_available: OnceLock<bool>,
+    ffprobe_available: OnceLock<bool>,
 }
 
 impl FFmpegService {
     /// 创建新的 FFmpeg 服务实例
     pub fn new(config: FFmpegConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            label_disabled: AtomicBool::new(false),
+            ffmpeg_available: OnceLock::new(),
+            ffprobe_available: OnceLock::new(),
+        }
+    }
+
+    /// ffmpeg 能否执行（`ffmpeg -version` 成功），结果会被缓存
+    pub fn is_ffmpeg_available(&self) -> bool {
+        *self
+            .ffmpeg_available
+            .get_or_init(|| runs_successfully(self.ffmpeg_command(), self.config.timeout))
+    }
+
+    /// ffprobe 能否执行（`ffprobe -version` 成功），结果会被缓存
+    pub fn is_ffprobe_available(&self) -> bool {
+        *self
+            .ffprobe_available
+            .get_or_init(|| runs_successfully(self.ffprobe_command(), self.config.timeout))
     }
 
     /// 使用默认配置创建服务
+    #[cfg(test)]
     pub fn with_defaults() -> Self {
         Self::new(FFmpegConfig::default())
     }
 
+    /// 创建 ffmpeg 命令（使用配置的可执行文件路径）
+    fn ffmpeg_command(&self) -> Command {
+        Command::new(&self.config.ffmpeg_path)
+    }
+
+    /// 创建 ffprobe 命令（使用配置的可执行文件路径）
+    fn ffprobe_command(&self) -> Command {
+        Command::new(&self.config.ffprobe_path)
+    }
+
     /// 一次性获取视频的所有元数据（时长、分辨率）并生成缩略图
     /// 这比分开调用更高效
     pub fn extract_video_info(&self, video_path: &Path, thumbnail_path: &Path) -> VideoMetadata {
@@ -73,36 +239,24 @@ impl FFmpegService {
             metadata.height = Some(height);
         }
 
-        // 2. 生成缩略图
-        if self.generate_thumbnail(video_path, thumbnail_path) {
+        // 2. 按配置的生成方式生成缩略图
+        if self.ensure_thumbnail(video_path, thumbnail_path, "video") {
             metadata.thumbnail_path = Some(thumbnail_path.to_string_lossy().to_string());
         }
 
         metadata
     }
 
-    /// 使用 ffprobe 一次性获取视频元数据
-    fn probe_video_metadata(&self, video_path: &Path) -> Option<(String, i32, i32)> {
-        let input = video_path.to_string_lossy().to_string();
-
-        // 使用 JSON 格式输出以便解析
-        let output = Command::new("ffprobe")
-            .args([
-                "-v",
-                "error",
-                "-select_streams",
-                "v:0",
-                "-show_entries",
-                "stream=width,height,duration:format=duration",
-                "-of",
-                "csv=p=0:s=,",
-                &input,
-            ])
-            .output()
+    /// 使用 ffprobe 一次性获取视频元数据（时长, 宽, 高）
+    pub fn probe_video_metadata(&self, video_path: &Path) -> Option<(String, i32, i32)> {
+        let output = self
+            .ffprobe_command()
+            .args(probe_metadata_args(video_path))
+            .output_with_timeout(self.config.timeout)
             .ok()?;
 
         if !output.status.success() {
-            debug!("ffprobe 失败: {:?}", video_path);
+            warn!("{}: {:?}", failure_message("ffprobe", &output), video_path);
             return None;
         }
 
@@ -118,51 +272,545 @@ impl FFmpegService {
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(0.0);
 
-            let duration = Self::format_duration(duration_secs);
+            let duration = format_duration(duration_secs);
             Some((duration, width, height))
         } else {
             None
         }
     }
 
-    /// 生成视频缩略图
+    /// 检查图片是否完整可读（ffprobe 能读出非零的宽高）
+    ///
+    /// 文件不存在、为空或 ffprobe 无法确认尺寸时都视为无效
+    pub fn is_valid_image(&self, image_path: &Path) -> bool {
+        match std::fs::metadata(image_path) {
+            Ok(meta) if meta.is_file() && meta.len() > 0 => {}
+            _ => return false,
+        }
+
+        let output = match self
+            .ffprobe_command()
+            .args(image_dimensions_args(image_path))
+            .output_with_timeout(self.config.timeout)
+        {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                warn!(
+                    "ffprobe 无法读取图片尺寸: {:?} - {}",
+                    image_path,
+                    failure_message("ffprobe", &output)
+                );
+                return false;
+            }
+            Err(e) => {
+                debug!("无法执行 ffprobe: {}", e);
+                return false;
+            }
+        };
+
+        parse_dimensions(&String::from_utf8_lossy(&output.stdout))
+            .is_some_and(|(width, height)| width > 0 && height > 0)
+    }
+
+    /// 获取 ffprobe 输出的原始格式和流信息（用于调试）
+    ///
+    /// ffprobe 无法执行或返回失败时返回错误信息（包含 stderr）
+    pub fn probe_json(&self, video_path: &Path) -> Result<serde_json::Value, String> {
+        let output = self
+            .ffprobe_command()
+            .args(probe_json_args(video_path))
+            .output_with_timeout(self.config.timeout)
+            .map_err(|e| format!("无法执行 ffprobe: {}", e))?;
+
+        if !output.status.success() {
+            return Err(failure_message("ffprobe", &output));
+        }
+
+        parse_probe_json(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// 读取视频编码和码率，返回 (编码, 码率)，ffprobe 失败时返回 None
+    pub fn probe_codec_bitrate(
+        &self,
+        video_path: &Path,
+    ) -> Option<(Option<String>, Option<String>)> {
+        match self.probe_json(video_path) {
+            Ok(probe) => Some(codec_and_bitrate(&probe)),
+            Err(e) => {
+                debug!("{}: {:?}", e, video_path);
+                None
+            }
+        }
+    }
+
+    /// 获取视频章节，ffprobe 执行失败时返回 None
+    pub fn try_get_chapters(&self, video_path: &Path) -> Option<Vec<Chapter>> {
+        let output = self
+            .ffprobe_command()
+            .args(chapters_args(video_path))
+            .output_with_timeout(self.config.timeout)
+            .ok()?;
+
+        if !output.status.success() {
+            warn!(
+                "ffprobe 获取章节失败: {:?} - {}",
+                video_path,
+                failure_message("ffprobe", &output)
+            );
+            return None;
+        }
+
+        parse_chapters(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// 文件的缩略图生成方式：先按扩展名、再按分类查找配置，
+    /// 都未配置时可截取画面的视频使用 frame，其他文件使用占位图
+    pub fn thumbnail_strategy(&self, source: &Path) -> ThumbnailStrategy {
+        let strategies = &self.config.thumbnail_strategies;
+        let configured = strategies
+            .get(&media_types::extension_of(source))
+            .or_else(|| media_types::classify(source).and_then(|kind| strategies.get(kind.name())));
+        match configured {
+            Some(strategy) => *strategy,
+            None if media_types::has_extension(source, media_types::FRAME_SOURCE) => {
+                ThumbnailStrategy::Frame
+            }
+            None => ThumbnailStrategy::Placeholder,
+        }
+    }
+
+    /// 按文件的缩略图生成方式生成缩略图，使用占位图时叠加 `placeholder_label`
+    pub fn ensure_thumbnail(
+        &self,
+        source: &Path,
+        thumbnail_path: &Path,
+        placeholder_label: &str,
+    ) -> bool {
+        match self.thumbnail_strategy(source) {
+            ThumbnailStrategy::Placeholder => {
+                self.generate_placeholder_thumbnail(thumbnail_path, placeholder_label)
+            }
+            ThumbnailStrategy::Frame | ThumbnailStrategy::FirstFrame => {
+                self.generate_thumbnail(source, thumbnail_path)
+            }
+        }
+    }
+
+    /// 生成视频缩略图（同时生成配置的其他尺寸）
     pub fn generate_thumbnail(&self, video_path: &Path, thumbnail_path: &Path) -> bool {
+        self.try_generate_thumbnail(video_path, thumbnail_path)
+            .is_ok()
+    }
+
+    /// 生成视频缩略图（同时生成配置的其他尺寸），失败时返回包含 ffmpeg stderr 末尾几行的错误信息
+    ///
+    /// 按文件的缩略图生成方式执行，配置为 placeholder 时生成占位图
+    pub fn try_generate_thumbnail(
+        &self,
+        video_path: &Path,
+        thumbnail_path: &Path,
+    ) -> Result<(), String> {
+        let strategy = self.thumbnail_strategy(video_path);
+        if strategy == ThumbnailStrategy::Placeholder {
+            return if self.generate_placeholder_thumbnail(thumbnail_path, "video") {
+                Ok(())
+            } else {
+                Err(format!("占位缩略图生成失败: {}", thumbnail_path.display()))
+            };
+        }
+
+        // 截取画面的视频优先使用内嵌封面图，没有封面时再截取画面
+        if self.prefers_cover(video_path, strategy)
+            && self.extract_embedded_cover(video_path, thumbnail_path)
+        {
+            for &width in &self.config.thumbnail_sizes {
+                let variant_path = thumbnail_variant_path(thumbnail_path, width);
+                if !is_variant_fresh(&variant_path, thumbnail_path) {
+                    self.run_cover(video_path, &variant_path, width);
+                }
+            }
+            return Ok(());
+        }
+
+        self.generate_thumbnail_with_width(
+            video_path,
+            thumbnail_path,
+            self.config.thumbnail_width,
+            self.seek_time_for(video_path, strategy),
+        )?;
+
+        self.generate_thumbnail_variants(video_path, thumbnail_path);
+        Ok(())
+    }
+
+    /// 只生成缩略图的一个尺寸变体，返回变体路径
+    ///
+    /// 与默认尺寸一样优先使用内嵌封面图，`thumbnail_path` 为默认尺寸缩略图的路径
+    pub fn generate_thumbnail_variant(
+        &self,
+        video_path: &Path,
+        thumbnail_path: &Path,
+        width: u32,
+    ) -> Result<PathBuf, String> {
+        let variant_path = thumbnail_variant_path(thumbnail_path, width);
+        let strategy = self.thumbnail_strategy(video_path);
+        if self.prefers_cover(video_path, strategy)
+            && self.run_cover(video_path, &variant_path, width)
+        {
+            return Ok(variant_path);
+        }
+
+        self.generate_thumbnail_with_width(
+            video_path,
+            &variant_path,
+            width,
+            self.seek_time_for(video_path, strategy),
+        )?;
+        Ok(variant_path)
+    }
+
+    /// 是否先尝试内嵌封面图：只有截取画面的视频才可能带封面
+    fn prefers_cover(&self, source: &Path, strategy: ThumbnailStrategy) -> bool {
+        strategy == ThumbnailStrategy::Frame
+            && media_types::classify(source) != Some(media_types::MediaKind::Image)
+    }
+
+    /// 截取画面的时间点：first_frame 和图片（只有一帧）从开头截取，其他使用配置的时间点
+    fn seek_time_for(&self, source: &Path, strategy: ThumbnailStrategy) -> f32 {
+        if strategy == ThumbnailStrategy::FirstFrame
+            || media_types::classify(source) == Some(media_types::MediaKind::Image)
+        {
+            0.0
+        } else {
+            self.config.thumbnail_seek_time
+        }
+    }
+
+    /// 提取视频内嵌的封面图（attached_pic 流）作为缩略图
+    ///
+    /// 文件没有封面图或提取失败时返回 false，不留下任何输出
+    pub fn extract_embedded_cover(&self, video_path: &Path, out_path: &Path) -> bool {
+        self.run_cover(video_path, out_path, self.config.thumbnail_width)
+    }
+
+    /// 执行 ffmpeg 提取指定宽度的封面图
+    fn run_cover(&self, video_path: &Path, out_path: &Path, width: u32) -> bool {
+        if let Some(parent) = out_path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                error!("创建缩略图目录失败: {}", e);
+                return false;
+            }
+        }
+
+        let temp_path = temp_path_for(out_path);
+        let args = cover_art_args(video_path, &temp_path, width, self.config.thumbnail_quality);
+        match self
+            .ffmpeg_command()
+            .args(args)
+            .output_with_timeout(self.config.timeout)
+        {
+            Ok(output) => {
+                let extracted = commit_temp_output(&temp_path, out_path, output.status.success());
+                if extracted {
+                    debug!("使用内嵌封面生成缩略图: {:?}", out_path);
+                }
+                extracted
+            }
+            Err(e) => {
+                debug!("提取内嵌封面失败: {:?} - {}", video_path, e);
+                let _ = std::fs::remove_file(&temp_path);
+                false
+            }
+        }
+    }
+
+    /// 从指定时间点截取缩略图，覆盖已有的缩略图及其各尺寸变体
+    pub fn generate_thumbnail_at(
+        &self,
+        video_path: &Path,
+        thumbnail_path: &Path,
+        at_seconds: f32,
+    ) -> bool {
+        if self
+            .generate_thumbnail_with_width(
+                video_path,
+                thumbnail_path,
+                self.config.thumbnail_width,
+                at_seconds,
+            )
+            .is_err()
+        {
+            return false;
+        }
+
+        for &width in &self.config.thumbnail_sizes {
+            let variant_path = thumbnail_variant_path(thumbnail_path, width);
+            let _ =
+                self.generate_thumbnail_with_width(video_path, &variant_path, width, at_seconds);
+        }
+        true
+    }
+
+    /// 截取指定时间点的画面，返回 JPEG 数据（通过管道输出，不写入缩略图目录）
+    ///
+    /// ffmpeg 无法执行、返回失败或没有输出（例如时间点超出视频长度）时返回错误信息
+    pub fn extract_frame(&self, video_path: &Path, at_seconds: f32) -> Result<Vec<u8>, String> {
+        let output = self
+            .ffmpeg_command()
+            .args(frame_args(
+                video_path,
+                at_seconds,
+                self.config.thumbnail_quality,
+            ))
+            .output_with_timeout(self.config.timeout)
+            .map_err(|e| format!("无法执行 ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            return Err(failure_message("ffmpeg", &output));
+        }
+        if output.stdout.is_empty() {
+            return Err("ffmpeg 没有输出画面".to_string());
+        }
+
+        Ok(output.stdout)
+    }
+
+    /// 启动实时转码进程，分片 MP4 从子进程的标准输出读取
+    ///
+    /// 子进程在 `Child` 被丢弃时结束（kill_on_drop），调用方通过持有 `Child` 控制进程的生命周期。
+    /// stderr 在后台读取，进程结束时如有错误输出，记录末尾几行。需要在 tokio 运行时中调用
+    pub fn spawn_transcode(
+        &self,
+        video_path: &Path,
+        video_encoder: &str,
+    ) -> Result<tokio::process::Child, String> {
+        let mut child = tokio::process::Command::new(&self.config.ffmpeg_path)
+            .args(transcode_fmp4_args(video_path, video_encoder))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("无法执行 ffmpeg: {}", e))?;
+
+        if let Some(stderr) = child.stderr.take() {
+            let video_path = video_path.to_path_buf();
+            tokio::spawn(async move {
+                let tail = read_stderr_tail(stderr).await;
+                if !tail.is_empty() {
+                    warn!("实时转码出错: {:?} - {}", video_path, tail);
+                }
+            });
+        }
+        Ok(child)
+    }
+
+    /// 按布局生成故事板雪碧图，失败时返回包含 ffmpeg stderr 末尾几行的错误信息
+    ///
+    /// 布局保存在雪碧图旁边（见 [`load_storyboard_layout`]），之后的请求按生成时的布局返回帧位置
+    pub fn generate_storyboard(
+        &self,
+        video_path: &Path,
+        sprite_path: &Path,
+        layout: &StoryboardLayout,
+    ) -> Result<(), String> {
+        if let Some(parent) = sprite_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("创建故事板目录失败: {}", e))?;
+        }
+
+        // 同一个视频的并发请求各自写入不同的临时文件
+        let temp_path = unique_temp_path_for(sprite_path);
+        let output = self
+            .ffmpeg_command()
+            .args(storyboard_args(
+                video_path,
+                &temp_path,
+                layout.interval,
+                (layout.tile_width, layout.tile_height),
+                (layout.columns, layout.rows),
+                self.config.thumbnail_quality,
+            ))
+            .output_with_timeout(self.config.long_timeout)
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&temp_path);
+                format!("无法执行 ffmpeg: {}", e)
+            })?;
+
+        if commit_temp_output(&temp_path, sprite_path, output.status.success()) {
+            debug!("故事板生成成功: {:?}", sprite_path);
+            save_storyboard_layout(sprite_path, layout)
+        } else {
+            let message = output_failure_message("ffmpeg", &output);
+            warn!("故事板生成失败: {:?} - {}", video_path, message);
+            Err(message)
+        }
+    }
+
+    /// 将 MP4 无损切片为 HLS，输出 `index.m3u8` 和 `.ts` 分片，成功时返回播放列表路径
+    ///
+    /// 先写入同级的临时目录，成功后再重命名为 `out_dir`，失败时删除临时目录；`out_dir` 已存在时返回错误
+    pub fn package_hls(
+        &self,
+        video_path: &Path,
+        out_dir: &Path,
+        segment_secs: u32,
+    ) -> Result<PathBuf, String> {
+        if out_dir.exists() {
+            return Err(format!("输出目录已存在: {}", out_dir.display()));
+        }
+
+        // 在媒体库之外切片，扫描不会看到写到一半的目录
+        let temp_dir = scratch_path("hls");
+        std::fs::create_dir_all(&temp_dir).map_err(|e| format!("创建 HLS 目录失败: {}", e))?;
+
+        let temp_playlist = temp_dir.join(HLS_PLAYLIST_NAME);
+        let result = self
+            .ffmpeg_command()
+            .args(package_hls_args(
+                video_path,
+                &temp_dir,
+                &temp_playlist,
+                segment_secs,
+            ))
+            .output_with_timeout(self.config.long_timeout)
+            .map_err(|e| format!("无法执行 ffmpeg: {}", e))
+            .and_then(|output| {
+                let has_playlist = std::fs::metadata(&temp_playlist)
+                    .map(|m| m.len() > 0)
+                    .unwrap_or(false);
+                if output.status.success() && has_playlist {
+                    move_path(&temp_dir, out_dir).map_err(|e| format!("重命名 HLS 目录失败: {}", e))
+                } else {
+                    Err(output_failure_message("ffmpeg", &output))
+                }
+            });
+
+        match result {
+            Ok(()) => {
+                debug!("HLS 切片完成: {:?}", out_dir);
+                Ok(out_dir.join(HLS_PLAYLIST_NAME))
+            }
+            Err(message) => {
+                warn!("HLS 切片失败: {:?} - {}", video_path, message);
+                let _ = std::fs::remove_dir_all(&temp_dir);
+                Err(message)
+            }
+        }
+    }
+
+    /// 完整解码一遍检查文件是否可以播放
+    ///
+    /// ffmpeg 返回失败，或者在 `-v error` 下输出了任何错误（损坏的文件常常仍以 0 退出）时返回错误信息
+    pub fn check_decodes(&self, video_path: &Path) -> Result<(), String> {
+        let output = self
+            .ffmpeg_command()
+            .args(decode_check_args(video_path))
+            .output_with_timeout(self.config.long_timeout)
+            .map_err(|e| format!("无法执行 ffmpeg: {}", e))?;
+
+        if !output.status.success() {
+            return Err(failure_message("ffmpeg", &output));
+        }
+        let errors = stderr_tail(&output.stderr);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(())
+    }
+
+    /// 按配置的尺寸列表生成缩略图变体，返回成功生成的数量
+    ///
+    /// 已有变体早于默认尺寸缩略图时说明是旧视频生成的，会重新生成
+    pub fn generate_thumbnail_variants(&self, video_path: &Path, thumbnail_path: &Path) -> usize {
+        let seek_time = self.seek_time_for(video_path, self.thumbnail_strategy(video_path));
+        self.config
+            .thumbnail_sizes
+            .iter()
+            .filter(|&&width| {
+                let variant_path = thumbnail_variant_path(thumbnail_path, width);
+                is_variant_fresh(&variant_path, thumbnail_path)
+                    || self
+                        .generate_thumbnail_with_width(video_path, &variant_path, width, seek_time)
+                        .is_ok()
+            })
+            .count()
+    }
+
+    /// 从指定时间点生成指定宽度的视频缩略图
+    fn generate_thumbnail_with_width(
+        &self,
+        video_path: &Path,
+        thumbnail_path: &Path,
+        width: u32,
+        seek_time: f32,
+    ) -> Result<(), String> {
         // 确保父目录存在
         if let Some(parent) = thumbnail_path.parent() {
             if !parent.exists() {
                 if let Err(e) = std::fs::create_dir_all(parent) {
                     error!("创建缩略图目录失败: {}", e);
-                    return false;
+                    return Err(format!("创建缩略图目录失败: {}", e));
                 }
             }
         }
 
-        let input = video_path.to_string_lossy().to_string();
-        let output = thumbnail_path.to_string_lossy().to_string();
-        let seek_time = format!("{:.2}", self.config.thumbnail_seek_time);
-        let scale = format!("scale={}:-1", self.config.thumbnail_width);
-        let quality = self.config.thumbnail_quality.to_string();
+        let label = self
+            .config
+            .thumbnail_label
+            .as_deref()
+            .filter(|_| !self.label_disabled.load(Ordering::Relaxed));
+        match self.run_thumbnail(video_path, thumbnail_path, width, seek_time, label) {
+            // 叠加文字失败时去掉文字重试，成功说明 drawtext 不可用，之后不再叠加
+            Err(e) if label.is_some() => {
+                self.run_thumbnail(video_path, thumbnail_path, width, seek_time, None)?;
+                warn!("缩略图文字叠加不可用，已禁用: {}", e);
+                self.label_disabled.store(true, Ordering::Relaxed);
+                Ok(())
+            }
+            result => result,
+        }
+    }
 
-        let result = Command::new("ffmpeg")
-            .args([
-                "-ss", &seek_time, "-i", &input, "-vframes", "1", "-vf", &scale, "-q:v", &quality,
-                "-y", &output,
-            ])
-            .output();
+    /// 执行 ffmpeg 生成单个缩略图
+    fn run_thumbnail(
+        &self,
+        video_path: &Path,
+        thumbnail_path: &Path,
+        width: u32,
+        seek_time: f32,
+        label: Option<&str>,
+    ) -> Result<(), String> {
+        // 先写入临时文件，成功后再重命名，避免中断时留下残缺的缩略图
+        let temp_path = temp_path_for(thumbnail_path);
+        let args = thumbnail_args(
+            video_path,
+            &temp_path,
+            seek_time,
+            width,
+            self.config.thumbnail_quality,
+            label,
+        );
+
+        let result = self
+            .ffmpeg_command()
+            .args(args)
+            .output_with_timeout(self.config.timeout);
 
         match result {
-            Ok(_) => {
-                if thumbnail_path.exists() {
+            Ok(output) => {
+                if commit_temp_output(&temp_path, thumbnail_path, output.status.success()) {
                     debug!("缩略图生成成功: {:?}", thumbnail_path);
-                    true
+                    Ok(())
                 } else {
-                    warn!("缩略图生成失败: {:?}", video_path);
-                    false
+                    let message = output_failure_message("ffmpeg", &output);
+                    warn!("缩略图生成失败: {:?} - {}", video_path, message);
+                    Err(message)
                 }
             }
             Err(e) => {
                 error!("FFmpeg 执行错误: {}", e);
-                false
+                let _ = std::fs::remove_file(&temp_path);
+                Err(format!("无法执行 ffmpeg: {}", e))
             }
         }
     }
@@ -176,46 +824,399 @@ impl FFmpegService {
             }
         }
 
-        let svg_content = match label {
-            "video" => {
-                r##"<svg width="320" height="180" xmlns="http://www.w3.org/2000/svg"><rect width="320" height="180" fill="#4A90E2"/><text x="160" y="90" font-family="Arial" font-size="20" fill="white" text-anchor="middle">VIDEO</text></svg>"##
-            }
-            "media" => {
-                r##"<svg width="320" height="180" xmlns="http://www.w3.org/2000/svg"><rect width="320" height="180" fill="#F5A623"/><text x="160" y="90" font-family="Arial" font-size="20" fill="white" text-anchor="middle">MEDIA</text></svg>"##
-            }
-            _ => {
-                r##"<svg width="320" height="180" xmlns="http://www.w3.org/2000/svg"><rect width="320" height="180" fill="#95A5A6"/><text x="160" y="90" font-family="Arial" font-size="20" fill="white" text-anchor="middle">FILE</text></svg>"##
-            }
-        };
+        let svg_content = placeholder_svg(label);
 
         let svg_path = thumbnail_path.with_extension("svg");
         if std::fs::write(&svg_path, svg_content).is_err() {
             return false;
         }
 
-        let input = svg_path.to_string_lossy().to_string();
-        let output = thumbnail_path.to_string_lossy().to_string();
-
-        let result = Command::new("ffmpeg")
-            .args(["-i", &input, "-y", &output])
-            .output();
+        let temp_path = temp_path_for(thumbnail_path);
+        let result = self
+            .ffmpeg_command()
+            .args(placeholder_args(&svg_path, &temp_path))
+            .output_with_timeout(self.config.timeout);
 
         let _ = std::fs::remove_file(&svg_path);
 
         match result {
-            Ok(_) => thumbnail_path.exists(),
-            Err(_) => false,
+            Ok(output) => {
+                let committed =
+                    commit_temp_output(&temp_path, thumbnail_path, output.status.success());
+                if !committed {
+                    warn!(
+                        "占位缩略图生成失败: {:?} - {}",
+                        thumbnail_path,
+                        output_failure_message("ffmpeg", &output)
+                    );
+                }
+                committed
+            }
+            Err(_) => {
+                let _ = std::fs::remove_file(&temp_path);
+                false
+            }
+        }
+    }
+
+    /// 将 M3U8 播放列表合并为 MP4 文件（不重新编码）
+    ///
+    /// 输出到播放列表所在目录的旁边，见 [`merged_output_path`]，成功时返回输出路径
+    pub fn merge_m3u8_to_mp4(&self, playlist_path: &Path) -> Option<PathBuf> {
+        let hls_dir = playlist_path.parent()?;
+        let output_path = merged_output_path(hls_dir);
+        let temp_path = temp_path_for(&output_path);
+        let result = self
+            .ffmpeg_command()
+            .args(merge_m3u8_args(playlist_path, &temp_path))
+            .output_with_timeout(self.config.long_timeout);
+
+        match result {
+            Ok(output) => {
+                if commit_temp_output(&temp_path, &output_path, output.status.success()) {
+                    debug!("M3U8 合并成功: {:?}", output_path);
+                    Some(output_path)
+                } else {
+                    warn!(
+                        "M3U8 合并失败: {:?} - {}",
+                        playlist_path,
+                        output_failure_message("ffmpeg", &output)
+                    );
+                    None
+                }
+            }
+            Err(e) => {
+                error!("FFmpeg 执行错误: {}", e);
+                let _ = std::fs::remove_file(&temp_path);
+                None
+            }
+        }
+    }
+}
+
+/// 从 ffprobe JSON 中取第一个视频流的编码和总码率（如 "h264"、"5000 kbps"）
+fn codec_and_bitrate(probe: &serde_json::Value) -> (Option<String>, Option<String>) {
+    let codec = probe["streams"]
+        .as_array()
+        .and_then(|streams| {
+            streams
+                .iter()
+                .find(|stream| stream["codec_type"] == "video")
+        })
+        .and_then(|stream| stream["codec_name"].as_str())
+        .map(str::to_string);
+    let bitrate = probe["format"]["bit_rate"]
+        .as_str()
+        .and_then(|b| b.parse::<u64>().ok())
+        .map(|bits| format!("{} kbps", bits / 1000));
+    (codec, bitrate)
+}
+
+/// HLS 目录合并后的 MP4 路径：与目录同级、同名，例如 `show/` -> `show.mp4`
+pub fn merged_output_path(hls_dir: &Path) -> PathBuf {
+    let name = hls_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "merged".to_string());
+    hls_dir.with_file_name(format!("{}.mp4", name))
+}
+
+/// 目标编码对应的 ffmpeg 视频编码器，不支持的编码返回 None
+pub fn transcode_encoder(codec: &str) -> Option<&'static str> {
+    match codec.trim().to_ascii_lowercase().as_str() {
+        "h264" => Some("libx264"),
+        _ => None,
+    }
+}
+
+/// 占位缩略图的 SVG 内容，`label` 为 "video"、"media"，其他值显示为 FILE
+pub fn placeholder_svg(label: &str) -> &'static str {
+    match label {
+        "video" => {
+            r##"<svg width="320" height="180" xmlns="http://www.w3.org/2000/svg"><rect width="320" height="180" fill="#4A90E2"/><text x="160" y="90" font-family="Arial" font-size="20" fill="white" text-anchor="middle">VIDEO</text></svg>"##
         }
+        "media" => {
+            r##"<svg width="320" height="180" xmlns="http://www.w3.org/2000/svg"><rect width="320" height="180" fill="#F5A623"/><text x="160" y="90" font-family="Arial" font-size="20" fill="white" text-anchor="middle">MEDIA</text></svg>"##
+        }
+        _ => {
+            r##"<svg width="320" height="180" xmlns="http://www.w3.org/2000/svg"><rect width="320" height="180" fill="#95A5A6"/><text x="160" y="90" font-family="Arial" font-size="20" fill="white" text-anchor="middle">FILE</text></svg>"##
+        }
+    }
+}
+
+/// MP4 切片为 HLS 的输出目录：与文件同级，例如 `show/ep1.mp4` -> `show/ep1.hls/`
+///
+/// 带 `.hls` 后缀，不会与同名的普通目录（`show/ep1/`）冲突。
+/// 同步扫描会跳过这类目录，其记录只由切片任务登记
+pub fn hls_output_dir(video_path: &Path) -> PathBuf {
+    let stem = video_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    video_path.with_file_name(format!("{}.{}", stem, HLS_OUTPUT_EXTENSION))
+}
+
+/// 路径是否为 MP4 切片的输出目录（只看名称，不检查磁盘）
+pub fn is_hls_output_dir(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == HLS_OUTPUT_EXTENSION)
+}
+
+/// 查找 HLS 目录中的播放列表，优先使用 index.m3u8
+pub fn find_playlist(hls_dir: &Path) -> Option<PathBuf> {
+    let index = hls_dir.join(HLS_PLAYLIST_NAME);
+    if index.is_file() {
+        return Some(index);
     }
 
-    /// 格式化时长为 HH:MM:SS
-    fn format_duration(seconds: f64) -> String {
-        let total_seconds = seconds as u64;
-        let hours = total_seconds / 3600;
-        let minutes = (total_seconds % 3600) / 60;
-        let secs = total_seconds % 60;
-        format!("{:02}:{:02}:{:02}", hours, minutes, secs)
+    let mut playlists: Vec<PathBuf> = std::fs::read_dir(hls_dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| media_types::is_playlist(p))
+        .collect();
+    playlists.sort();
+    playlists.into_iter().next()
+}
+
+/// 计算故事板雪碧图的路径
+///
+/// `thumbnails/disk1/a.jpg` 的故事板为 `thumbnails/storyboards/disk1/a.jpg`
+pub fn storyboard_path(thumbnail_path: &Path) -> PathBuf {
+    let relative = thumbnail_path
+        .strip_prefix(THUMBNAILS_DIR)
+        .unwrap_or_else(|_| Path::new(thumbnail_path.file_name().unwrap_or_default()));
+    Path::new(THUMBNAILS_DIR).join("storyboards").join(relative)
+}
+
+/// 故事板布局文件的路径，与雪碧图同名，扩展名为 `.json`
+fn storyboard_layout_path(sprite_path: &Path) -> PathBuf {
+    sprite_path.with_extension("json")
+}
+
+/// 读取雪碧图生成时保存的布局，文件不存在或无法解析时返回 None
+pub fn load_storyboard_layout(sprite_path: &Path) -> Option<StoryboardLayout> {
+    let json = std::fs::read_to_string(storyboard_layout_path(sprite_path)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// 先写入临时文件再重命名，读取方不会看到写了一半的布局
+fn save_storyboard_layout(sprite_path: &Path, layout: &StoryboardLayout) -> Result<(), String> {
+    let layout_path = storyboard_layout_path(sprite_path);
+    let temp_path = unique_temp_path_for(&layout_path);
+    let json = serde_json::to_string(layout).map_err(|e| format!("序列化故事板布局失败: {}", e))?;
+    std::fs::write(&temp_path, json)
+        .and_then(|_| std::fs::rename(&temp_path, &layout_path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            format!("保存故事板布局失败: {}", e)
+        })
+}
+
+/// 变体存在且不早于默认尺寸缩略图时仍然有效
+fn is_variant_fresh(variant_path: &Path, thumbnail_path: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    matches!(
+        (modified(variant_path), modified(thumbnail_path)),
+        (Some(variant), Some(thumbnail)) if variant >= thumbnail
+    )
+}
+
+/// 计算缩略图尺寸变体的路径
+///
+/// `thumbnails/disk1/a.jpg` 的 160 宽度变体为 `thumbnails/160/disk1/a.jpg`
+pub fn thumbnail_variant_path(thumbnail_path: &Path, width: u32) -> PathBuf {
+    match thumbnail_path.strip_prefix(THUMBNAILS_DIR) {
+        Ok(relative) => Path::new(THUMBNAILS_DIR)
+            .join(width.to_string())
+            .join(relative),
+        Err(_) => {
+            let parent = thumbnail_path.parent().unwrap_or(Path::new(""));
+            let file_name = thumbnail_path.file_name().unwrap_or_default();
+            parent.join(width.to_string()).join(file_name)
+        }
+    }
+}
+
+/// 解析 `宽x高` 格式的 ffprobe 输出
+fn parse_dimensions(output: &str) -> Option<(u32, u32)> {
+    let (width, height) = output.lines().next()?.trim().split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// 解析 `ffprobe -show_format -show_streams -of json` 的输出
+fn parse_probe_json(json: &str) -> Result<serde_json::Value, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| format!("无法解析 ffprobe 输出: {}", e))?;
+
+    if value.get("format").is_none() || value.get("streams").is_none() {
+        return Err("ffprobe 输出缺少 format 或 streams".to_string());
+    }
+    Ok(value)
+}
+
+/// 解析 `ffprobe -show_chapters -of json` 的输出
+fn parse_chapters(json: &str) -> Option<Vec<Chapter>> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+
+    let chapters = value
+        .get("chapters")
+        .and_then(|c| c.as_array())
+        .map(|chapters| {
+            chapters
+                .iter()
+                .map(|chapter| {
+                    // ffprobe 以字符串形式输出 start_time/end_time
+                    let time = |key: &str| {
+                        chapter
+                            .get(key)
+                            .and_then(|v| v.as_str())
+                            .and_then(|v| v.parse::<f64>().ok())
+                            .unwrap_or(0.0)
+                    };
+                    Chapter {
+                        id: chapter.get("id").and_then(|v| v.as_i64()).unwrap_or(0),
+                        start: time("start_time"),
+                        end: time("end_time"),
+                        title: chapter
+                            .get("tags")
+                            .and_then(|tags| tags.get("title"))
+                            .and_then(|v| v.as_str())
+                            .map(str::to_string),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(chapters)
+}
+
+/// 超时秒数转换为超时时间，0 表示不限制
+fn timeout_from_secs(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// 以 `-version` 参数执行命令，判断可执行文件是否可用
+fn runs_successfully(mut command: Command, timeout: Option<Duration>) -> bool {
+    command
+        .arg("-version")
+        .output_with_timeout(timeout)
+        .is_ok_and(|output| output.status.success())
+}
+
+/// 取 stderr 的最后几行（忽略空行），ffmpeg 的错误原因通常在末尾
+fn stderr_tail(stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n")
+}
+
+/// 持续读取子进程的 stderr 直到结束，只保留末尾 [`STDERR_TAIL_LINES`] 行
+async fn read_stderr_tail(stderr: tokio::process::ChildStderr) -> String {
+    use tokio::io::AsyncBufReadExt;
+
+    let mut lines = tokio::io::BufReader::new(stderr).lines();
+    let mut tail = std::collections::VecDeque::with_capacity(STDERR_TAIL_LINES);
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        if tail.len() == STDERR_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
     }
+    Vec::from(tail).join("\n")
+}
+
+/// 命令返回非零退出码时的错误信息，包含退出码和 stderr 末尾几行
+fn failure_message(program: &str, output: &Output) -> String {
+    format!(
+        "{} 执行失败（{}）: {}",
+        program,
+        output.status,
+        stderr_tail(&output.stderr)
+    )
+}
+
+/// 输出文件未生成时的错误信息：命令失败时包含 stderr，成功但没有输出时单独说明
+fn output_failure_message(program: &str, output: &Output) -> String {
+    if output.status.success() {
+        format!("{} 没有生成输出文件", program)
+    } else {
+        failure_message(program, output)
+    }
+}
+
+/// 获取输出文件对应的临时文件路径，例如 `a.jpg` -> `a.tmp.jpg`
+///
+/// 保留原扩展名，以便 ffmpeg 根据扩展名推断输出格式
+fn temp_path_for(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.tmp.{}", stem, ext.to_string_lossy()),
+        None => format!("{}.tmp", stem),
+    };
+    path.with_file_name(file_name)
+}
+
+/// 与 [`temp_path_for`] 相同，但文件名带有唯一后缀，例如 `a.jpg` -> `a.tmp-123-0.jpg`
+fn unique_temp_path_for(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.tmp-{}.{}", stem, unique_suffix(), ext.to_string_lossy()),
+        None => format!("{}.tmp-{}", stem, unique_suffix()),
+    };
+    path.with_file_name(file_name)
+}
+
+/// 系统临时目录下的唯一路径，并发的任务不会共用同一个临时位置
+fn scratch_path(label: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("videos-{}-{}", label, unique_suffix()))
+}
+
+/// 进程 id 加进程内计数器，在同一台机器上唯一
+fn unique_suffix() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// 提交临时输出文件：成功时原子重命名为最终文件，失败时删除临时文件
+///
+/// 临时文件不存在或为空都视为失败
+fn commit_temp_output(temp_path: &Path, final_path: &Path, success: bool) -> bool {
+    let has_output = std::fs::metadata(temp_path)
+        .map(|m| m.len() > 0)
+        .unwrap_or(false);
+
+    if success && has_output {
+        match move_path(temp_path, final_path) {
+            Ok(_) => return true,
+            Err(e) => error!(
+                "重命名临时文件失败: {:?} -> {:?}: {}",
+                temp_path, final_path, e
+            ),
+        }
+    }
+
+    let _ = std::fs::remove_file(temp_path);
+    false
 }
 
 /// 全局 FFmpeg 服务实例（惰性初始化）
@@ -223,7 +1224,19 @@ static FFMPEG_SERVICE: std::sync::OnceLock<FFmpegService> = std::sync::OnceLock:
 
 /// 获取全局 FFmpeg 服务实例
 pub fn get_ffmpeg_service() -> &'static FFmpegService {
-    FFMPEG_SERVICE.get_or_init(|| FFmpegService::with_defaults())
+    FFMPEG_SERVICE.get_or_init(|| {
+        let config = get_config();
+        FFmpegService::new(FFmpegConfig {
+            thumbnail_sizes: config.thumbnail.sizes.clone(),
+            ffmpeg_path: config.ffmpeg.ffmpeg_path.clone(),
+            ffprobe_path: config.ffmpeg.ffprobe_path.clone(),
+            thumbnail_label: config.thumbnail.label.clone(),
+            thumbnail_strategies: config.thumbnail.strategies.clone(),
+            timeout: timeout_from_secs(config.ffmpeg.timeout_secs),
+            long_timeout: timeout_from_secs(config.ffmpeg.long_timeout_secs),
+            ..FFmpegConfig::default()
+        })
+    })
 }
 
 #[cfg(test)]
@@ -231,9 +1244,685 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_format_duration() {
-        assert_eq!(FFmpegService::format_duration(0.0), "00:00:00");
-        assert_eq!(FFmpegService::format_duration(61.0), "00:01:01");
-        assert_eq!(FFmpegService::format_duration(3661.0), "01:01:01");
+    fn test_storyboard_layout_frames() {
+        // 125 秒、每 10 秒一帧：13 帧，10 列 2 行
+        let layout = StoryboardLayout::new(125.0, Some(1920), Some(1080));
+        assert_eq!(
+            (
+                layout.interval,
+                layout.frame_count,
+                layout.columns,
+                layout.rows
+            ),
+            (10, 13, 10, 2)
+        );
+        assert_eq!((layout.tile_width, layout.tile_height), (160, 90));
+
+        let frames = layout.frames();
+        assert_eq!(frames.len(), 13);
+        assert_eq!(
+            frames[0],
+            StoryboardFrame {
+                time: 0.0,
+                x: 0,
+                y: 0,
+                w: 160,
+                h: 90
+            }
+        );
+        assert_eq!((frames[9].time, frames[9].x, frames[9].y), (90.0, 1440, 0));
+        assert_eq!(
+            (frames[12].time, frames[12].x, frames[12].y),
+            (120.0, 320, 90)
+        );
+
+        // 长视频加大间隔，帧数不超过上限
+        let layout = StoryboardLayout::new(3600.0, Some(640), Some(480));
+        assert_eq!(
+            (layout.interval, layout.frame_count, layout.rows),
+            (36, 100, 10)
+        );
+        assert_eq!(layout.tile_height, 120);
+
+        // 极短视频也至少有一帧
+        let layout = StoryboardLayout::new(3.0, None, None);
+        assert_eq!((layout.frame_count, layout.columns, layout.rows), (1, 1, 1));
+        assert_eq!(layout.tile_height, 90);
+        assert_eq!(
+            storyboard_path(Path::new("thumbnails/disk1/a.jpg")),
+            Path::new("thumbnails/storyboards/disk1/a.jpg")
+        );
+    }
+
+    #[test]
+    fn test_parse_chapters() {
+        let json = r#"{
+            "chapters": [
+                {
+                    "id": 0,
+                    "time_base": "1/1000",
+                    "start": 0,
+                    "start_time": "0.000000",
+                    "end": 90500,
+                    "end_time": "90.500000",
+                    "tags": { "title": "Opening" }
+                },
+                {
+                    "id": 1,
+                    "time_base": "1/1000",
+                    "start": 90500,
+                    "start_time": "90.500000",
+                    "end": 600000,
+                    "end_time": "600.000000"
+                }
+            ]
+        }"#;
+
+        let chapters = parse_chapters(json).unwrap();
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter {
+                    id: 0,
+                    start: 0.0,
+                    end: 90.5,
+                    title: Some("Opening".to_string()),
+                },
+                Chapter {
+                    id: 1,
+                    start: 90.5,
+                    end: 600.0,
+                    title: None,
+                },
+            ]
+        );
+
+        assert_eq!(parse_chapters(r#"{ "chapters": [] }"#), Some(Vec::new()));
+        assert_eq!(parse_chapters("not json"), None);
+    }
+
+    #[test]
+    fn test_parse_dimensions() {
+        assert_eq!(parse_dimensions("320x180\n"), Some((320, 180)));
+        assert_eq!(parse_dimensions("0x0"), Some((0, 0)));
+        assert_eq!(parse_dimensions(""), None);
+        assert_eq!(parse_dimensions("N/AxN/A"), None);
+    }
+
+    #[test]
+    fn test_empty_image_is_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.jpg");
+        std::fs::write(&empty, b"").unwrap();
+
+        let service = FFmpegService::with_defaults();
+        assert!(!service.is_valid_image(&empty));
+        assert!(!service.is_valid_image(&dir.path().join("missing.jpg")));
+    }
+
+    #[test]
+    fn test_parse_probe_json() {
+        let fixture = r#"{
+            "streams": [
+                { "index": 0, "codec_name": "h264", "codec_type": "video", "width": 1920, "height": 1080 },
+                { "index": 1, "codec_name": "aac", "codec_type": "audio" }
+            ],
+            "format": { "filename": "a.mp4", "format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "60.000000" }
+        }"#;
+
+        let value = parse_probe_json(fixture).unwrap();
+        assert_eq!(value["streams"].as_array().unwrap().len(), 2);
+        assert_eq!(value["format"]["duration"], "60.000000");
+
+        assert!(parse_probe_json(r#"{ "streams": [] }"#).is_err());
+        assert!(parse_probe_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_codec_and_bitrate() {
+        let probe = serde_json::json!({
+            "streams": [
+                { "codec_name": "aac", "codec_type": "audio" },
+                { "codec_name": "hevc", "codec_type": "video" }
+            ],
+            "format": { "bit_rate": "5123456" }
+        });
+        assert_eq!(
+            codec_and_bitrate(&probe),
+            (Some("hevc".to_string()), Some("5123 kbps".to_string()))
+        );
+        assert_eq!(codec_and_bitrate(&serde_json::json!({})), (None, None));
+    }
+
+    #[test]
+    fn test_temp_path_for() {
+        assert_eq!(
+            temp_path_for(Path::new("thumbnails/a.jpg")),
+            PathBuf::from("thumbnails/a.tmp.jpg")
+        );
+
+        let first = unique_temp_path_for(Path::new("thumbnails/a.jpg"));
+        let second = unique_temp_path_for(Path::new("thumbnails/a.jpg"));
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), Some(Path::new("thumbnails")));
+        assert_eq!(first.extension(), Some("jpg".as_ref()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_storyboard_layout_saved_next_to_sprite() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // 用脚本代替 ffmpeg：把内容写入最后一个参数（输出文件）
+        let dir = tempfile::tempdir().unwrap();
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            "#!/bin/sh\nfor last; do :; done\necho jpeg > \"$last\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            ..FFmpegConfig::default()
+        });
+
+        let sprite_path = dir.path().join("storyboards").join("a.jpg");
+        assert_eq!(load_storyboard_layout(&sprite_path), None);
+        let layout = StoryboardLayout::new(125.0, Some(1920), Some(1080));
+        // 同一个视频的并发请求不会互相覆盖临时文件
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    service
+                        .generate_storyboard(Path::new("a.mp4"), &sprite_path, &layout)
+                        .unwrap()
+                });
+            }
+        });
+
+        assert_eq!(load_storyboard_layout(&sprite_path), Some(layout));
+        let mut files: Vec<String> = std::fs::read_dir(sprite_path.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["a.jpg", "a.json"]);
+    }
+
+    #[test]
+    fn test_custom_binary_paths() {
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: "/usr/local/bin/ffmpeg".to_string(),
+            ffprobe_path: "/usr/local/bin/ffprobe".to_string(),
+            ..FFmpegConfig::default()
+        });
+        assert_eq!(
+            service.ffmpeg_command().get_program(),
+            "/usr/local/bin/ffmpeg"
+        );
+        assert_eq!(
+            service.ffprobe_command().get_program(),
+            "/usr/local/bin/ffprobe"
+        );
+
+        let missing = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: "/nonexistent/ffmpeg".to_string(),
+            ffprobe_path: "/nonexistent/ffprobe".to_string(),
+            ..FFmpegConfig::default()
+        });
+        assert!(!missing.is_ffmpeg_available());
+        assert!(!missing.is_ffprobe_available());
+
+        let service = FFmpegService::with_defaults();
+        assert_eq!(service.ffmpeg_command().get_program(), "ffmpeg");
+        assert_eq!(service.ffprobe_command().get_program(), "ffprobe");
+    }
+
+    #[test]
+    fn test_merged_output_is_placed_beside_source_directory() {
+        assert_eq!(
+            merged_output_path(Path::new("/data/disk1/shows/ep1")),
+            PathBuf::from("/data/disk1/shows/ep1.mp4")
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.m3u8"), "#EXTM3U\n").unwrap();
+        std::fs::write(dir.path().join("a.m3u8"), "#EXTM3U\n").unwrap();
+        assert_eq!(find_playlist(dir.path()), Some(dir.path().join("a.m3u8")));
+        std::fs::write(dir.path().join("index.m3u8"), "#EXTM3U\n").unwrap();
+        assert_eq!(
+            find_playlist(dir.path()),
+            Some(dir.path().join("index.m3u8"))
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_variant_path() {
+        assert_eq!(
+            thumbnail_variant_path(Path::new("thumbnails/disk1/a.jpg"), 160),
+            PathBuf::from("thumbnails/160/disk1/a.jpg")
+        );
+        assert_eq!(
+            thumbnail_variant_path(Path::new("/tmp/x/a.jpg"), 480),
+            PathBuf::from("/tmp/x/480/a.jpg")
+        );
+    }
+
+    #[test]
+    fn test_variant_older_than_thumbnail_is_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbnail = dir.path().join("a.jpg");
+        let variant = thumbnail_variant_path(&thumbnail, 160);
+        assert!(!is_variant_fresh(&variant, &thumbnail));
+
+        std::fs::create_dir_all(variant.parent().unwrap()).unwrap();
+        std::fs::write(&variant, "old").unwrap();
+        std::fs::write(&thumbnail, "new").unwrap();
+        let now = std::time::SystemTime::now();
+        let set_modified = |path: &Path, time| {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap()
+        };
+        set_modified(&variant, now - std::time::Duration::from_secs(60));
+        set_modified(&thumbnail, now);
+        assert!(!is_variant_fresh(&variant, &thumbnail));
+
+        set_modified(&variant, now);
+        assert!(is_variant_fresh(&variant, &thumbnail));
+    }
+
+    #[test]
+    fn test_configured_sizes_produce_variant_files() {
+        // 需要本机安装 ffmpeg
+        if Command::new("ffmpeg").arg("-version").output().is_err() {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let video_path = dir.path().join("sample.mp4");
+        let status = Command::new("ffmpeg")
+            .args([
+                "-f",
+                "lavfi",
+                "-i",
+                "testsrc=duration=2:size=640x360:rate=10",
+                "-y",
+            ])
+            .arg(&video_path)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+
+        let service = FFmpegService::new(FFmpegConfig {
+            thumbnail_sizes: vec![160, 480],
+            ..FFmpegConfig::default()
+        });
+        let thumbnail_path = dir.path().join("thumbs").join("sample.jpg");
+        assert!(service.generate_thumbnail(&video_path, &thumbnail_path));
+        assert!(thumbnail_variant_path(&thumbnail_path, 160).exists());
+        assert!(thumbnail_variant_path(&thumbnail_path, 480).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_thumbnail_reports_stderr_tail() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // 用脚本代替 ffmpeg：向 stderr 输出 15 行后以非零退出码退出
+        let dir = tempfile::tempdir().unwrap();
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            "#!/bin/sh\nfor i in $(seq -w 1 14); do echo \"line $i\" >&2; done\n\
+             echo 'a.mp4: Invalid data found when processing input' >&2\nexit 1\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            thumbnail_sizes: Vec::new(),
+            ..FFmpegConfig::default()
+        });
+        let thumbnail_path = dir.path().join("thumbs").join("a.jpg");
+        let error = service
+            .try_generate_thumbnail(Path::new("a.mp4"), &thumbnail_path)
+            .unwrap_err();
+
+        assert!(error.contains("exit status: 1"), "{}", error);
+        assert!(error.contains("Invalid data found when processing input"));
+        assert!(error.contains("line 06"));
+        assert!(!error.contains("line 05"));
+        assert!(!thumbnail_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_label_dropped_when_drawtext_unavailable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // 用脚本代替没有 drawtext 的 ffmpeg（文件没有内嵌封面）：参数包含 drawtext 时失败，否则写出输出文件
+        let dir = tempfile::tempdir().unwrap();
+        let args_log = dir.path().join("args.log");
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            format!(
+                "#!/bin/sh\ncase \"$*\" in *-0:V*) exit 1;; esac\n\
+                 echo \"$@\" >> '{}'\ncase \"$*\" in *drawtext*) \
+                 echo \"No such filter: 'drawtext'\" >&2; exit 1;; esac\n\
+                 for last; do :; done\necho frame > \"$last\"\n",
+                args_log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            thumbnail_label: Some("HD".to_string()),
+            ..FFmpegConfig::default()
+        });
+        let thumbs = dir.path().join("thumbs");
+        assert!(service.generate_thumbnail(Path::new("a.mp4"), &thumbs.join("a.jpg")));
+        assert!(service.generate_thumbnail(Path::new("b.mp4"), &thumbs.join("b.jpg")));
+
+        // 只有第一次尝试叠加文字，之后直接生成不带文字的缩略图
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        let calls: Vec<&str> = log.lines().collect();
+        assert_eq!(calls.len(), 3);
+        assert!(calls[0].contains("drawtext"));
+        assert!(calls[1..].iter().all(|call| !call.contains("drawtext")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_generate_thumbnail_at_uses_requested_seek_time() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // 用脚本代替 ffmpeg：记录参数并写出输出文件（最后一个参数）
+        let dir = tempfile::tempdir().unwrap();
+        let args_log = dir.path().join("args.log");
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> '{}'\nfor last; do :; done\necho frame > \"$last\"\n",
+                args_log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            thumbnail_sizes: vec![160],
+            ..FFmpegConfig::default()
+        });
+        let thumbnail_path = dir.path().join("thumbs").join("a.jpg");
+        std::fs::create_dir_all(thumbnail_path.parent().unwrap()).unwrap();
+        std::fs::write(&thumbnail_path, b"old").unwrap();
+
+        assert!(service.generate_thumbnail_at(Path::new("a.mp4"), &thumbnail_path, 42.0));
+        assert_eq!(std::fs::read(&thumbnail_path).unwrap(), b"frame\n");
+        assert!(thumbnail_variant_path(&thumbnail_path, 160).exists());
+
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(log
+            .lines()
+            .all(|line| line.starts_with("-ss 42.00 -i a.mp4")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_embedded_cover_preferred_over_frame_grab() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // 用脚本代替 ffmpeg：记录参数，只有 has-cover.mp4 带有封面图
+        let dir = tempfile::tempdir().unwrap();
+        let args_log = dir.path().join("args.log");
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> '{}'\ncase \"$*\" in *-0:V*) \
+                 case \"$*\" in *has-cover.mp4*) ;; *) exit 1;; esac;; esac\n\
+                 for last; do :; done\necho \"$1$2\" > \"$last\"\n",
+                args_log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            thumbnail_sizes: vec![160],
+            ..FFmpegConfig::default()
+        });
+        let thumbs = dir.path().join("thumbs");
+
+        // 有封面：只提取封面（含各尺寸变体），不截取画面
+        let cover_thumb = thumbs.join("cover.jpg");
+        assert!(service.generate_thumbnail(Path::new("has-cover.mp4"), &cover_thumb));
+        assert_eq!(std::fs::read(&cover_thumb).unwrap(), b"-verror\n");
+        assert!(thumbnail_variant_path(&cover_thumb, 160).exists());
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        let calls: Vec<&str> = log.lines().collect();
+        assert_eq!(calls.len(), 2);
+        assert!(calls.iter().all(|call| call.contains("-map -0:V")));
+
+        // 没有封面：先尝试封面，失败后截取画面
+        std::fs::remove_file(&args_log).unwrap();
+        let frame_thumb = thumbs.join("frame.jpg");
+        assert!(service.generate_thumbnail(Path::new("plain.mp4"), &frame_thumb));
+        assert_eq!(std::fs::read(&frame_thumb).unwrap(), b"-ss1.00\n");
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        let calls: Vec<&str> = log.lines().collect();
+        assert_eq!(calls.len(), 3);
+        assert!(calls[0].contains("-map -0:V"));
+        assert!(calls[1..].iter().all(|call| call.starts_with("-ss ")));
+        assert!(!temp_path_for(&frame_thumb).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hls_packaged_outside_library_then_moved_into_place() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let args_log = dir.path().join("args.log");
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> '{}'\nfor last; do :; done\necho '#EXTM3U' > \"$last\"\n",
+                args_log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            ..FFmpegConfig::default()
+        });
+
+        // 已有同名的普通目录，输出目录不会与它冲突
+        let library = dir.path().join("library");
+        std::fs::create_dir_all(library.join("ep1")).unwrap();
+        let video = library.join("ep1.mp4");
+        std::fs::write(&video, b"mp4").unwrap();
+        let out_dir = hls_output_dir(&video);
+        assert_eq!(out_dir, library.join("ep1.hls"));
+
+        let playlist = service.package_hls(&video, &out_dir, 6).unwrap();
+        assert_eq!(playlist, out_dir.join(HLS_PLAYLIST_NAME));
+        assert!(playlist.is_file());
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        assert!(!log.contains(&format!("{}/ep1.hls", library.display())));
+        let mut entries: Vec<String> = std::fs::read_dir(&library)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, vec!["ep1", "ep1.hls", "ep1.mp4"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_configured_gif_strategy_extracts_first_frame() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let args_log = dir.path().join("args.log");
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> '{}'\nfor last; do :; done\necho frame > \"$last\"\n",
+                args_log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let defaults = FFmpegService::with_defaults();
+        assert_eq!(
+            defaults.thumbnail_strategy(Path::new("a.GIF")),
+            ThumbnailStrategy::Placeholder
+        );
+        assert_eq!(
+            defaults.thumbnail_strategy(Path::new("a.mkv")),
+            ThumbnailStrategy::Frame
+        );
+
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            thumbnail_strategies: BTreeMap::from([
+                ("gif".to_string(), ThumbnailStrategy::FirstFrame),
+                ("image".to_string(), ThumbnailStrategy::Frame),
+                ("mp4".to_string(), ThumbnailStrategy::Placeholder),
+            ]),
+            ..FFmpegConfig::default()
+        });
+        // 扩展名优先于分类
+        assert_eq!(
+            service.thumbnail_strategy(Path::new("a.gif")),
+            ThumbnailStrategy::FirstFrame
+        );
+        assert_eq!(
+            service.thumbnail_strategy(Path::new("a.png")),
+            ThumbnailStrategy::Frame
+        );
+        assert_eq!(
+            service.thumbnail_strategy(Path::new("a.mp4")),
+            ThumbnailStrategy::Placeholder
+        );
+
+        let thumbnail_path = dir.path().join("a.jpg");
+        assert!(service.ensure_thumbnail(Path::new("a.gif"), &thumbnail_path, "file"));
+        assert_eq!(std::fs::read(&thumbnail_path).unwrap(), b"frame\n");
+
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.starts_with("-ss 0.00 -i a.gif -vframes 1"));
+
+        // 图片只有一帧，frame 也从开头截取，且不尝试提取封面
+        std::fs::remove_file(&args_log).unwrap();
+        assert!(service.ensure_thumbnail(Path::new("a.png"), &thumbnail_path, "file"));
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.starts_with("-ss 0.00 -i a.png -vframes 1"));
+
+        // 直接生成（后台任务、同步读取元数据）同样按生成方式执行
+        std::fs::remove_file(&args_log).unwrap();
+        service
+            .try_generate_thumbnail(Path::new("a.mp4"), &thumbnail_path)
+            .unwrap();
+        let metadata = service.extract_video_info(Path::new("b.mp4"), &dir.path().join("b.jpg"));
+        assert!(metadata.thumbnail_path.is_some());
+        let log = std::fs::read_to_string(&args_log).unwrap();
+        let calls: Vec<&str> = log.lines().collect();
+        assert_eq!(calls.len(), 2, "{}", log);
+        assert!(calls
+            .iter()
+            .all(|call| call.starts_with("-i ") && call.contains(".svg") && !call.contains("-ss")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hung_ffmpeg_is_killed_after_timeout() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(&fake_ffmpeg, "#!/bin/sh\nexec sleep 10\n").unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            timeout: Some(Duration::from_millis(200)),
+            long_timeout: Some(Duration::from_millis(300)),
+            ..FFmpegConfig::default()
+        });
+
+        let start = std::time::Instant::now();
+        let thumbnail_path = dir.path().join("a.jpg");
+        let err = service
+            .try_generate_thumbnail(Path::new("a.mp4"), &thumbnail_path)
+            .unwrap_err();
+        assert!(
+            err.ends_with("fake-ffmpeg 执行超过 200ms，已终止进程"),
+            "{}",
+            err
+        );
+        assert!(!thumbnail_path.exists());
+        assert!(!temp_path_for(&thumbnail_path).exists());
+
+        let err = service.check_decodes(Path::new("a.mp4")).unwrap_err();
+        assert!(
+            err.ends_with("fake-ffmpeg 执行超过 300ms，已终止进程"),
+            "{}",
+            err
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_failed_generation_leaves_no_partial_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let final_path = dir.path().join("a.jpg");
+        let temp_path = temp_path_for(&final_path);
+
+        // 模拟 ffmpeg 中途退出：写入了部分数据但返回非零状态
+        std::fs::write(&temp_path, b"partial").unwrap();
+        assert!(!commit_temp_output(&temp_path, &final_path, false));
+        assert!(!temp_path.exists());
+        assert!(!final_path.exists());
+
+        // 无效的输入文件同样不能留下任何输出
+        let bogus_video = dir.path().join("bogus.mp4");
+        std::fs::write(&bogus_video, b"not a video").unwrap();
+        let service = FFmpegService::with_defaults();
+        assert!(!service.generate_thumbnail(&bogus_video, &final_path));
+        assert!(!temp_path.exists());
+        assert!(!final_path.exists());
+    }
+
+    #[test]
+    fn test_successful_generation_renames_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let final_path = dir.path().join("a.jpg");
+        let temp_path = temp_path_for(&final_path);
+
+        std::fs::write(&temp_path, b"jpeg data").unwrap();
+        assert!(commit_temp_output(&temp_path, &final_path, true));
+        assert!(!temp_path.exists());
+        assert_eq!(std::fs::read(&final_path).unwrap(), b"jpeg data");
     }
 }
@@ -0,0 +1,519 @@
+//! FFmpeg 命令参数构建器
+//!
+//! 所有 ffmpeg/ffprobe 调用都先通过 [`FfmpegCommand`] 构建参数列表再执行，
+//! 测试可以直接断言参数，而无需真正运行 ffmpeg。
+
+use std::path::Path;
+
+/// ffmpeg/ffprobe 参数构建器
+#[derive(Debug, Clone, Default)]
+pub struct FfmpegCommand {
+    args: Vec<String>,
+}
+
+impl FfmpegCommand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加单个参数
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// 添加选项及其值，例如 `-vf scale=320:-1`
+    pub fn option(self, key: &str, value: impl Into<String>) -> Self {
+        self.arg(key).arg(value)
+    }
+
+    /// 只输出错误日志（`-v error`）
+    pub fn quiet(self) -> Self {
+        self.option("-v", "error")
+    }
+
+    /// 输入文件（`-i <path>`）
+    pub fn input(self, path: &Path) -> Self {
+        self.option("-i", path.to_string_lossy())
+    }
+
+    /// 从指定时间点开始读取（`-ss`，放在输入之前以快速定位）
+    pub fn seek(self, seconds: f32) -> Self {
+        self.option("-ss", format!("{:.2}", seconds))
+    }
+
+    /// 覆盖已存在的输出文件并写入到指定路径
+    pub fn output(self, path: &Path) -> Self {
+        self.arg("-y").arg(path.to_string_lossy())
+    }
+
+    /// 生成参数列表
+    pub fn build(self) -> Vec<String> {
+        self.args
+    }
+}
+
+/// 截取单帧生成缩略图的参数，`label` 不为空时用 drawtext 在右下角叠加文字
+pub fn thumbnail_args(
+    input: &Path,
+    output: &Path,
+    seek_time: f32,
+    width: u32,
+    quality: u8,
+    label: Option<&str>,
+) -> Vec<String> {
+    let mut filter = format!("scale={}:-1", width);
+    if let Some(label) = label {
+        filter.push(',');
+        filter.push_str(&drawtext_filter(label));
+    }
+
+    FfmpegCommand::new()
+        .seek(seek_time)
+        .input(input)
+        .option("-vframes", "1")
+        .option("-vf", filter)
+        .option("-q:v", quality.to_string())
+        .output(output)
+        .build()
+}
+
+/// 提取内嵌封面图生成缩略图的参数
+///
+/// `-map 0:v -map -0:V` 只保留 attached_pic 视频流（`V` 不包含封面图），
+/// 文件没有封面时 ffmpeg 因没有可输出的流而失败
+pub fn cover_art_args(input: &Path, output: &Path, width: u32, quality: u8) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .input(input)
+        .option("-map", "0:v")
+        .option("-map", "-0:V")
+        .option("-frames:v", "1")
+        .option("-vf", format!("scale={}:-1", width))
+        .option("-q:v", quality.to_string())
+        .output(output)
+        .build()
+}
+
+/// 在右下角绘制半透明底色文字的 drawtext 滤镜
+fn drawtext_filter(label: &str) -> String {
+    format!(
+        "drawtext=text={}:fontcolor=white:fontsize=h/12:box=1:boxcolor=black@0.5:boxborderw=4:x=w-tw-8:y=h-th-8",
+        escape_filter_value(label)
+    )
+}
+
+/// 转义滤镜参数值：先按 drawtext 选项转义，再按滤镜图语法转义
+fn escape_filter_value(value: &str) -> String {
+    let escape = |value: &str, special: &[char]| {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let option = escape(value, &['\\', '\'', ':', '%']);
+    escape(&option, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+/// 截取单帧并以 JPEG 输出到标准输出的参数（`-f image2pipe`，不写入文件）
+pub fn frame_args(input: &Path, seek_time: f32, quality: u8) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .seek(seek_time)
+        .input(input)
+        .option("-vframes", "1")
+        .option("-f", "image2pipe")
+        .option("-vcodec", "mjpeg")
+        .option("-q:v", quality.to_string())
+        .arg("pipe:1")
+        .build()
+}
+
+/// 实时转码为分片 MP4 并输出到标准输出的参数
+///
+/// `empty_moov` + `frag_keyframe` 使输出不需要回写文件头，播放器收到第一个分片即可开始播放
+pub fn transcode_fmp4_args(input: &Path, video_encoder: &str) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .input(input)
+        .option("-map", "0:v:0")
+        .option("-map", "0:a:0?")
+        .option("-c:v", video_encoder)
+        .option("-preset", "veryfast")
+        .option("-c:a", "aac")
+        .option("-movflags", "frag_keyframe+empty_moov+default_base_moof")
+        .option("-f", "mp4")
+        .arg("pipe:1")
+        .build()
+}
+
+/// 每隔 `interval` 秒截取一帧，缩放为 `tile_width`x`tile_height` 后拼接为
+/// `columns`x`rows` 的雪碧图（只输出一张图片）
+pub fn storyboard_args(
+    input: &Path,
+    output: &Path,
+    interval: u32,
+    tile: (u32, u32),
+    grid: (u32, u32),
+    quality: u8,
+) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .input(input)
+        .option(
+            "-vf",
+            format!(
+                "fps=1/{},scale={}:{},tile={}x{}",
+                interval, tile.0, tile.1, grid.0, grid.1
+            ),
+        )
+        .option("-frames:v", "1")
+        .option("-q:v", quality.to_string())
+        .output(output)
+        .build()
+}
+
+/// 完整解码一遍但不输出的参数（`-f null -`），用于检查文件是否损坏
+pub fn decode_check_args(input: &Path) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .input(input)
+        .option("-f", "null")
+        .arg("-")
+        .build()
+}
+
+/// 将 SVG 占位图转换为 JPG 的参数
+pub fn placeholder_args(input: &Path, output: &Path) -> Vec<String> {
+    FfmpegCommand::new().input(input).output(output).build()
+}
+
+/// 读取视频宽高和时长的 ffprobe 参数（CSV 输出：width,height,duration）
+pub fn probe_metadata_args(input: &Path) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .option("-select_streams", "v:0")
+        .option(
+            "-show_entries",
+            "stream=width,height,duration:format=duration",
+        )
+        .option("-of", "csv=p=0:s=,")
+        .arg(input.to_string_lossy())
+        .build()
+}
+
+/// 读取图片宽高的 ffprobe 参数（输出：`宽x高`）
+pub fn image_dimensions_args(input: &Path) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .option("-select_streams", "v:0")
+        .option("-show_entries", "stream=width,height")
+        .option("-of", "csv=s=x:p=0")
+        .arg(input.to_string_lossy())
+        .build()
+}
+
+/// 读取完整格式和流信息的 ffprobe 参数（JSON 输出，用于调试）
+pub fn probe_json_args(input: &Path) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .arg("-show_format")
+        .arg("-show_streams")
+        .option("-of", "json")
+        .arg(input.to_string_lossy())
+        .build()
+}
+
+/// 读取章节信息的 ffprobe 参数（JSON 输出）
+pub fn chapters_args(input: &Path) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .arg("-show_chapters")
+        .option("-of", "json")
+        .arg(input.to_string_lossy())
+        .build()
+}
+
+/// 将 M3U8 播放列表无损合并为 MP4 的参数
+pub fn merge_m3u8_args(playlist: &Path, output: &Path) -> Vec<String> {
+    FfmpegCommand::new()
+        .option("-allowed_extensions", "ALL")
+        .input(playlist)
+        .option("-c", "copy")
+        .option("-bsf:a", "aac_adtstoasc")
+        .output(output)
+        .build()
+}
+
+/// 将 MP4 无损切片为 HLS 的参数：分片写入 `segment_%03d.ts`，播放列表写入 `playlist`
+pub fn package_hls_args(
+    input: &Path,
+    out_dir: &Path,
+    playlist: &Path,
+    segment_secs: u32,
+) -> Vec<String> {
+    FfmpegCommand::new()
+        .quiet()
+        .input(input)
+        .option("-c", "copy")
+        .option("-f", "hls")
+        .option("-hls_time", segment_secs.to_string())
+        .option("-hls_playlist_type", "vod")
+        .option(
+            "-hls_segment_filename",
+            out_dir.join("segment_%03d.ts").to_string_lossy(),
+        )
+        .output(playlist)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_args() {
+        assert_eq!(
+            thumbnail_args(Path::new("in.mp4"), Path::new("out.jpg"), 1.0, 320, 2, None),
+            vec![
+                "-ss",
+                "1.00",
+                "-i",
+                "in.mp4",
+                "-vframes",
+                "1",
+                "-vf",
+                "scale=320:-1",
+                "-q:v",
+                "2",
+                "-y",
+                "out.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_args_with_label() {
+        let args = thumbnail_args(
+            Path::new("in.mp4"),
+            Path::new("out.jpg"),
+            1.0,
+            320,
+            2,
+            Some("HD: 1080p"),
+        );
+        let filter = &args[args.iter().position(|a| a == "-vf").unwrap() + 1];
+        assert!(filter.starts_with("scale=320:-1,drawtext=text=HD\\\\: 1080p:"));
+        assert!(filter.contains(":x=w-tw-8:y=h-th-8"));
+    }
+
+    #[test]
+    fn test_cover_art_args() {
+        assert_eq!(
+            cover_art_args(Path::new("in.mkv"), Path::new("out.jpg"), 320, 2),
+            vec![
+                "-v",
+                "error",
+                "-i",
+                "in.mkv",
+                "-map",
+                "0:v",
+                "-map",
+                "-0:V",
+                "-frames:v",
+                "1",
+                "-vf",
+                "scale=320:-1",
+                "-q:v",
+                "2",
+                "-y",
+                "out.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frame_args() {
+        assert_eq!(
+            frame_args(Path::new("in.mp4"), 12.5, 2),
+            vec![
+                "-v",
+                "error",
+                "-ss",
+                "12.50",
+                "-i",
+                "in.mp4",
+                "-vframes",
+                "1",
+                "-f",
+                "image2pipe",
+                "-vcodec",
+                "mjpeg",
+                "-q:v",
+                "2",
+                "pipe:1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_storyboard_args() {
+        assert_eq!(
+            storyboard_args(
+                Path::new("in.mp4"),
+                Path::new("sprite.jpg"),
+                10,
+                (160, 90),
+                (10, 2),
+                5
+            ),
+            vec![
+                "-v",
+                "error",
+                "-i",
+                "in.mp4",
+                "-vf",
+                "fps=1/10,scale=160:90,tile=10x2",
+                "-frames:v",
+                "1",
+                "-q:v",
+                "5",
+                "-y",
+                "sprite.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_check_args() {
+        assert_eq!(
+            decode_check_args(Path::new("in.mp4")),
+            vec!["-v", "error", "-i", "in.mp4", "-f", "null", "-"]
+        );
+    }
+
+    #[test]
+    fn test_probe_args() {
+        assert_eq!(
+            probe_metadata_args(Path::new("in.mp4")),
+            vec![
+                "-v",
+                "error",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=width,height,duration:format=duration",
+                "-of",
+                "csv=p=0:s=,",
+                "in.mp4"
+            ]
+        );
+        assert_eq!(
+            image_dimensions_args(Path::new("a.jpg")),
+            vec![
+                "-v",
+                "error",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=width,height",
+                "-of",
+                "csv=s=x:p=0",
+                "a.jpg"
+            ]
+        );
+        assert_eq!(
+            probe_json_args(Path::new("in.mp4")),
+            vec![
+                "-v",
+                "error",
+                "-show_format",
+                "-show_streams",
+                "-of",
+                "json",
+                "in.mp4"
+            ]
+        );
+        assert_eq!(
+            chapters_args(Path::new("in.mp4")),
+            vec!["-v", "error", "-show_chapters", "-of", "json", "in.mp4"]
+        );
+    }
+
+    #[test]
+    fn test_merge_m3u8_args() {
+        assert_eq!(
+            merge_m3u8_args(Path::new("index.m3u8"), Path::new("out.mp4")),
+            vec![
+                "-allowed_extensions",
+                "ALL",
+                "-i",
+                "index.m3u8",
+                "-c",
+                "copy",
+                "-bsf:a",
+                "aac_adtstoasc",
+                "-y",
+                "out.mp4"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_package_hls_args() {
+        let out_dir = Path::new("out");
+        assert_eq!(
+            package_hls_args(Path::new("in.mp4"), out_dir, &out_dir.join("index.m3u8"), 6),
+            vec![
+                "-v",
+                "error",
+                "-i",
+                "in.mp4",
+                "-c",
+                "copy",
+                "-f",
+                "hls",
+                "-hls_time",
+                "6",
+                "-hls_playlist_type",
+                "vod",
+                "-hls_segment_filename",
+                "out/segment_%03d.ts",
+                "-y",
+                "out/index.m3u8"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transcode_fmp4_args() {
+        assert_eq!(
+            transcode_fmp4_args(Path::new("in.mkv"), "libx264"),
+            vec![
+                "-v",
+                "error",
+                "-i",
+                "in.mkv",
+                "-map",
+                "0:v:0",
+                "-map",
+                "0:a:0?",
+                "-c:v",
+                "libx264",
+                "-preset",
+                "veryfast",
+                "-c:a",
+                "aac",
+                "-movflags",
+                "frag_keyframe+empty_moov+default_base_moof",
+                "-f",
+                "mp4",
+                "pipe:1"
+            ]
+        );
+    }
+}
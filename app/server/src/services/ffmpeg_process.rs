@@ -0,0 +1,117 @@
+//! 带超时的子进程执行
+//!
+//! ffmpeg 处理损坏的文件时可能一直不退出，`Command::output` 会因此永久阻塞任务队列的工作线程。
+//! 这里的 [`OutputWithTimeout`] 在超时后终止子进程并返回 `TimedOut` 错误。
+
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// 检查子进程是否退出的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 带超时的 `Command::output`
+pub trait OutputWithTimeout {
+    /// 执行命令并收集输出，与 `Command::output` 相同，但超过 `timeout` 时终止子进程
+    ///
+    /// `timeout` 为 None 时不限制执行时间。超时返回 [`io::ErrorKind::TimedOut`] 错误
+    fn output_with_timeout(&mut self, timeout: Option<Duration>) -> io::Result<Output>;
+}
+
+impl OutputWithTimeout for Command {
+    fn output_with_timeout(&mut self, timeout: Option<Duration>) -> io::Result<Output> {
+        match timeout {
+            Some(timeout) => output_until(self, timeout),
+            None => self.output(),
+        }
+    }
+}
+
+fn output_until(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // 在后台线程读取输出，避免管道写满后子进程阻塞
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let Some(status) = wait_until(&mut child, Instant::now() + timeout)? else {
+        let _ = child.kill();
+        let _ = child.wait();
+        // 子进程派生的进程可能仍持有管道，不等待读取线程结束
+        let program = Path::new(command.get_program())
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("{} 执行超过 {:?}，已终止进程", program, timeout),
+        ));
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// 等待子进程退出，到达 `deadline` 仍未退出时返回 None
+fn wait_until(
+    child: &mut Child,
+    deadline: Instant,
+) -> io::Result<Option<std::process::ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hung_process_is_killed_after_timeout() {
+        let start = Instant::now();
+        let err = Command::new("sleep")
+            .arg("10")
+            .output_with_timeout(Some(Duration::from_millis(200)))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(err.to_string(), "sleep 执行超过 200ms，已终止进程");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_output_is_collected_within_timeout() {
+        let output = Command::new("sh")
+            .args(["-c", "echo out; echo err >&2; exit 3"])
+            .output_with_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+}
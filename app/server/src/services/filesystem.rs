@@ -3,31 +3,66 @@
 //! 提供文件系统相关的操作，包括：
 //! - 缩略图目录初始化
 //! - 批量缩略图生成
+//! - 删除文件后清理空目录
 
-use log::{debug, info};
+use log::{debug, info, warn};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use std::path::Path as StdPath;
 use std::time::Instant;
+use walkdir::WalkDir;
 
+use crate::config::{get_config, ThumbnailMode};
+use crate::services::db::VideoDao;
 use crate::services::ffmpeg::get_ffmpeg_service;
-use crate::utils::get_files_without_thumbnails;
+use crate::utils::{get_files_without_thumbnails, is_video_or_container, IgnoreMatcher};
+use crate::DiskMapping;
 
 /// 使用自定义数据源目录初始化缩略图目录
 pub fn initialize_thumbnails_with_source(source_dirs: &[String]) {
+    initialize_thumbnails_in(
+        source_dirs,
+        StdPath::new("thumbnails"),
+        get_config().thumbnail.mode,
+    );
+}
+
+/// 创建缩略图目录，eager 模式下为缺少缩略图的文件生成缩略图，返回需要生成的文件数量
+///
+/// lazy 模式只创建目录，缩略图在第一次请求时生成
+fn initialize_thumbnails_in(
+    source_dirs: &[String],
+    thumbnails_path: &StdPath,
+    mode: ThumbnailMode,
+) -> usize {
     let start = Instant::now();
-    let thumbnails_path = StdPath::new("thumbnails");
 
-    // 创建 thumbnails 目录
+    // 创建 thumbnails 目录，失败时只记录警告，缩略图会在之后按需生成
     if !thumbnails_path.exists() {
-        std::fs::create_dir(thumbnails_path).expect("Failed to create thumbnails directory");
-        info!("已创建缩略图目录");
+        match std::fs::create_dir_all(thumbnails_path) {
+            Ok(_) => info!("已创建缩略图目录"),
+            Err(e) => warn!("创建缩略图目录失败: {}", e),
+        }
+    }
+
+    if mode == ThumbnailMode::Lazy {
+        info!("缩略图为按需生成模式，跳过启动时的生成");
+        return 0;
     }
 
     let mut total_files_without_thumbnails = 0;
+    let ignore = IgnoreMatcher::new(&get_config().scan.ignore);
 
     for source_dir in source_dirs {
         let source_path = StdPath::new(source_dir);
-        let files_without_thumbnails = get_files_without_thumbnails(source_path, thumbnails_path);
+        if !source_path.is_dir() {
+            warn!("数据源目录不存在，跳过缩略图生成: {}", source_dir);
+            continue;
+        }
+        let files_without_thumbnails: Vec<_> =
+            get_files_without_thumbnails(source_path, thumbnails_path)
+                .into_iter()
+                .filter(|(_, file)| !ignore.is_ignored(file))
+                .collect();
 
         if files_without_thumbnails.is_empty() {
             debug!("目录 {} 的所有文件都已有缩略图", source_dir);
@@ -59,21 +94,7 @@ pub fn initialize_thumbnails_with_source(source_dirs: &[String]) {
                 }
 
                 // 使用 FFmpeg 服务生成缩略图
-                let extension = file
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-
-                if extension == "mp4"
-                    || extension == "avi"
-                    || extension == "mkv"
-                    || extension == "mov"
-                {
-                    ffmpeg.generate_thumbnail(file, &thumbnail_path);
-                } else {
-                    ffmpeg.generate_placeholder_thumbnail(&thumbnail_path, "media");
-                }
+                ffmpeg.ensure_thumbnail(file, &thumbnail_path, "media");
             });
     }
 
@@ -86,4 +107,73 @@ pub fn initialize_thumbnails_with_source(source_dirs: &[String]) {
     } else {
         info!("所有文件都已有缩略图，耗时: {:?}", start.elapsed());
     }
+    total_files_without_thumbnails
+}
+
+/// 删除文件后，向上清理不再包含媒体文件的目录及其数据库记录
+///
+/// 只处理数据源根目录内部的目录，根目录本身永远不会被删除。
+/// 目录中仍有其他（非媒体）文件时只删除数据库记录，保留磁盘上的目录。
+/// 返回被清理的目录路径。
+pub fn remove_empty_parent_dirs(
+    video_dao: &VideoDao,
+    file_path: &StdPath,
+    mappings: &[DiskMapping],
+) -> Vec<String> {
+    let Some(root) = mappings
+        .iter()
+        .map(|mapping| StdPath::new(&mapping.physical_path))
+        .find(|root| file_path.starts_with(root))
+    else {
+        return Vec::new();
+    };
+
+    let mut removed = Vec::new();
+    for dir in file_path.ancestors().skip(1) {
+        if dir == root || !dir.starts_with(root) || contains_media(dir) {
+            break;
+        }
+
+        let dir_str = dir.to_string_lossy().to_string();
+        if let Err(e) = video_dao.delete_from_database_by_path(&dir_str) {
+            warn!("删除目录记录失败 {}: {}", dir_str, e);
+            break;
+        }
+        // remove_dir 只能删除空目录，目录中还有其他文件时保留
+        match std::fs::remove_dir(dir) {
+            Ok(_) => info!("已删除空目录: {}", dir_str),
+            Err(e) => debug!("保留目录 {}: {}", dir_str, e),
+        }
+        removed.push(dir_str);
+    }
+
+    removed
+}
+
+/// 目录（递归）中是否还有媒体文件
+fn contains_media(dir: &StdPath) -> bool {
+    WalkDir::new(dir)
+        .follow_links(get_config().scan.follow_symlinks)
+        .into_iter()
+        .filter_map(Result::ok)
+        .any(|entry| is_video_or_container(entry.path()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_mode_skips_startup_generation() {
+        let source = tempfile::tempdir().unwrap();
+        std::fs::write(source.path().join("a.mp4"), b"not really a video").unwrap();
+        let thumbnails = tempfile::tempdir().unwrap();
+        let thumbnails_path = thumbnails.path().join("thumbnails");
+
+        let source_dirs = vec![source.path().to_string_lossy().to_string()];
+        let pending = initialize_thumbnails_in(&source_dirs, &thumbnails_path, ThumbnailMode::Lazy);
+        assert_eq!(pending, 0);
+        assert!(thumbnails_path.is_dir());
+        assert_eq!(std::fs::read_dir(&thumbnails_path).unwrap().count(), 0);
+    }
 }
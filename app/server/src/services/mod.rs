@@ -1,8 +1,19 @@
 pub mod db;
 pub mod ffmpeg;
+pub mod ffmpeg_command;
+pub mod ffmpeg_process;
 pub mod filesystem;
+pub mod rebuild;
 pub mod task_queue;
+pub mod upload;
+pub mod validation;
+pub mod watcher;
 
-pub use db::{DirectorySync, VideoDao, VideoDbManager};
+pub use db::{
+    AuditReport, DirectorySync, DirectoryTotals, HistogramMetric, ListCursor, RefreshedFile,
+    SyncReport, TopBy, VideoDao, VideoDbManager, VideoFilter, VideoSort,
+};
 pub use filesystem::initialize_thumbnails_with_source;
+pub use rebuild::RebuildTracker;
 pub use task_queue::{get_task_queue, init_task_queue};
+pub use watcher::{start_watcher_if_enabled, FileWatcher};
@@ -0,0 +1,155 @@
+//! 数据库重建服务
+//!
+//! 删除并重新创建 videos 表，然后执行一次强制全量同步，用于数据库损坏后的恢复。
+
+use log::{error, info};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::services::db::{DirectorySync, SyncReport, VideoDbManager};
+use crate::DiskMapping;
+
+/// 重建任务状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebuildState {
+    Idle,
+    Running,
+    Completed,
+    Failed(String),
+}
+
+/// 重建任务信息
+#[derive(Debug, Clone)]
+pub struct RebuildStatus {
+    pub job_id: u64,
+    pub state: RebuildState,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+/// 重建任务跟踪器
+///
+/// 同一时间只允许一个重建任务运行
+pub struct RebuildTracker {
+    job_id_counter: AtomicU64,
+    status: Mutex<RebuildStatus>,
+}
+
+impl Default for RebuildTracker {
+    fn default() -> Self {
+        Self {
+            job_id_counter: AtomicU64::new(1),
+            status: Mutex::new(RebuildStatus {
+                job_id: 0,
+                state: RebuildState::Idle,
+                started_at: None,
+                finished_at: None,
+            }),
+        }
+    }
+}
+
+impl RebuildTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 尝试开始新的重建任务，已有任务运行时返回 None
+    pub fn try_start(&self) -> Option<u64> {
+        let mut status = self.status.lock().unwrap();
+        if status.state == RebuildState::Running {
+            return None;
+        }
+
+        let job_id = self.job_id_counter.fetch_add(1, Ordering::SeqCst);
+        *status = RebuildStatus {
+            job_id,
+            state: RebuildState::Running,
+            started_at: Some(now()),
+            finished_at: None,
+        };
+        Some(job_id)
+    }
+
+    /// 标记重建任务结束
+    pub fn finish(&self, job_id: u64, result: Result<(), String>) {
+        let mut status = self.status.lock().unwrap();
+        if status.job_id != job_id {
+            return;
+        }
+
+        status.state = match result {
+            Ok(_) => RebuildState::Completed,
+            Err(e) => RebuildState::Failed(e),
+        };
+        status.finished_at = Some(now());
+    }
+
+    /// 获取当前重建任务状态
+    pub fn status(&self) -> RebuildStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+/// 删除并重建所有表，然后从数据源目录执行强制全量同步
+pub fn rebuild_database(
+    db_manager: &VideoDbManager,
+    mappings: &[DiskMapping],
+) -> rusqlite::Result<SyncReport> {
+    info!("开始重建数据库");
+    db_manager.recreate_schema()?;
+
+    let sync = DirectorySync::new(db_manager);
+    match sync.initialize_from_directory_with_progress(mappings, true, false) {
+        Ok(report) => {
+            info!("数据库重建完成");
+            Ok(report)
+        }
+        Err(e) => {
+            error!("数据库重建失败: {}", e);
+            Err(e)
+        }
+    }
+}
+
+fn now() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::VideoDao;
+    use crate::test_utils::insert_video;
+
+    #[test]
+    fn test_rebuild_clears_rows_and_repopulates() {
+        let dir = tempfile::tempdir().unwrap();
+        let subtitle_path = dir.path().join("episode.vtt");
+        std::fs::write(&subtitle_path, "WEBVTT\n").unwrap();
+
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }];
+        let db_manager = VideoDbManager::in_memory();
+        insert_video(&db_manager, "/stale/old.mp4", "mp4", "/public/disk1");
+
+        rebuild_database(&db_manager, &mappings).unwrap();
+
+        let videos = VideoDao::new(&db_manager).get_root_videos().unwrap();
+        let paths: Vec<&str> = videos.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, vec![subtitle_path.to_string_lossy().as_ref()]);
+    }
+
+    #[test]
+    fn test_tracker_rejects_concurrent_rebuild() {
+        let tracker = RebuildTracker::new();
+        let job_id = tracker.try_start().unwrap();
+        assert!(tracker.try_start().is_none());
+
+        tracker.finish(job_id, Ok(()));
+        assert_eq!(tracker.status().state, RebuildState::Completed);
+        assert!(tracker.try_start().is_some());
+    }
+}
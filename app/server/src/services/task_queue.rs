@@ -3,77 +3,110 @@
 //! 提供异步任务队列管理，支持：
 //! - 任务优先级排队
 //! - 并发数控制
+//! - 暂停和恢复
 //! - 任务状态监控
 //! - 错误处理和重试
 
 use log::{debug, error, info, warn};
 use std::collections::VecDeque;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex, Semaphore};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::{mpsc, oneshot, Mutex, Notify, Semaphore};
 
 use crate::services::ffmpeg::{get_ffmpeg_service, VideoMetadata};
+use crate::services::validation::{get_validation_tracker, validate_file};
 
 /// 任务类型
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub enum TaskType {
     /// 生成视频缩略图
     GenerateThumbnail {
         video_path: PathBuf,
         thumbnail_path: PathBuf,
     },
+    /// 只生成视频缩略图的一个尺寸变体，`thumbnail_path` 为默认尺寸缩略图的路径
+    GenerateThumbnailVariant {
+        video_path: PathBuf,
+        thumbnail_path: PathBuf,
+        width: u32,
+    },
     /// 提取视频元数据
     ExtractMetadata {
         video_path: PathBuf,
         thumbnail_path: PathBuf,
     },
+    /// 完整解码检查文件是否损坏，结果记录到对应的校验批次
+    ValidateFile { video_path: PathBuf, run_id: u64 },
+    /// 将 MP4 切片为 HLS，输出到 `out_dir`
+    PackageHls {
+        video_path: PathBuf,
+        out_dir: PathBuf,
+        segment_secs: u32,
+    },
+}
+
+impl TaskType {
+    /// 任务类型名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskType::GenerateThumbnail { .. } => "generate_thumbnail",
+            TaskType::GenerateThumbnailVariant { .. } => "generate_thumbnail_variant",
+            TaskType::ExtractMetadata { .. } => "extract_metadata",
+            TaskType::ValidateFile { .. } => "validate_file",
+            TaskType::PackageHls { .. } => "package_hls",
+        }
+    }
+
+    /// 任务处理的文件
+    pub fn target_path(&self) -> &PathBuf {
+        match self {
+            TaskType::GenerateThumbnail { video_path, .. } => video_path,
+            TaskType::GenerateThumbnailVariant { video_path, .. } => video_path,
+            TaskType::ExtractMetadata { video_path, .. } => video_path,
+            TaskType::ValidateFile { video_path, .. } => video_path,
+            TaskType::PackageHls { video_path, .. } => video_path,
+        }
+    }
 }
 
-/// 任务优先级
+/// 任务优先级：有更高优先级的任务等待时，较低优先级的任务让出执行名额
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-#[allow(dead_code)]
 pub enum TaskPriority {
     Low = 0,
     Normal = 1,
     High = 2,
 }
 
-/// 任务状态
-#[derive(Debug, Clone, PartialEq, Eq)]
-#[allow(dead_code)]
-pub enum TaskStatus {
-    Pending,
-    Running,
-    Completed,
-    Failed(String),
-}
-
 /// 后台任务
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct BackgroundTask {
     pub id: u64,
     pub task_type: TaskType,
     pub priority: TaskPriority,
-    pub status: TaskStatus,
-    pub created_at: std::time::Instant,
 }
 
 /// 任务结果
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum TaskResult {
+    /// 缩略图生成完成，包含缩略图路径
     ThumbnailGenerated(PathBuf),
     MetadataExtracted(VideoMetadata),
-    Failed(String),
+    FileValidated,
+    /// HLS 切片完成，包含生成的播放列表路径
+    HlsPackaged(PathBuf),
 }
 
+/// 任务完成后接收结果的通道
+pub type TaskResultReceiver = oneshot::Receiver<std::result::Result<TaskResult, String>>;
+
 /// 任务执行器消息
 enum ExecutorMessage {
-    NewTask(BackgroundTask),
-    Shutdown,
+    /// 新任务，以及可选的结果通道
+    NewTask(
+        BackgroundTask,
+        Option<oneshot::Sender<std::result::Result<TaskResult, String>>>,
+    ),
 }
 
 /// 任务队列统计信息
@@ -83,6 +116,94 @@ pub struct QueueStats {
     pub running_count: usize,
     pub completed_count: u64,
     pub failed_count: u64,
+    /// 是否已暂停（暂停时不会开始新任务）
+    pub paused: bool,
+}
+
+/// 暂停开关：暂停时新任务停留在待处理队列中，恢复后继续执行
+#[derive(Default)]
+pub struct PauseGate {
+    paused: AtomicBool,
+    resumed: Notify,
+}
+
+impl PauseGate {
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.resumed.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// 暂停时等待恢复
+    async fn wait_until_resumed(&self) {
+        loop {
+            // 先注册通知再检查状态，避免错过检查之后的 resume
+            let resumed = self.resumed.notified();
+            if !self.is_paused() {
+                return;
+            }
+            resumed.await;
+        }
+    }
+}
+
+/// 正在执行的任务信息
+#[derive(Debug, Clone)]
+pub struct RunningTask {
+    pub id: u64,
+    pub task_type: &'static str,
+    pub target_path: PathBuf,
+    pub started_at: String,
+}
+
+/// 正在执行的任务列表
+#[derive(Default)]
+pub struct RunningTasks {
+    tasks: StdMutex<Vec<RunningTask>>,
+}
+
+impl RunningTasks {
+    /// 登记任务开始执行，返回的守卫被丢弃时自动移除该任务
+    pub fn start<'a>(&'a self, task: &BackgroundTask) -> RunningTaskGuard<'a> {
+        self.tasks.lock().unwrap().push(RunningTask {
+            id: task.id,
+            task_type: task.task_type.name(),
+            target_path: task.task_type.target_path().clone(),
+            started_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        });
+        RunningTaskGuard {
+            tasks: self,
+            id: task.id,
+        }
+    }
+
+    /// 获取正在执行的任务（按开始顺序）
+    pub fn list(&self) -> Vec<RunningTask> {
+        self.tasks.lock().unwrap().clone()
+    }
+
+    fn remove(&self, id: u64) {
+        self.tasks.lock().unwrap().retain(|t| t.id != id);
+    }
+}
+
+/// 正在执行任务的守卫，任务结束（包括 panic）时移除登记
+pub struct RunningTaskGuard<'a> {
+    tasks: &'a RunningTasks,
+    id: u64,
+}
+
+impl Drop for RunningTaskGuard<'_> {
+    fn drop(&mut self) {
+        self.tasks.remove(self.id);
+    }
 }
 
 /// 后台任务队列管理器
@@ -99,6 +220,10 @@ pub struct TaskQueue {
     failed_count: Arc<AtomicU64>,
     /// 待处理任务队列（用于统计）
     pending_queue: Arc<Mutex<VecDeque<BackgroundTask>>>,
+    /// 正在执行的任务
+    running_tasks: Arc<RunningTasks>,
+    /// 暂停开关
+    pause_gate: Arc<PauseGate>,
 }
 
 impl TaskQueue {
@@ -109,6 +234,8 @@ impl TaskQueue {
         let completed_count = Arc::new(AtomicU64::new(0));
         let failed_count = Arc::new(AtomicU64::new(0));
         let pending_queue = Arc::new(Mutex::new(VecDeque::new()));
+        let running_tasks = Arc::new(RunningTasks::default());
+        let pause_gate = Arc::new(PauseGate::default());
 
         // 启动任务执行器
         Self::start_executor(
@@ -118,6 +245,8 @@ impl TaskQueue {
             completed_count.clone(),
             failed_count.clone(),
             pending_queue.clone(),
+            running_tasks.clone(),
+            pause_gate.clone(),
         );
 
         Self {
@@ -127,10 +256,13 @@ impl TaskQueue {
             completed_count,
             failed_count,
             pending_queue,
+            running_tasks,
+            pause_gate,
         }
     }
 
     /// 启动任务执行器
+    #[allow(clippy::too_many_arguments)]
     fn start_executor(
         mut receiver: mpsc::Receiver<ExecutorMessage>,
         max_concurrent: usize,
@@ -138,6 +270,8 @@ impl TaskQueue {
         completed_count: Arc<AtomicU64>,
         failed_count: Arc<AtomicU64>,
         pending_queue: Arc<Mutex<VecDeque<BackgroundTask>>>,
+        running_tasks: Arc<RunningTasks>,
+        pause_gate: Arc<PauseGate>,
     ) {
         let semaphore = Arc::new(Semaphore::new(max_concurrent));
 
@@ -146,31 +280,54 @@ impl TaskQueue {
 
             while let Some(msg) = receiver.recv().await {
                 match msg {
-                    ExecutorMessage::NewTask(task) => {
-                        // 从待处理队列移除
-                        {
-                            let mut queue = pending_queue.lock().await;
-                            queue.retain(|t| t.id != task.id);
-                        }
-
+                    ExecutorMessage::NewTask(task, result_sender) => {
                         let sem = semaphore.clone();
                         let running = running_count.clone();
                         let completed = completed_count.clone();
                         let failed = failed_count.clone();
+                        let running_tasks = running_tasks.clone();
+                        let pending_queue = pending_queue.clone();
+                        let pause_gate = pause_gate.clone();
 
                         // 在新的 tokio 任务中执行
                         tokio::spawn(async move {
-                            // 获取信号量许可
-                            let _permit = sem.acquire().await.unwrap();
+                            // 暂停时等待恢复再获取信号量许可；等待许可期间被暂停，
+                            // 或者有更高优先级的任务在等待时，放回许可重新排队
+                            let _permit = loop {
+                                pause_gate.wait_until_resumed().await;
+                                let permit = sem.acquire().await.unwrap();
+                                if pause_gate.is_paused() {
+                                    continue;
+                                }
+                                let outranked = pending_queue
+                                    .lock()
+                                    .await
+                                    .iter()
+                                    .any(|t| t.priority > task.priority);
+                                if !outranked {
+                                    break permit;
+                                }
+                                drop(permit);
+                                tokio::task::yield_now().await;
+                            };
+
+                            // 开始执行后才从待处理队列移除
+                            {
+                                let mut queue = pending_queue.lock().await;
+                                queue.retain(|t| t.id != task.id);
+                            }
                             running.fetch_add(1, Ordering::SeqCst);
 
                             debug!("开始执行任务 #{}: {:?}", task.id, task.task_type);
 
-                            let result = execute_task(&task).await;
+                            let result = {
+                                let _guard = running_tasks.start(&task);
+                                execute_task(&task).await
+                            };
 
                             running.fetch_sub(1, Ordering::SeqCst);
 
-                            match result {
+                            match &result {
                                 Ok(_) => {
                                     completed.fetch_add(1, Ordering::SeqCst);
                                     debug!("任务 #{} 完成", task.id);
@@ -180,12 +337,12 @@ impl TaskQueue {
                                     warn!("任务 #{} 失败: {}", task.id, e);
                                 }
                             }
+                            // 接收方已放弃等待时忽略发送失败
+                            if let Some(sender) = result_sender {
+                                let _ = sender.send(result);
+                            }
                         });
                     }
-                    ExecutorMessage::Shutdown => {
-                        info!("任务执行器收到关闭信号");
-                        break;
-                    }
                 }
             }
 
@@ -195,45 +352,93 @@ impl TaskQueue {
 
     /// 添加任务到队列
     pub async fn enqueue(&self, task_type: TaskType, priority: TaskPriority) -> u64 {
-        let task_id = self.task_id_counter.fetch_add(1, Ordering::SeqCst);
+        self.submit(task_type, priority, None).await
+    }
+
+    /// 添加任务到队列，并返回在任务结束时接收结果的通道
+    pub async fn enqueue_with_result(
+        &self,
+        task_type: TaskType,
+        priority: TaskPriority,
+    ) -> (u64, TaskResultReceiver) {
+        let (sender, receiver) = oneshot::channel();
+        let task_id = self.submit(task_type, priority, Some(sender)).await;
+        (task_id, receiver)
+    }
+
+    /// 添加任务到队列；同类型、同文件的任务已在等待或执行时不重复添加，返回 None
+    pub async fn enqueue_unique_with_result(
+        &self,
+        task_type: TaskType,
+        priority: TaskPriority,
+    ) -> Option<(u64, TaskResultReceiver)> {
+        let (sender, receiver) = oneshot::channel();
+        let task_id = self
+            .submit_if(task_type, priority, Some(sender), |queue, task| {
+                let (name, target) = (task.task_type.name(), task.task_type.target_path());
+                !queue
+                    .iter()
+                    .any(|t| t.task_type.name() == name && t.task_type.target_path() == target)
+                    && !self
+                        .running_tasks
+                        .list()
+                        .iter()
+                        .any(|t| t.task_type == name && &t.target_path == target)
+            })
+            .await?;
+        Some((task_id, receiver))
+    }
+
+    async fn submit(
+        &self,
+        task_type: TaskType,
+        priority: TaskPriority,
+        result_sender: Option<oneshot::Sender<std::result::Result<TaskResult, String>>>,
+    ) -> u64 {
+        self.submit_if(task_type, priority, result_sender, |_, _| true)
+            .await
+            .expect("unconditional submit")
+    }
 
+    /// `accept` 在持有待处理队列锁时检查，返回 false 时不添加任务
+    async fn submit_if(
+        &self,
+        task_type: TaskType,
+        priority: TaskPriority,
+        result_sender: Option<oneshot::Sender<std::result::Result<TaskResult, String>>>,
+        accept: impl FnOnce(&VecDeque<BackgroundTask>, &BackgroundTask) -> bool,
+    ) -> Option<u64> {
         let task = BackgroundTask {
-            id: task_id,
+            id: 0,
             task_type,
             priority,
-            status: TaskStatus::Pending,
-            created_at: std::time::Instant::now(),
         };
 
         // 添加到待处理队列（用于统计）
-        {
+        let task = {
             let mut queue = self.pending_queue.lock().await;
+            if !accept(&queue, &task) {
+                return None;
+            }
+            let task = BackgroundTask {
+                id: self.task_id_counter.fetch_add(1, Ordering::SeqCst),
+                ..task
+            };
             queue.push_back(task.clone());
-        }
+            task
+        };
+        let task_id = task.id;
 
         // 发送到执行器
-        if let Err(e) = self.sender.send(ExecutorMessage::NewTask(task)).await {
+        if let Err(e) = self
+            .sender
+            .send(ExecutorMessage::NewTask(task, result_sender))
+            .await
+        {
             error!("发送任务失败: {}", e);
         }
 
-        task_id
-    }
-
-    /// 批量添加缩略图生成任务
-    #[allow(dead_code)]
-    pub async fn enqueue_thumbnail_batch(&self, tasks: Vec<(PathBuf, PathBuf)>) -> Vec<u64> {
-        let mut task_ids = Vec::with_capacity(tasks.len());
-
-        for (video_path, thumbnail_path) in tasks {
-            let task_type = TaskType::GenerateThumbnail {
-                video_path,
-                thumbnail_path,
-            };
-            let id = self.enqueue(task_type, TaskPriority::Normal).await;
-            task_ids.push(id);
-        }
-
-        task_ids
+        Some(task_id)
     }
 
     /// 获取队列统计信息
@@ -245,13 +450,29 @@ impl TaskQueue {
             running_count: self.running_count.load(Ordering::SeqCst),
             completed_count: self.completed_count.load(Ordering::SeqCst),
             failed_count: self.failed_count.load(Ordering::SeqCst),
+            paused: self.pause_gate.is_paused(),
         }
     }
 
-    /// 关闭任务队列
-    #[allow(dead_code)]
-    pub async fn shutdown(&self) {
-        let _ = self.sender.send(ExecutorMessage::Shutdown).await;
+    /// 暂停任务队列：正在执行的任务继续完成，新任务保持待处理状态
+    pub fn pause(&self) {
+        if !self.pause_gate.is_paused() {
+            info!("任务队列已暂停");
+        }
+        self.pause_gate.pause();
+    }
+
+    /// 恢复任务队列
+    pub fn resume(&self) {
+        if self.pause_gate.is_paused() {
+            info!("任务队列已恢复");
+        }
+        self.pause_gate.resume();
+    }
+
+    /// 获取正在执行的任务
+    pub fn running_tasks(&self) -> Vec<RunningTask> {
+        self.running_tasks.list()
     }
 }
 
@@ -264,12 +485,20 @@ async fn execute_task(task: &BackgroundTask) -> std::result::Result<TaskResult,
             video_path,
             thumbnail_path,
         } => {
-            if ffmpeg.generate_thumbnail(video_path, thumbnail_path) {
-                Ok(TaskResult::ThumbnailGenerated(thumbnail_path.clone()))
-            } else {
-                Err("缩略图生成失败".to_string())
-            }
+            // 失败信息包含 ffmpeg stderr 的末尾几行
+            ffmpeg
+                .try_generate_thumbnail(video_path, thumbnail_path)
+                .map(|_| TaskResult::ThumbnailGenerated(thumbnail_path.clone()))
+                .map_err(|e| format!("缩略图生成失败: {}", e))
         }
+        TaskType::GenerateThumbnailVariant {
+            video_path,
+            thumbnail_path,
+            width,
+        } => ffmpeg
+            .generate_thumbnail_variant(video_path, thumbnail_path, *width)
+            .map(TaskResult::ThumbnailGenerated)
+            .map_err(|e| format!("缩略图生成失败: {}", e)),
         TaskType::ExtractMetadata {
             video_path,
             thumbnail_path,
@@ -277,6 +506,19 @@ async fn execute_task(task: &BackgroundTask) -> std::result::Result<TaskResult,
             let metadata = ffmpeg.extract_video_info(video_path, thumbnail_path);
             Ok(TaskResult::MetadataExtracted(metadata))
         }
+        TaskType::ValidateFile { video_path, run_id } => {
+            // 解码出错记录在校验报告中，任务本身视为完成
+            validate_file(ffmpeg, get_validation_tracker(), *run_id, video_path);
+            Ok(TaskResult::FileValidated)
+        }
+        TaskType::PackageHls {
+            video_path,
+            out_dir,
+            segment_secs,
+        } => ffmpeg
+            .package_hls(video_path, out_dir, *segment_secs)
+            .map(TaskResult::HlsPackaged)
+            .map_err(|e| format!("HLS 切片失败: {}", e)),
     }
 }
 
@@ -292,3 +534,111 @@ pub fn get_task_queue() -> &'static TaskQueue {
 pub fn init_task_queue(max_concurrent: usize) {
     let _ = TASK_QUEUE.set(TaskQueue::new(max_concurrent));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thumbnail_task(id: u64, path: &str) -> BackgroundTask {
+        BackgroundTask {
+            id,
+            task_type: TaskType::GenerateThumbnail {
+                video_path: PathBuf::from(path),
+                thumbnail_path: PathBuf::from("thumbnails/a.jpg"),
+            },
+            priority: TaskPriority::Normal,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paused_tasks_stay_pending_until_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = TaskQueue::new(2);
+        queue.pause();
+
+        for name in ["a", "b", "c"] {
+            let task_type = TaskType::GenerateThumbnail {
+                video_path: dir.path().join(format!("{}.mp4", name)),
+                thumbnail_path: dir.path().join(format!("{}.jpg", name)),
+            };
+            queue.enqueue(task_type, TaskPriority::Normal).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let stats = queue.get_stats().await;
+        assert!(stats.paused);
+        assert_eq!(stats.pending_count, 3);
+        assert_eq!(stats.running_count, 0);
+        assert_eq!(stats.completed_count + stats.failed_count, 0);
+
+        // 源文件不存在，任务会失败，但都会被执行
+        queue.resume();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        loop {
+            let stats = queue.get_stats().await;
+            if stats.completed_count + stats.failed_count == 3 {
+                assert!(!stats.paused);
+                assert_eq!(stats.pending_count, 0);
+                break;
+            }
+            assert!(std::time::Instant::now() < deadline, "tasks did not run");
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_higher_priority_tasks_run_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = TaskQueue::new(1);
+        queue.pause();
+
+        let order = Arc::new(StdMutex::new(Vec::new()));
+        let mut waiting = Vec::new();
+        for (name, priority) in [
+            ("low", TaskPriority::Low),
+            ("normal", TaskPriority::Normal),
+            ("high", TaskPriority::High),
+        ] {
+            let task_type = TaskType::GenerateThumbnail {
+                video_path: dir.path().join(format!("{}.mp4", name)),
+                thumbnail_path: dir.path().join(format!("{}.jpg", name)),
+            };
+            let (_, receiver) = queue.enqueue_with_result(task_type, priority).await;
+            let order = Arc::clone(&order);
+            waiting.push(tokio::spawn(async move {
+                let _ = receiver.await;
+                order.lock().unwrap().push(name);
+            }));
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // 同一时间只执行一个任务，后加入的高优先级任务先执行
+        queue.resume();
+        for handle in waiting {
+            tokio::time::timeout(std::time::Duration::from_secs(10), handle)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["high", "normal", "low"]);
+    }
+
+    #[test]
+    fn test_running_task_listed_while_in_flight() {
+        let running = RunningTasks::default();
+
+        let first = running.start(&thumbnail_task(1, "/data/a.mp4"));
+        let second = running.start(&thumbnail_task(2, "/data/b.mp4"));
+        let tasks = running.list();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].task_type, "generate_thumbnail");
+        assert_eq!(tasks[0].target_path, PathBuf::from("/data/a.mp4"));
+
+        drop(first);
+        let ids: Vec<u64> = running.list().iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2]);
+
+        drop(second);
+        assert!(running.list().is_empty());
+    }
+}
@@ -0,0 +1,545 @@
+//! 分片上传会话
+//!
+//! 每个会话对应 `UPLOAD_CHUNK_DIR` 下的一个目录：`upload.json` 保存会话信息，
+//! 已收到的分片保存为 `<index>.chunk`。会话状态完全来自磁盘，服务重启后可以继续上传。
+//! 超过有效期的会话会被删除。
+
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use crate::utils::{move_file_no_clobber_async, move_path_async};
+
+const MANIFEST_FILE: &str = "upload.json";
+
+/// 单个会话最多的分片数量
+pub const MAX_CHUNK_COUNT: u64 = 10_000;
+
+/// 会话锁：写入分片时持有读锁（分片可以并发写入），完成上传时持有写锁
+static SESSION_LOCKS: OnceLock<Mutex<HashMap<String, Arc<RwLock<()>>>>> = OnceLock::new();
+
+fn session_lock(id: &str) -> Arc<RwLock<()>> {
+    let locks = SESSION_LOCKS.get_or_init(Default::default);
+    Arc::clone(locks.lock().unwrap().entry(id.to_string()).or_default())
+}
+
+fn forget_session_lock(id: &str) {
+    if let Some(locks) = SESSION_LOCKS.get() {
+        locks.lock().unwrap().remove(id);
+    }
+}
+
+/// 分片上传会话信息
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UploadSession {
+    pub id: String,
+    /// 目标目录（物理路径）
+    pub target_dir: PathBuf,
+    pub file_name: String,
+    /// 文件总字节数
+    pub total_size: u64,
+    /// 每个分片的字节数，最后一个分片可以更小
+    pub chunk_size: u64,
+    pub created_at: i64,
+}
+
+impl UploadSession {
+    /// 分片数量，空文件也有一个（空的）分片
+    pub fn chunk_count(&self) -> u64 {
+        self.total_size.div_ceil(self.chunk_size).max(1)
+    }
+
+    /// 指定分片应有的字节数
+    pub fn chunk_len(&self, index: u64) -> u64 {
+        let start = index * self.chunk_size;
+        self.chunk_size.min(self.total_size.saturating_sub(start))
+    }
+
+    pub fn destination(&self) -> PathBuf {
+        self.target_dir.join(&self.file_name)
+    }
+}
+
+/// 写入中的临时文件
+///
+/// 文件名带有唯一后缀，同名文件的并发上传不会写入同一个临时文件；
+/// 未调用 [`PartFile::persist`] 就被丢弃时（出错或客户端中途断开）自动删除
+pub struct PartFile {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl PartFile {
+    /// 在 `destination` 所在目录创建临时文件，保证最终的移动在同一文件系统内
+    pub async fn create(destination: &Path) -> Result<(Self, tokio::fs::File)> {
+        let file_name = destination
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let path = destination.with_file_name(format!(".{}.{}.part", file_name, new_upload_id()));
+        let file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await?;
+        Ok((
+            Self {
+                path,
+                persisted: false,
+            },
+            file,
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 移动到 `destination`，不覆盖已存在的文件（返回 `AlreadyExists`）
+    pub async fn persist(mut self, destination: &Path) -> Result<()> {
+        move_file_no_clobber_async(&self.path, destination).await?;
+        self.persisted = true;
+        Ok(())
+    }
+
+    /// 移动到 `destination`，覆盖已存在的文件
+    pub async fn replace(mut self, destination: &Path) -> Result<()> {
+        move_path_async(&self.path, destination).await?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for PartFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 分片上传会话存储
+pub struct ChunkedUploads {
+    root: PathBuf,
+    /// 会话有效期，从创建时开始计算
+    ttl: Duration,
+}
+
+impl ChunkedUploads {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// 使用指定的会话有效期
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 创建会话并写入会话信息
+    pub async fn init(
+        &self,
+        target_dir: PathBuf,
+        file_name: String,
+        total_size: u64,
+        chunk_size: u64,
+    ) -> Result<UploadSession> {
+        if chunk_size == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "chunk_size must be > 0",
+            ));
+        }
+        if total_size.div_ceil(chunk_size) > MAX_CHUNK_COUNT {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("too many chunks (max {})", MAX_CHUNK_COUNT),
+            ));
+        }
+        self.remove_expired().await;
+
+        let session = UploadSession {
+            id: new_upload_id(),
+            target_dir,
+            file_name,
+            total_size,
+            chunk_size,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        let dir = self.root.join(&session.id);
+        tokio::fs::create_dir_all(&dir).await?;
+        let manifest = serde_json::to_vec_pretty(&session)?;
+        tokio::fs::write(dir.join(MANIFEST_FILE), manifest).await?;
+        Ok(session)
+    }
+
+    /// 读取会话信息，会话不存在或已过期时返回 `NotFound`（过期的会话同时被删除）
+    pub async fn session(&self, id: &str) -> Result<UploadSession> {
+        let dir = self.session_dir(id)?;
+        let manifest = tokio::fs::read(dir.join(MANIFEST_FILE)).await?;
+        let session: UploadSession = serde_json::from_slice(&manifest)?;
+        if self.is_expired(&session) {
+            self.remove_session(&session.id).await;
+            return Err(Error::new(ErrorKind::NotFound, "Upload expired"));
+        }
+        Ok(session)
+    }
+
+    /// 已完整收到的分片序号（升序）
+    ///
+    /// 分片只有在长度正确时才会被重命名为 `<index>.chunk`，读取一次目录即可，不需要逐个检查
+    pub async fn received_chunks(&self, session: &UploadSession) -> Result<Vec<u64>> {
+        let dir = self.session_dir(&session.id)?;
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        let mut received = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let index = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".chunk"))
+                .and_then(|index| index.parse::<u64>().ok());
+            if let Some(index) = index.filter(|&index| index < session.chunk_count()) {
+                received.push(index);
+            }
+        }
+        received.sort_unstable();
+        Ok(received)
+    }
+
+    /// 删除所有过期的会话
+    pub async fn remove_expired(&self) {
+        let Ok(mut entries) = tokio::fs::read_dir(&self.root).await else {
+            return;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let id = entry.file_name().to_string_lossy().to_string();
+            // session() 会删除过期的会话
+            if let Err(e) = self.session(&id).await {
+                if e.kind() != ErrorKind::NotFound {
+                    log::debug!("读取上传会话失败: {} - {}", id, e);
+                }
+            }
+        }
+    }
+
+    fn is_expired(&self, session: &UploadSession) -> bool {
+        let age = chrono::Utc::now().timestamp() - session.created_at;
+        age > self.ttl.as_secs() as i64
+    }
+
+    async fn remove_session(&self, id: &str) {
+        let Ok(dir) = self.session_dir(id) else {
+            return;
+        };
+        match tokio::fs::remove_dir_all(&dir).await {
+            Ok(()) => log::debug!("删除上传会话: {}", id),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => log::warn!("删除上传会话目录失败: {} - {}", dir.display(), e),
+        }
+        forget_session_lock(id);
+    }
+
+    /// 写入一个分片，可以乱序上传，重复上传会覆盖之前的内容
+    ///
+    /// 分片先写入临时文件，长度与预期一致后才重命名，中断的写入不会被当作已收到
+    pub async fn write_chunk<S, B, E>(
+        &self,
+        session: &UploadSession,
+        index: u64,
+        mut body: S,
+    ) -> Result<()>
+    where
+        S: Stream<Item = std::result::Result<B, E>> + Unpin,
+        B: AsRef<[u8]>,
+        E: std::fmt::Display,
+    {
+        if index >= session.chunk_count() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "chunk index {} out of range (0..{})",
+                    index,
+                    session.chunk_count()
+                ),
+            ));
+        }
+        let expected = session.chunk_len(index);
+        let dir = self.session_dir(&session.id)?;
+        let path = chunk_path(&dir, index);
+
+        let lock = session_lock(&session.id);
+        let _guard = lock.read().await;
+        // 等待锁期间会话可能已完成或过期
+        self.session(&session.id).await?;
+
+        let (part, mut file) = PartFile::create(&path).await?;
+        let mut written: u64 = 0;
+        while let Some(data) = body.next().await {
+            let data = data.map_err(|e| Error::other(e.to_string()))?;
+            let data = data.as_ref();
+            written += data.len() as u64;
+            if written > expected {
+                break;
+            }
+            file.write_all(data).await?;
+        }
+        if written != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("chunk {} must be {} bytes", index, expected),
+            ));
+        }
+        file.flush().await?;
+        drop(file);
+        part.replace(&path).await
+    }
+
+    /// 检查分片是否齐全，按顺序拼接后移动到目标位置并删除会话
+    ///
+    /// 缺少分片时返回 `InvalidData`，目标文件已存在时返回 `AlreadyExists`
+    pub async fn complete(&self, session: &UploadSession) -> Result<PathBuf> {
+        // 持有写锁：没有正在写入的分片，其他完成请求需要等待，之后会发现会话已不存在
+        let lock = session_lock(&session.id);
+        let _guard = lock.write().await;
+        self.session(&session.id).await?;
+
+        let received = self.received_chunks(session).await?;
+        if received.len() as u64 != session.chunk_count() {
+            let missing: Vec<String> = (0..session.chunk_count())
+                .filter(|index| !received.contains(index))
+                .map(|index| index.to_string())
+                .collect();
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("missing chunks: {}", missing.join(",")),
+            ));
+        }
+
+        let destination = session.destination();
+        if tokio::fs::try_exists(&destination).await? {
+            return Err(Error::new(ErrorKind::AlreadyExists, "File already exists"));
+        }
+
+        // 先拼接到目标目录中的临时文件，最后不覆盖地移动到目标位置
+        let dir = self.session_dir(&session.id)?;
+        let (part, mut output) = PartFile::create(&destination).await?;
+        for index in 0..session.chunk_count() {
+            let mut chunk = tokio::fs::File::open(chunk_path(&dir, index)).await?;
+            tokio::io::copy(&mut chunk, &mut output).await?;
+        }
+        output.flush().await?;
+        drop(output);
+        if tokio::fs::metadata(part.path()).await?.len() != session.total_size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "assembled size does not match",
+            ));
+        }
+        part.persist(&destination).await?;
+
+        self.remove_session(&session.id).await;
+        Ok(destination)
+    }
+
+    /// 会话目录，id 只允许十六进制字符，防止路径穿越
+    fn session_dir(&self, id: &str) -> Result<PathBuf> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::new(ErrorKind::NotFound, "Upload not found"));
+        }
+        Ok(self.root.join(id))
+    }
+}
+
+fn chunk_path(dir: &Path, index: u64) -> PathBuf {
+    dir.join(format!("{}.chunk", index))
+}
+
+/// 生成上传 id：当前时间（纳秒）加进程内计数器
+fn new_upload_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!(
+        "{:x}{:04x}{:04x}",
+        nanos,
+        std::process::id() & 0xffff,
+        COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(data: &[u8]) -> impl Stream<Item = std::result::Result<Vec<u8>, String>> + Unpin {
+        futures_util::stream::iter(vec![Ok(data.to_vec())])
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_chunks_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        std::fs::create_dir(&target).unwrap();
+        let root = dir.path().join("uploads");
+
+        let uploads = ChunkedUploads::new(&root);
+        let session = uploads
+            .init(target.clone(), "movie.mp4".to_string(), 10, 4)
+            .await
+            .unwrap();
+        assert_eq!(session.chunk_count(), 3);
+        uploads.write_chunk(&session, 2, body(b"89")).await.unwrap();
+        uploads
+            .write_chunk(&session, 0, body(b"0123"))
+            .await
+            .unwrap();
+
+        // 模拟重启：新的存储实例从磁盘读取会话
+        let uploads = ChunkedUploads::new(&root);
+        let session = uploads.session(&session.id).await.unwrap();
+        assert_eq!(uploads.received_chunks(&session).await.unwrap(), vec![0, 2]);
+
+        let err = uploads.complete(&session).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("missing chunks: 1"));
+
+        uploads
+            .write_chunk(&session, 1, body(b"4567"))
+            .await
+            .unwrap();
+        let destination = uploads.complete(&session).await.unwrap();
+        assert_eq!(destination, target.join("movie.mp4"));
+        assert_eq!(std::fs::read(&destination).unwrap(), b"0123456789");
+        assert_eq!(std::fs::read_dir(&target).unwrap().count(), 1);
+        assert_eq!(
+            uploads.session(&session.id).await.unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_part_files_do_not_clobber() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("a.mp4");
+
+        let (first, mut first_file) = PartFile::create(&destination).await.unwrap();
+        let (second, mut second_file) = PartFile::create(&destination).await.unwrap();
+        assert_ne!(first.path(), second.path());
+        first_file.write_all(b"first").await.unwrap();
+        second_file.write_all(b"second").await.unwrap();
+        drop((first_file, second_file));
+
+        first.persist(&destination).await.unwrap();
+        let err = second.persist(&destination).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(&destination).unwrap(), b"first");
+
+        // 未完成（例如客户端断开）的临时文件在丢弃时删除
+        let (abandoned, _file) = PartFile::create(&destination).await.unwrap();
+        drop(abandoned);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_sessions_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("uploads");
+        let uploads = ChunkedUploads::new(&root);
+        let session = uploads
+            .init(dir.path().to_path_buf(), "a.mp4".to_string(), 6, 4)
+            .await
+            .unwrap();
+        uploads.session(&session.id).await.unwrap();
+
+        let expired = ChunkedUploads::new(&root).with_ttl(Duration::ZERO);
+        let mut old = session.clone();
+        old.created_at -= 10;
+        std::fs::write(
+            root.join(&session.id).join(MANIFEST_FILE),
+            serde_json::to_vec(&old).unwrap(),
+        )
+        .unwrap();
+        expired.remove_expired().await;
+        assert!(!root.join(&session.id).exists());
+
+        let err = uploads
+            .init(dir.path().to_path_buf(), "b.mp4".to_string(), 10_001, 1)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_completes_produce_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = ChunkedUploads::new(dir.path().join("uploads"));
+        let session = uploads
+            .init(dir.path().to_path_buf(), "a.mp4".to_string(), 4, 4)
+            .await
+            .unwrap();
+        uploads
+            .write_chunk(&session, 0, body(b"0123"))
+            .await
+            .unwrap();
+
+        let (first, second) = tokio::join!(uploads.complete(&session), uploads.complete(&session));
+        let errors: Vec<ErrorKind> = [first, second]
+            .into_iter()
+            .filter_map(|result| result.err().map(|e| e.kind()))
+            .collect();
+        assert_eq!(errors, vec![ErrorKind::NotFound]);
+        assert_eq!(std::fs::read(dir.path().join("a.mp4")).unwrap(), b"0123");
+
+        // 完成后不能再写入分片
+        let err = uploads
+            .write_chunk(&session, 0, body(b"0123"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_chunks_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let uploads = ChunkedUploads::new(dir.path().join("uploads"));
+        let session = uploads
+            .init(dir.path().to_path_buf(), "a.mp4".to_string(), 6, 4)
+            .await
+            .unwrap();
+
+        // 序号越界、长度不对的分片都不会被记录
+        let err = uploads
+            .write_chunk(&session, 2, body(b"xx"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = uploads
+            .write_chunk(&session, 1, body(b"xyz"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = uploads
+            .write_chunk(&session, 0, body(b"toolong"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(uploads.received_chunks(&session).await.unwrap().is_empty());
+
+        assert_eq!(
+            uploads.session("../etc").await.unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
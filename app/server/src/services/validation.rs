@@ -0,0 +1,185 @@
+//! 媒体库校验服务
+//!
+//! 对每个视频用 ffmpeg 完整解码一遍（通过任务队列在后台执行），记录解码出错的文件。
+
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use crate::services::ffmpeg::FFmpegService;
+
+/// 解码出错的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidFile {
+    pub path: PathBuf,
+    /// ffmpeg 输出的错误（stderr 末尾几行）
+    pub error: String,
+}
+
+/// 一次校验的进度和结果
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// 校验批次，0 表示还没有执行过校验
+    pub run_id: u64,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// 本次提交的文件数
+    pub total: usize,
+    /// 已检查的文件数
+    pub checked: usize,
+    pub invalid: Vec<InvalidFile>,
+}
+
+impl ValidationReport {
+    /// 是否还有文件未检查
+    pub fn is_running(&self) -> bool {
+        self.run_id > 0 && self.checked < self.total
+    }
+}
+
+/// 校验任务跟踪器
+///
+/// 同一时间只允许一次校验；旧批次遗留的任务结果会被忽略
+#[derive(Default)]
+pub struct ValidationTracker {
+    run_id_counter: AtomicU64,
+    report: Mutex<ValidationReport>,
+}
+
+impl ValidationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始新的校验批次，上一次校验仍在进行时返回 None
+    pub fn try_start(&self, total: usize) -> Option<u64> {
+        let mut report = self.report.lock().unwrap();
+        if report.is_running() {
+            return None;
+        }
+
+        let run_id = self.run_id_counter.fetch_add(1, Ordering::SeqCst) + 1;
+        let now = now();
+        *report = ValidationReport {
+            run_id,
+            finished_at: (total == 0).then(|| now.clone()),
+            started_at: Some(now),
+            total,
+            checked: 0,
+            invalid: Vec::new(),
+        };
+        Some(run_id)
+    }
+
+    /// 记录单个文件的检查结果
+    pub fn record(&self, run_id: u64, path: &Path, result: Result<(), String>) {
+        let mut report = self.report.lock().unwrap();
+        if report.run_id != run_id {
+            return;
+        }
+
+        report.checked += 1;
+        if let Err(error) = result {
+            warn!("文件解码出错: {} - {}", path.display(), error);
+            report.invalid.push(InvalidFile {
+                path: path.to_path_buf(),
+                error,
+            });
+        }
+        if report.checked == report.total {
+            report.finished_at = Some(now());
+            info!(
+                "媒体库校验完成: {} 个文件，{} 个有错误",
+                report.total,
+                report.invalid.len()
+            );
+        }
+    }
+
+    /// 获取当前（或最近一次）校验的结果
+    pub fn report(&self) -> ValidationReport {
+        self.report.lock().unwrap().clone()
+    }
+}
+
+/// 检查单个文件并记录结果
+pub fn validate_file(
+    ffmpeg: &FFmpegService,
+    tracker: &ValidationTracker,
+    run_id: u64,
+    video_path: &Path,
+) {
+    let result = if video_path.is_file() {
+        ffmpeg.check_decodes(video_path)
+    } else {
+        Err("文件不存在".to_string())
+    };
+    tracker.record(run_id, video_path, result);
+}
+
+/// 全局校验跟踪器（任务队列执行校验任务时使用）
+static VALIDATION_TRACKER: OnceLock<ValidationTracker> = OnceLock::new();
+
+/// 获取全局校验跟踪器
+pub fn get_validation_tracker() -> &'static ValidationTracker {
+    VALIDATION_TRACKER.get_or_init(ValidationTracker::new)
+}
+
+fn now() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ffmpeg::FFmpegConfig;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_truncated_file_is_reported_invalid() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // 用脚本代替 ffmpeg：文件不以 END 结尾时像解码截断文件一样输出错误，但退出码仍为 0
+        let dir = tempfile::tempdir().unwrap();
+        let fake_ffmpeg = dir.path().join("fake-ffmpeg");
+        std::fs::write(
+            &fake_ffmpeg,
+            "#!/bin/sh\nwhile [ \"$1\" != \"-i\" ]; do shift; done\n\
+             if [ \"$(tail -c 3 \"$2\")\" != \"END\" ]; then \
+             echo \"$2: partial file\" >&2; fi\nexit 0\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let service = FFmpegService::new(FFmpegConfig {
+            ffmpeg_path: fake_ffmpeg.to_string_lossy().to_string(),
+            ..FFmpegConfig::default()
+        });
+
+        let good = dir.path().join("good.mp4");
+        let truncated = dir.path().join("truncated.mp4");
+        std::fs::write(&good, b"HEADER frames END").unwrap();
+        std::fs::write(&truncated, b"HEADER fra").unwrap();
+
+        let tracker = ValidationTracker::new();
+        let run_id = tracker.try_start(2).unwrap();
+        assert!(tracker.try_start(1).is_none());
+        validate_file(&service, &tracker, run_id, &good);
+        validate_file(&service, &tracker, run_id, &truncated);
+
+        let report = tracker.report();
+        assert!(!report.is_running());
+        assert_eq!(report.checked, 2);
+        assert!(report.finished_at.is_some());
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].path, truncated);
+        assert!(report.invalid[0].error.contains("partial file"));
+
+        // 新批次开始后，旧批次的结果被忽略
+        let next = tracker.try_start(1).unwrap();
+        tracker.record(run_id, &good, Err("stale".to_string()));
+        let report = tracker.report();
+        assert_eq!((report.run_id, report.checked), (next, 0));
+        assert!(report.invalid.is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+//! 文件监听服务
+//!
+//! 监听数据源目录的文件变化，在防抖时间窗口内合并事件后触发一次增量同步。
+
+use log::{debug, info, warn};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::WatcherConfig;
+use crate::services::db::{DirectorySync, SyncReport, VideoDbManager};
+use crate::DiskMapping;
+
+/// 文件监听器状态
+#[derive(Debug, Clone)]
+pub struct WatcherStatus {
+    pub running: bool,
+    pub debounce_ms: u64,
+    pub watched_paths: Vec<String>,
+}
+
+/// 运行中的监听器
+struct ActiveWatcher {
+    /// notify 监听器，drop 后事件通道关闭，同步线程随之退出
+    _watcher: RecommendedWatcher,
+    debounce_ms: u64,
+    watched_paths: Vec<String>,
+}
+
+/// 文件监听器
+///
+/// 同一时间最多只有一个活动的监听器，重复启动会先停止旧的监听器
+#[derive(Default)]
+pub struct FileWatcher {
+    active: Mutex<Option<ActiveWatcher>>,
+}
+
+impl FileWatcher {
+    /// 创建未启动的文件监听器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始监听所有数据源目录
+    pub fn start(
+        &self,
+        mappings: Arc<Vec<DiskMapping>>,
+        db_manager: Arc<Mutex<VideoDbManager>>,
+        last_sync: Arc<Mutex<Option<SyncReport>>>,
+        debounce_ms: u64,
+    ) -> notify::Result<()> {
+        let mut active = self.active.lock().unwrap();
+        // 停止旧的监听器
+        active.take();
+
+        let (tx, rx) = mpsc::channel::<()>();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            match res {
+                Ok(event) => {
+                    // 只关心创建、修改、删除事件
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) {
+                        let _ = tx.send(());
+                    }
+                }
+                Err(e) => warn!("文件监听错误: {}", e),
+            }
+        })?;
+
+        let mut watched_paths = Vec::new();
+        for mapping in mappings.iter() {
+            let path = Path::new(&mapping.physical_path);
+            if !path.exists() {
+                warn!("监听目录不存在，跳过: {}", mapping.physical_path);
+                continue;
+            }
+            watcher.watch(path, RecursiveMode::Recursive)?;
+            watched_paths.push(mapping.physical_path.clone());
+        }
+
+        std::thread::spawn(move || {
+            Self::run_sync_loop(rx, mappings, db_manager, last_sync, debounce_ms)
+        });
+
+        info!(
+            "文件监听已启动，防抖时间: {}ms，监听目录: {:?}",
+            debounce_ms, watched_paths
+        );
+
+        *active = Some(ActiveWatcher {
+            _watcher: watcher,
+            debounce_ms,
+            watched_paths,
+        });
+        Ok(())
+    }
+
+    /// 停止监听，返回停止前是否处于运行状态
+    pub fn stop(&self) -> bool {
+        let stopped = self.active.lock().unwrap().take().is_some();
+        if stopped {
+            info!("文件监听已停止");
+        }
+        stopped
+    }
+
+    /// 获取监听器状态
+    pub fn status(&self) -> WatcherStatus {
+        match self.active.lock().unwrap().as_ref() {
+            Some(active) => WatcherStatus {
+                running: true,
+                debounce_ms: active.debounce_ms,
+                watched_paths: active.watched_paths.clone(),
+            },
+            None => WatcherStatus {
+                running: false,
+                debounce_ms: 0,
+                watched_paths: Vec::new(),
+            },
+        }
+    }
+
+    /// 同步线程：等待文件事件，防抖后执行增量同步
+    fn run_sync_loop(
+        rx: mpsc::Receiver<()>,
+        mappings: Arc<Vec<DiskMapping>>,
+        db_manager: Arc<Mutex<VideoDbManager>>,
+        last_sync: Arc<Mutex<Option<SyncReport>>>,
+        debounce_ms: u64,
+    ) {
+        let debounce = Duration::from_millis(debounce_ms);
+
+        // recv 返回错误说明监听器已被停止
+        while rx.recv().is_ok() {
+            // 防抖：直到在 debounce 时间内没有新事件才开始同步
+            loop {
+                match rx.recv_timeout(debounce) {
+                    Ok(()) => continue,
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            debug!("检测到文件变化，开始增量同步");
+            let db_manager = db_manager.lock().unwrap();
+            let sync = DirectorySync::new(&db_manager);
+            match sync.initialize_from_directory_with_progress(&mappings, false, false) {
+                Ok(report) => *last_sync.lock().unwrap() = Some(report),
+                Err(e) => warn!("文件变化触发的同步失败: {}", e),
+            }
+        }
+
+        debug!("文件监听同步线程已退出");
+    }
+}
+
+/// 根据配置在启动时自动开启文件监听
+pub fn start_watcher_if_enabled(
+    watcher: &FileWatcher,
+    config: &WatcherConfig,
+    mappings: Arc<Vec<DiskMapping>>,
+    db_manager: Arc<Mutex<VideoDbManager>>,
+    last_sync: Arc<Mutex<Option<SyncReport>>>,
+) {
+    if !config.enabled {
+        info!("文件监听未启用（WATCHER_ENABLED=false）");
+        return;
+    }
+
+    if let Err(e) = watcher.start(mappings, db_manager, last_sync, config.debounce_ms) {
+        warn!("启动文件监听失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher_running_after_startup_when_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let mappings = Arc::new(vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: dir.path().to_string_lossy().to_string(),
+        }]);
+        let db_manager = Arc::new(Mutex::new(VideoDbManager::in_memory()));
+        let config = WatcherConfig {
+            enabled: true,
+            debounce_ms: 500,
+        };
+
+        let watcher = FileWatcher::new();
+        start_watcher_if_enabled(&watcher, &config, mappings, db_manager, Default::default());
+
+        let status = watcher.status();
+        assert!(status.running);
+        assert_eq!(status.debounce_ms, 500);
+        assert_eq!(status.watched_paths.len(), 1);
+
+        assert!(watcher.stop());
+        assert!(!watcher.status().running);
+    }
+
+    #[test]
+    fn test_watcher_not_started_when_disabled() {
+        let db_manager = Arc::new(Mutex::new(VideoDbManager::in_memory()));
+        let config = WatcherConfig {
+            enabled: false,
+            debounce_ms: 500,
+        };
+
+        let watcher = FileWatcher::new();
+        start_watcher_if_enabled(
+            &watcher,
+            &config,
+            Arc::new(Vec::new()),
+            db_manager,
+            Default::default(),
+        );
+
+        assert!(!watcher.status().running);
+    }
+}
@@ -0,0 +1,97 @@
+//! 测试辅助工具
+
+use std::sync::{Arc, Mutex};
+
+use crate::config::AppConfig;
+use crate::services::db::schema::queries;
+use crate::services::{FileWatcher, RebuildTracker, VideoDbManager};
+use crate::{AppState, DiskMapping};
+
+/// 使用内存数据库构建应用状态
+pub fn test_state(mappings: Vec<DiskMapping>) -> Arc<AppState> {
+    test_state_with_config(mappings, AppConfig::default())
+}
+
+/// 使用内存数据库和指定配置构建应用状态
+pub fn test_state_with_config(mappings: Vec<DiskMapping>, config: AppConfig) -> Arc<AppState> {
+    let db_manager = VideoDbManager::in_memory();
+
+    Arc::new(AppState {
+        db_manager: Arc::new(Mutex::new(db_manager)),
+        data_source_dirs: Arc::new(mappings),
+        transcodes: Arc::new(tokio::sync::Semaphore::new(config.ffmpeg.max_transcodes)),
+        config: Arc::new(config),
+        watcher: Arc::new(FileWatcher::new()),
+        rebuild: Arc::new(RebuildTracker::new()),
+        last_sync: Arc::new(Mutex::new(None)),
+    })
+}
+
+/// 插入一条测试视频记录
+pub fn insert_video(db_manager: &VideoDbManager, path: &str, file_type: &str, parent_path: &str) {
+    let name = std::path::Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    db_manager
+        .conn
+        .execute(
+            queries::INSERT_NEW,
+            rusqlite::params![
+                name,
+                path,
+                file_type,
+                parent_path,
+                "thumbnails/test.jpg",
+                "1.00 MB",
+                "2025-01-01 00:00:00",
+                "",
+                0,
+                "00:01:00",
+                1920,
+                1080,
+                None::<i64>,
+                None::<String>,
+                None::<i64>,
+                60.0,
+                1024 * 1024,
+                crate::utils::normalize_for_search(&name),
+                None::<String>,
+            ],
+        )
+        .unwrap();
+}
+
+/// 读取响应体并解析为 JSON
+pub async fn response_json(response: axum::response::Response) -> serde_json::Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+/// 构造最小的 MPEG-TS 数据：一个 PAT 包，之后每秒一个带 PTS 的视频 PES 包，共 `seconds` 秒
+pub fn mpegts_fixture(seconds: u32) -> Vec<u8> {
+    let packet = |pid: u16, payload: &[u8]| {
+        let mut packet = vec![0x47, 0x40 | (pid >> 8) as u8, pid as u8, 0x10];
+        packet.extend_from_slice(payload);
+        packet.resize(188, 0xff);
+        packet
+    };
+
+    let mut data = packet(0, &[0x00, 0x00, 0xb0, 0x0d]);
+    for second in 0..=seconds {
+        let pts = 126_000 + u64::from(second) * 90_000;
+        let mut pes = vec![0x00, 0x00, 0x01, 0xe0, 0x00, 0x00, 0x80, 0x80, 0x05];
+        pes.extend([
+            0x21 | ((pts >> 29) & 0x0e) as u8,
+            (pts >> 22) as u8,
+            0x01 | ((pts >> 14) & 0xfe) as u8,
+            (pts >> 7) as u8,
+            0x01 | ((pts << 1) & 0xfe) as u8,
+        ]);
+        data.extend(packet(0x100, &pes));
+    }
+    data
+}
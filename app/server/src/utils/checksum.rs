@@ -0,0 +1,256 @@
+//! 文件校验和（SHA-256、CRC-32）
+//!
+//! 按固定大小的块读取文件并逐块计算，内存占用与文件大小无关。
+//! 结果按路径缓存，文件的修改时间或大小变化后重新计算。
+//! 同一个文件同时只计算一次，其他请求等待后直接读取缓存
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Instant, SystemTime};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// 支持的算法名称
+pub const CHECKSUM_ALGOS: &[&str] = &["sha256", "crc32"];
+
+/// 每次读取的字节数
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// 最多缓存的校验和数量，超过时淘汰最久未使用的
+const MAX_CACHED_CHECKSUMS: usize = 1024;
+
+/// 同时计算校验和的最大数量
+pub const MAX_CONCURRENT_CHECKSUMS: usize = 2;
+
+/// 校验和算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgo {
+    Sha256,
+    Crc32,
+}
+
+impl ChecksumAlgo {
+    /// 解析算法名称（不区分大小写），不支持时返回错误信息
+    pub fn parse(algo: &str) -> Result<Self, String> {
+        match algo.to_ascii_lowercase().as_str() {
+            "sha256" => Ok(Self::Sha256),
+            "crc32" => Ok(Self::Crc32),
+            _ => Err(format!(
+                "Invalid algo '{}', expected one of: {}",
+                algo,
+                CHECKSUM_ALGOS.join(", ")
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Crc32 => "crc32",
+        }
+    }
+}
+
+/// 文件的校验和
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChecksum {
+    /// 小写十六进制摘要
+    pub digest: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 是否来自缓存
+    pub cached: bool,
+}
+
+/// 缓存的校验和，修改时间和大小都不变时有效
+struct CachedChecksum {
+    modified: SystemTime,
+    size: u64,
+    digest: String,
+    /// 最近一次读取或写入的时间，用于淘汰
+    used: Instant,
+}
+
+type ChecksumKey = (String, ChecksumAlgo);
+type ChecksumCache = Mutex<HashMap<ChecksumKey, CachedChecksum>>;
+
+static CHECKSUM_CACHE: OnceLock<ChecksumCache> = OnceLock::new();
+
+/// 正在计算的校验和，同一个文件的请求依次执行
+static IN_FLIGHT: OnceLock<Mutex<HashMap<ChecksumKey, Arc<Mutex<()>>>>> = OnceLock::new();
+
+static CHECKSUM_PERMITS: OnceLock<Semaphore> = OnceLock::new();
+
+/// 获取计算校验和的许可，已有 [`MAX_CONCURRENT_CHECKSUMS`] 个在计算时返回 None
+pub fn try_acquire_checksum_permit() -> Option<SemaphorePermit<'static>> {
+    CHECKSUM_PERMITS
+        .get_or_init(|| Semaphore::new(MAX_CONCURRENT_CHECKSUMS))
+        .try_acquire()
+        .ok()
+}
+
+/// 计算文件的校验和，文件未变化时直接返回缓存的结果
+///
+/// 会读取整个文件，需要在阻塞线程中调用
+pub fn file_checksum(path: &Path, algo: ChecksumAlgo) -> io::Result<FileChecksum> {
+    let key = (path.to_string_lossy().to_string(), algo);
+    let key_lock = in_flight_lock(&key);
+    let result = {
+        let _guard = key_lock.lock().unwrap();
+        checksum_with_cache(path, &key)
+    };
+    release_in_flight_lock(&key, key_lock);
+    result
+}
+
+/// 查询缓存，未命中时计算并写入缓存
+fn checksum_with_cache(path: &Path, key: &ChecksumKey) -> io::Result<FileChecksum> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata.modified()?;
+    let size = metadata.len();
+    let cache = CHECKSUM_CACHE.get_or_init(Default::default);
+
+    if let Some(cached) = cache.lock().unwrap().get_mut(key) {
+        if cached.modified == modified && cached.size == size {
+            cached.used = Instant::now();
+            return Ok(FileChecksum {
+                digest: cached.digest.clone(),
+                size,
+                cached: true,
+            });
+        }
+    }
+
+    let digest = compute(path, key.1)?;
+    let mut cache = cache.lock().unwrap();
+    if cache.len() >= MAX_CACHED_CHECKSUMS && !cache.contains_key(key) {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, cached)| cached.used)
+            .map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(
+        key.clone(),
+        CachedChecksum {
+            modified,
+            size,
+            digest: digest.clone(),
+            used: Instant::now(),
+        },
+    );
+    Ok(FileChecksum {
+        digest,
+        size,
+        cached: false,
+    })
+}
+
+fn in_flight_lock(key: &ChecksumKey) -> Arc<Mutex<()>> {
+    let locks = IN_FLIGHT.get_or_init(Default::default);
+    Arc::clone(locks.lock().unwrap().entry(key.clone()).or_default())
+}
+
+/// 没有其他请求在等待时移除该文件的锁，避免锁表无限增长
+fn release_in_flight_lock(key: &ChecksumKey, key_lock: Arc<Mutex<()>>) {
+    let mut locks = IN_FLIGHT.get_or_init(Default::default).lock().unwrap();
+    // 锁表和当前请求各持有一份
+    if Arc::strong_count(&key_lock) <= 2 {
+        locks.remove(key);
+    }
+}
+
+/// 逐块读取文件并计算摘要
+fn compute(path: &Path, algo: ChecksumAlgo) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut sha256 = Sha256::new();
+    let mut crc32 = crc32fast::Hasher::new();
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        match algo {
+            ChecksumAlgo::Sha256 => sha256.update(&buffer[..read]),
+            ChecksumAlgo::Crc32 => crc32.update(&buffer[..read]),
+        }
+    }
+    Ok(match algo {
+        ChecksumAlgo::Sha256 => to_hex(&sha256.finalize()),
+        ChecksumAlgo::Crc32 => format!("{:08x}", crc32.finalize()),
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.bin");
+        // 大于一个读取块，跨块计算
+        let message = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".repeat(2000);
+        std::fs::write(&path, &message).unwrap();
+        assert_eq!(
+            compute(&path, ChecksumAlgo::Sha256).unwrap(),
+            to_hex(&Sha256::digest(&message))
+        );
+
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(
+            compute(&path, ChecksumAlgo::Sha256).unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+
+        assert!(ChecksumAlgo::parse("MD5")
+            .unwrap_err()
+            .contains("sha256, crc32"));
+    }
+
+    #[test]
+    fn test_concurrent_requests_compute_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.bin");
+        std::fs::write(&path, vec![7u8; 4 * CHUNK_SIZE]).unwrap();
+
+        let results: Vec<FileChecksum> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| file_checksum(&path, ChecksumAlgo::Sha256).unwrap()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(results.iter().filter(|r| !r.cached).count(), 1);
+        assert!(results.windows(2).all(|w| w[0].digest == w[1].digest));
+        let key = (path.to_string_lossy().to_string(), ChecksumAlgo::Sha256);
+        assert!(!IN_FLIGHT.get().unwrap().lock().unwrap().contains_key(&key));
+    }
+
+    #[test]
+    fn test_checksum_is_cached_until_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.bin");
+        std::fs::write(&path, b"123456789").unwrap();
+
+        let first = file_checksum(&path, ChecksumAlgo::Crc32).unwrap();
+        assert_eq!(first.digest, "cbf43926");
+        assert!(!first.cached);
+        assert!(file_checksum(&path, ChecksumAlgo::Crc32).unwrap().cached);
+
+        // 大小变化后重新计算
+        std::fs::write(&path, b"1234567890").unwrap();
+        let changed = file_checksum(&path, ChecksumAlgo::Crc32).unwrap();
+        assert!(!changed.cached);
+        assert_eq!(changed.size, 10);
+    }
+}
@@ -1,15 +1,18 @@
 use rayon::prelude::*;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     path::{Path, PathBuf},
     time::SystemTime,
 };
 
-use chrono::{DateTime, FixedOffset};
-use log::info;
+use super::media_types;
+use log::warn;
 use std::fs::File;
 use std::io::BufReader;
-use walkdir::WalkDir;
+
+use super::duration::format_duration;
+use crate::config::get_config;
+
 /// 辅助函数：格式化文件大小
 pub fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -27,73 +30,43 @@ pub fn format_size(bytes: u64) -> String {
     }
 }
 
-/// 辅助函数：格式化系统时间
-pub fn get_systemtime_created(metadata: &std::fs::Metadata) -> Option<String> {
-    use std::time::UNIX_EPOCH;
-
-    metadata.created().ok().and_then(|time| {
-        let timestamp = time.duration_since(UNIX_EPOCH).ok()?;
-        let nanos = timestamp.as_nanos() as i64;
-        let datetime = DateTime::from_timestamp_nanos(nanos);
-        let beijing = FixedOffset::east_opt(8 * 3600).unwrap();
-        let bj = datetime.with_timezone(&beijing);
-        Some(bj.format("%Y-%m-%d %H:%M:%S").to_string())
+/// 解析 [`format_size`] 格式的文件大小，返回字节数（精度受格式化时的两位小数限制）
+pub fn parse_size(size: &str) -> Option<u64> {
+    const UNITS: &[(&str, f64)] = &[
+        ("GB", 1024.0 * 1024.0 * 1024.0),
+        ("MB", 1024.0 * 1024.0),
+        ("KB", 1024.0),
+        ("B", 1.0),
+    ];
+
+    let size = size.trim();
+    UNITS.iter().find_map(|(unit, factor)| {
+        let value: f64 = size.strip_suffix(unit)?.trim().parse().ok()?;
+        (value >= 0.0).then(|| (value * factor).round() as u64)
     })
 }
 
-/// 辅助函数：获取创建时间
-pub fn get_created_at(path: &Path) -> Option<String> {
-    std::fs::metadata(path)
-        .ok()
-        .and_then(|m| get_systemtime_created(&m))
+/// 文件创建时间（Unix 秒），文件系统不支持时返回 None
+pub fn created_epoch(metadata: &std::fs::Metadata) -> Option<i64> {
+    let created = metadata.created().ok()?;
+    let timestamp = created.duration_since(std::time::UNIX_EPOCH).ok()?;
+    i64::try_from(timestamp.as_secs()).ok()
 }
 
-/// 辅助函数：检查路径是否为视频或容器
-pub fn is_video_or_container(path: &Path) -> bool {
-    if path.is_file() {
-        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-        return extension.eq_ignore_ascii_case("mp4")
-            || extension.eq_ignore_ascii_case("vtt")
-            || extension.eq_ignore_ascii_case("srt")
-            || extension.eq_ignore_ascii_case("jpg")
-            || extension.eq_ignore_ascii_case("png")
-            || extension.eq_ignore_ascii_case("gif");
-    }
-    false
+/// 按配置的显示时区（DISPLAY_TIMEZONE）格式化 Unix 时间戳
+pub fn format_created_at(secs: i64) -> Option<String> {
+    get_config().display_timezone.format_timestamp(secs)
 }
-/// 获取多个目录下面的文件数据
-pub fn get_files(root_paths: &[String]) -> Vec<(String, String, String, PathBuf)> {
-    let mut files: Vec<(String, String, String, PathBuf)> = Vec::new();
-
-    for root_path_str in root_paths {
-        let root_path = Path::new(root_path_str);
-
-        for entry in WalkDir::new(root_path)
-            .max_depth(1)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            // 过滤掉当前的目录
-            if root_path == path {
-                continue;
-            }
-            if is_video_or_container(path) {
-                // 获取不带扩展名的文件名
-                let name_without_ext = path
-                    .file_stem()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
-                let size = format_size(path.metadata().unwrap().len());
-                let created_at = get_created_at(path).unwrap_or_default();
-                files.push((name_without_ext, size, created_at, path.to_path_buf()));
-            }
-        }
-    }
-    files
+
+/// 辅助函数：格式化系统时间
+pub fn get_systemtime_created(metadata: &std::fs::Metadata) -> Option<String> {
+    created_epoch(metadata).and_then(format_created_at)
 }
 
+/// 辅助函数：检查路径是否为视频或容器
+pub fn is_video_or_container(path: &Path) -> bool {
+    path.is_file() && media_types::classify(path).is_some()
+}
 // 新增函数：获取没有缩略图的文件路径,以及文件名
 pub fn get_files_without_thumbnails(
     source_dir: &Path,
@@ -101,10 +74,22 @@ pub fn get_files_without_thumbnails(
 ) -> Vec<(String, PathBuf)> {
     // 获取文件元数据
     let get_metadata = |path: &Path| -> HashMap<String, (PathBuf, SystemTime)> {
-        std::fs::read_dir(path)
-            .unwrap()
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("无法读取目录 {}: {}", path.display(), e);
+                return HashMap::new();
+            }
+        };
+        entries
             .filter_map(|entry| {
-                let entry = entry.ok()?;
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        warn!("无法访问 {} 下的条目: {}", path.display(), e);
+                        return None;
+                    }
+                };
                 let path = entry.path();
                 let name = path.file_stem()?.to_string_lossy().into_owned();
                 let metadata = entry.metadata().ok()?;
@@ -140,7 +125,19 @@ pub struct VideoInfo {
     pub height: u16,
 }
 
+/// 读取 MP4 头部获取时长和分辨率
+///
+/// mp4 crate 解析截断或损坏的文件时可能 panic，这里捕获 panic 并转换为错误，
+/// 错误信息包含文件路径，调用方可以改用 ffprobe
 pub fn get_video_info(file_path: &str) -> Result<VideoInfo, Box<dyn std::error::Error>> {
+    match std::panic::catch_unwind(|| read_mp4_info(file_path)) {
+        Ok(Ok(info)) => Ok(info),
+        Ok(Err(e)) => Err(format!("无法解析 MP4 头部 {}: {}", file_path, e).into()),
+        Err(_) => Err(format!("解析 MP4 头部时 panic: {}", file_path).into()),
+    }
+}
+
+fn read_mp4_info(file_path: &str) -> Result<VideoInfo, Box<dyn std::error::Error>> {
     // 打开文件并获取大小
     let file = File::open(file_path)?;
     let size = file.metadata()?.len();
@@ -169,15 +166,37 @@ pub fn get_video_info(file_path: &str) -> Result<VideoInfo, Box<dyn std::error::
 
     Ok(video_info)
 }
-fn format_duration(seconds: f64) -> String {
-    let total_seconds = seconds.round() as u32;
-    let hours = total_seconds / 3600;
-    let minutes = (total_seconds % 3600) / 60;
-    let seconds = total_seconds % 60;
-
-    if hours > 0 {
-        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
-    } else {
-        format!("{:02}:{:02}", minutes, seconds)
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512 B"), Some(512));
+        assert_eq!(parse_size("1.00 MB"), Some(1024 * 1024));
+        assert_eq!(
+            parse_size(&format_size(3 * 1024 * 1024 * 1024)),
+            Some(3 * 1024 * 1024 * 1024)
+        );
+        assert_eq!(parse_size(""), None);
+        assert_eq!(parse_size("big"), None);
+    }
+
+    #[test]
+    fn test_truncated_mp4_returns_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("truncated.mp4");
+        // ftyp 之后的 moov box 声明了 4096 字节，但文件在此处截断
+        let mut bytes = vec![0, 0, 0, 16];
+        bytes.extend_from_slice(b"ftypisom");
+        bytes.extend_from_slice(&[0, 0, 2, 0]);
+        bytes.extend_from_slice(&[0, 0, 16, 0]);
+        bytes.extend_from_slice(b"moov");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let path = path.to_string_lossy().to_string();
+        let error = get_video_info(&path).unwrap_err();
+        assert!(error.to_string().contains(&path));
     }
 }
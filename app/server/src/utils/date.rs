@@ -0,0 +1,59 @@
+//! 日期参数解析
+//!
+//! 将查询参数中的 ISO 日期转换为 Unix 时间戳边界，时间按本地时区解释。
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+/// 解析日期范围的下界（包含）
+///
+/// 支持 `2025-01-01`（当天 00:00:00）、`2025-01-01T08:00:00` 和 RFC 3339 格式
+pub fn parse_date_start(value: &str) -> Option<i64> {
+    parse_date_bound(value, NaiveTime::MIN)
+}
+
+/// 解析日期范围的上界（包含）
+///
+/// 只有日期时取当天 23:59:59，其余格式与 [`parse_date_start`] 相同
+pub fn parse_date_end(value: &str) -> Option<i64> {
+    parse_date_bound(value, NaiveTime::from_hms_opt(23, 59, 59)?)
+}
+
+fn parse_date_bound(value: &str, day_time: NaiveTime) -> Option<i64> {
+    let value = value.trim();
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.timestamp());
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.and_time(day_time))
+        })?;
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|datetime| datetime.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_only_covers_whole_day() {
+        let start = parse_date_start("2025-01-01").unwrap();
+        let end = parse_date_end("2025-01-01").unwrap();
+        assert_eq!(end - start, 24 * 3600 - 1);
+    }
+
+    #[test]
+    fn test_rfc3339_and_invalid() {
+        assert_eq!(parse_date_start("1970-01-01T00:00:10Z"), Some(10));
+        assert_eq!(parse_date_end("1970-01-01T00:00:10+00:00"), Some(10));
+        assert_eq!(parse_date_start("last week"), None);
+    }
+}
@@ -0,0 +1,56 @@
+//! 时长格式化
+
+/// 格式化时长（秒）
+///
+/// 统一输出 `HH:MM:SS`（不足一小时也保留小时位），秒数四舍五入，
+/// 数据库中的时长字符串因此可以直接比较和排序
+pub fn format_duration(seconds: f64) -> String {
+    let total_seconds = seconds.max(0.0).round() as u64;
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let secs = total_seconds % 60;
+
+    format!("{:02}:{:02}:{:02}", hours, minutes, secs)
+}
+
+/// 解析时长（[`format_duration`] 的 `HH:MM:SS`，或旧版本的 `MM:SS`），返回秒数
+pub fn parse_duration(duration: &str) -> Option<f64> {
+    let parts = duration
+        .trim()
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let seconds = match parts.as_slice() {
+        [minutes, secs] => minutes * 60 + secs,
+        [hours, minutes, secs] => hours * 3600 + minutes * 60 + secs,
+        _ => return None,
+    };
+    Some(seconds as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0.0), "00:00:00");
+        assert_eq!(format_duration(59.0), "00:00:59");
+        assert_eq!(format_duration(190.0), "00:03:10");
+        assert_eq!(format_duration(3661.0), "01:01:01");
+        // 四舍五入
+        assert_eq!(format_duration(59.6), "00:01:00");
+        // 长度一致，字符串顺序与时长顺序相同
+        assert!(format_duration(599.0) < format_duration(3600.0));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("03:10"), Some(190.0));
+        assert_eq!(parse_duration("01:01:01"), Some(3661.0));
+        assert_eq!(parse_duration(&format_duration(42.0)), Some(42.0));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("abc"), None);
+    }
+}
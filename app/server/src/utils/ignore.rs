@@ -0,0 +1,69 @@
+//! 扫描忽略规则
+
+use log::warn;
+use regex::Regex;
+use std::path::Path;
+
+/// 文件/目录名忽略规则匹配器
+///
+/// 规则为简单通配符：`*` 匹配任意字符，`?` 匹配单个字符，其余字符按字面匹配
+pub struct IgnoreMatcher {
+    patterns: Vec<Regex>,
+}
+
+impl IgnoreMatcher {
+    /// 根据通配符列表创建匹配器，无效的规则会被跳过
+    pub fn new(patterns: &[String]) -> Self {
+        let patterns = patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(&glob_to_regex(pattern)) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    warn!("无效的忽略规则 {}: {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self { patterns }
+    }
+
+    /// 路径的文件名是否匹配任一忽略规则
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        self.patterns.iter().any(|pattern| pattern.is_match(name))
+    }
+}
+
+/// 将通配符转换为完整匹配的正则表达式
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_matcher() {
+        let matcher =
+            IgnoreMatcher::new(&[".*".to_string(), "*.part".to_string(), "@eaDir".to_string()]);
+
+        assert!(matcher.is_ignored(Path::new("/media/.DS_Store")));
+        assert!(matcher.is_ignored(Path::new("/media/movie.mp4.part")));
+        assert!(matcher.is_ignored(Path::new("/media/@eaDir")));
+        assert!(!matcher.is_ignored(Path::new("/media/movie.mp4")));
+        assert!(!matcher.is_ignored(Path::new("/media/eaDir")));
+    }
+}
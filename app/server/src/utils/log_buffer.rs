@@ -0,0 +1,112 @@
+//! 内存中的日志缓冲区
+//!
+//! 保留最近的日志并实时转发给订阅者，用于没有控制台的部署环境通过
+//! `GET /api/logs/stream` 查看日志
+
+use log4rs::append::Append;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::broadcast;
+
+/// 缓冲区保留的日志条数，同时也是订阅者允许落后的条数
+pub const LOG_BUFFER_CAPACITY: usize = 1000;
+
+/// 一条日志
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// 有界的日志环形缓冲区
+pub struct LogBuffer {
+    lines: Mutex<VecDeque<LogLine>>,
+    capacity: usize,
+    sender: broadcast::Sender<LogLine>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            sender,
+        }
+    }
+
+    /// 追加一条日志，超出容量时丢弃最旧的一条
+    pub fn push(&self, line: LogLine) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line.clone());
+        // 在持有锁时发送，保证订阅时拿到的历史和之后收到的日志不重复也不遗漏
+        let _ = self.sender.send(line);
+    }
+
+    /// 订阅日志：返回缓冲区中已有的日志，以及接收之后新日志的通道
+    pub fn subscribe(&self) -> (Vec<LogLine>, broadcast::Receiver<LogLine>) {
+        let lines = self.lines.lock().unwrap();
+        (lines.iter().cloned().collect(), self.sender.subscribe())
+    }
+}
+
+static LOG_BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+
+/// 获取全局日志缓冲区
+pub fn get_log_buffer() -> &'static LogBuffer {
+    LOG_BUFFER.get_or_init(|| LogBuffer::new(LOG_BUFFER_CAPACITY))
+}
+
+/// 把日志写入全局缓冲区的 log4rs appender
+#[derive(Debug)]
+pub struct LogBufferAppender;
+
+impl Append for LogBufferAppender {
+    fn append(&self, record: &log::Record) -> anyhow::Result<()> {
+        get_log_buffer().push(LogLine {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            level: record.level().as_str().to_string(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        });
+        Ok(())
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(message: &str) -> LogLine {
+        LogLine {
+            timestamp: String::new(),
+            level: "INFO".to_string(),
+            target: "server".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_buffer_keeps_only_latest_lines() {
+        let buffer = LogBuffer::new(2);
+        for message in ["a", "b", "c"] {
+            buffer.push(line(message));
+        }
+
+        let (history, mut receiver) = buffer.subscribe();
+        let messages: Vec<&str> = history.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(messages, ["b", "c"]);
+
+        buffer.push(line("d"));
+        assert_eq!(receiver.try_recv().unwrap().message, "d");
+    }
+}
@@ -12,6 +12,9 @@ use log4rs::{
 use log::LevelFilter;
 use nu_ansi_term::Color;
 
+use crate::config::LogFormat;
+use crate::utils::log_buffer::LogBufferAppender;
+
 struct ColorEncoder;
 impl std::fmt::Debug for ColorEncoder {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -62,12 +65,38 @@ impl log4rs::encode::Encode for ColorEncoder {
     }
 }
 
-pub fn init_logger() {
+/// JSON 行格式：每条日志输出一个包含 timestamp、level、target、message 的 JSON 对象
+#[derive(Debug)]
+struct JsonEncoder;
+
+impl log4rs::encode::Encode for JsonEncoder {
+    fn encode(
+        &self,
+        buf: &mut dyn log4rs::encode::Write,
+        record: &log::Record,
+    ) -> Result<(), anyhow::Error> {
+        let line = serde_json::json!({
+            "timestamp": chrono::Local::now().to_rfc3339(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        });
+        writeln!(buf, "{}", line).map_err(anyhow::Error::new)
+    }
+}
+
+/// 按格式选择控制台和日志文件使用的编码器
+fn encoder_for(format: LogFormat, console: bool) -> Box<dyn log4rs::encode::Encode> {
+    match format {
+        LogFormat::Json => Box::new(JsonEncoder),
+        LogFormat::Text if console => Box::new(ColorEncoder),
+        LogFormat::Text => Box::new(PatternEncoder::new("{l} - {d(%Y-%m-%d %H:%M:%S)} - {m}{n}")),
+    }
+}
+
+pub fn init_logger(format: LogFormat) {
     let stdout = ConsoleAppender::builder()
-        .encoder(Box::new(PatternEncoder::new(
-            "{l} - {d(%Y-%m-%d %H:%M:%S)} - {m}{n}",
-        )))
-        .encoder(Box::new(ColorEncoder))
+        .encoder(encoder_for(format, true))
         .build();
 
     let size_trigger = SizeTrigger::new(1024 * 1024 * 10); // 1MB
@@ -78,22 +107,61 @@ pub fn init_logger() {
 
     let compound_policy = CompoundPolicy::new(Box::new(size_trigger), Box::new(window_roller));
     let file_appender = RollingFileAppender::builder()
-        .encoder(Box::new(PatternEncoder::new(
-            "{l} - {d(%Y-%m-%d %H:%M:%S)} - {m}{n}",
-        )))
+        .encoder(encoder_for(format, false))
         .build("logs/log.log", Box::new(compound_policy))
         .unwrap();
 
     let config = Config::builder()
         .appender(Appender::builder().build("stdout", Box::new(stdout)))
         .appender(Appender::builder().build("file", Box::new(file_appender)))
+        // 内存缓冲区，供 /api/logs/stream 实时查看
+        .appender(Appender::builder().build("memory", Box::new(LogBufferAppender)))
         .build(
             Root::builder()
                 .appender("stdout")
                 .appender("file")
+                .appender("memory")
                 .build(LevelFilter::Debug),
         )
         .unwrap();
 
     let _handle = log4rs::init_config(config).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log4rs::encode::writer::simple::SimpleWriter;
+
+    #[test]
+    fn test_json_format_emits_json_lines() {
+        let mut output = SimpleWriter(Vec::new());
+        let encoder = encoder_for(LogFormat::Json, true);
+        for message in ["first", "second \"quoted\"\nline"] {
+            encoder
+                .encode(
+                    &mut output,
+                    &log::Record::builder()
+                        .args(format_args!("{}", message))
+                        .level(Level::Warn)
+                        .target("server::sync")
+                        .build(),
+                )
+                .unwrap();
+        }
+
+        let text = String::from_utf8(output.0).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "WARN");
+        assert_eq!(lines[0]["target"], "server::sync");
+        assert_eq!(lines[0]["message"], "first");
+        assert_eq!(lines[1]["message"], "second \"quoted\"\nline");
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(lines[0]["timestamp"].as_str().unwrap()).is_ok()
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! HLS 播放列表（m3u8）解析
+
+use serde::Serialize;
+use std::path::Path;
+
+/// 主播放列表嵌套的最大层数，防止播放列表互相引用导致无限递归
+const MAX_VARIANT_DEPTH: usize = 3;
+
+/// m3u8 播放列表的内容
+#[derive(Debug, PartialEq)]
+enum Playlist {
+    /// 媒体播放列表：切片时长之和，以及是否以 `#EXT-X-ENDLIST` 结束
+    Media { duration: f64, ended: bool },
+    /// 主播放列表（`#EXT-X-STREAM-INF`）中各码率的子播放列表地址
+    Master { variants: Vec<String> },
+}
+
+/// 媒体播放列表中的一个切片
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MediaSegment {
+    /// 切片序号，从 0 开始
+    pub index: usize,
+    /// `#EXTINF` 中的时长（秒）
+    pub duration: f64,
+    /// 切片地址（播放列表中的原始写法）
+    pub uri: String,
+    /// `#EXT-X-BYTERANGE` 指定的字节范围
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byte_range: Option<ByteRange>,
+    /// 切片使用的 `#EXT-X-KEY`，未加密时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<SegmentKey>,
+}
+
+/// 切片在文件中的字节范围
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub length: u64,
+    /// 起始位置；播放列表省略时为同一文件上一个范围的结束位置
+    pub offset: u64,
+}
+
+/// 切片的加密方式
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SegmentKey {
+    /// 加密方法，例如 AES-128、SAMPLE-AES
+    pub method: String,
+    /// 密钥地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// 初始化向量（十六进制，例如 0x1A2B...）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iv: Option<String>,
+}
+
+/// 读取 m3u8 播放列表的总时长（秒）
+///
+/// - 没有 `#EXT-X-ENDLIST` 的播放列表视为直播，时长未知，返回 None
+/// - 主播放列表读取第一个子播放列表的时长（各码率时长相同）
+pub fn get_m3u8_duration(path: &Path) -> Option<f64> {
+    playlist_duration(path, 0)
+}
+
+fn playlist_duration(path: &Path, depth: usize) -> Option<f64> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match parse_playlist(&content) {
+        Playlist::Media { duration, ended } => ended.then_some(duration),
+        Playlist::Master { variants } => {
+            if depth >= MAX_VARIANT_DEPTH {
+                return None;
+            }
+            // 只支持相对路径的子播放列表
+            let variant = variants.iter().find(|uri| !uri.contains("://"))?;
+            playlist_duration(&path.parent()?.join(variant), depth + 1)
+        }
+    }
+}
+
+fn parse_playlist(content: &str) -> Playlist {
+    let mut duration = 0.0;
+    let mut ended = false;
+    let mut variants = Vec::new();
+    let mut is_master = false;
+    let mut expect_variant_uri = false;
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<时长>,[标题]
+            let seconds = info.split(',').next().unwrap_or("").trim();
+            duration += seconds.parse::<f64>().unwrap_or(0.0);
+        } else if line == "#EXT-X-ENDLIST" {
+            ended = true;
+        } else if line.starts_with("#EXT-X-STREAM-INF") {
+            is_master = true;
+            expect_variant_uri = true;
+        } else if !line.starts_with('#') && expect_variant_uri {
+            variants.push(line.to_string());
+            expect_variant_uri = false;
+        }
+    }
+
+    if is_master {
+        Playlist::Master { variants }
+    } else {
+        Playlist::Media { duration, ended }
+    }
+}
+
+/// 解析媒体播放列表中的切片（`#EXTINF`、`#EXT-X-BYTERANGE`、`#EXT-X-KEY`）
+///
+/// 主播放列表没有切片，返回 None；没有 `#EXTINF` 的地址行会被忽略
+pub fn parse_media_segments(content: &str) -> Option<Vec<MediaSegment>> {
+    let mut segments: Vec<MediaSegment> = Vec::new();
+    let mut duration = None;
+    let mut byte_range: Option<(u64, Option<u64>)> = None;
+    let mut key: Option<SegmentKey> = None;
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let seconds = info.split(',').next().unwrap_or("").trim();
+            duration = Some(seconds.parse::<f64>().unwrap_or(0.0));
+        } else if let Some(range) = line.strip_prefix("#EXT-X-BYTERANGE:") {
+            // #EXT-X-BYTERANGE:<长度>[@<起始位置>]
+            let (length, offset) = match range.split_once('@') {
+                Some((length, offset)) => (length, offset.trim().parse().ok()),
+                None => (range, None),
+            };
+            byte_range = length.trim().parse().ok().map(|length| (length, offset));
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            key = parse_key(attributes);
+        } else if line.starts_with("#EXT-X-STREAM-INF") {
+            return None;
+        } else if !line.starts_with('#') {
+            let Some(duration) = duration.take() else {
+                continue;
+            };
+            let byte_range = byte_range.take().map(|(length, offset)| {
+                // 省略起始位置时紧接同一文件的上一个范围
+                let offset = offset.unwrap_or_else(|| {
+                    segments
+                        .last()
+                        .filter(|previous| previous.uri == line)
+                        .and_then(|previous| previous.byte_range)
+                        .map_or(0, |previous| previous.offset + previous.length)
+                });
+                ByteRange { length, offset }
+            });
+            segments.push(MediaSegment {
+                index: segments.len(),
+                duration,
+                uri: line.to_string(),
+                byte_range,
+                key: key.clone(),
+            });
+        }
+    }
+    Some(segments)
+}
+
+/// 解析 `#EXT-X-KEY` 的属性列表，METHOD=NONE 表示之后的切片不加密
+fn parse_key(attributes: &str) -> Option<SegmentKey> {
+    let mut key = SegmentKey {
+        method: String::new(),
+        uri: None,
+        iv: None,
+    };
+    for (name, value) in parse_attributes(attributes) {
+        match name {
+            "METHOD" => key.method = value.to_string(),
+            "URI" => key.uri = Some(value.to_string()),
+            "IV" => key.iv = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    (!key.method.is_empty() && key.method != "NONE").then_some(key)
+}
+
+/// 解析 `名称=值,名称="带引号的值"` 形式的属性列表（引号内可以有逗号）
+fn parse_attributes(list: &str) -> Vec<(&str, &str)> {
+    let mut attributes = Vec::new();
+    let mut rest = list;
+    while let Some((name, value)) = rest.split_once('=') {
+        let (value, tail) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        attributes.push((name.trim(), value));
+        rest = tail.strip_prefix(',').unwrap_or(tail);
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VOD: &str = "#EXTM3U\n\
+        #EXT-X-TARGETDURATION:10\n\
+        #EXTINF:10.0,\nseg0.ts\n\
+        #EXTINF:10.0,\nseg1.ts\n\
+        #EXTINF:4.5,\nseg2.ts\n\
+        #EXT-X-ENDLIST\n";
+
+    #[test]
+    fn test_parse_media_segments() {
+        let content = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXTINF:6.0,\nclear.ts\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"keys/a,b.key\",IV=0x0123456789ABCDEF0123456789ABCDEF\n\
+            #EXTINF:6.0,\n#EXT-X-BYTERANGE:1000@0\nmain.ts\n\
+            #EXTINF:5.5,\n#EXT-X-BYTERANGE:800\nmain.ts\n\
+            #EXT-X-KEY:METHOD=NONE\n\
+            #EXTINF:2.0,title\nhttps://cdn.example.com/last.ts\n\
+            #EXT-X-ENDLIST\n";
+
+        let key = SegmentKey {
+            method: "AES-128".to_string(),
+            uri: Some("keys/a,b.key".to_string()),
+            iv: Some("0x0123456789ABCDEF0123456789ABCDEF".to_string()),
+        };
+        assert_eq!(
+            parse_media_segments(content).unwrap(),
+            vec![
+                MediaSegment {
+                    index: 0,
+                    duration: 6.0,
+                    uri: "clear.ts".to_string(),
+                    byte_range: None,
+                    key: None,
+                },
+                MediaSegment {
+                    index: 1,
+                    duration: 6.0,
+                    uri: "main.ts".to_string(),
+                    byte_range: Some(ByteRange {
+                        length: 1000,
+                        offset: 0
+                    }),
+                    key: Some(key.clone()),
+                },
+                MediaSegment {
+                    index: 2,
+                    duration: 5.5,
+                    uri: "main.ts".to_string(),
+                    byte_range: Some(ByteRange {
+                        length: 800,
+                        offset: 1000
+                    }),
+                    key: Some(key),
+                },
+                MediaSegment {
+                    index: 3,
+                    duration: 2.0,
+                    uri: "https://cdn.example.com/last.ts".to_string(),
+                    byte_range: None,
+                    key: None,
+                },
+            ]
+        );
+
+        assert_eq!(
+            parse_media_segments("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\n720p.m3u8\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_vod_playlist_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.m3u8");
+        std::fs::write(&path, VOD).unwrap();
+
+        assert_eq!(get_m3u8_duration(&path), Some(24.5));
+    }
+
+    #[test]
+    fn test_live_playlist_has_unknown_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("live.m3u8");
+        std::fs::write(&path, VOD.replace("#EXT-X-ENDLIST\n", "")).unwrap();
+
+        assert_eq!(
+            parse_playlist(&std::fs::read_to_string(&path).unwrap()),
+            Playlist::Media {
+                duration: 24.5,
+                ended: false
+            }
+        );
+        assert_eq!(get_m3u8_duration(&path), None);
+    }
+
+    #[test]
+    fn test_master_playlist_uses_variant_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("720p")).unwrap();
+        std::fs::write(dir.path().join("720p").join("index.m3u8"), VOD).unwrap();
+        let master = dir.path().join("master.m3u8");
+        std::fs::write(
+            &master,
+            "#EXTM3U\n\
+             #EXT-X-STREAM-INF:BANDWIDTH=2000000,RESOLUTION=1280x720\n\
+             720p/index.m3u8\n\
+             #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360\n\
+             360p/index.m3u8\n",
+        )
+        .unwrap();
+
+        assert_eq!(get_m3u8_duration(&master), Some(24.5));
+
+        // 引用自身的主播放列表不会无限递归
+        let looping = dir.path().join("loop.m3u8");
+        std::fs::write(
+            &looping,
+            "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nloop.m3u8\n",
+        )
+        .unwrap();
+        assert_eq!(get_m3u8_duration(&looping), None);
+    }
+}
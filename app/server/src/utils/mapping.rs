@@ -0,0 +1,371 @@
+//! 数据源映射相关的路径处理
+
+use log::warn;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::models::{BreadcrumbEntry, VideoInfo};
+use crate::DiskMapping;
+
+/// 将请求中的路径解析为数据源目录内的物理路径
+///
+/// 支持两种形式：
+/// - 路由路径，例如 `/public/disk1/a.mp4`
+/// - 数据库中存储的物理路径，例如 `F:/public/a.mp4`
+///
+/// 包含 `..` 或不在任何数据源目录内的路径返回 None
+pub fn resolve_media_path(path: &str, mappings: &[DiskMapping]) -> Option<PathBuf> {
+    let requested = Path::new(path);
+    if requested
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return None;
+    }
+
+    for mapping in mappings {
+        // 路由路径形式
+        if let Ok(rest) = requested.strip_prefix(&mapping.route_path) {
+            return Some(Path::new(&mapping.physical_path).join(rest));
+        }
+        // 物理路径形式
+        if requested.starts_with(&mapping.physical_path) {
+            return Some(requested.to_path_buf());
+        }
+    }
+
+    None
+}
+
+/// 查找物理路径所属的数据源，返回该数据源及路径在其中的相对部分（以 `/` 分隔）
+///
+/// 数据库中可能存储 Windows 风格的路径，比较前统一将 `\` 视为分隔符
+fn find_mapping<'a>(path: &str, mappings: &'a [DiskMapping]) -> Option<(&'a DiskMapping, String)> {
+    let path = normalize_separators(path);
+    mappings.iter().find_map(|mapping| {
+        let root = normalize_separators(&mapping.physical_path);
+        let rest = path.strip_prefix(root.trim_end_matches('/'))?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            // 只匹配完整的目录名，/data/videos2 不属于 /data/videos
+            return None;
+        }
+        Some((mapping, rest.trim_start_matches('/').to_string()))
+    })
+}
+
+/// 将 Windows 路径中的 `\` 统一替换为 `/`
+///
+/// 数据库中存储和查询的路径都使用 `/` 分隔，保证 Windows 上的路径和路由路径可以直接比较。
+/// 其他平台上 `\` 是文件名中的合法字符，只转换 Windows 风格的路径（见 [`is_windows_style`]）
+pub fn normalize_separators(path: &str) -> String {
+    if cfg!(windows) || is_windows_style(path) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+/// 路径是否为 Windows 风格：以盘符（`F:`）或 `\\` 开头，或者只用 `\` 分隔（不含 `/`）
+fn is_windows_style(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let has_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    path.contains('\\') && (has_drive || path.starts_with("\\\\") || !path.contains('/'))
+}
+
+/// 路径在数据库中存储的形式（`/` 分隔）
+pub fn db_path(path: &Path) -> String {
+    normalize_separators(&path.to_string_lossy())
+}
+
+/// 查找物理路径所属的数据源，返回其路由路径
+pub fn source_for_path(path: &str, mappings: &[DiskMapping]) -> Option<String> {
+    find_mapping(path, mappings).map(|(mapping, _)| mapping.route_path.clone())
+}
+
+/// 将数据库中存储的物理路径转换为路由路径形式的访问地址
+///
+/// 例如 `/data/videos/show/a.mp4` -> `/public/disk1/show/a.mp4`，不在任何数据源内时返回 None
+pub fn map_physical_to_web(path: &str, mappings: &[DiskMapping]) -> Option<String> {
+    let (mapping, rest) = find_mapping(path, mappings)?;
+    let route = mapping.route_path.trim_end_matches('/');
+    if rest.is_empty() {
+        Some(route.to_string())
+    } else {
+        Some(format!("{}/{}", route, rest))
+    }
+}
+
+/// 将路由路径拼接为对外的访问地址
+///
+/// 配置了 `base_url` 时返回 `https://host/public/disk1/a.mp4` 形式的绝对地址，否则原样返回路径
+pub fn absolute_url(base_url: Option<&str>, path: &str) -> String {
+    match base_url {
+        Some(base) => format!(
+            "{}/{}",
+            base.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        ),
+        None => path.to_string(),
+    }
+}
+
+/// 从数据源根目录到指定路径的面包屑，每一级包含名称和路由路径
+///
+/// 根目录的名称取路由路径的最后一段（`/public/disk1` -> `disk1`），
+/// 路径本身就是根目录时只返回这一级；不在任何数据源内时返回 None
+pub fn breadcrumb(path: &str, mappings: &[DiskMapping]) -> Option<Vec<BreadcrumbEntry>> {
+    let physical = resolve_media_path(path, mappings)?;
+    let (mapping, rest) = find_mapping(&physical.to_string_lossy(), mappings)?;
+
+    let mut web_path = mapping.route_path.trim_end_matches('/').to_string();
+    let root_name = web_path.rsplit('/').next().unwrap_or_default().to_string();
+    let mut entries = vec![BreadcrumbEntry {
+        name: root_name,
+        path: web_path.clone(),
+    }];
+    for name in rest.split('/').filter(|name| !name.is_empty()) {
+        web_path = format!("{}/{}", web_path, name);
+        entries.push(BreadcrumbEntry {
+            name: name.to_string(),
+            path: web_path.clone(),
+        });
+    }
+    Some(entries)
+}
+
+/// 为视频列表（包括子节点）填充 source 和 url 字段
+pub fn fill_web_fields(videos: &mut [VideoInfo], mappings: &[DiskMapping]) {
+    for video in videos.iter_mut() {
+        video.source = source_for_path(&video.path, mappings);
+        video.url = map_physical_to_web(&video.path, mappings);
+        if let Some(children) = video.children.as_mut() {
+            fill_web_fields(children, mappings);
+        }
+    }
+}
+
+/// 检查是否所有数据源目录都不存在（未配置数据源时返回 false）
+///
+/// 不存在的目录会以 warn 级别记录
+pub fn all_sources_missing(mappings: &[DiskMapping]) -> bool {
+    let mut missing = 0;
+    for mapping in mappings {
+        if !Path::new(&mapping.physical_path).is_dir() {
+            warn!(
+                "数据源目录不存在: {} ({})",
+                mapping.physical_path, mapping.route_path
+            );
+            missing += 1;
+        }
+    }
+
+    !mappings.is_empty() && missing == mappings.len()
+}
+
+/// 启动时校验数据源映射：至少一个数据源，路由路径以 `/` 开头且互不重复，物理目录存在
+///
+/// 返回的错误信息列出所有问题，而不只是第一个
+pub fn validate_mappings(mappings: &[DiskMapping]) -> Result<(), String> {
+    if mappings.is_empty() {
+        return Err("至少需要配置一个数据源目录（DATA_SOURCE_DIRS）".to_string());
+    }
+
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    for mapping in mappings {
+        let route = mapping.route_path.trim_end_matches('/');
+        if !route.starts_with('/') {
+            problems.push(format!(
+                "路由路径必须以 / 开头且不能是根路径: '{}'",
+                mapping.route_path
+            ));
+        } else if !seen.insert(route) {
+            problems.push(format!("路由路径重复: {}", mapping.route_path));
+        }
+        if !Path::new(&mapping.physical_path).is_dir() {
+            problems.push(format!(
+                "数据源目录不存在: {} ({})",
+                mapping.physical_path, mapping.route_path
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mappings() -> Vec<DiskMapping> {
+        vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: "/data/videos".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_resolve_media_path() {
+        assert_eq!(
+            resolve_media_path("/public/disk1/a.mp4", &mappings()),
+            Some(PathBuf::from("/data/videos/a.mp4"))
+        );
+        assert_eq!(
+            resolve_media_path("/data/videos/a.mp4", &mappings()),
+            Some(PathBuf::from("/data/videos/a.mp4"))
+        );
+        assert_eq!(resolve_media_path("/etc/passwd", &mappings()), None);
+        assert_eq!(
+            resolve_media_path("/public/disk1/../../etc/passwd", &mappings()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_source_for_path_with_two_mappings() {
+        let mappings = vec![
+            DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: "/data/videos".to_string(),
+            },
+            DiskMapping {
+                route_path: "/public/disk2".to_string(),
+                physical_path: "/data/videos2".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            source_for_path("/data/videos/a.mp4", &mappings).as_deref(),
+            Some("/public/disk1")
+        );
+        assert_eq!(
+            source_for_path("/data/videos2/b.mp4", &mappings).as_deref(),
+            Some("/public/disk2")
+        );
+        assert_eq!(source_for_path("/other/c.mp4", &mappings), None);
+    }
+
+    #[test]
+    fn test_only_windows_style_paths_are_normalized() {
+        assert_eq!(normalize_separators("F:\\public\\a.mp4"), "F:/public/a.mp4");
+        assert_eq!(normalize_separators("F:/public\\a.mp4"), "F:/public/a.mp4");
+        assert_eq!(normalize_separators("\\\\nas\\videos"), "//nas/videos");
+        assert_eq!(normalize_separators("public\\show"), "public/show");
+        if !cfg!(windows) {
+            // `\` 是 Unix 文件名中的合法字符
+            assert_eq!(normalize_separators("/data/a\\b.mp4"), "/data/a\\b.mp4");
+        }
+    }
+
+    #[test]
+    fn test_map_physical_to_web_with_two_mappings() {
+        let mappings = vec![
+            DiskMapping {
+                route_path: "/public/disk1".to_string(),
+                physical_path: "/data/videos".to_string(),
+            },
+            DiskMapping {
+                route_path: "/public/disk2".to_string(),
+                physical_path: "F:\\media\\public".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            map_physical_to_web("/data/videos/show/a.mp4", &mappings).as_deref(),
+            Some("/public/disk1/show/a.mp4")
+        );
+        assert_eq!(
+            map_physical_to_web("F:\\media\\public\\b.mp4", &mappings).as_deref(),
+            Some("/public/disk2/b.mp4")
+        );
+        assert_eq!(
+            map_physical_to_web("/data/videos", &mappings).as_deref(),
+            Some("/public/disk1")
+        );
+        // 前缀相同但不是同一个目录
+        assert_eq!(map_physical_to_web("/data/videos2/c.mp4", &mappings), None);
+        assert_eq!(map_physical_to_web("/other/c.mp4", &mappings), None);
+    }
+
+    #[test]
+    fn test_breadcrumb() {
+        let mappings = vec![DiskMapping {
+            route_path: "/public/disk1".to_string(),
+            physical_path: "/data/videos".to_string(),
+        }];
+        let entry = |name: &str, path: &str| BreadcrumbEntry {
+            name: name.to_string(),
+            path: path.to_string(),
+        };
+
+        assert_eq!(
+            breadcrumb("/public/disk1/show/s01/ep1.mp4", &mappings).unwrap(),
+            vec![
+                entry("disk1", "/public/disk1"),
+                entry("show", "/public/disk1/show"),
+                entry("s01", "/public/disk1/show/s01"),
+                entry("ep1.mp4", "/public/disk1/show/s01/ep1.mp4"),
+            ]
+        );
+        // 物理路径形式和根目录
+        assert_eq!(
+            breadcrumb("/data/videos/show", &mappings).unwrap(),
+            vec![
+                entry("disk1", "/public/disk1"),
+                entry("show", "/public/disk1/show")
+            ]
+        );
+        assert_eq!(
+            breadcrumb("/public/disk1/", &mappings).unwrap(),
+            vec![entry("disk1", "/public/disk1")]
+        );
+        assert!(breadcrumb("/public/disk1/../etc", &mappings).is_none());
+        assert!(breadcrumb("/elsewhere", &mappings).is_none());
+    }
+
+    #[test]
+    fn test_absolute_url() {
+        assert_eq!(absolute_url(None, "/public/a.mp4"), "/public/a.mp4");
+        assert_eq!(
+            absolute_url(Some("https://media.example.com/"), "/public/a.mp4"),
+            "https://media.example.com/public/a.mp4"
+        );
+        assert_eq!(
+            absolute_url(Some("http://host:3003/base"), "public/a.mp4"),
+            "http://host:3003/base/public/a.mp4"
+        );
+    }
+
+    #[test]
+    fn test_validate_mappings() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().to_string_lossy().to_string();
+        let mapping = |route: &str, physical: &str| DiskMapping {
+            route_path: route.to_string(),
+            physical_path: physical.to_string(),
+        };
+
+        assert!(validate_mappings(&[mapping("/public/disk1", &existing)]).is_ok());
+        assert!(validate_mappings(&[]).is_err());
+
+        let err = validate_mappings(&[
+            mapping("/public/disk1", &existing),
+            mapping("/public/disk1/", &existing),
+        ])
+        .unwrap_err();
+        assert!(err.contains("路由路径重复: /public/disk1/"), "{}", err);
+
+        let missing = dir.path().join("missing").to_string_lossy().to_string();
+        let err = validate_mappings(&[
+            mapping("/public/disk1", &existing),
+            mapping("/public/disk2", &missing),
+        ])
+        .unwrap_err();
+        assert!(err.contains("数据源目录不存在"), "{}", err);
+        assert!(err.contains(&missing), "{}", err);
+    }
+}
@@ -0,0 +1,125 @@
+//! 支持的媒体文件类型
+//!
+//! 扫描、同步、缩略图生成和 `/api/capabilities` 都通过这里判断文件类型，扩展名均为小写
+
+use std::path::Path;
+
+/// 视频文件
+pub const VIDEO: &[&str] = &["mp4"];
+/// HLS 播放列表
+pub const PLAYLIST: &[&str] = &["m3u8"];
+/// 字幕文件
+pub const SUBTITLE: &[&str] = &["vtt", "srt"];
+/// 独立的 MPEG-TS 视频（HLS 目录中的切片不单独入库）
+pub const TRANSPORT_STREAM: &[&str] = &["ts"];
+/// 图片文件
+pub const IMAGE: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "bmp"];
+/// 可以用 ffmpeg 截取画面生成缩略图的视频文件，其他文件使用占位缩略图
+pub const FRAME_SOURCE: &[&str] = &["mp4", "avi", "mkv", "mov", "ts"];
+
+/// 媒体文件分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Playlist,
+    TransportStream,
+    Subtitle,
+    Image,
+}
+
+impl MediaKind {
+    /// 分类名，用于配置项（例如缩略图生成方式）
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Video => "video",
+            Self::Playlist => "playlist",
+            Self::TransportStream => "transport_stream",
+            Self::Subtitle => "subtitle",
+            Self::Image => "image",
+        }
+    }
+}
+
+/// 获取小写的文件扩展名，没有扩展名时返回空字符串
+pub fn extension_of(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// 文件扩展名是否在列表中（不区分大小写）
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    extensions.contains(&extension_of(path).as_str())
+}
+
+/// 根据扩展名判断媒体文件分类，不是会被扫描入库的媒体文件时返回 None
+pub fn classify(path: &Path) -> Option<MediaKind> {
+    let extension = extension_of(path);
+    [
+        (VIDEO, MediaKind::Video),
+        (PLAYLIST, MediaKind::Playlist),
+        (TRANSPORT_STREAM, MediaKind::TransportStream),
+        (SUBTITLE, MediaKind::Subtitle),
+        (IMAGE, MediaKind::Image),
+    ]
+    .into_iter()
+    .find(|(extensions, _)| extensions.contains(&extension.as_str()))
+    .map(|(_, kind)| kind)
+}
+
+/// 是否为 HLS 播放列表
+pub fn is_playlist(path: &Path) -> bool {
+    classify(path) == Some(MediaKind::Playlist)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{content_type_for, is_video_or_container};
+
+    #[test]
+    fn test_extensions_classify_consistently() {
+        let dir = tempfile::tempdir().unwrap();
+        let groups = [
+            (VIDEO, MediaKind::Video),
+            (PLAYLIST, MediaKind::Playlist),
+            (TRANSPORT_STREAM, MediaKind::TransportStream),
+            (SUBTITLE, MediaKind::Subtitle),
+            (IMAGE, MediaKind::Image),
+        ];
+
+        for (extensions, kind) in groups {
+            for extension in extensions {
+                let path = dir.path().join(format!("a.{}", extension));
+                std::fs::write(&path, b"").unwrap();
+                let upper = dir.path().join(format!("b.{}", extension.to_uppercase()));
+                std::fs::write(&upper, b"").unwrap();
+
+                assert_eq!(classify(&path), Some(kind), "{}", extension);
+                assert_eq!(classify(&upper), Some(kind), "{}", extension);
+                assert!(is_video_or_container(&path), "{}", extension);
+                assert!(is_video_or_container(&upper), "{}", extension);
+                assert_ne!(
+                    content_type_for(&path),
+                    "application/octet-stream",
+                    "{}",
+                    extension
+                );
+            }
+        }
+
+        // 每个扩展名只属于一个分类
+        let all: Vec<&str> = groups.iter().flat_map(|(e, _)| e.iter().copied()).collect();
+        let mut unique = all.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), all.len());
+
+        let other = dir.path().join("notes.txt");
+        std::fs::write(&other, b"").unwrap();
+        assert_eq!(classify(&other), None);
+        assert!(!is_video_or_container(&other));
+        assert!(is_playlist(Path::new("index.M3U8")));
+    }
+}
@@ -0,0 +1,69 @@
+//! 媒体文件 MIME 类型识别
+
+use std::path::Path;
+
+/// 根据文件扩展名获取 Content-Type
+///
+/// 覆盖了 ServeDir 默认猜测不正确的 m3u8/ts/vtt 类型
+pub fn content_type_for(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match extension.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "ts" => "video/MP2T",
+        "m3u8" => "application/vnd.apple.mpegurl",
+        "vtt" => "text/vtt",
+        "srt" => "application/x-subrip",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 是否需要覆盖静态文件服务推断的 Content-Type
+pub fn needs_content_type_override(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    matches!(extension.as_str(), "m3u8" | "ts" | "vtt")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_type_for() {
+        assert_eq!(content_type_for(Path::new("a/video.mp4")), "video/mp4");
+        assert_eq!(content_type_for(Path::new("a/VIDEO.MP4")), "video/mp4");
+        assert_eq!(content_type_for(Path::new("seg_001.ts")), "video/MP2T");
+        assert_eq!(
+            content_type_for(Path::new("index.m3u8")),
+            "application/vnd.apple.mpegurl"
+        );
+        assert_eq!(content_type_for(Path::new("sub.vtt")), "text/vtt");
+        assert_eq!(content_type_for(Path::new("cover.jpg")), "image/jpeg");
+        assert_eq!(
+            content_type_for(Path::new("unknown.bin")),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn test_needs_content_type_override() {
+        assert!(needs_content_type_override(Path::new("index.m3u8")));
+        assert!(needs_content_type_override(Path::new("seg.ts")));
+        assert!(needs_content_type_override(Path::new("sub.vtt")));
+        assert!(!needs_content_type_override(Path::new("video.mp4")));
+    }
+}
@@ -1,7 +1,35 @@
+mod checksum;
 mod common;
+mod date;
+mod duration;
+mod ignore;
+pub mod log_buffer;
 mod logger;
+mod m3u8;
+mod mapping;
+pub mod media_types;
+mod mime;
+mod move_path;
+mod mpegts;
+mod search;
+mod sort;
+pub use checksum::{file_checksum, try_acquire_checksum_permit, ChecksumAlgo};
 pub use common::{
-    format_size, get_files_without_thumbnails, get_systemtime_created, get_video_info,
-    is_video_or_container,
+    created_epoch, format_created_at, format_size, get_files_without_thumbnails,
+    get_systemtime_created, get_video_info, is_video_or_container, parse_size,
 };
+pub use date::{parse_date_end, parse_date_start};
+pub use duration::{format_duration, parse_duration};
+pub use ignore::IgnoreMatcher;
+pub use log_buffer::{get_log_buffer, LogLine};
 pub use logger::init_logger;
+pub use m3u8::{get_m3u8_duration, parse_media_segments, MediaSegment};
+pub use mapping::{
+    absolute_url, all_sources_missing, breadcrumb, db_path, fill_web_fields, map_physical_to_web,
+    normalize_separators, resolve_media_path, validate_mappings,
+};
+pub use mime::{content_type_for, needs_content_type_override};
+pub use move_path::{move_file_no_clobber_async, move_path, move_path_async};
+pub use mpegts::get_ts_duration;
+pub use search::normalize_for_search;
+pub use sort::natural_cmp;
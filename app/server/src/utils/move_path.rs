@@ -0,0 +1,173 @@
+//! 跨文件系统安全的移动/重命名
+
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// 将文件或目录移动到新位置
+///
+/// 优先使用 `rename`；源和目标位于不同文件系统时（`EXDEV`）改为复制后删除源。
+/// 复制先写入目标旁的临时位置再重命名，其他读者不会看到复制到一半的内容
+pub fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    move_path_with(from, to, |from, to| std::fs::rename(from, to))
+}
+
+/// [`move_path`] 的异步版本，在阻塞线程池中执行
+pub async fn move_path_async(from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> io::Result<()> {
+    let (from, to) = (from.into(), to.into());
+    tokio::task::spawn_blocking(move || move_path(&from, &to))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// 将文件移动到新位置，目标已存在时返回 `AlreadyExists` 而不是覆盖
+///
+/// 先用 `hard_link` 创建目标（由文件系统原子地检查目标是否存在）再删除源；
+/// 不支持硬链接时改为以 `create_new` 创建目标并复制内容
+pub fn move_file_no_clobber(from: &Path, to: &Path) -> io::Result<()> {
+    match std::fs::hard_link(from, to) {
+        Ok(()) => std::fs::remove_file(from),
+        Err(e) if matches!(e.kind(), ErrorKind::AlreadyExists | ErrorKind::NotFound) => Err(e),
+        Err(_) => {
+            let mut output = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(to)?;
+            let result = std::fs::File::open(from)
+                .and_then(|mut input| io::copy(&mut input, &mut output))
+                .and_then(|_| output.sync_all());
+            if let Err(e) = result {
+                let _ = std::fs::remove_file(to);
+                return Err(e);
+            }
+            std::fs::remove_file(from)
+        }
+    }
+}
+
+/// [`move_file_no_clobber`] 的异步版本，在阻塞线程池中执行
+pub async fn move_file_no_clobber_async(
+    from: impl Into<PathBuf>,
+    to: impl Into<PathBuf>,
+) -> io::Result<()> {
+    let (from, to) = (from.into(), to.into());
+    tokio::task::spawn_blocking(move || move_file_no_clobber(&from, &to))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// 用给定的 `rename` 实现移动，跨设备错误时回退到复制+删除
+fn move_path_with(
+    from: &Path,
+    to: &Path,
+    rename: impl Fn(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    match rename(from, to) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => copy_then_remove(from, to),
+        result => result,
+    }
+}
+
+fn copy_then_remove(from: &Path, to: &Path) -> io::Result<()> {
+    let file_name = to
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = to.with_file_name(format!(".{}.moving", file_name));
+    let is_dir = from.is_dir();
+
+    let result = copy_recursive(from, &temp).and_then(|_| std::fs::rename(&temp, to));
+    if let Err(e) = result {
+        let _ = remove_path(&temp, is_dir);
+        return Err(e);
+    }
+    remove_path(from, is_dir)
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return std::fs::copy(from, to).map(|_| ());
+    }
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+fn remove_path(path: &Path, is_dir: bool) -> io::Result<()> {
+    if is_dir {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cross_device(_: &Path, _: &Path) -> io::Result<()> {
+        Err(io::Error::from(ErrorKind::CrossesDevices))
+    }
+
+    #[test]
+    fn test_cross_device_falls_back_to_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("thumb.tmp.jpg");
+        std::fs::write(&file, b"jpeg").unwrap();
+        let target = dir.path().join("thumb.jpg");
+        move_path_with(&file, &target, cross_device).unwrap();
+        assert!(!file.exists());
+        assert_eq!(std::fs::read(&target).unwrap(), b"jpeg");
+
+        let hls = dir.path().join("hls.tmp");
+        std::fs::create_dir_all(hls.join("sub")).unwrap();
+        std::fs::write(hls.join("index.m3u8"), b"#EXTM3U").unwrap();
+        std::fs::write(hls.join("sub/seg0.ts"), b"ts").unwrap();
+        let target = dir.path().join("hls");
+        move_path_with(&hls, &target, cross_device).unwrap();
+        assert!(!hls.exists());
+        assert_eq!(
+            std::fs::read(target.join("index.m3u8")).unwrap(),
+            b"#EXTM3U"
+        );
+        assert_eq!(std::fs::read(target.join("sub/seg0.ts")).unwrap(), b"ts");
+        assert!(!dir.path().join(".hls.moving").exists());
+    }
+
+    #[test]
+    fn test_other_errors_are_not_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.jpg");
+        std::fs::write(&file, b"jpeg").unwrap();
+        let target = dir.path().join("b.jpg");
+        let err = move_path_with(&file, &target, |_, _| {
+            Err(io::Error::from(ErrorKind::PermissionDenied))
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert!(file.exists());
+        assert!(!target.exists());
+
+        move_path(&file, &target).unwrap();
+        assert!(!file.exists() && target.exists());
+    }
+
+    #[test]
+    fn test_no_clobber_move_keeps_existing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join(".a.1.part");
+        let second = dir.path().join(".a.2.part");
+        let target = dir.path().join("a.mp4");
+        std::fs::write(&first, b"first").unwrap();
+        std::fs::write(&second, b"second").unwrap();
+
+        move_file_no_clobber(&first, &target).unwrap();
+        assert!(!first.exists());
+        let err = move_file_no_clobber(&second, &target).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(&target).unwrap(), b"first");
+        assert!(second.exists());
+    }
+}
@@ -0,0 +1,129 @@
+//! MPEG-TS（.ts）时长估算
+//!
+//! 读取文件开头和末尾的 PES 包时间戳（PTS），两者之差即为时长，不需要 ffprobe
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// TS 包长度
+const PACKET_SIZE: usize = 188;
+/// TS 包同步字节
+const SYNC_BYTE: u8 = 0x47;
+/// 从开头和末尾各读取的字节数
+const SCAN_BYTES: u64 = 1024 * 1024;
+/// PTS 的时钟频率（Hz）
+const PTS_CLOCK: f64 = 90_000.0;
+/// PTS 为 33 位，超过后回绕
+const PTS_WRAP: u64 = 1 << 33;
+
+/// 估算 MPEG-TS 文件的时长（秒）
+///
+/// 取开头第一个带 PTS 的 PES 包，再取末尾同一 PID 的最后一个 PTS；找不到时间戳时返回 None
+pub fn get_ts_duration(path: &Path) -> Option<f64> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+
+    let mut head = Vec::new();
+    (&mut file).take(SCAN_BYTES).read_to_end(&mut head).ok()?;
+    let (pid, first) = pts_in(&head).next()?;
+
+    let tail_start = len.saturating_sub(SCAN_BYTES);
+    file.seek(SeekFrom::Start(tail_start)).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    let last = pts_in(&tail)
+        .filter(|(p, _)| *p == pid)
+        .last()
+        .map(|(_, pts)| pts)?;
+
+    let ticks = (last + PTS_WRAP - first) % PTS_WRAP;
+    (ticks > 0).then(|| ticks as f64 / PTS_CLOCK)
+}
+
+/// 依次返回数据中每个 PES 包起始处的 (PID, PTS)
+fn pts_in(data: &[u8]) -> impl Iterator<Item = (u16, u64)> + '_ {
+    let start = sync_offset(data).unwrap_or(data.len());
+    data[start..]
+        .chunks_exact(PACKET_SIZE)
+        .take_while(|packet| packet[0] == SYNC_BYTE)
+        .filter_map(packet_pts)
+}
+
+/// 第一个连续三个包都以同步字节开头的位置（末尾数据不一定从包边界开始）
+fn sync_offset(data: &[u8]) -> Option<usize> {
+    (0..PACKET_SIZE.min(data.len())).find(|&offset| {
+        (0..3)
+            .map(|i| offset + i * PACKET_SIZE)
+            .take_while(|&pos| pos < data.len())
+            .all(|pos| data[pos] == SYNC_BYTE)
+    })
+}
+
+/// 以 PES 包开始的 TS 包中的 PID 和 PTS
+fn packet_pts(packet: &[u8]) -> Option<(u16, u64)> {
+    let payload_start = packet[1] & 0x40 != 0;
+    let pid = u16::from(packet[1] & 0x1f) << 8 | u16::from(packet[2]);
+    let adaptation = (packet[3] >> 4) & 0x3;
+    if !payload_start || adaptation & 0x1 == 0 {
+        return None;
+    }
+
+    let offset = if adaptation & 0x2 != 0 {
+        5 + usize::from(packet[4])
+    } else {
+        4
+    };
+    let pes = packet.get(offset..)?;
+    // PES 起始码 00 00 01，PTS_DTS_flags 的高位表示带有 PTS
+    if pes.len() < 14 || pes[..3] != [0, 0, 1] || pes[7] & 0x80 == 0 {
+        return None;
+    }
+    Some((pid, decode_timestamp(&pes[9..14])))
+}
+
+/// 解码 PES 头中 5 字节的 33 位时间戳
+fn decode_timestamp(bytes: &[u8]) -> u64 {
+    (u64::from(bytes[0] >> 1) & 0x7) << 30
+        | u64::from(bytes[1]) << 22
+        | u64::from(bytes[2] >> 1) << 15
+        | u64::from(bytes[3]) << 7
+        | u64::from(bytes[4] >> 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mpegts_fixture;
+
+    #[test]
+    fn test_ts_duration_from_pts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.ts");
+        std::fs::write(&path, mpegts_fixture(90)).unwrap();
+        assert_eq!(get_ts_duration(&path), Some(90.0));
+
+        // 末尾数据不从包边界开始时重新找到同步位置
+        let mut shifted = vec![0u8; 100];
+        shifted.extend(mpegts_fixture(12));
+        assert_eq!(pts_in(&shifted).count(), 13);
+
+        let empty = dir.path().join("empty.ts");
+        std::fs::write(&empty, b"not a transport stream").unwrap();
+        assert_eq!(get_ts_duration(&empty), None);
+    }
+
+    #[test]
+    fn test_decode_timestamp() {
+        // PTS = 0x1_2345_6789，按 PES 头的标记位布局编码
+        let pts: u64 = 0x1_2345_6789;
+        let bytes = [
+            0x21 | ((pts >> 29) & 0x0e) as u8,
+            (pts >> 22) as u8,
+            0x01 | ((pts >> 14) & 0xfe) as u8,
+            (pts >> 7) as u8,
+            0x01 | ((pts << 1) & 0xfe) as u8,
+        ];
+        assert_eq!(decode_timestamp(&bytes), pts);
+    }
+}
@@ -0,0 +1,68 @@
+//! 搜索用的文本规范化
+
+use unicode_normalization::char::{canonical_combining_class, is_combining_mark};
+use unicode_normalization::UnicodeNormalization;
+
+/// 将文本转换为搜索用的形式：小写并去掉重音符号，例如 `Café` -> `cafe`
+///
+/// 先做 NFD 分解，丢弃分解出的附加符号，再转为小写；
+/// 没有规范分解的字母（`ł`、`ø`、`ß` 等）按对照表替换
+pub fn normalize_for_search(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text
+        .nfd()
+        .filter(|&c| !is_diacritic(c))
+        .flat_map(char::to_lowercase)
+    {
+        match fold_letter(c) {
+            Some(folded) => normalized.push_str(folded),
+            None => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// 是否为附加在前一个字符上的符号（Mn，例如 U+0301）
+///
+/// 规范组合类为 0 的 Mark（例如天城文的元音符号）是字母的一部分，保留
+fn is_diacritic(c: char) -> bool {
+    is_combining_mark(c) && canonical_combining_class(c) != 0
+}
+
+/// 没有规范分解、NFD 后仍带变音的小写拉丁字母对应的基本字母
+fn fold_letter(c: char) -> Option<&'static str> {
+    let folded = match c {
+        'æ' => "ae",
+        'đ' | 'ð' => "d",
+        'ħ' => "h",
+        'ı' => "i",
+        'ĳ' => "ij",
+        'ŀ' | 'ł' => "l",
+        'ŉ' => "n",
+        'ø' => "o",
+        'œ' => "oe",
+        'ß' => "ss",
+        'ŧ' => "t",
+        'þ' => "th",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_for_search() {
+        assert_eq!(normalize_for_search("Café.mp4"), "cafe.mp4");
+        // 分解形式：e + U+0301
+        assert_eq!(normalize_for_search("Cafe\u{301}"), "cafe");
+        assert_eq!(normalize_for_search("ÉCOLE Straße"), "ecole strasse");
+        assert_eq!(normalize_for_search("Łódź"), "lodz");
+        assert_eq!(normalize_for_search("动画 第1集"), "动画 第1集");
+        // 对照表之外的预组合字母同样去掉重音
+        assert_eq!(normalize_for_search("Ǎǹ Ṕhở"), "an pho");
+        assert_eq!(normalize_for_search("İstanbul"), "istanbul");
+    }
+}